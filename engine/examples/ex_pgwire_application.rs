@@ -0,0 +1,60 @@
+use std::error::Error;
+use std::net::SocketAddr;
+use engine::datafusion::SaDataFusion;
+use engine::server::SaPgWireServer;
+use tokio::net::TcpListener;
+use tokio_postgres::{Client, NoTls, Row, SimpleQueryMessage, Statement};
+
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let base_path: &str = env!("CARGO_MANIFEST_DIR");
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    sa_datafusion.load_catalog(&format!("{}/.data/bin/ex-catalog-application/catalog.toml", base_path))?;
+
+    // The server runs in this process on a free local port, `sqlanywhere_pgwire` serves the same way
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await?;
+    let address: SocketAddr = listener.local_addr()?;
+    tokio::spawn(SaPgWireServer::try_new(sa_datafusion)?.serve(listener));
+    let (client, connection) = tokio_postgres::connect(
+        &format!("host={} port={} user=sqlanywhere dbname=datafusion", address.ip(), address.port()),
+        NoTls,
+    ).await?;
+    tokio::spawn(connection);
+    let client: Client = client;
+
+    println!("Querying a source by URI with the simple query protocol...");
+    let stm: String = format!(
+        r#"SELECT region, COUNT(*) AS sales, SUM(amount) AS amount FROM "file://{}/.data/bin/ex-delta-application/sales/region=*/*.parquet" GROUP BY region ORDER BY region"#,
+        base_path
+    );
+    for message in client.simple_query(&stm).await? {
+        if let SimpleQueryMessage::Row(row) = message {
+            println!("{:?} {:?} {:?}", row.get("region"), row.get("sales"), row.get("amount"));
+        }
+    }
+
+    println!("Running a prepared statement on a catalog table with a parameter...");
+    let statement: Statement = client
+        .prepare("SELECT name, subject, score FROM school.scores AS s JOIN school.students AS st ON st.id = s.student_id WHERE s.score >= $1 ORDER BY s.id")
+        .await?;
+    println!("Parameters: {:?}", statement.params());
+    for row in client.query(&statement, &[&80.0_f64]).await? {
+        let (name, subject, score): (String, String, f64) = (row.get("name"), row.get("subject"), row.get("score"));
+        println!("{} {} {}", name, subject, score);
+    }
+
+    println!("Listing the tables of the school schema as psql does...");
+    let rows: Vec<Row> = client
+        .query(
+            "SELECT n.nspname, c.relname, pg_catalog.pg_get_userbyid(c.relowner) FROM pg_catalog.pg_class c \
+             JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace WHERE n.nspname = $1 ORDER BY 1, 2",
+            &[&"school"],
+        )
+        .await?;
+    for row in rows {
+        let (schema, table, owner): (String, String, String) = (row.get(0), row.get(1), row.get(2));
+        println!("{}.{} owned by {}", schema, table, owner);
+    }
+    Ok(())
+}
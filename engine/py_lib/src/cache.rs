@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use engine::cache::{SaCacheCounters, SaMetadataCache, SaMetadataCacheStatistics, SaRangeCache, SaRangeCacheStatistics};
+use engine::datafusion::SaDataFusion;
+use pyo3::prelude::*;
+use crate::errors;
+
+
+pub type StatisticsDict = HashMap<&'static str, HashMap<&'static str, u64>>;
+
+
+/// Statistics of the metadata cache `options` enable, as `execute_sql` takes them, e.g.
+/// `{"metadata_cache": "true"}`; `None` when they do not enable it.
+///
+/// Caches are shared by every query and session of the process using the same directory.
+#[pyfunction]
+#[pyo3(signature = (options=None))]
+pub fn metadata_cache_statistics(options: Option<HashMap<String, String>>) -> PyResult<Option<StatisticsDict>> {
+    get_statistics(&new_session(options)?)
+}
+
+
+/// Statistics of the range cache `options` enable, e.g. `{"range_cache": "true"}`; `None`
+/// when they do not enable it.
+#[pyfunction]
+#[pyo3(signature = (options=None))]
+pub fn range_cache_statistics(options: Option<HashMap<String, String>>) -> PyResult<Option<HashMap<&'static str, u64>>> {
+    get_range_statistics(&new_session(options)?)
+}
+
+
+fn new_session(options: Option<HashMap<String, String>>) -> PyResult<SaDataFusion> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    for (key, value) in options.unwrap_or_default() {
+        sa_datafusion.set_option(&key, &value).map_err(errors::to_py_err)?;
+    }
+    Ok(sa_datafusion)
+}
+
+
+/// Statistics of the metadata cache of `sa_datafusion`, as a dict such as
+/// `{"schemas": {"hits": 2, "misses": 1, "invalidations": 0}, "listings": {...}, "parquet_metadata": {...}}`.
+pub fn get_statistics(sa_datafusion: &SaDataFusion) -> PyResult<Option<StatisticsDict>> {
+    let metadata_cache: Option<Arc<SaMetadataCache>> = sa_datafusion
+        .get_metadata_cache()
+        .map_err(errors::to_py_err)?;
+    Ok(metadata_cache.map(|metadata_cache| {
+        let statistics: SaMetadataCacheStatistics = metadata_cache.get_statistics();
+        HashMap::from([
+            ("schemas", to_dict(statistics.schemas)),
+            ("listings", to_dict(statistics.listings)),
+            ("parquet_metadata", to_dict(statistics.parquet_metadata)),
+        ])
+    }))
+}
+
+
+/// Statistics of the range cache of `sa_datafusion`, as a dict such as
+/// `{"hits": 21, "misses": 13, "evictions": 0, "bytes_read": 2231, "bytes_fetched": 1219, "size": 3151, "max_size": 1073741824}`.
+pub fn get_range_statistics(sa_datafusion: &SaDataFusion) -> PyResult<Option<HashMap<&'static str, u64>>> {
+    let range_cache: Option<Arc<SaRangeCache>> = sa_datafusion
+        .get_range_cache()
+        .map_err(errors::to_py_err)?;
+    Ok(range_cache.map(|range_cache| {
+        let statistics: SaRangeCacheStatistics = range_cache.get_statistics();
+        HashMap::from([
+            ("hits", statistics.hits),
+            ("misses", statistics.misses),
+            ("evictions", statistics.evictions),
+            ("bytes_read", statistics.bytes_read),
+            ("bytes_fetched", statistics.bytes_fetched),
+            ("size", statistics.size),
+            ("max_size", statistics.max_size),
+        ])
+    }))
+}
+
+
+fn to_dict(counters: SaCacheCounters) -> HashMap<&'static str, u64> {
+    HashMap::from([
+        ("hits", counters.hits),
+        ("misses", counters.misses),
+        ("invalidations", counters.invalidations),
+    ])
+}
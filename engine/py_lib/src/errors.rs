@@ -0,0 +1,89 @@
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use engine::SaError;
+
+
+create_exception!(sa_rust, SqlAnyWhereError, PyException, "Base class of every SQLAnyWhere error.");
+create_exception!(sa_rust, UriParseError, SqlAnyWhereError, "A source URI could not be parsed.");
+create_exception!(sa_rust, UnsupportedFormatError, SqlAnyWhereError, "The file format of a source is unknown or cannot be read.");
+create_exception!(sa_rust, UnsupportedSchemeError, SqlAnyWhereError, "No storage is registered for the URI scheme.");
+create_exception!(sa_rust, CredentialsError, SqlAnyWhereError, "Credentials are missing or rejected by the storage.");
+create_exception!(sa_rust, ProviderNotInitialisedError, SqlAnyWhereError, "A storage was queried before its table provider was initialised.");
+create_exception!(sa_rust, TableMetadataError, SqlAnyWhereError, "The metadata of a table format such as Iceberg or Delta is missing or invalid.");
+create_exception!(sa_rust, CatalogError, SqlAnyWhereError, "A catalog file is missing or invalid.");
+create_exception!(sa_rust, ObjectStoreError, SqlAnyWhereError, "The object store failed to serve a request.");
+create_exception!(sa_rust, DataFusionError, SqlAnyWhereError, "Planning or executing the query failed.");
+
+
+/// Maps an engine error onto its Python exception class.
+pub fn to_py_err(error: SaError) -> PyErr {
+    let message: String = error.to_string();
+    match error {
+        SaError::UriParse { .. } => UriParseError::new_err(message),
+        SaError::UnsupportedFormat { .. } => UnsupportedFormatError::new_err(message),
+        SaError::UnsupportedScheme { .. } => UnsupportedSchemeError::new_err(message),
+        SaError::Credentials(_) => CredentialsError::new_err(message),
+        SaError::ProviderNotInitialised { .. } => ProviderNotInitialisedError::new_err(message),
+        SaError::TableMetadata { .. } => TableMetadataError::new_err(message),
+        SaError::Catalog { .. } => CatalogError::new_err(message),
+        SaError::ObjectStore(_) | SaError::ObjectStoreConflict { .. } => ObjectStoreError::new_err(message),
+        SaError::Io(error) => error.into(),
+        SaError::DataFusion(_) => DataFusionError::new_err(message),
+    }
+}
+
+
+pub fn register_exceptions(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("SqlAnyWhereError", py.get_type::<SqlAnyWhereError>())?;
+    m.add("UriParseError", py.get_type::<UriParseError>())?;
+    m.add("UnsupportedFormatError", py.get_type::<UnsupportedFormatError>())?;
+    m.add("UnsupportedSchemeError", py.get_type::<UnsupportedSchemeError>())?;
+    m.add("CredentialsError", py.get_type::<CredentialsError>())?;
+    m.add("ProviderNotInitialisedError", py.get_type::<ProviderNotInitialisedError>())?;
+    m.add("TableMetadataError", py.get_type::<TableMetadataError>())?;
+    m.add("CatalogError", py.get_type::<CatalogError>())?;
+    m.add("ObjectStoreError", py.get_type::<ObjectStoreError>())?;
+    m.add("DataFusionError", py.get_type::<DataFusionError>())?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use pyo3::exceptions::PyFileNotFoundError;
+    use super::*;
+
+
+    #[test]
+    fn test_to_py_err() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let error: PyErr = to_py_err(SaError::uri_parse("s3://", "missing bucket"));
+            assert!(error.is_instance_of::<UriParseError>(py));
+            assert!(error.is_instance_of::<SqlAnyWhereError>(py));
+            assert_eq!(error.value(py).to_string(), "Could not parse the URI 's3://': missing bucket");
+
+            let error: PyErr = to_py_err(SaError::table_metadata("s3://bucket/table", "no snapshot"));
+            assert!(error.is_instance_of::<TableMetadataError>(py));
+            let error: PyErr = to_py_err(SaError::catalog("catalog.toml", "unknown profile"));
+            assert!(error.is_instance_of::<CatalogError>(py));
+            let error: PyErr = to_py_err(SaError::object_store_conflict("s3://bucket", "profile default"));
+            assert!(error.is_instance_of::<ObjectStoreError>(py));
+            let error: PyErr = to_py_err(SaError::Credentials("expired token".to_string()));
+            assert!(error.is_instance_of::<CredentialsError>(py));
+
+            // Our errors wrapped by DataFusion raise their own class
+            let error: datafusion::error::DataFusionError = SaError::provider_not_initialised("s3://bucket/data.csv").into();
+            let error: PyErr = to_py_err(SaError::from(error));
+            assert!(error.is_instance_of::<ProviderNotInitialisedError>(py));
+            let error: PyErr = to_py_err(SaError::from(datafusion::error::DataFusionError::Plan("bad".to_string())));
+            assert!(error.is_instance_of::<DataFusionError>(py));
+
+            // IO errors raise the built-in class of their kind
+            let error: PyErr = to_py_err(SaError::Io(std::io::Error::from(std::io::ErrorKind::NotFound)));
+            assert!(error.is_instance_of::<PyFileNotFoundError>(py));
+            assert!(!error.is_instance_of::<SqlAnyWhereError>(py));
+        });
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::sync::Arc;
+use arrow::datatypes::{DataType, Schema as ArrowSchema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::ffi::FFI_ArrowSchema;
+use arrow::ffi_stream::{ArrowArrayStreamReader, FFI_ArrowArrayStream};
+use arrow::record_batch::RecordBatch;
+use datafusion::error::Result as DFResult;
+use datafusion::execution::SendableRecordBatchStream;
+use engine::builder::pipelines;
+use engine::object_storage::schema;
+use futures::stream::StreamExt;
+use pyo3::exceptions::{PyRuntimeError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyCapsule, PyString};
+use pyo3_asyncio::tokio::{future_into_py, get_runtime};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use crate::errors;
+
+
+/// Pulls the batches of a query result one by one from a task running the query.
+///
+/// The task computes a batch once the previous one is taken; consumers wait for it from their
+/// own thread, a runtime thread waiting for it could be the one the task needs.
+struct BlockingBatches {
+    schema: SchemaRef,
+    receiver: Receiver<DFResult<RecordBatch>>,
+}
+
+
+impl BlockingBatches {
+    fn new(mut batches: SendableRecordBatchStream) -> Self {
+        let schema: SchemaRef = batches.schema();
+        let (sender, receiver): (Sender<DFResult<RecordBatch>>, Receiver<DFResult<RecordBatch>>) = mpsc::channel(1);
+        get_runtime().spawn(async move {
+            while let Some(batch) = batches.next().await {
+                // The reader is dropped, the rest of the query is not needed
+                if sender.send(batch).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            schema,
+            receiver,
+        }
+    }
+}
+
+
+impl Iterator for BlockingBatches {
+    type Item = Result<RecordBatch, ArrowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if Handle::try_current().is_ok() {
+            return Some(Err(ArrowError::ComputeError(
+                "The batches of a query cannot be read from an async runtime thread".to_string()
+            )));
+        }
+        // Without the GIL, so tables read from Python objects can be scanned meanwhile
+        Python::with_gil(|py| py.allow_threads(|| self.receiver.blocking_recv()))
+            .map(|batch| batch.map_err(|e| ArrowError::ExternalError(Box::new(e))))
+    }
+}
+
+
+impl arrow::record_batch::RecordBatchReader for BlockingBatches {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+
+/// Result of a query, exported through the Arrow PyCapsule interface so PyArrow, pandas,
+/// polars or DuckDB read its batches without copy, e.g. `pyarrow.table(reader)`.
+///
+/// Batches are computed while they are read, one ahead, and can only be read once.
+#[pyclass]
+pub struct RecordBatchReader {
+    schema: SchemaRef,
+    batches: Option<BlockingBatches>,
+}
+
+
+impl RecordBatchReader {
+    pub fn new(batches: SendableRecordBatchStream) -> Self {
+        Self {
+            schema: batches.schema(),
+            batches: Some(BlockingBatches::new(batches)),
+        }
+    }
+}
+
+
+#[pymethods]
+impl RecordBatchReader {
+    /// Exports the schema of the result as an `arrow_schema` capsule.
+    fn __arrow_c_schema__<'py>(&self, py: Python<'py>) -> PyResult<&'py PyCapsule> {
+        to_schema_capsule(py, &self.schema)
+    }
+
+    /// Moves the batches into an `arrow_array_stream` capsule.
+    ///
+    /// `requested_schema` is ignored, which the interface allows: batches keep their own schema.
+    #[pyo3(signature = (requested_schema=None))]
+    fn __arrow_c_stream__<'py>(&mut self, py: Python<'py>, requested_schema: Option<&PyAny>) -> PyResult<&'py PyCapsule> {
+        let _ = requested_schema;
+        let batches: BlockingBatches = self
+            .batches
+            .take()
+            .ok_or_else(|| PyRuntimeError::new_err("The batches of this reader have already been read"))?;
+        let ffi_stream: FFI_ArrowArrayStream = FFI_ArrowArrayStream::new(Box::new(batches));
+        PyCapsule::new(py, ffi_stream, Some(CString::new("arrow_array_stream")?))
+    }
+}
+
+
+/// Schema of a table, exported through the Arrow PyCapsule interface, e.g. `pyarrow.schema(schema)`.
+#[pyclass]
+pub struct Schema {
+    schema: SchemaRef,
+}
+
+
+impl Schema {
+    pub fn new(schema: SchemaRef) -> Self {
+        Self { schema }
+    }
+}
+
+
+#[pymethods]
+impl Schema {
+    fn __arrow_c_schema__<'py>(&self, py: Python<'py>) -> PyResult<&'py PyCapsule> {
+        to_schema_capsule(py, &self.schema)
+    }
+
+    /// Column names, in order.
+    #[getter]
+    fn names(&self) -> Vec<String> {
+        self.schema.fields().iter().map(|field| field.name().clone()).collect()
+    }
+
+    fn __repr__(&self) -> String {
+        self.schema
+            .fields()
+            .iter()
+            .map(|field| format!("{}: {}", field.name(), field.data_type()))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+
+fn to_schema_capsule<'py>(py: Python<'py>, schema: &SchemaRef) -> PyResult<&'py PyCapsule> {
+    let ffi_schema: FFI_ArrowSchema = FFI_ArrowSchema::try_from(schema.as_ref())
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    PyCapsule::new(py, ffi_schema, Some(CString::new("arrow_schema")?))
+}
+
+
+/// Imports the Arrow C stream of `data`: any object with `__arrow_c_stream__` (PyArrow tables
+/// and readers, polars DataFrames, ...), or a pandas DataFrame converted with `pyarrow.table`.
+pub fn import_arrow_stream(py: Python<'_>, data: &PyAny) -> PyResult<ArrowArrayStreamReader> {
+    let data: &PyAny = match data.hasattr("__arrow_c_stream__")? {
+        true => data,
+        false => py.import("pyarrow")?.call_method1("table", (data,))?,
+    };
+    let capsule: &PyCapsule = data.call_method0("__arrow_c_stream__")?.downcast()?;
+    if capsule.name()? != Some(c"arrow_array_stream") {
+        return Err(PyTypeError::new_err("__arrow_c_stream__ did not return an arrow_array_stream capsule"));
+    }
+    // Moves the stream out of the capsule, which is left with nothing to release
+    let ffi_stream: FFI_ArrowArrayStream = unsafe {
+        FFI_ArrowArrayStream::from_raw(capsule.pointer() as *mut FFI_ArrowArrayStream)
+    };
+    ArrowArrayStreamReader::try_new(ffi_stream).map_err(|e| errors::to_py_err(e.into()))
+}
+
+
+/// Reads a schema given as `name: Type` pairs, e.g. `"id: Int64, score: Float64"`, or as any
+/// object with `__arrow_c_schema__` such as a `pyarrow.Schema`.
+pub fn import_arrow_schema(schema: &PyAny) -> PyResult<SchemaRef> {
+    if let Ok(fields) = schema.downcast::<PyString>() {
+        let fields = schema::parse_fields(fields.to_str()?).map_err(errors::to_py_err)?;
+        return Ok(Arc::new(ArrowSchema::new(fields)));
+    }
+    let ffi_schema: &FFI_ArrowSchema = borrow_schema_capsule(schema)?;
+    let schema: ArrowSchema = ArrowSchema::try_from(ffi_schema).map_err(|e| errors::to_py_err(e.into()))?;
+    Ok(Arc::new(schema))
+}
+
+
+/// Reads a type hint given as an Arrow type name, e.g. `"Float64"`, or as any object with
+/// `__arrow_c_schema__` such as a `pyarrow.DataType`, and returns its name.
+pub fn import_type_hint(data_type: &PyAny) -> PyResult<String> {
+    if let Ok(type_name) = data_type.downcast::<PyString>() {
+        return Ok(type_name.to_str()?.to_string());
+    }
+    let ffi_schema: &FFI_ArrowSchema = borrow_schema_capsule(data_type)?;
+    let data_type: DataType = DataType::try_from(ffi_schema).map_err(|e| errors::to_py_err(e.into()))?;
+    Ok(data_type.to_string())
+}
+
+
+/// Borrows the schema exported by `__arrow_c_schema__`, released with the capsule.
+fn borrow_schema_capsule(data: &PyAny) -> PyResult<&FFI_ArrowSchema> {
+    if !data.hasattr("__arrow_c_schema__")? {
+        return Err(PyTypeError::new_err("expected a str or an object with __arrow_c_schema__"));
+    }
+    let capsule: &PyCapsule = data.call_method0("__arrow_c_schema__")?.downcast()?;
+    if capsule.name()? != Some(c"arrow_schema") {
+        return Err(PyTypeError::new_err("__arrow_c_schema__ did not return an arrow_schema capsule"));
+    }
+    // The capsule is owned by the GIL pool, so it outlives the reference
+    Ok(unsafe { &*(capsule.pointer() as *const FFI_ArrowSchema) })
+}
+
+
+/// Runs `query` and resolves to a [`RecordBatchReader`] once it is planned.
+///
+/// `options` sets `sa.*` session options, e.g. `{"s3_endpoint": "http://localhost:9000"}`.
+#[pyfunction]
+#[pyo3(signature = (query, options=None))]
+pub fn execute_sql<'py>(
+    py: Python<'py>,
+    query: String,
+    options: Option<HashMap<String, String>>,
+) -> PyResult<&'py PyAny> {
+    let options: HashMap<String, String> = options.unwrap_or_default();
+    future_into_py(py, async move {
+        let batches: SendableRecordBatchStream = pipelines::sa_to_record_batch_stream_pipeline(&query, &options)
+            .await
+            .map_err(errors::to_py_err)?;
+        Ok(RecordBatchReader::new(batches))
+    })
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use arrow::ffi_stream::ArrowArrayStreamReader;
+use arrow::record_batch::RecordBatchReader as _;
+use datafusion::arrow::datatypes::{Schema as ArrowSchema, SchemaRef};
+use datafusion::arrow::error::ArrowError;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::common::DFSchema;
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::prelude::DataFrame;
+use engine::builder::pipelines;
+use engine::builder::write::SaWriteOptions;
+use engine::datafusion::SaDataFusion;
+use engine::SaError;
+use pyo3::exceptions::PyKeyError;
+use pyo3::prelude::*;
+use pyo3_asyncio::tokio::{future_into_py, get_runtime};
+use crate::{cache, errors, write};
+use crate::reader::{self, RecordBatchReader, Schema};
+
+
+/// Query session keeping its tables, object stores and inferred schemas across calls.
+///
+/// Sources queried by URI are registered on first use and read again from the cache afterwards.
+#[pyclass]
+pub struct Session {
+    sa_datafusion: SaDataFusion,
+}
+
+
+#[pymethods]
+impl Session {
+    /// `options` sets `sa.*` session options, e.g. `{"s3_endpoint": "http://localhost:9000"}`.
+    #[new]
+    #[pyo3(signature = (options=None))]
+    fn new(options: Option<HashMap<String, String>>) -> PyResult<Self> {
+        let sa_datafusion: SaDataFusion = SaDataFusion::new();
+        for (key, value) in options.unwrap_or_default() {
+            sa_datafusion.set_option(&key, &value).map_err(errors::to_py_err)?;
+        }
+        Ok(Self { sa_datafusion })
+    }
+
+    /// Registers the source `uri` as the table `name`, replacing any table of that name.
+    ///
+    /// `format` forces the file format; `options` are `sa.*` options of this source only.
+    /// `schema` replaces the inferred schema, as `"id: Int64, score: Float64"` or a `pyarrow.Schema`;
+    /// `type_hints` only override some columns, e.g. `{"score": "Float64"}` or `{"score": pa.float64()}`;
+    /// `sample_size` is the number of CSV or JSON records read to infer the schema.
+    #[pyo3(signature = (name, uri, format=None, options=None, schema=None, type_hints=None, sample_size=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn register<'py>(
+        &self,
+        py: Python<'py>,
+        name: String,
+        uri: String,
+        format: Option<String>,
+        options: Option<HashMap<String, String>>,
+        schema: Option<&PyAny>,
+        type_hints: Option<HashMap<String, &PyAny>>,
+        sample_size: Option<usize>,
+    ) -> PyResult<&'py PyAny> {
+        let sa_datafusion: SaDataFusion = self.sa_datafusion.clone();
+        let mut options: HashMap<String, String> = options.unwrap_or_default();
+        let schema: Option<SchemaRef> = schema.map(reader::import_arrow_schema).transpose()?;
+        if let Some(type_hints) = type_hints {
+            let mut hints: Vec<String> = Vec::new();
+            for (column, data_type) in type_hints {
+                hints.push(format!("\"{}\": {}", column, reader::import_type_hint(data_type)?));
+            }
+            options.insert("schema_hints".to_string(), hints.join(", "));
+        }
+        if let Some(sample_size) = sample_size {
+            options.insert("schema_sample_size".to_string(), sample_size.to_string());
+        }
+        future_into_py(py, async move {
+            pipelines::sa_register_source_pipeline(&sa_datafusion, &name, &uri, format.as_deref(), schema, &options)
+                .await
+                .map_err(errors::to_py_err)
+        })
+    }
+
+    /// Runs `query` on the session and resolves to a [`RecordBatchReader`] once it is planned.
+    fn sql<'py>(&self, py: Python<'py>, query: String) -> PyResult<&'py PyAny> {
+        let sa_datafusion: SaDataFusion = self.sa_datafusion.clone();
+        future_into_py(py, async move {
+            let batches: Result<SendableRecordBatchStream, SaError> = async {
+                let df: DataFrame = pipelines::sa_query(&sa_datafusion, &query).await?;
+                Ok(df.execute_stream().await?)
+            }.await;
+            Ok(RecordBatchReader::new(batches.map_err(errors::to_py_err)?))
+        })
+    }
+
+    /// Registers the Arrow data `data` as the table `name`, replacing any table of that name.
+    ///
+    /// `data` is a PyArrow table or reader, a pandas or polars DataFrame, or any object with
+    /// `__arrow_c_stream__`. Its batches are copied into memory, unless `streaming` is set: the
+    /// first query scanning the table then reads them, and the table cannot be scanned again.
+    #[pyo3(signature = (name, data, streaming=false))]
+    fn register_arrow(&self, py: Python<'_>, name: &str, data: &PyAny, streaming: bool) -> PyResult<()> {
+        let reader: ArrowArrayStreamReader = reader::import_arrow_stream(py, data)?;
+        if streaming {
+            return self.sa_datafusion
+                .register_record_batch_reader(name, Box::new(reader))
+                .map_err(errors::to_py_err);
+        }
+        let schema: SchemaRef = reader.schema();
+        let batches: Vec<RecordBatch> = py
+            .allow_threads(|| reader.collect::<Result<Vec<RecordBatch>, ArrowError>>())
+            .map_err(|e| errors::to_py_err(e.into()))?;
+        self.sa_datafusion
+            .register_batches(name, schema, batches)
+            .map_err(errors::to_py_err)
+    }
+
+    /// Runs `query` on the session and writes its result to `target`; resolves to the number
+    /// of rows written. Arguments are those of `sa_rust.write_sql`.
+    #[pyo3(signature = (
+        query,
+        target,
+        format=None,
+        compression=None,
+        row_group_size=None,
+        partition_by=None,
+        single_file=None,
+        format_options=None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn write<'py>(
+        &self,
+        py: Python<'py>,
+        query: String,
+        target: String,
+        format: Option<String>,
+        compression: Option<String>,
+        row_group_size: Option<usize>,
+        partition_by: Option<Vec<String>>,
+        single_file: Option<bool>,
+        format_options: Option<HashMap<String, String>>,
+    ) -> PyResult<&'py PyAny> {
+        let sa_datafusion: SaDataFusion = self.sa_datafusion.clone();
+        let write_options: SaWriteOptions = write::to_write_options(
+            format,
+            compression,
+            row_group_size,
+            partition_by,
+            single_file,
+            format_options
+        );
+        future_into_py(py, async move {
+            pipelines::sa_write(&sa_datafusion, &query, &target, &write_options)
+                .await
+                .map_err(errors::to_py_err)
+        })
+    }
+
+    /// Names of the registered tables.
+    fn tables(&self) -> Vec<String> {
+        self.sa_datafusion.get_table_names()
+    }
+
+    /// Schema of the table `name`, which can be a table of the `catalog` option not queried yet.
+    fn schema(&self, py: Python<'_>, name: &str) -> PyResult<Schema> {
+        let sa_datafusion: &SaDataFusion = &self.sa_datafusion;
+        let df_schema: DFSchema = py.allow_threads(|| get_runtime().block_on(async {
+            pipelines::sa_register_catalog_table_pipeline(sa_datafusion, name).await.map_err(errors::to_py_err)?;
+            sa_datafusion.get_schema(name).await.map_err(errors::to_py_err)
+        }))?;
+        let schema: ArrowSchema = df_schema.as_arrow().clone();
+        Ok(Schema::new(Arc::new(schema)))
+    }
+
+    /// Hits, misses and invalidations of the metadata cache per kind of metadata, `None` unless
+    /// the `metadata_cache` option is set; see `sa_rust.metadata_cache_statistics`.
+    fn metadata_cache_statistics(&self) -> PyResult<Option<cache::StatisticsDict>> {
+        cache::get_statistics(&self.sa_datafusion)
+    }
+
+    /// Hits, misses, evictions and sizes of the range cache, `None` unless the `range_cache`
+    /// option is set; see `sa_rust.range_cache_statistics`.
+    fn range_cache_statistics(&self) -> PyResult<Option<HashMap<&'static str, u64>>> {
+        cache::get_range_statistics(&self.sa_datafusion)
+    }
+
+    /// Removes the table `name`.
+    fn deregister(&self, name: &str) -> PyResult<()> {
+        match self.sa_datafusion.deregister_table(name).map_err(errors::to_py_err)? {
+            true => Ok(()),
+            false => Err(PyKeyError::new_err(format!("No table named '{}'", name))),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use engine::builder::pipelines;
+use engine::error::Result;
+use futures::lock::Mutex;
+use futures::stream::{BoxStream, StreamExt};
+use pyo3::exceptions::PyStopAsyncIteration;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use pyo3_asyncio::tokio::future_into_py;
+use crate::errors;
+
+
+type ArrowIpcChunks = BoxStream<'static, Result<Vec<u8>>>;
+
+
+/// Chunk converted to `bytes` (not a `list`) when the future resolves.
+struct ArrowIpcChunk(Vec<u8>);
+
+
+impl IntoPy<PyObject> for ArrowIpcChunk {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        PyBytes::new(py, &self.0).into()
+    }
+}
+
+
+/// Async iterator over the Arrow IPC chunks of a query result, as `bytes`.
+///
+/// The query runs on the first iteration; the concatenated chunks form one IPC stream.
+#[pyclass]
+pub struct ArrowIpcStream {
+    query: String,
+    options: HashMap<String, String>,
+    chunks: Arc<Mutex<Option<ArrowIpcChunks>>>,
+}
+
+
+#[pymethods]
+impl ArrowIpcStream {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Option<&'py PyAny>> {
+        let query: String = self.query.clone();
+        let options: HashMap<String, String> = self.options.clone();
+        let chunks: Arc<Mutex<Option<ArrowIpcChunks>>> = self.chunks.clone();
+        let next_chunk = future_into_py(py, async move {
+            let mut chunks = chunks.lock().await;
+            if chunks.is_none() {
+                let query_chunks: ArrowIpcChunks = pipelines::sa_to_arrow_ipc_stream_pipeline(&query, &options)
+                    .await
+                    .map_err(errors::to_py_err)?;
+                *chunks = Some(query_chunks);
+            }
+            let next: Option<Result<Vec<u8>>> = match chunks.as_mut() {
+                Some(query_chunks) => query_chunks.next().await,
+                None => None,
+            };
+            match next {
+                Some(chunk) => Ok(ArrowIpcChunk(chunk.map_err(errors::to_py_err)?)),
+                None => Err(PyStopAsyncIteration::new_err(())),
+            }
+        })?;
+        Ok(Some(next_chunk))
+    }
+}
+
+
+/// Runs `query` and returns an async iterator of Arrow IPC chunks, produced while the
+/// query runs instead of once it has finished.
+#[pyfunction]
+#[pyo3(signature = (query, options=None))]
+pub fn execute_sql_stream(query: String, options: Option<HashMap<String, String>>) -> ArrowIpcStream {
+    ArrowIpcStream {
+        query,
+        options: options.unwrap_or_default(),
+        chunks: Arc::new(Mutex::new(None)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use arrow::datatypes::SchemaRef;
+    use arrow::ipc::reader::StreamReader;
+    use arrow::record_batch::RecordBatch;
+
+
+    /// Chunks of `query`, awaited one by one on a Python event loop until the iterator stops.
+    fn collect_chunks(query: &str) -> PyResult<Vec<Vec<u8>>> {
+        pyo3::prepare_freethreaded_python();
+        let stream: ArrowIpcStream = execute_sql_stream(query.to_string(), None);
+        Python::with_gil(|py| {
+            pyo3_asyncio::tokio::run(py, async move {
+                let mut chunks: Vec<Vec<u8>> = Vec::new();
+                loop {
+                    let next_chunk = Python::with_gil(|py| {
+                        let awaitable: &PyAny = stream.__anext__(py)?.expect("__anext__ returns an awaitable");
+                        pyo3_asyncio::tokio::into_future(awaitable)
+                    })?;
+                    match next_chunk.await {
+                        Ok(chunk) => chunks.push(Python::with_gil(|py| chunk.extract::<&[u8]>(py).map(<[u8]>::to_vec))?),
+                        Err(error) if Python::with_gil(|py| error.is_instance_of::<PyStopAsyncIteration>(py)) => return Ok(chunks),
+                        Err(error) => return Err(error),
+                    }
+                }
+            })
+        })
+    }
+
+
+    fn read_arrow_ipc(chunks: &[Vec<u8>]) -> (SchemaRef, Vec<RecordBatch>) {
+        let stream: Vec<u8> = chunks.concat();
+        let reader: StreamReader<Cursor<Vec<u8>>> = StreamReader::try_new(Cursor::new(stream), None).unwrap();
+        let schema: SchemaRef = reader.schema();
+        (schema, reader.collect::<std::result::Result<_, _>>().unwrap())
+    }
+
+
+    #[test]
+    fn test_empty_result() {
+        // The schema then the end-of-stream marker, with no batch in between
+        let chunks: Vec<Vec<u8>> = collect_chunks("SELECT * FROM (VALUES (1, 'ada'), (2, 'grace')) AS t(id, name) WHERE id > 2").unwrap();
+        assert_eq!(chunks.len(), 2);
+        let (schema, batches): (SchemaRef, Vec<RecordBatch>) = read_arrow_ipc(&chunks);
+        assert_eq!(schema.fields().iter().map(|field| field.name().as_str()).collect::<Vec<&str>>(), ["id", "name"]);
+        assert!(batches.is_empty());
+    }
+
+
+    #[test]
+    fn test_batches() {
+        let chunks: Vec<Vec<u8>> = collect_chunks(
+            "SELECT * FROM (VALUES (1, 'ada'), (2, 'grace')) AS t(id, name) UNION ALL SELECT * FROM (VALUES (3, 'alan')) AS t(id, name)"
+        ).unwrap();
+        // One chunk per batch of the union, in the order its inputs finish
+        assert_eq!(chunks.len(), 4);
+        let (_, batches): (SchemaRef, Vec<RecordBatch>) = read_arrow_ipc(&chunks);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 3);
+    }
+
+
+    #[test]
+    fn test_query_error() {
+        let error: PyErr = collect_chunks("SELECT missing FROM (VALUES (1)) AS t(id)").unwrap_err();
+        Python::with_gil(|py| assert!(error.is_instance_of::<errors::DataFusionError>(py)));
+    }
+}
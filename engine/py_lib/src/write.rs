@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use engine::builder::pipelines;
+use engine::builder::write::SaWriteOptions;
+use pyo3::prelude::*;
+use pyo3_asyncio::tokio::future_into_py;
+use crate::errors;
+
+
+/// Builds the [`SaWriteOptions`] of the Python keyword arguments.
+pub fn to_write_options(
+    format: Option<String>,
+    compression: Option<String>,
+    row_group_size: Option<usize>,
+    partition_by: Option<Vec<String>>,
+    single_file: Option<bool>,
+    format_options: Option<HashMap<String, String>>,
+) -> SaWriteOptions {
+    let mut write_options: SaWriteOptions = SaWriteOptions::new()
+        .with_partition_by(partition_by.unwrap_or_default());
+    if let Some(format) = format {
+        write_options = write_options.with_format(&format);
+    }
+    if let Some(compression) = compression {
+        write_options = write_options.with_compression(&compression);
+    }
+    if let Some(row_group_size) = row_group_size {
+        write_options = write_options.with_row_group_size(row_group_size);
+    }
+    if let Some(single_file) = single_file {
+        write_options = write_options.with_single_file(single_file);
+    }
+    for (key, value) in format_options.unwrap_or_default() {
+        write_options = write_options.with_format_option(&key, &value);
+    }
+    write_options
+}
+
+
+/// Runs `query` and writes its result to `target`, a `file://` or `s3://` file or directory;
+/// resolves to the number of rows written.
+///
+/// `format` (`parquet`, `csv`, `json`, `arrow`) defaults to the extension of `target`.
+/// `single_file` forces a single file or a directory of files, decided by `target` otherwise.
+#[pyfunction]
+#[pyo3(signature = (
+    query,
+    target,
+    format=None,
+    compression=None,
+    row_group_size=None,
+    partition_by=None,
+    single_file=None,
+    format_options=None,
+    options=None
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn write_sql<'py>(
+    py: Python<'py>,
+    query: String,
+    target: String,
+    format: Option<String>,
+    compression: Option<String>,
+    row_group_size: Option<usize>,
+    partition_by: Option<Vec<String>>,
+    single_file: Option<bool>,
+    format_options: Option<HashMap<String, String>>,
+    options: Option<HashMap<String, String>>,
+) -> PyResult<&'py PyAny> {
+    let write_options: SaWriteOptions = to_write_options(
+        format,
+        compression,
+        row_group_size,
+        partition_by,
+        single_file,
+        format_options
+    );
+    let options: HashMap<String, String> = options.unwrap_or_default();
+    future_into_py(py, async move {
+        pipelines::sa_write_pipeline(&query, &options, &target, &write_options)
+            .await
+            .map_err(errors::to_py_err)
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+
+    /// Runs [`write_sql`] on a Python event loop, resolving to the number of rows written.
+    fn run_write_sql(target: &str, format: Option<&str>, partition_by: Option<Vec<String>>, single_file: Option<bool>) -> PyResult<u64> {
+        pyo3::prepare_freethreaded_python();
+        let query: String = format!(
+            "SELECT * FROM 'file://{}/../.data/bin/ex-local-storage-application/scores.csv'",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let target: String = target.to_string();
+        let format: Option<String> = format.map(str::to_string);
+        Python::with_gil(|py| {
+            pyo3_asyncio::tokio::run(py, async move {
+                let rows = Python::with_gil(|py| {
+                    let awaitable: &PyAny = write_sql(py, query, target, format, None, None, partition_by, single_file, None, None)?;
+                    pyo3_asyncio::tokio::into_future(awaitable)
+                })?;
+                let rows: PyObject = rows.await?;
+                Python::with_gil(|py| rows.extract::<u64>(py))
+            })
+        })
+    }
+
+
+    fn list_dir(path: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+
+    #[test]
+    fn test_to_write_options() {
+        let write_options: SaWriteOptions = to_write_options(
+            Some("parquet".to_string()),
+            Some("snappy".to_string()),
+            Some(1000),
+            Some(vec!["subject".to_string()]),
+            None,
+            Some(HashMap::from([("bloom_filter_enabled".to_string(), "true".to_string())])),
+        );
+        assert_eq!(write_options.get_format(), Some("parquet"));
+        assert_eq!(write_options.get_partition_by(), ["subject"]);
+        assert_eq!(
+            write_options.get_copy_options(),
+            HashMap::from([
+                ("format.compression".to_string(), "snappy".to_string()),
+                ("format.max_row_group_size".to_string(), "1000".to_string()),
+                ("format.bloom_filter_enabled".to_string(), "true".to_string()),
+            ])
+        );
+
+        let write_options: SaWriteOptions = to_write_options(None, None, None, None, Some(false), None);
+        assert_eq!(write_options.get_format(), None);
+        assert_eq!(write_options.get_output_url("file:///tmp/out.csv").unwrap(), "file:///tmp/out.csv/");
+    }
+
+
+    #[test]
+    fn test_write_sql() {
+        let dir: TempDir = tempfile::tempdir().unwrap();
+        let target: String = format!("file://{}/scores.parquet", dir.path().display());
+        assert_eq!(run_write_sql(&target, None, None, None).unwrap(), 5);
+        assert!(dir.path().join("scores.parquet").is_file());
+
+        let target: String = format!("file://{}/scores", dir.path().display());
+        assert_eq!(run_write_sql(&target, Some("csv"), Some(vec!["subject".to_string()]), None).unwrap(), 5);
+        assert_eq!(list_dir(&dir.path().join("scores")), ["subject=chem", "subject=math", "subject=physic"]);
+
+        // A single file needs a file name
+        let target: String = format!("file://{}/single/", dir.path().display());
+        let error: PyErr = run_write_sql(&target, Some("csv"), None, Some(true)).unwrap_err();
+        Python::with_gil(|py| assert!(error.is_instance_of::<pyo3::exceptions::PyOSError>(py)));
+    }
+}
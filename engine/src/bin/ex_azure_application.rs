@@ -0,0 +1,45 @@
+use engine::object_storage::storage::SaStorage;
+use engine::object_storage::SaAzure;
+use engine::object_storage::azure::SaAzureCredentials;
+use engine::error::Result;
+use engine::datafusion::SaDataFusion;
+use engine::object_storage::SaSchemaOptions;
+use std::env;
+use std::sync::Arc;
+use datafusion::prelude::DataFrame;
+use datafusion::datasource::file_format::csv::CsvFormat;
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let azure_uri: &str = "az://sql-anywhere/ex-azure-application/students.csv";
+    // Point to Azurite with AZURE_STORAGE_USE_EMULATOR=true (AZURITE_BLOB_STORAGE_URL defaults to http://127.0.0.1:10000)
+    let credentials: SaAzureCredentials = match env::var("AZURE_STORAGE_ACCOUNT_KEY") {
+        Ok(account_key) => SaAzureCredentials::AccountKey(account_key),
+        Err(_) => SaAzureCredentials::Env,
+    };
+
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    let student_storage: SaAzure = SaAzure::new_from_azure_uri(azure_uri)?
+        .with_credentials(credentials)
+        .init_table_provider(
+            &sa_datafusion,
+            Arc::new(CsvFormat::default()),
+            &SaSchemaOptions::default()
+        ).await?;
+
+    sa_datafusion.register_sa_storage(Arc::new(student_storage.clone())).await?;
+    println!("{}", student_storage.get_azure_container());
+    println!("{}", student_storage.get_azure_blob_path());
+
+    let stm: String = format!(r#"
+        SELECT
+            *
+        FROM
+            "{}"
+        "#, student_storage.get_file_url());
+    println!("{}", stm);
+    let df: DataFrame = sa_datafusion.execute_sql(&stm).await?;
+    df.show().await?;
+    Ok(())
+}
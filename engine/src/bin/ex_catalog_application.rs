@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use engine::builder::pipelines::{sa_query, sa_to_dataframe_pipeline_with_options};
+use engine::catalog::SaCatalog;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let base_path: &str = env!("CARGO_MANIFEST_DIR");
+    let catalog_path: String = format!("{}/.data/bin/ex-catalog-application/catalog.toml", base_path);
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+
+    println!("Loading the catalog {}...", catalog_path);
+    let catalog: Arc<SaCatalog> = sa_datafusion.load_catalog(&catalog_path)?;
+    println!("Tables: {}", catalog.get_table_names().join(", "));
+    println!();
+
+    println!("Joining scores and students by name, scores are read as floats...");
+    let stm: &str = r#"
+        SELECT st.name, s.subject, s.score
+        FROM school.scores AS s
+        JOIN school.students AS st ON st.id = s.student_id
+        ORDER BY s.id
+    "#;
+    sa_query(&sa_datafusion, stm).await?.show().await?;
+
+    println!("Reading the Delta table sales.orders...");
+    let stm: &str = "SELECT region, COUNT(*) AS sales, SUM(amount) AS amount FROM sales.orders GROUP BY region ORDER BY region";
+    sa_query(&sa_datafusion, stm).await?.show().await?;
+
+    println!("Reading a YAML catalog from the options of a pipeline...");
+    let options: HashMap<String, String> = HashMap::from([(
+        "catalog".to_string(),
+        format!("{}/.data/bin/ex-catalog-application/catalog.yaml", base_path),
+    )]);
+    let stm: &str = "SELECT student_id, SUM(minutes) AS minutes FROM school.daily GROUP BY student_id ORDER BY student_id";
+    sa_to_dataframe_pipeline_with_options(stm, &options).await?.show().await?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use engine::builder::pipelines::sa_to_dataframe_pipeline;
+use engine::error::Result;
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let base_path: &str = env!("CARGO_MANIFEST_DIR");
+    let data_uri: String = format!("file://{}/.data/bin/ex-compressed-application", base_path);
+
+    println!("Reading a gzip-compressed CSV file...");
+    let stm: String = format!(r#"SELECT * FROM "{}/scores.csv.gz""#, data_uri);
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+
+    println!("Reading zstd-compressed JSON lines...");
+    let stm: String = format!(
+        r#"SELECT kind, COUNT(*) AS events FROM "{}/events.jsonl.zst" GROUP BY kind ORDER BY kind"#,
+        data_uri
+    );
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+
+    println!("Reading a gzip-compressed CSV file without an extension, detected from its first bytes...");
+    let stm: String = format!(r#"SELECT * FROM "{}/students_export""#, data_uri);
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+
+    println!("Reading a directory of bzip2-compressed CSV files...");
+    let stm: String = format!(
+        r#"SELECT student_id, SUM(minutes) AS minutes FROM "{}/daily/" GROUP BY student_id ORDER BY student_id"#,
+        data_uri
+    );
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use engine::builder::pipelines::sa_to_dataframe_pipeline;
+use engine::error::Result;
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let base_path: &str = env!("CARGO_MANIFEST_DIR");
+    let table_uri: String = format!("file://{}/.data/bin/ex-delta-application/sales", base_path);
+
+    println!("Showing the history of the table...");
+    let stm: String = format!(
+        r#"SELECT version, timestamp, operation, operation_parameters FROM "{}#history""#,
+        table_uri
+    );
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+
+    println!("Reading the latest version, from the checkpoint of version 2 and the next commit...");
+    let stm: String = format!(r#"SELECT * FROM "{}" ORDER BY id"#, table_uri);
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+
+    println!("Reading version 1, before the us sales were deleted...");
+    let stm: String = format!(
+        r#"SELECT region, COUNT(*) AS sales, SUM(amount) AS amount
+        FROM "{}" FOR VERSION AS OF 1
+        GROUP BY region
+        ORDER BY region"#,
+        table_uri
+    );
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+
+    println!("Reading the table as it was on 2024-03-15...");
+    let stm: String = format!(r#"SELECT COUNT(*) FROM "{}" FOR TIMESTAMP AS OF '2024-03-15 00:00:00'"#, table_uri);
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+
+    println!("Filtering on the partition and on file statistics, only one data file is read:");
+    let stm: String = format!(r#"EXPLAIN SELECT * FROM "{}" WHERE region = 'eu' AND amount > 500"#, table_uri);
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+    Ok(())
+}
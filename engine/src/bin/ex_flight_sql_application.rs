@@ -0,0 +1,70 @@
+use std::error::Error;
+use std::net::SocketAddr;
+use arrow_flight::sql::client::{FlightSqlServiceClient, PreparedStatement};
+use arrow_flight::sql::CommandGetTables;
+use arrow_flight::FlightInfo;
+use datafusion::arrow::array::{ArrayRef, Float64Array};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::arrow::util::pretty::print_batches;
+use engine::datafusion::SaDataFusion;
+use engine::server::SaFlightSqlService;
+use futures::TryStreamExt;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tonic::transport::{Channel, Endpoint};
+
+
+/// Fetches and prints every endpoint of `flight_info`.
+async fn print_flight_info(client: &mut FlightSqlServiceClient<Channel>, flight_info: FlightInfo) -> Result<(), Box<dyn Error>> {
+    let mut batches: Vec<RecordBatch> = Vec::new();
+    for endpoint in flight_info.endpoint {
+        let ticket = endpoint.ticket.expect("endpoint with a ticket");
+        batches.extend(client.do_get(ticket).await?.try_collect::<Vec<RecordBatch>>().await?);
+    }
+    print_batches(&batches)?;
+    Ok(())
+}
+
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let base_path: &str = env!("CARGO_MANIFEST_DIR");
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    sa_datafusion.load_catalog(&format!("{}/.data/bin/ex-catalog-application/catalog.toml", base_path))?;
+
+    // The server runs in this process on a free local port, `sqlanywhere_flight` serves the same way
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await?;
+    let address: SocketAddr = listener.local_addr()?;
+    tokio::spawn(SaFlightSqlService::new(sa_datafusion).serve(listener));
+    let channel: Channel = Endpoint::from_shared(format!("http://{}", address))?.connect().await?;
+    let mut client: FlightSqlServiceClient<Channel> = FlightSqlServiceClient::new(channel);
+
+    println!("Querying a source by URI...");
+    let stm: String = format!(
+        r#"SELECT region, COUNT(*) AS sales, SUM(amount) AS amount FROM "file://{}/.data/bin/ex-delta-application/sales/region=*/*.parquet" GROUP BY region ORDER BY region"#,
+        base_path
+    );
+    let flight_info: FlightInfo = client.execute(stm, None).await?;
+    print_flight_info(&mut client, flight_info).await?;
+
+    println!("Running a prepared statement on a catalog table with a parameter...");
+    let mut prepared_statement: PreparedStatement<Channel> = client
+        .prepare("SELECT name, subject, score FROM school.scores AS s JOIN school.students AS st ON st.id = s.student_id WHERE s.score >= $1 ORDER BY s.id".to_string(), None)
+        .await?;
+    println!("Parameters: {:?}", prepared_statement.parameter_schema()?.fields());
+    let parameters: ArrayRef = Arc::new(Float64Array::from(vec![80.0]));
+    prepared_statement.set_parameters(RecordBatch::try_from_iter([("$1", parameters)])?)?;
+    let flight_info: FlightInfo = prepared_statement.execute().await?;
+    print_flight_info(&mut client, flight_info).await?;
+    prepared_statement.close().await?;
+
+    println!("Listing the tables of the school schema...");
+    let flight_info: FlightInfo = client
+        .get_tables(CommandGetTables {
+            db_schema_filter_pattern: Some("school".to_string()),
+            ..Default::default()
+        })
+        .await?;
+    print_flight_info(&mut client, flight_info).await?;
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use engine::object_storage::storage::SaStorage;
+use engine::object_storage::SaGcs;
+use engine::object_storage::gcs::SaGcsCredentials;
+use engine::error::Result;
+use engine::datafusion::SaDataFusion;
+use engine::object_storage::SaSchemaOptions;
+use std::env;
+use std::sync::Arc;
+use datafusion::prelude::DataFrame;
+use datafusion::datasource::file_format::csv::CsvFormat;
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let gcs_uri: &str = "gs://sql-anywhere/ex-gcs-application/students.csv";
+    // Point to a local emulator (e.g. fake-gcs-server) with STORAGE_EMULATOR_HOST=http://localhost:4443
+    let credentials: SaGcsCredentials = match env::var("STORAGE_EMULATOR_HOST") {
+        Ok(base_url) => SaGcsCredentials::Emulator { base_url },
+        Err(_) => SaGcsCredentials::Env,
+    };
+
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    let student_storage: SaGcs = SaGcs::new_from_gcs_uri(gcs_uri)?
+        .with_credentials(credentials)
+        .init_table_provider(
+            &sa_datafusion,
+            Arc::new(CsvFormat::default()),
+            &SaSchemaOptions::default()
+        ).await?;
+
+    sa_datafusion.register_sa_storage(Arc::new(student_storage.clone())).await?;
+    println!("{}", student_storage.get_gcs_bucket());
+    println!("{}", student_storage.get_gcs_object_path());
+
+    let stm: String = format!(r#"
+        SELECT
+            *
+        FROM
+            "{}"
+        "#, student_storage.get_file_url());
+    println!("{}", stm);
+    let df: DataFrame = sa_datafusion.execute_sql(&stm).await?;
+    df.show().await?;
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use engine::object_storage::storage::SaStorage;
+use engine::object_storage::SaHttp;
+use engine::error::Result;
+use engine::datafusion::SaDataFusion;
+use engine::object_storage::SaSchemaOptions;
+use std::env;
+use std::sync::Arc;
+use datafusion::prelude::DataFrame;
+use datafusion::datasource::file_format::csv::CsvFormat;
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let http_uri: &str = "https://raw.githubusercontent.com/mwaskom/seaborn-data/master/iris.csv";
+    let mut iris_storage: SaHttp = SaHttp::new_from_http_uri(http_uri)?;
+    // Private files take a token, e.g. HTTP_AUTH_TOKEN=<token>
+    if let Ok(token) = env::var("HTTP_AUTH_TOKEN") {
+        iris_storage = iris_storage.with_header("Authorization", &format!("Bearer {}", token));
+    }
+
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    let iris_storage: SaHttp = iris_storage
+        .init_table_provider(
+            &sa_datafusion,
+            Arc::new(CsvFormat::default()),
+            &SaSchemaOptions::default()
+        ).await?;
+
+    sa_datafusion.register_sa_storage(Arc::new(iris_storage.clone())).await?;
+    println!("{}", iris_storage.get_http_host());
+    println!("{}", iris_storage.get_http_path());
+
+    let stm: String = format!(r#"
+        SELECT
+            COUNT(*)
+        FROM
+            "{}"
+        "#, iris_storage.get_file_url());
+    println!("{}", stm);
+    let df: DataFrame = sa_datafusion.execute_sql(&stm).await?;
+    df.show().await?;
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use engine::builder::pipelines::sa_to_dataframe_pipeline;
+use engine::error::Result;
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let base_path: &str = env!("CARGO_MANIFEST_DIR");
+    // Written at s3://warehouse-bucket/db/events, then copied here
+    let table_uri: String = format!("file://{}/.data/bin/ex-iceberg-application/warehouse/db/events", base_path);
+
+    println!("Reading the current snapshot, after the games events were deleted...");
+    let stm: String = format!(r#"SELECT * FROM "{}" ORDER BY id"#, table_uri);
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+
+    println!("Reading the first snapshot by id...");
+    let stm: String = format!(r#"SELECT * FROM "{}" FOR VERSION AS OF 3051729675574597004 ORDER BY id"#, table_uri);
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+
+    println!("Reading the table as it was on 2024-02-15...");
+    let stm: String = format!(
+        r#"SELECT category, COUNT(*) AS events, SUM(amount) AS amount
+        FROM "{}" FOR TIMESTAMP AS OF '2024-02-15 00:00:00'
+        GROUP BY category
+        ORDER BY category"#,
+        table_uri
+    );
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+
+    println!("Filtering on the partition and on column bounds, only one data file is read:");
+    let stm: String = format!(r#"EXPLAIN SELECT * FROM "{}" WHERE category = 'books' AND amount > 40"#, table_uri);
+    sa_to_dataframe_pipeline(&stm).await?.show().await?;
+    Ok(())
+}
@@ -1,8 +1,10 @@
 use engine::object_storage::storage::SaStorage;
 use engine::object_storage::SaLocalStorage;
-use datafusion::common::Result;
+use engine::error::Result;
 use engine::datafusion::SaDataFusion;
+use engine::object_storage::SaSchemaOptions;
 use std::sync::Arc;
+use datafusion::arrow::datatypes::DataType;
 use datafusion::prelude::DataFrame;
 use datafusion::datasource::file_format::csv::CsvFormat;
 
@@ -15,15 +17,18 @@ async fn main() -> Result<()> {
     let sa_datafusion: SaDataFusion = SaDataFusion::new();
 
     println!("Initializing and registering scores.csv into SQLAnyWhere...");
+    // Scores are inferred as integers, read them as floats
+    let score_schema_options: SaSchemaOptions = SaSchemaOptions::new()
+        .with_type_hint("score", DataType::Float64);
     let score_storage: SaLocalStorage = SaLocalStorage::new(&score_path)
         .init_table_provider(
             &sa_datafusion,
             Arc::new(CsvFormat::default()),
-            Some(false)
+            &score_schema_options
         ).await?;
     sa_datafusion.register_sa_storage(Arc::new(score_storage.clone())).await?;
     println!("Score schema:");
-    sa_datafusion.display_schema(&score_storage.get_file_url().as_str()).await?;
+    sa_datafusion.display_schema(score_storage.get_file_url().as_str()).await?;
     println!();
 
     println!("Initializing and registering student.csv into SQLAnyWhere...");
@@ -31,11 +36,11 @@ async fn main() -> Result<()> {
         .init_table_provider(
             &sa_datafusion,
             Arc::new(CsvFormat::default()),
-            Some(false)
+            &SaSchemaOptions::default()
         ).await?;
     sa_datafusion.register_sa_storage(Arc::new(student_storage.clone())).await?;
     println!("Student schema:");
-    sa_datafusion.display_schema(&student_storage.get_file_url().as_str()).await?;
+    sa_datafusion.display_schema(student_storage.get_file_url().as_str()).await?;
     println!();
 
     println!("Joining and showing scores and students...");
@@ -54,7 +59,7 @@ async fn main() -> Result<()> {
                 st.id = s.student_id
         "#, student_storage.get_file_url(), score_storage.get_file_url());
     println!("{}", stm);
-    let df: DataFrame = sa_datafusion.execute_sql(&stm.as_str()).await?;
+    let df: DataFrame = sa_datafusion.execute_sql(stm.as_str()).await?;
     df.show().await?;
     Ok(())
 }
\ No newline at end of file
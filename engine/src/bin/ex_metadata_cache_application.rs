@@ -0,0 +1,41 @@
+use std::sync::Arc;
+use engine::builder::pipelines::sa_query;
+use engine::cache::SaMetadataCache;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use tempfile::TempDir;
+
+
+/// New session caching its metadata in `cache_dir`, as each notebook query creates one.
+fn new_session(cache_dir: &str) -> Result<SaDataFusion> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    sa_datafusion.set_option("metadata_cache", "true")?;
+    sa_datafusion.set_option("metadata_cache_dir", cache_dir)?;
+    Ok(sa_datafusion)
+}
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let base_path: &str = env!("CARGO_MANIFEST_DIR");
+    let data_uri: String = format!("file://{}/.data/bin/ex-delta-application/sales", base_path);
+    let cache_dir: TempDir = tempfile::tempdir()?;
+    let cache_dir: &str = cache_dir.path().to_str().expect("UTF-8 temporary directory");
+    let stm: String = format!(
+        r#"SELECT region, COUNT(*) AS sales FROM "{}/region=*/*.parquet" GROUP BY region ORDER BY region"#,
+        data_uri
+    );
+
+    println!("Listing the files and reading their footers in a first session...");
+    let sa_datafusion: SaDataFusion = new_session(cache_dir)?;
+    sa_query(&sa_datafusion, &stm).await?.show().await?;
+    let metadata_cache: Arc<SaMetadataCache> = sa_datafusion.get_metadata_cache()?.expect("metadata cache enabled");
+    println!("{}", metadata_cache.get_statistics());
+    println!();
+
+    println!("Reading the listing, schema and footers from the cache in a second session...");
+    let sa_datafusion: SaDataFusion = new_session(cache_dir)?;
+    sa_query(&sa_datafusion, &stm).await?.show().await?;
+    println!("{}", metadata_cache.get_statistics());
+    Ok(())
+}
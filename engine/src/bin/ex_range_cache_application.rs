@@ -0,0 +1,42 @@
+use std::env;
+use std::sync::Arc;
+use engine::builder::pipelines::sa_query;
+use engine::cache::SaRangeCache;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use tempfile::TempDir;
+
+
+/// New session reading S3 through the range cache in `cache_dir`, as each notebook query creates one.
+fn new_session(cache_dir: &str) -> Result<SaDataFusion> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    // Local MinIO, e.g. `minio server /data` with AWS_ACCESS_KEY_ID=minioadmin AWS_SECRET_ACCESS_KEY=minioadmin
+    sa_datafusion.set_option("s3_endpoint", &env::var("S3_ENDPOINT").unwrap_or("http://localhost:9000".to_string()))?;
+    sa_datafusion.set_option("s3_path_style", "true")?;
+    sa_datafusion.set_option("s3_allow_http", "true")?;
+    sa_datafusion.set_option("s3_region", "us-east-1")?;
+    sa_datafusion.set_option("range_cache", "true")?;
+    sa_datafusion.set_option("range_cache_dir", cache_dir)?;
+    Ok(sa_datafusion)
+}
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let stm: &str = r#"SELECT COUNT(*) AS houses FROM "s3://sql-anywhere/ex-s3-application/house-price.parquet""#;
+    let cache_dir: TempDir = tempfile::tempdir()?;
+    let cache_dir: &str = cache_dir.path().to_str().expect("UTF-8 temporary directory");
+
+    println!("Downloading the byte ranges of the query in a first session...");
+    let sa_datafusion: SaDataFusion = new_session(cache_dir)?;
+    sa_query(&sa_datafusion, stm).await?.show().await?;
+    let range_cache: Arc<SaRangeCache> = sa_datafusion.get_range_cache()?.expect("range cache enabled");
+    println!("{}", range_cache.get_statistics());
+    println!();
+
+    println!("Reading them from the local disk in a second session...");
+    let sa_datafusion: SaDataFusion = new_session(cache_dir)?;
+    sa_query(&sa_datafusion, stm).await?.show().await?;
+    println!("{}", range_cache.get_statistics());
+    Ok(())
+}
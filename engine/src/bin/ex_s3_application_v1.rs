@@ -1,7 +1,8 @@
 use engine::object_storage::storage::SaStorage;
 use engine::object_storage::{SaS3, SaLocalStorage};
-use datafusion::common::Result;
+use engine::error::Result;
 use engine::datafusion::SaDataFusion;
+use engine::object_storage::SaSchemaOptions;
 use std::sync::Arc;
 use datafusion::prelude::DataFrame;
 use datafusion::datasource::file_format::csv::CsvFormat;
@@ -21,7 +22,7 @@ async fn main() -> Result<()> {
             s3_region,
             &sa_datafusion,
             Arc::new(CsvFormat::default()),
-            Some(false)
+            &SaSchemaOptions::default()
         ).await?;
     sa_datafusion.register_sa_storage(Arc::new(student_storage.clone())).await?;
     println!("Students schema:");
@@ -34,7 +35,7 @@ async fn main() -> Result<()> {
         .init_table_provider(
             &sa_datafusion,
             Arc::new(CsvFormat::default()),
-            Some(false)
+            &SaSchemaOptions::default()
         ).await?;
     sa_datafusion.register_sa_storage(Arc::new(score_storage.clone())).await?;
     println!("Score schema:");
@@ -57,7 +58,7 @@ async fn main() -> Result<()> {
                 st.id = s.student_id
         "#, student_storage.get_file_url(), score_storage.get_file_url());
     println!("{}", stm);
-    let df: DataFrame = sa_datafusion.execute_sql(&stm.to_string()).await?;
+    let df: DataFrame = sa_datafusion.execute_sql(&stm).await?;
     df.show().await?;
     Ok(())
 }
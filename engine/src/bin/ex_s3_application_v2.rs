@@ -1,7 +1,8 @@
 use engine::object_storage::storage::SaStorage;
 use engine::object_storage::SaS3;
-use datafusion::common::Result;
+use engine::error::Result;
 use engine::datafusion::SaDataFusion;
+use engine::object_storage::SaSchemaOptions;
 use std::sync::Arc;
 use datafusion::prelude::DataFrame;
 use datafusion::datasource::file_format::parquet::ParquetFormat;
@@ -13,12 +14,12 @@ async fn main() -> Result<()> {
     let s3_region: &str = "us-east-1";
 
     let sa_datafusion: SaDataFusion = SaDataFusion::new();
-    let house_price_storage: SaS3 = SaS3::new_from_s3_uri(s3_uri)
+    let house_price_storage: SaS3 = SaS3::new_from_s3_uri(s3_uri)?
         .init_table_provider(
             s3_region,
             &sa_datafusion,
             Arc::new(ParquetFormat::default()),
-            Some(false)
+            &SaSchemaOptions::default()
         ).await?;
 
     sa_datafusion.register_sa_storage(Arc::new(house_price_storage.clone())).await?;
@@ -33,7 +34,7 @@ async fn main() -> Result<()> {
             "{}"
         "#, house_price_storage.get_file_url());
     println!("{}", stm);
-    let df: DataFrame = sa_datafusion.execute_sql(&stm.to_string()).await?;
+    let df: DataFrame = sa_datafusion.execute_sql(&stm).await?;
     df.show().await?;
     Ok(())
 }
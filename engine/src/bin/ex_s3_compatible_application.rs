@@ -0,0 +1,48 @@
+use engine::object_storage::storage::SaStorage;
+use engine::object_storage::SaS3;
+use engine::object_storage::s3::SaS3Options;
+use engine::error::Result;
+use engine::datafusion::SaDataFusion;
+use engine::object_storage::SaSchemaOptions;
+use std::env;
+use std::sync::Arc;
+use datafusion::prelude::DataFrame;
+use datafusion::datasource::file_format::parquet::ParquetFormat;
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let s3_uri: &str = "s3://sql-anywhere/ex-s3-application/house-price.parquet";
+    let s3_region: &str = "us-east-1";
+    // Local MinIO, e.g. `minio server /data` with AWS_ACCESS_KEY_ID=minioadmin AWS_SECRET_ACCESS_KEY=minioadmin
+    let s3_options: SaS3Options = SaS3Options {
+        endpoint: Some(env::var("S3_ENDPOINT").unwrap_or("http://localhost:9000".to_string())),
+        is_path_style: Some(true),
+        allow_http: Some(true),
+        profile: env::var("S3_PROFILE").ok(),
+    };
+
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    let house_price_storage: SaS3 = SaS3::new_from_s3_uri(s3_uri)?
+        .with_options(s3_options)
+        .init_table_provider(
+            s3_region,
+            &sa_datafusion,
+            Arc::new(ParquetFormat::default()),
+            &SaSchemaOptions::default()
+        ).await?;
+
+    sa_datafusion.register_sa_storage(Arc::new(house_price_storage.clone())).await?;
+    println!("{:?}", house_price_storage.get_options());
+
+    let stm: String = format!(r#"
+        SELECT
+            *
+        FROM
+            "{}"
+        "#, house_price_storage.get_file_url());
+    println!("{}", stm);
+    let df: DataFrame = sa_datafusion.execute_sql(&stm).await?;
+    df.show().await?;
+    Ok(())
+}
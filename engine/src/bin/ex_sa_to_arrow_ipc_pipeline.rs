@@ -1,5 +1,5 @@
 use engine::builder::pipelines::sa_to_arrow_ipc_pipeline;
-use datafusion::common::Result;
+use engine::error::Result;
 
 #[tokio::main]
 async fn main() -> Result<()>{
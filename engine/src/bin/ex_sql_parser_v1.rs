@@ -1,12 +1,12 @@
 use engine::helper::sql_parser;
+use engine::helper::SaTableReference;
 use engine::datafusion::SaDataFusion;
-use datafusion::common::Result;
-use engine::object_storage::{SaS3, SaLocalStorage};
-use datafusion::datasource::file_format::{
-    FileFormat,
-    csv::CsvFormat,
-    parquet::ParquetFormat
-};
+use engine::error::Result;
+use engine::object_storage::storage::SaStorage;
+use engine::object_storage::{SaS3, SaStorageFactory};
+use engine::object_storage::s3::SaS3Factory;
+use engine::object_storage::SaSchemaOptions;
+use datafusion::datasource::file_format::FileFormat;
 use datafusion::prelude::DataFrame;
 use std::sync::Arc;
 
@@ -28,40 +28,25 @@ async fn main() -> Result<()> {
     "#;
     let sa_datafusion: SaDataFusion = SaDataFusion::new();
     let s3_region: &str = "us-east-1";
+    sa_datafusion.register_storage_factory(SaS3::PROTOCAL, Arc::new(SaS3Factory::new(s3_region)));
 
-    let uris: Vec<&str> = sql_parser(stm);
-    for uri in uris {
-        println!("Detected URI: {}", uri);
-        let file_format: Arc<dyn FileFormat> = if uri.ends_with(".csv") {
-            Arc::new(CsvFormat::default())
-        } else if uri.ends_with(".parquet") {
-            Arc::new(ParquetFormat::default())
-        } else {
-            panic!("Unsupported file format")
-        };
-
-        if uri.starts_with("file://") {
-            let local_storage: SaLocalStorage = SaLocalStorage::new_with_file_uri(uri)
-                .init_table_provider(
-                    &sa_datafusion,
-                    file_format,
-                    Some(false)
-            ).await?;
-            sa_datafusion.register_sa_storage(Arc::new(local_storage)).await?;
-        }
-        else if uri.starts_with("s3://") {
-            let s3_storage: SaS3 = SaS3::new_from_s3_uri(uri)
-                .init_table_provider(
-                    s3_region,
-                    &sa_datafusion,
-                    file_format,
-                    Some(false)
-                ).await?;
-                sa_datafusion.register_sa_storage(Arc::new(s3_storage)).await?;
-        }
+    let references: Vec<SaTableReference> = sql_parser(stm)?;
+    for reference in references.iter() {
+        let uri: &str = reference.uri.as_str();
+        println!(
+            "Detected URI: {} (scheme: {}, alias: {:?}, line: {}, column: {})",
+            uri, reference.scheme, reference.alias, reference.line, reference.column
+        );
+        let storage_factory: Arc<dyn SaStorageFactory> = sa_datafusion.get_storage_factory(&reference.scheme)?;
+        storage_factory.register_object_store(uri, &sa_datafusion).await?;
+        let file_format: Arc<dyn FileFormat> = sa_datafusion.resolve_file_format(uri, None).await?;
+        let sa_storage: Arc<dyn SaStorage> = storage_factory
+            .create_storage(uri, &sa_datafusion, file_format, &SaSchemaOptions::default())
+            .await?;
+        sa_datafusion.register_sa_storage(sa_storage).await?;
     }
     println!("{}", stm);
-    let df: DataFrame = sa_datafusion.execute_sql(&stm.to_string()).await?;
+    let df: DataFrame = sa_datafusion.execute_sql(stm).await?;
     df.show().await?;
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use engine::builder::pipelines::{sa_to_dataframe_pipeline, sa_write_pipeline};
+use engine::builder::write::SaWriteOptions;
+use engine::error::Result;
+use tempfile::TempDir;
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let base_path: &str = env!("CARGO_MANIFEST_DIR");
+    let output_dir: TempDir = tempfile::tempdir()?;
+    let output_path: String = output_dir.path().display().to_string();
+    let stm: String = format!(
+        r#"SELECT
+            st."name" AS "student_name",
+            s."subject" AS "subject",
+            s."score" AS "score"
+        FROM
+            "file://{base_path}/.data/bin/ex-local-storage-application/students.csv" AS st
+        JOIN
+            "file://{base_path}/.data/bin/ex-local-storage-application/scores.csv" AS s
+            ON
+                st.id = s.student_id
+        "#
+    );
+
+    println!("Writing the scores as one Parquet file...");
+    let write_options: SaWriteOptions = SaWriteOptions::new()
+        .with_compression("zstd(3)")
+        .with_row_group_size(1024);
+    let target: String = format!("file://{}/scores.parquet", output_path);
+    let row_count: u64 = sa_write_pipeline(&stm, &HashMap::new(), &target, &write_options).await?;
+    println!("{} rows written to {}", row_count, target);
+
+    println!("Writing the scores as CSV files partitioned by subject...");
+    let write_options: SaWriteOptions = SaWriteOptions::new()
+        .with_format("csv")
+        .with_partition_by(vec!["subject".to_string()]);
+    let target: String = format!("file://{}/scores/", output_path);
+    let row_count: u64 = sa_write_pipeline(&stm, &HashMap::new(), &target, &write_options).await?;
+    println!("{} rows written to {}", row_count, target);
+
+    println!("The same with SQL, read back:");
+    let copy_stm: String = format!(
+        "COPY (SELECT * FROM \"file://{output_path}/scores.parquet\") TO 'file://{output_path}/scores.json';"
+    );
+    sa_to_dataframe_pipeline(&copy_stm).await?.show().await?;
+    let read_stm: String = format!("SELECT * FROM \"file://{}/scores/\" ORDER BY student_name", output_path);
+    sa_to_dataframe_pipeline(&read_stm).await?.show().await?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use clap::Parser;
+use engine::cli::{SaPrintFormat, SaRepl};
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+
+
+/// Query files on local disk, S3, GCS, Azure or HTTP with SQL.
+#[derive(Parser)]
+#[command(name = "sqlanywhere")]
+struct Args {
+    /// Runs these SQL statements, or dot-command, and exits instead of starting the shell
+    #[arg(short, long, value_name = "SQL")]
+    command: Option<String>,
+
+    /// Output format: table, csv, json or parquet
+    #[arg(short, long, default_value = "table")]
+    format: SaPrintFormat,
+
+    /// Writes the results to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Session option, e.g. `--option s3_endpoint=http://localhost:9000` for `sa.s3_endpoint`
+    #[arg(long = "option", value_name = "KEY=VALUE")]
+    options: Vec<String>,
+
+    /// Catalog file (TOML or YAML) mapping table names such as `sales.orders` to sources
+    #[arg(long)]
+    catalog: Option<String>,
+
+    /// History file of the shell, `~/.sqlanywhere_history` by default
+    #[arg(long)]
+    history: Option<PathBuf>,
+}
+
+
+async fn run(args: Args) -> Result<()> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    for option in &args.options {
+        let (key, value) = option.split_once('=').unwrap_or((option.as_str(), ""));
+        sa_datafusion.set_option(key, value)?;
+    }
+    if let Some(catalog) = &args.catalog {
+        sa_datafusion.load_catalog(catalog)?;
+    }
+    let mut repl: SaRepl = SaRepl::new(sa_datafusion)
+        .with_format(args.format)
+        .with_output(args.output);
+
+    match args.command {
+        Some(command) => {
+            repl.run_command(&command).await?;
+        },
+        None => {
+            let history_path: Option<PathBuf> = args.history.or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".sqlanywhere_history"))
+            });
+            repl.run(history_path.as_deref()).await?;
+        },
+    }
+    Ok(())
+}
+
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Args::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        },
+    }
+}
@@ -0,0 +1,56 @@
+use std::process::ExitCode;
+use clap::Parser;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use engine::server::SaFlightSqlService;
+use tokio::net::TcpListener;
+
+
+/// Serves SQLAnyWhere over Arrow Flight SQL, so Flight SQL clients (JDBC, ADBC, ...) query
+/// files on local disk, S3, GCS, Azure or HTTP with SQL.
+#[derive(Parser)]
+#[command(name = "sqlanywhere_flight")]
+struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 50051)]
+    port: u16,
+
+    /// Session option, e.g. `--option s3_endpoint=http://localhost:9000` for `sa.s3_endpoint`
+    #[arg(long = "option", value_name = "KEY=VALUE")]
+    options: Vec<String>,
+
+    /// Catalog file (TOML or YAML) mapping table names such as `sales.orders` to sources
+    #[arg(long)]
+    catalog: Option<String>,
+}
+
+
+async fn run(args: Args) -> Result<()> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    for option in &args.options {
+        let (key, value) = option.split_once('=').unwrap_or((option.as_str(), ""));
+        sa_datafusion.set_option(key, value)?;
+    }
+    if let Some(catalog) = &args.catalog {
+        sa_datafusion.load_catalog(catalog)?;
+    }
+    let listener: TcpListener = TcpListener::bind((args.host.as_str(), args.port)).await?;
+    eprintln!("[sa_flight_sql]: Listening on {}", listener.local_addr()?);
+    SaFlightSqlService::new(sa_datafusion).serve(listener).await
+}
+
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Args::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        },
+    }
+}
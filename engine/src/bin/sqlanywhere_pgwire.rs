@@ -0,0 +1,56 @@
+use std::process::ExitCode;
+use clap::Parser;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use engine::server::SaPgWireServer;
+use tokio::net::TcpListener;
+
+
+/// Serves SQLAnyWhere over the PostgreSQL wire protocol, so Postgres clients (psql, JDBC,
+/// BI tools, ...) query files on local disk, S3, GCS, Azure or HTTP with SQL.
+#[derive(Parser)]
+#[command(name = "sqlanywhere_pgwire")]
+struct Args {
+    /// Address to listen on, clients are not authenticated
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 5432)]
+    port: u16,
+
+    /// Session option, e.g. `--option s3_endpoint=http://localhost:9000` for `sa.s3_endpoint`
+    #[arg(long = "option", value_name = "KEY=VALUE")]
+    options: Vec<String>,
+
+    /// Catalog file (TOML or YAML) mapping table names such as `sales.orders` to sources
+    #[arg(long)]
+    catalog: Option<String>,
+}
+
+
+async fn run(args: Args) -> Result<()> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    for option in &args.options {
+        let (key, value) = option.split_once('=').unwrap_or((option.as_str(), ""));
+        sa_datafusion.set_option(key, value)?;
+    }
+    if let Some(catalog) = &args.catalog {
+        sa_datafusion.load_catalog(catalog)?;
+    }
+    let listener: TcpListener = TcpListener::bind((args.host.as_str(), args.port)).await?;
+    eprintln!("[sa_pgwire]: Listening on {}", listener.local_addr()?);
+    SaPgWireServer::try_new(sa_datafusion)?.serve(listener).await
+}
+
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Args::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        },
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::Write;
+use std::mem;
+use futures::stream::{self, BoxStream, StreamExt};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::ipc::writer::StreamWriter;
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::prelude::DataFrame;
+use crate::error::Result;
+
+
+/// Writes the results of `df` to `writer` as an Arrow IPC stream, batch by batch as
+/// they are produced, and returns `writer`.
+///
+/// The schema comes from the plan, so a query without rows still writes a valid stream.
+pub async fn write_arrow_ipc<W: Write>(df: DataFrame, writer: W) -> Result<W> {
+    let mut batches: SendableRecordBatchStream = df.execute_stream().await?;
+    let schema: SchemaRef = batches.schema();
+    let mut writer: StreamWriter<W> = StreamWriter::try_new(writer, &schema)?;
+    while let Some(batch) = batches.next().await {
+        writer.write(&batch?)?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+
+/// Same as [`write_arrow_ipc`] for an async `sink`, which is flushed at the end.
+pub async fn write_arrow_ipc_async<W: AsyncWrite + Unpin>(df: DataFrame, sink: &mut W) -> Result<()> {
+    let mut chunks: BoxStream<'static, Result<Vec<u8>>> = to_arrow_ipc_chunks(df).await?;
+    while let Some(chunk) = chunks.next().await {
+        sink.write_all(&chunk?).await?;
+    }
+    sink.flush().await?;
+    Ok(())
+}
+
+
+/// Streams the results of `df` as Arrow IPC chunks: the schema, one chunk per record
+/// batch (with its dictionaries), then the end-of-stream marker.
+///
+/// Concatenated, the chunks are the stream [`write_arrow_ipc`] writes.
+pub async fn to_arrow_ipc_chunks(df: DataFrame) -> Result<BoxStream<'static, Result<Vec<u8>>>> {
+    let batches: SendableRecordBatchStream = df.execute_stream().await?;
+    let schema: SchemaRef = batches.schema();
+    let mut writer: StreamWriter<Vec<u8>> = StreamWriter::try_new(Vec::new(), &schema)?;
+    let schema_chunk: Vec<u8> = mem::take(writer.get_mut());
+
+    let batch_chunks = stream::try_unfold((batches, Some(writer)), |(mut batches, writer)| async move {
+        let Some(mut writer) = writer else {
+            return Ok(None);
+        };
+        match batches.next().await {
+            Some(batch) => {
+                let batch: RecordBatch = batch?;
+                writer.write(&batch)?;
+                let chunk: Vec<u8> = mem::take(writer.get_mut());
+                Ok(Some((chunk, (batches, Some(writer)))))
+            },
+            None => {
+                let end_chunk: Vec<u8> = writer.into_inner()?;
+                Ok(Some((end_chunk, (batches, None))))
+            },
+        }
+    });
+    Ok(stream::once(async move { Ok(schema_chunk) }).chain(batch_chunks).boxed())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use datafusion::arrow::array::{Int64Array, StringArray};
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::arrow::ipc::reader::StreamReader;
+    use futures::TryStreamExt;
+    use crate::builder::pipelines;
+    use crate::datafusion::SaDataFusion;
+
+
+    /// Schema and record batches of the IPC `stream`.
+    fn read_arrow_ipc(stream: &[u8]) -> (SchemaRef, Vec<RecordBatch>) {
+        let reader: StreamReader<Cursor<&[u8]>> = StreamReader::try_new(Cursor::new(stream), None).unwrap();
+        let schema: SchemaRef = reader.schema();
+        let batches: Vec<RecordBatch> = reader.collect::<std::result::Result<_, _>>().unwrap();
+        (schema, batches)
+    }
+
+
+    fn get_batches() -> Vec<RecordBatch> {
+        let schema: SchemaRef = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        [(vec![1, 2], vec!["ada", "grace"]), (vec![3], vec!["alan"]), (vec![4, 5, 6], vec!["edsger", "barbara", "donald"])]
+            .into_iter()
+            .map(|(ids, names)| {
+                RecordBatch::try_new(schema.clone(), vec![Arc::new(Int64Array::from(ids)), Arc::new(StringArray::from(names))]).unwrap()
+            })
+            .collect()
+    }
+
+
+    #[tokio::test]
+    async fn test_empty_result() -> Result<()> {
+        let scores_uri: String = format!("file://{}/.data/bin/ex-local-storage-application/scores.csv", env!("CARGO_MANIFEST_DIR"));
+        let stm: String = format!("SELECT id, subject, score FROM '{}' WHERE score > 100", scores_uri);
+        let stream: Vec<u8> = pipelines::sa_to_arrow_ipc_pipeline(&stm).await?;
+        let (schema, batches): (SchemaRef, Vec<RecordBatch>) = read_arrow_ipc(&stream);
+        let names: Vec<&str> = schema.fields().iter().map(|field| field.name().as_str()).collect();
+        assert_eq!(names, ["id", "subject", "score"]);
+        assert!(batches.iter().all(|batch| batch.num_rows() == 0));
+
+        // The chunks of an empty result are the schema then the end-of-stream marker
+        let df: DataFrame = SaDataFusion::new().ctx.read_batches(Vec::<RecordBatch>::new())?;
+        let chunks: Vec<Vec<u8>> = to_arrow_ipc_chunks(df).await?.try_collect().await?;
+        assert_eq!(chunks.len(), 2);
+        let (schema, batches): (SchemaRef, Vec<RecordBatch>) = read_arrow_ipc(&chunks.concat());
+        assert!(schema.fields().is_empty());
+        assert!(batches.is_empty());
+        Ok(())
+    }
+
+
+    #[tokio::test]
+    async fn test_batches() -> Result<()> {
+        let sa_datafusion: SaDataFusion = SaDataFusion::new();
+        let (schema, batches): (SchemaRef, Vec<RecordBatch>) = read_arrow_ipc(
+            &write_arrow_ipc(sa_datafusion.ctx.read_batches(get_batches())?, Vec::new()).await?
+        );
+        assert_eq!(schema, get_batches()[0].schema());
+        assert_eq!(batches, get_batches());
+
+        // Each batch is a chunk of its own, between the schema and the end-of-stream marker
+        let chunks: Vec<Vec<u8>> = to_arrow_ipc_chunks(sa_datafusion.ctx.read_batches(get_batches())?).await?.try_collect().await?;
+        assert_eq!(chunks.len(), 5);
+        assert_eq!(chunks.concat(), write_arrow_ipc(sa_datafusion.ctx.read_batches(get_batches())?, Vec::new()).await?);
+
+        let mut sink: Vec<u8> = Vec::new();
+        write_arrow_ipc_async(sa_datafusion.ctx.read_batches(get_batches())?, &mut sink).await?;
+        assert_eq!(read_arrow_ipc(&sink).1, get_batches());
+        Ok(())
+    }
+}
@@ -1 +1,3 @@
-pub mod pipelines;
\ No newline at end of file
+pub mod pipelines;
+pub mod arrow_ipc;
+pub mod write;
\ No newline at end of file
@@ -1,80 +1,307 @@
-use crate::datafusion::SaDataFusion;
-use crate::object_storage::{SaS3, SaLocalStorage};
+use crate::datafusion::{SaDataFusion, SaOptions};
+use crate::object_storage::storage::SaStorage;
+use crate::object_storage::{SaSchemaOptions, SaStorageFactory};
 use crate::helper;
+use crate::helper::{SaTableReference, SaTableAccess};
+use crate::builder::arrow_ipc;
+use crate::format::SaJsonArrayFormat;
+use crate::table::SaTableStorage;
+use crate::builder::write::{self, SaWriteOptions};
+use std::collections::HashMap;
+use std::io::Write;
 use std::sync::Arc;
-use datafusion::common::Result;
-use datafusion::datasource::file_format::{
-    FileFormat,
-    csv::CsvFormat,
-    parquet::ParquetFormat
-};
+use crate::error::{Result, SaError};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::common::{DataFusionError, TableReference};
+use datafusion::config::{CatalogOptions, ExtensionOptions};
+use datafusion::datasource::file_format::FileFormat;
+use datafusion::datasource::file_format::json::JsonFormat;
+use datafusion::execution::{SendableRecordBatchStream, SessionState};
+use datafusion::logical_expr::LogicalPlan;
 use datafusion::prelude::DataFrame;
-use datafusion::arrow::array::RecordBatch;
-use datafusion::arrow::ipc::writer::StreamWriter;
-use std::env;
-
-
-async fn sa_query(sa_datafusion: SaDataFusion, stm: &str) -> Result<DataFrame> {
-    let uris: Vec<&str> = helper::sql_parser(stm);
-    for uri in uris {
-        println!("[sa_query]: Detected URI: {}", uri);
-        let file_format: Arc<dyn FileFormat> = if uri.ends_with(".csv") {
-            Arc::new(CsvFormat::default())
-        } else if uri.ends_with(".parquet") {
-            Arc::new(ParquetFormat::default())
-        } else {
-            panic!("[sa_query]: Unsupported file format")
-        };
-
-        if uri.starts_with("file://") {
-            let local_storage: SaLocalStorage = SaLocalStorage::new_with_file_uri(uri)
-                .init_table_provider(
-                    &sa_datafusion,
-                    file_format,
-                    Some(false)
-            ).await?;
-            sa_datafusion.register_sa_storage(Arc::new(local_storage)).await?;
+use datafusion::sql::parser::Statement as DFStatement;
+use futures::stream::BoxStream;
+use tokio::io::AsyncWrite;
 
+
+/// Creates the storage reading `uri`, with the file format `format_override` or the detected one.
+///
+/// Table formats such as Iceberg and Delta are read through their metadata, at the version of
+/// the `uri` suffix if any (see [`helper::SaTableVersion`]). With `sa.json_array`, JSON sources
+/// are read as arrays of objects.
+async fn create_sa_storage(
+    sa_datafusion: &SaDataFusion,
+    uri: &str,
+    scheme: &str,
+    format_override: Option<&str>,
+    schema_options: &SaSchemaOptions,
+) -> Result<Arc<dyn SaStorage>> {
+    let storage_factory: Arc<dyn SaStorageFactory> = sa_datafusion.get_storage_factory(scheme)?;
+    storage_factory.register_object_store(SaTableStorage::get_base_uri(uri)?, sa_datafusion).await?;
+    if let Some(table_storage) = SaTableStorage::open(sa_datafusion, uri, format_override).await? {
+        return Ok(Arc::new(table_storage));
+    }
+    let mut file_format: Arc<dyn FileFormat> = sa_datafusion
+        .resolve_file_format(uri, format_override)
+        .await?;
+    if sa_datafusion.get_options().json_array {
+        if let Some(json_format) = file_format.as_any().downcast_ref::<JsonFormat>() {
+            file_format = Arc::new(SaJsonArrayFormat::from(json_format));
         }
-        else if uri.starts_with("s3://") {
-            let s3_region: String  = env::var("AWS_S3_REGION").unwrap_or("us-east-1".to_string());
-            let s3_storage: SaS3 = SaS3::new_from_s3_uri(uri)
-                .init_table_provider(
-                    s3_region.as_str(),
-                    &sa_datafusion,
-                    file_format,
-                    Some(false)
-                ).await?;
-                sa_datafusion.register_sa_storage(Arc::new(s3_storage)).await?;
+    }
+    storage_factory
+        .create_storage(uri, sa_datafusion, file_format, schema_options)
+        .await
+}
+
+
+/// Registers the object store of `uri`, written by a statement, without creating a table.
+async fn register_target(sa_datafusion: &SaDataFusion, uri: &str, scheme: &str) -> Result<()> {
+    sa_datafusion
+        .get_storage_factory(scheme)?
+        .register_object_store(uri, sa_datafusion)
+        .await
+}
+
+
+/// Creates and registers a table for every source read by `references` that is not registered
+/// yet, and the object store of every target written by them (e.g. by `COPY ... TO`).
+async fn register_sources(sa_datafusion: &SaDataFusion, references: &[SaTableReference]) -> Result<()> {
+    let sa_options: SaOptions = sa_datafusion.get_options();
+    let schema_options: SaSchemaOptions = SaSchemaOptions::from_sa_options(&sa_options)?;
+    for reference in references {
+        let uri: &str = reference.uri.as_str();
+        if reference.access == SaTableAccess::Write {
+            register_target(sa_datafusion, uri, &reference.scheme).await?;
+            continue;
         }
-        else {
-            panic!("[sa_query]: Unsupported file protocal")
+        // Each source needs a table provider only once
+        if sa_datafusion.ctx.table_exist(uri)? {
+            continue;
         }
+        log::debug!("Detected URI: {}", uri);
+        let sa_storage: Arc<dyn SaStorage> = create_sa_storage(
+            sa_datafusion,
+            uri,
+            &reference.scheme,
+            sa_options.get_format(),
+            &schema_options
+        ).await?;
+        sa_datafusion.register_sa_storage(sa_storage).await?;
     }
-    let df: DataFrame = sa_datafusion.execute_sql(&stm.to_string()).await?;
-    Ok(df)
+    Ok(())
 }
 
 
-pub async fn sa_to_dataframe_pipeline(stm: &str) -> Result<DataFrame> {
-    let sa_datafusion: SaDataFusion = SaDataFusion::new();
-    Ok(sa_query(sa_datafusion, stm).await?)
+/// Registers the tables of the `sa.catalog` file read by `statement` that are not registered yet.
+async fn register_catalog_tables(sa_datafusion: &SaDataFusion, statement: &DFStatement) -> Result<()> {
+    if sa_datafusion.get_options().get_catalog().is_none() {
+        return Ok(());
+    }
+    let table_names: Vec<TableReference> = sa_datafusion
+        .get_session_state()
+        .resolve_table_references(statement)?;
+    for table_name in table_names {
+        sa_register_catalog_table_pipeline(sa_datafusion, table_name).await?;
+    }
+    Ok(())
 }
 
 
-pub async fn sa_to_arrow_ipc_pipeline(stm: &str) -> Result<Vec<u8>> {
-    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+/// Registers the table `table_name` of the `sa.catalog` file, with its format, schema and
+/// profile, unless it is registered already. Returns whether the catalog has such a table.
+pub async fn sa_register_catalog_table_pipeline(sa_datafusion: &SaDataFusion, table_name: impl Into<TableReference>) -> Result<bool> {
+    let Some(catalog) = sa_datafusion.get_catalog()? else {
+        return Ok(false);
+    };
+    let table_name: TableReference = table_name.into();
+    let session_state: SessionState = sa_datafusion.get_session_state();
+    let catalog_options: &CatalogOptions = &session_state.config_options().catalog;
+    let Some(catalog_table) = catalog.get_table(&table_name, &catalog_options.default_catalog, &catalog_options.default_schema) else {
+        return Ok(false);
+    };
+    sa_datafusion.create_schema(&table_name)?;
+    if sa_datafusion.ctx.table_exist(table_name.clone())? {
+        return Ok(true);
+    }
+    log::debug!("Detected catalog table: {}", table_name);
+    sa_register_source_pipeline(
+        sa_datafusion,
+        table_name,
+        &catalog_table.uri,
+        catalog_table.format.as_deref(),
+        None,
+        &catalog.get_options(catalog_table)
+    ).await?;
+    Ok(true)
+}
+
+
+/// Registers the source `uri` as the table `table_name` of `sa_datafusion`, replacing any
+/// table of that name, so later queries read it by name.
+///
+/// The file format is `format` if set, then `sa.format`, then the detected one, and the schema
+/// `schema` if set, then `sa.schema`, then the inferred one. `options` are `sa.*` options of
+/// this registration only, e.g. `{"s3_endpoint": "http://localhost:9000"}`.
+pub async fn sa_register_source_pipeline(
+    sa_datafusion: &SaDataFusion,
+    table_name: impl Into<TableReference>,
+    uri: &str,
+    format: Option<&str>,
+    schema: Option<SchemaRef>,
+    options: &HashMap<String, String>,
+) -> Result<()> {
+    let scheme: &str = helper::uri_scheme(uri).ok_or_else(|| SaError::uri_parse(uri, "expected <scheme>://<path>"))?;
+    // The storage is created in a session of its own, concurrent queries keep the session options
+    let mut sa_options: SaOptions = sa_datafusion.get_options();
+    for (key, value) in options {
+        sa_options.set(key.strip_prefix("sa.").unwrap_or(key), value)?;
+    }
+    let mut schema_options: SaSchemaOptions = SaSchemaOptions::from_sa_options(&sa_options)?;
+    if let Some(schema) = schema {
+        schema_options = schema_options.with_schema(schema);
+    }
+    let format: Option<&str> = format.or(sa_options.get_format());
+    let sa_storage: Arc<dyn SaStorage> = create_sa_storage(
+        &sa_datafusion.with_options(sa_options.clone()),
+        uri,
+        scheme,
+        format,
+        &schema_options
+    ).await?;
+    let table_name: TableReference = table_name.into();
+    sa_datafusion.register_sa_storage_as(table_name.clone(), sa_storage).await?;
+    log::debug!("Registered {} as {}", uri, table_name);
+    Ok(())
+}
+
+
+/// Runs every statement of `stm` in order and returns the result of the last one.
+///
+/// Sources are registered right before the statement that reads them, so a preceding
+/// `SET sa.format = '...'` or `SET sa.schema_hints = '...'` applies to them, and so are the tables
+/// of the `sa.catalog` file it reads by name. Sources already registered on `sa_datafusion` are
+/// not read again.
+pub async fn sa_query(sa_datafusion: &SaDataFusion, stm: &str) -> Result<DataFrame> {
+    let statements: Vec<DFStatement> = helper::parse_statements(stm)?;
+    let mut df: Option<DataFrame> = None;
+    for statement in statements {
+        let plan: LogicalPlan = sa_plan_statement(sa_datafusion, statement).await?;
+        df = Some(sa_datafusion.ctx.execute_logical_plan(plan).await?);
+    }
+    df.ok_or_else(|| DataFusionError::Plan("[sa_query]: No SQL statement to execute".to_string()).into())
+}
+
+
+/// Plans the single statement `stm` without running it, once the sources and catalog tables
+/// it reads are registered as in [`sa_query`]; its `$1`, `$2`... placeholders stay unbound.
+pub async fn sa_plan(sa_datafusion: &SaDataFusion, stm: &str) -> Result<LogicalPlan> {
+    let mut statements: Vec<DFStatement> = helper::parse_statements(stm)?;
+    if statements.len() != 1 {
+        return Err(DataFusionError::Plan(format!("[sa_query]: Expected a single SQL statement to plan, got {}", statements.len())).into());
+    }
+    sa_plan_statement(sa_datafusion, statements.remove(0)).await
+}
+
+
+/// Plans the parsed `statement` without running it, once the sources and catalog tables it
+/// reads are registered, see [`sa_plan`].
+pub async fn sa_plan_statement(sa_datafusion: &SaDataFusion, statement: DFStatement) -> Result<LogicalPlan> {
+    register_sources(sa_datafusion, &helper::get_table_references(&statement)).await?;
+    register_catalog_tables(sa_datafusion, &statement).await?;
+    Ok(sa_datafusion.get_session_state().statement_to_plan(statement).await?)
+}
+
+
+/// Runs `stm` on `sa_datafusion` and writes its result to `target`, a `file://` or `s3://`
+/// file or directory, see [`write::SaWriteOptions`]; returns the number of rows written.
+pub async fn sa_write(
+    sa_datafusion: &SaDataFusion,
+    stm: &str,
+    target: &str,
+    write_options: &SaWriteOptions,
+) -> Result<u64> {
+    let scheme: &str = helper::uri_scheme(target).ok_or_else(|| SaError::uri_parse(target, "expected <scheme>://<path>"))?;
     let df: DataFrame = sa_query(sa_datafusion, stm).await?;
-    let record_batches: Vec<RecordBatch> = df.collect().await?;
-    // Serialize RecordBatches to Arrow IPC
-    let mut buffer: Vec<u8> = Vec::new();
-    {
-        let mut writer: StreamWriter<&mut Vec<u8>> = StreamWriter::try_new(&mut buffer, &record_batches[0].schema())?;
-        for batch in record_batches {
-            writer.write(&batch)?
-        }
-        writer.finish()?;
+    register_target(sa_datafusion, target, scheme).await?;
+    write::write_dataframe(sa_datafusion, df, target, write_options).await
+}
+
+
+/// Creates a session with the `sa.*` `options`, e.g. `{"s3_endpoint": "http://localhost:9000"}`.
+fn new_sa_datafusion(options: &HashMap<String, String>) -> Result<SaDataFusion> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    for (key, value) in options {
+        sa_datafusion.set_option(key, value)?;
     }
+    Ok(sa_datafusion)
+}
+
+
+pub async fn sa_to_dataframe_pipeline(stm: &str) -> Result<DataFrame> {
+    sa_to_dataframe_pipeline_with_options(stm, &HashMap::new()).await
+}
+
+
+pub async fn sa_to_dataframe_pipeline_with_options(stm: &str, options: &HashMap<String, String>) -> Result<DataFrame> {
+    let sa_datafusion: SaDataFusion = new_sa_datafusion(options)?;
+    sa_query(&sa_datafusion, stm).await
+}
+
+
+pub async fn sa_to_arrow_ipc_pipeline(stm: &str) -> Result<Vec<u8>> {
+    sa_to_arrow_ipc_pipeline_with_options(stm, &HashMap::new()).await
+}
+
+
+pub async fn sa_to_arrow_ipc_pipeline_with_options(stm: &str, options: &HashMap<String, String>) -> Result<Vec<u8>> {
+    sa_to_arrow_ipc_writer_pipeline(stm, options, Vec::new()).await
+}
+
+
+/// Writes the result of `stm` to `writer` as an Arrow IPC stream while it is computed.
+pub async fn sa_to_arrow_ipc_writer_pipeline<W: Write>(stm: &str, options: &HashMap<String, String>, writer: W) -> Result<W> {
+    let sa_datafusion: SaDataFusion = new_sa_datafusion(options)?;
+    let df: DataFrame = sa_query(&sa_datafusion, stm).await?;
+    arrow_ipc::write_arrow_ipc(df, writer).await
+}
+
+
+/// Writes the result of `stm` to the async `sink` as an Arrow IPC stream while it is computed.
+pub async fn sa_to_arrow_ipc_async_writer_pipeline<W: AsyncWrite + Unpin>(
+    stm: &str,
+    options: &HashMap<String, String>,
+    sink: &mut W,
+) -> Result<()> {
+    let sa_datafusion: SaDataFusion = new_sa_datafusion(options)?;
+    let df: DataFrame = sa_query(&sa_datafusion, stm).await?;
+    arrow_ipc::write_arrow_ipc_async(df, sink).await
+}
+
+
+/// Streams the result of `stm` as Arrow IPC chunks, see [`arrow_ipc::to_arrow_ipc_chunks`].
+pub async fn sa_to_arrow_ipc_stream_pipeline(stm: &str, options: &HashMap<String, String>) -> Result<BoxStream<'static, Result<Vec<u8>>>> {
+    let sa_datafusion: SaDataFusion = new_sa_datafusion(options)?;
+    let df: DataFrame = sa_query(&sa_datafusion, stm).await?;
+    arrow_ipc::to_arrow_ipc_chunks(df).await
+}
+
+
+/// Streams the record batches of `stm`, computed as they are pulled.
+pub async fn sa_to_record_batch_stream_pipeline(stm: &str, options: &HashMap<String, String>) -> Result<SendableRecordBatchStream> {
+    let sa_datafusion: SaDataFusion = new_sa_datafusion(options)?;
+    let df: DataFrame = sa_query(&sa_datafusion, stm).await?;
+    Ok(df.execute_stream().await?)
+}
+
 
-    Ok(buffer)
+/// Writes the result of `stm` to `target`, see [`sa_write`].
+pub async fn sa_write_pipeline(
+    stm: &str,
+    options: &HashMap<String, String>,
+    target: &str,
+    write_options: &SaWriteOptions,
+) -> Result<u64> {
+    let sa_datafusion: SaDataFusion = new_sa_datafusion(options)?;
+    sa_write(&sa_datafusion, stm, target, write_options).await
 }
\ No newline at end of file
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+use datafusion::arrow::array::{AsArray, RecordBatch};
+use datafusion::arrow::datatypes::UInt64Type;
+use datafusion::datasource::file_format::{format_as_file_type, FileFormatFactory};
+use datafusion::execution::SessionState;
+use datafusion::logical_expr::{LogicalPlan, LogicalPlanBuilder};
+use datafusion::prelude::DataFrame;
+use crate::datafusion::SaDataFusion;
+use crate::error::Result;
+
+
+/// How [`write_dataframe`] writes a result; `COPY (...) TO '<uri>' STORED AS ... OPTIONS (...)`
+/// is the SQL equivalent.
+#[derive(Clone, Debug, Default)]
+pub struct SaWriteOptions {
+    /// Format name (`parquet`, `csv`, `json`, `arrow`), the target extension's otherwise.
+    format: Option<String>,
+    /// e.g. `snappy` or `zstd(3)` for Parquet, `gzip` or `zstd` for CSV and JSON.
+    compression: Option<String>,
+    /// Maximum number of rows of a Parquet row group.
+    row_group_size: Option<usize>,
+    /// Columns written as Hive partitions (`<column>=<value>/`) instead of in the files.
+    partition_by: Vec<String>,
+    /// `Some(true)` for exactly one file, `Some(false)` for a directory, from the target otherwise.
+    is_single_file: Option<bool>,
+    /// Other options of the format, e.g. `delimiter` for CSV.
+    format_options: HashMap<String, String>,
+}
+
+
+impl SaWriteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_format(mut self, format: &str) -> Self {
+        self.format = Some(format.to_string());
+        self
+    }
+
+    pub fn with_compression(mut self, compression: &str) -> Self {
+        self.compression = Some(compression.to_string());
+        self
+    }
+
+    pub fn with_row_group_size(mut self, row_group_size: usize) -> Self {
+        self.row_group_size = Some(row_group_size);
+        self
+    }
+
+    pub fn with_partition_by(mut self, partition_by: Vec<String>) -> Self {
+        self.partition_by = partition_by;
+        self
+    }
+
+    pub fn with_single_file(mut self, is_single_file: bool) -> Self {
+        self.is_single_file = Some(is_single_file);
+        self
+    }
+
+    pub fn with_format_option(mut self, key: &str, value: &str) -> Self {
+        self.format_options.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn get_format(&self) -> Option<&str> {
+        self.format.as_deref()
+    }
+
+    pub fn get_partition_by(&self) -> &[String] {
+        &self.partition_by
+    }
+
+    /// URL written to: DataFusion writes a single file to a URL with an extension and
+    /// without a trailing `/`, a directory otherwise.
+    pub fn get_output_url(&self, target: &str) -> Result<String> {
+        let has_extension: bool = Path::new(target.trim_end_matches('/')).extension().is_some();
+        match self.is_single_file {
+            Some(true) if target.ends_with('/') || !has_extension || !self.partition_by.is_empty() => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("A single file output needs a file name with an extension and no partition columns, got {}", target),
+            ).into()),
+            Some(false) if !target.ends_with('/') => Ok(format!("{}/", target)),
+            _ => Ok(target.to_string()),
+        }
+    }
+
+    /// Options of the `COPY` plan, format options prefixed with `format.` as in SQL.
+    pub fn get_copy_options(&self) -> HashMap<String, String> {
+        let mut copy_options: HashMap<String, String> = self.format_options
+            .iter()
+            .map(|(key, value)| match key.contains('.') {
+                true => (key.to_lowercase(), value.clone()),
+                false => (format!("format.{}", key.to_lowercase()), value.clone()),
+            })
+            .collect();
+        if let Some(compression) = &self.compression {
+            copy_options.insert("format.compression".to_string(), compression.clone());
+        }
+        if let Some(row_group_size) = self.row_group_size {
+            copy_options.insert("format.max_row_group_size".to_string(), row_group_size.to_string());
+        }
+        copy_options
+    }
+}
+
+
+/// Writes the results of `df` to `target`, a file or directory URI of an object store
+/// registered on `sa_datafusion`, and returns the number of rows written.
+pub async fn write_dataframe(
+    sa_datafusion: &SaDataFusion,
+    df: DataFrame,
+    target: &str,
+    options: &SaWriteOptions,
+) -> Result<u64> {
+    let output_url: String = options.get_output_url(target)?;
+    let format_factory: Arc<dyn FileFormatFactory> = sa_datafusion.resolve_write_format(target, options.get_format())?;
+    let (session_state, plan): (SessionState, LogicalPlan) = df.into_parts();
+    let copy_plan: LogicalPlan = LogicalPlanBuilder::copy_to(
+        plan,
+        output_url,
+        format_as_file_type(format_factory),
+        options.get_copy_options(),
+        options.get_partition_by().to_vec(),
+    )?.build()?;
+    let batches: Vec<RecordBatch> = DataFrame::new(session_state, copy_plan).collect().await?;
+    Ok(get_row_count(&batches))
+}
+
+
+/// Number of rows reported by the single `count` column of a `COPY` result.
+pub fn get_row_count(batches: &[RecordBatch]) -> u64 {
+    batches
+        .iter()
+        .filter(|batch| batch.num_columns() == 1)
+        .filter_map(|batch| batch.column(0).as_primitive_opt::<UInt64Type>())
+        .flat_map(|counts| (0..counts.len()).map(move |index| counts.value(index)))
+        .sum()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_get_output_url() {
+        let options: SaWriteOptions = SaWriteOptions::new();
+        assert_eq!(options.get_output_url("s3://bucket/out.parquet").unwrap(), "s3://bucket/out.parquet");
+        assert_eq!(options.get_output_url("s3://bucket/out/").unwrap(), "s3://bucket/out/");
+
+        let options: SaWriteOptions = SaWriteOptions::new().with_single_file(false);
+        assert_eq!(options.get_output_url("s3://bucket/out.parquet").unwrap(), "s3://bucket/out.parquet/");
+        assert_eq!(options.get_output_url("s3://bucket/out/").unwrap(), "s3://bucket/out/");
+
+        let options: SaWriteOptions = SaWriteOptions::new().with_single_file(true);
+        assert_eq!(options.get_output_url("file:///tmp/out.csv").unwrap(), "file:///tmp/out.csv");
+        for target in ["file:///tmp/out/", "file:///tmp/out"] {
+            assert!(options.get_output_url(target).is_err(), "{}", target);
+        }
+        let options: SaWriteOptions = options.with_partition_by(vec!["year".to_string()]);
+        assert!(options.get_output_url("file:///tmp/out.csv").is_err());
+    }
+
+
+    #[test]
+    fn test_get_copy_options() {
+        let options: SaWriteOptions = SaWriteOptions::new()
+            .with_compression("zstd(3)")
+            .with_row_group_size(1000)
+            .with_format_option("Delimiter", ";")
+            .with_format_option("format.has_header", "false");
+        assert_eq!(
+            options.get_copy_options(),
+            HashMap::from([
+                ("format.compression".to_string(), "zstd(3)".to_string()),
+                ("format.max_row_group_size".to_string(), "1000".to_string()),
+                ("format.delimiter".to_string(), ";".to_string()),
+                ("format.has_header".to_string(), "false".to_string()),
+            ])
+        );
+        assert!(SaWriteOptions::new().get_copy_options().is_empty());
+    }
+}
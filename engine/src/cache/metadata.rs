@@ -0,0 +1,500 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::ipc::reader::StreamReader;
+use datafusion::arrow::ipc::writer::StreamWriter;
+use object_store::path::Path as ObjectPath;
+use object_store::ObjectMeta;
+use crate::error::Result;
+
+
+/// Caches opened so far, by directory, so every session of the process counts in the same statistics.
+static METADATA_CACHES: OnceLock<Mutex<HashMap<PathBuf, Arc<SaMetadataCache>>>> = OnceLock::new();
+
+/// Entries written by this process so far, naming their temporary files.
+static ENTRY_WRITES: AtomicU64 = AtomicU64::new(0);
+
+
+/// Kinds of metadata a [`SaMetadataCache`] holds, each in its own directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaMetadataKind {
+    /// Schemas inferred from the files of a source.
+    Schema,
+    /// Files listed below a directory or matched by a glob.
+    Listing,
+    /// Footers of Parquet files.
+    ParquetMetadata,
+}
+
+
+impl SaMetadataKind {
+    fn get_dir_name(&self) -> &'static str {
+        match self {
+            SaMetadataKind::Schema => "schemas",
+            SaMetadataKind::Listing => "listings",
+            SaMetadataKind::ParquetMetadata => "parquet",
+        }
+    }
+}
+
+
+/// Lookups of one kind of metadata; an invalidation is a miss on an entry found stale, and removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SaCacheCounters {
+    pub hits: u64,
+    pub misses: u64,
+    pub invalidations: u64,
+}
+
+
+impl fmt::Display for SaCacheCounters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} hit(s), {} miss(es), {} invalidation(s)", self.hits, self.misses, self.invalidations)
+    }
+}
+
+
+/// Lookups of a [`SaMetadataCache`] since the process started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SaMetadataCacheStatistics {
+    pub schemas: SaCacheCounters,
+    pub listings: SaCacheCounters,
+    pub parquet_metadata: SaCacheCounters,
+}
+
+
+impl fmt::Display for SaMetadataCacheStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "schemas: {}", self.schemas)?;
+        writeln!(f, "listings: {}", self.listings)?;
+        write!(f, "parquet metadata: {}", self.parquet_metadata)
+    }
+}
+
+
+#[derive(Debug, Default)]
+struct SaAtomicCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+
+impl SaAtomicCounters {
+    fn get(&self) -> SaCacheCounters {
+        SaCacheCounters {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+
+/// First line of an entry file, the payload following it.
+#[derive(Debug, Serialize, Deserialize)]
+struct SaEntryHeader {
+    /// Key of the entry, as the file name is only a hash of it.
+    key: String,
+    /// Version of the objects the payload was read from, see [`get_object_version`].
+    version: String,
+    /// Seconds since the epoch at which the entry was written.
+    created_at: u64,
+}
+
+
+/// [`ObjectMeta`] as listing entries store it.
+#[derive(Debug, Serialize, Deserialize)]
+struct SaCachedObjectMeta {
+    location: String,
+    last_modified_ms: i64,
+    size: usize,
+    e_tag: Option<String>,
+    version: Option<String>,
+}
+
+
+impl From<&ObjectMeta> for SaCachedObjectMeta {
+    fn from(meta: &ObjectMeta) -> Self {
+        Self {
+            location: meta.location.to_string(),
+            last_modified_ms: meta.last_modified.timestamp_millis(),
+            size: meta.size,
+            e_tag: meta.e_tag.clone(),
+            version: meta.version.clone(),
+        }
+    }
+}
+
+
+impl SaCachedObjectMeta {
+    fn into_object_meta(self) -> Option<ObjectMeta> {
+        Some(ObjectMeta {
+            location: ObjectPath::parse(&self.location).ok()?,
+            last_modified: DateTime::<Utc>::from_timestamp_millis(self.last_modified_ms)?,
+            size: self.size,
+            e_tag: self.e_tag,
+            version: self.version,
+        })
+    }
+}
+
+
+/// On-disk cache of the metadata read to plan queries: inferred schemas, file listings and
+/// Parquet footers, so sessions reading the same sources skip the listing and footer requests.
+///
+/// Entries are keyed by source URI and hold the version (ETag or modification time, and size)
+/// of the objects they were read from: an entry whose objects changed is invalidated on lookup.
+/// Listings cannot be checked without listing again, so they expire after a TTL instead.
+///
+/// The cache is best effort: entries that cannot be read or written are misses, never errors.
+#[derive(Debug)]
+pub struct SaMetadataCache {
+    dir: PathBuf,
+    schemas: SaAtomicCounters,
+    listings: SaAtomicCounters,
+    parquet_metadata: SaAtomicCounters,
+}
+
+
+impl SaMetadataCache {
+    /// Opens the cache stored in `dir`, created if needed; a directory opened twice in the
+    /// process returns the same cache.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Arc<Self>> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        let dir: PathBuf = std::fs::canonicalize(dir.as_ref())?;
+        let mut caches = METADATA_CACHES
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .expect("metadata caches lock poisoned");
+        let cache: &Arc<Self> = caches.entry(dir.clone()).or_insert_with(|| Arc::new(Self {
+            dir,
+            schemas: SaAtomicCounters::default(),
+            listings: SaAtomicCounters::default(),
+            parquet_metadata: SaAtomicCounters::default(),
+        }));
+        Ok(cache.clone())
+    }
+
+    /// `metadata` in the [`get_cache_home`] directory.
+    pub fn default_dir() -> PathBuf {
+        get_cache_home().join("metadata")
+    }
+
+    pub fn get_dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn get_statistics(&self) -> SaMetadataCacheStatistics {
+        SaMetadataCacheStatistics {
+            schemas: self.schemas.get(),
+            listings: self.listings.get(),
+            parquet_metadata: self.parquet_metadata.get(),
+        }
+    }
+
+    /// Removes every entry; the statistics are kept.
+    pub fn clear(&self) -> Result<()> {
+        for kind in [SaMetadataKind::Schema, SaMetadataKind::Listing, SaMetadataKind::ParquetMetadata] {
+            match std::fs::remove_dir_all(self.dir.join(kind.get_dir_name())) {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => return Err(error.into()),
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    /// Schema cached for `key`, if inferred from the same versions of `files`.
+    pub fn get_schema(&self, key: &str, files: &[ObjectMeta]) -> Option<SchemaRef> {
+        let payload: Vec<u8> = self.get(SaMetadataKind::Schema, key, |header| header.version == get_files_version(files))?;
+        StreamReader::try_new(Cursor::new(payload), None)
+            .ok()
+            .map(|reader| reader.schema())
+    }
+
+    /// Caches `schema`, inferred from `files`, for `key`.
+    pub fn put_schema(&self, key: &str, files: &[ObjectMeta], schema: &SchemaRef) {
+        let Ok(mut writer) = StreamWriter::try_new(Vec::new(), schema) else {
+            return;
+        };
+        if writer.finish().is_ok() {
+            if let Ok(payload) = writer.into_inner() {
+                self.put(SaMetadataKind::Schema, key, &get_files_version(files), &payload);
+            }
+        }
+    }
+
+    /// Files cached for the listing `key`, if listed less than `ttl` ago.
+    pub fn get_listing(&self, key: &str, ttl: Duration) -> Option<Vec<ObjectMeta>> {
+        let now: u64 = get_epoch_seconds();
+        let payload: Vec<u8> = self.get(SaMetadataKind::Listing, key, |header| header.created_at + ttl.as_secs() > now)?;
+        let files: Vec<SaCachedObjectMeta> = serde_json::from_slice(&payload).ok()?;
+        files.into_iter().map(SaCachedObjectMeta::into_object_meta).collect()
+    }
+
+    /// Caches `files`, the result of the listing `key`.
+    pub fn put_listing(&self, key: &str, files: &[ObjectMeta]) {
+        let files: Vec<SaCachedObjectMeta> = files.iter().map(SaCachedObjectMeta::from).collect();
+        if let Ok(payload) = serde_json::to_vec(&files) {
+            self.put(SaMetadataKind::Listing, key, "", &payload);
+        }
+    }
+
+    /// Raw Parquet footer (the metadata before the length and magic) cached for `key`, if read
+    /// from the same version of `meta`.
+    pub fn get_parquet_metadata(&self, key: &str, meta: &ObjectMeta) -> Option<Vec<u8>> {
+        self.get(SaMetadataKind::ParquetMetadata, key, |header| header.version == get_object_version(meta))
+    }
+
+    /// Caches `metadata`, the raw Parquet footer of `meta`, for `key`.
+    pub fn put_parquet_metadata(&self, key: &str, meta: &ObjectMeta, metadata: &[u8]) {
+        self.put(SaMetadataKind::ParquetMetadata, key, &get_object_version(meta), metadata);
+    }
+
+    fn get_counters(&self, kind: SaMetadataKind) -> &SaAtomicCounters {
+        match kind {
+            SaMetadataKind::Schema => &self.schemas,
+            SaMetadataKind::Listing => &self.listings,
+            SaMetadataKind::ParquetMetadata => &self.parquet_metadata,
+        }
+    }
+
+    fn get_entry_path(&self, kind: SaMetadataKind, key: &str) -> PathBuf {
+        self.dir.join(kind.get_dir_name()).join(format!("{:016x}.entry", fnv1a_hash(key.as_bytes())))
+    }
+
+    /// Payload of the entry `key` if `is_valid` holds for it, counting the lookup; a stale entry is removed.
+    fn get(&self, kind: SaMetadataKind, key: &str, is_valid: impl Fn(&SaEntryHeader) -> bool) -> Option<Vec<u8>> {
+        let counters: &SaAtomicCounters = self.get_counters(kind);
+        let entry_path: PathBuf = self.get_entry_path(kind, key);
+        let entry: Option<(SaEntryHeader, Vec<u8>)> = std::fs::read(&entry_path)
+            .ok()
+            .and_then(|content| parse_entry(&content))
+            .filter(|(header, _)| header.key == key);
+        match entry {
+            Some((header, payload)) if is_valid(&header) => {
+                counters.hits.fetch_add(1, Ordering::Relaxed);
+                Some(payload)
+            },
+            Some(_) => {
+                let _ = std::fs::remove_file(&entry_path);
+                counters.invalidations.fetch_add(1, Ordering::Relaxed);
+                counters.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            },
+            None => {
+                counters.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            },
+        }
+    }
+
+    /// Writes the entry `key`, replacing any previous one.
+    fn put(&self, kind: SaMetadataKind, key: &str, version: &str, payload: &[u8]) {
+        let header: SaEntryHeader = SaEntryHeader {
+            key: key.to_string(),
+            version: version.to_string(),
+            created_at: get_epoch_seconds(),
+        };
+        let Ok(mut content) = serde_json::to_vec(&header) else {
+            return;
+        };
+        content.push(b'\n');
+        content.extend_from_slice(payload);
+
+        write_entry_file(&self.get_entry_path(kind, key), &content);
+    }
+}
+
+
+/// Writes `content` to `entry_path` through a temporary file, so concurrent readers never see it
+/// partially written; returns whether it was written.
+pub fn write_entry_file(entry_path: &Path, content: &[u8]) -> bool {
+    let temporary_path: PathBuf = entry_path.with_extension(format!(
+        "{}-{}.tmp",
+        std::process::id(),
+        ENTRY_WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let is_written: bool = entry_path.parent().is_some_and(|dir| std::fs::create_dir_all(dir).is_ok())
+        && std::fs::write(&temporary_path, content).is_ok()
+        && std::fs::rename(&temporary_path, entry_path).is_ok();
+    if !is_written {
+        let _ = std::fs::remove_file(&temporary_path);
+    }
+    is_written
+}
+
+
+/// `$XDG_CACHE_HOME/sqlanywhere`, `~/.cache/sqlanywhere` without it, holding the caches of SQLAnyWhere.
+pub fn get_cache_home() -> PathBuf {
+    let cache_home: PathBuf = match (std::env::var_os("XDG_CACHE_HOME"), std::env::var_os("HOME")) {
+        (Some(cache_home), _) if !cache_home.is_empty() => PathBuf::from(cache_home),
+        (_, Some(home)) if !home.is_empty() => PathBuf::from(home).join(".cache"),
+        _ => std::env::temp_dir(),
+    };
+    cache_home.join("sqlanywhere")
+}
+
+
+fn parse_entry(content: &[u8]) -> Option<(SaEntryHeader, Vec<u8>)> {
+    let header_end: usize = content.iter().position(|byte| *byte == b'\n')?;
+    let header: SaEntryHeader = serde_json::from_slice(&content[..header_end]).ok()?;
+    Some((header, content[header_end + 1..].to_vec()))
+}
+
+
+/// Version of the object `meta` describes: its size and ETag, or modification time for stores without ETags.
+pub fn get_object_version(meta: &ObjectMeta) -> String {
+    match &meta.e_tag {
+        Some(e_tag) => format!("{}:{}", meta.size, e_tag),
+        None => format!("{}:{}", meta.size, meta.last_modified.timestamp_millis()),
+    }
+}
+
+
+/// Version of a set of files, changing when one is added, removed or changed.
+fn get_files_version(files: &[ObjectMeta]) -> String {
+    let versions: Vec<String> = files
+        .iter()
+        .map(|meta| format!("{}={}", meta.location, get_object_version(meta)))
+        .collect();
+    format!("{}:{:016x}", files.len(), fnv1a_hash(versions.join("\n").as_bytes()))
+}
+
+
+/// 64-bit FNV-1a, stable across processes and Rust versions unlike the standard hashers.
+pub fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash: u64, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3))
+}
+
+
+fn get_epoch_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+
+
+    fn get_meta(location: &str, size: usize, e_tag: Option<&str>, last_modified_ms: i64) -> ObjectMeta {
+        ObjectMeta {
+            location: ObjectPath::from(location),
+            last_modified: DateTime::<Utc>::from_timestamp_millis(last_modified_ms).unwrap(),
+            size,
+            e_tag: e_tag.map(str::to_string),
+            version: None,
+        }
+    }
+
+
+    #[test]
+    fn test_schema_invalidation() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let metadata_cache: Arc<SaMetadataCache> = SaMetadataCache::open(dir.path()).unwrap();
+        let schema: SchemaRef = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        let files: Vec<ObjectMeta> = vec![get_meta("data/a.csv", 10, Some("1"), 0), get_meta("data/b.csv", 20, Some("1"), 0)];
+        metadata_cache.put_schema("s3://bucket/data/", &files, &schema);
+        assert_eq!(metadata_cache.get_schema("s3://bucket/data/", &files), Some(schema.clone()));
+
+        // A changed ETag, a changed size and a new file all invalidate the schema
+        let changed_files: [Vec<ObjectMeta>; 3] = [
+            vec![files[0].clone(), get_meta("data/b.csv", 20, Some("2"), 0)],
+            vec![files[0].clone(), get_meta("data/b.csv", 21, Some("1"), 0)],
+            vec![files[0].clone(), files[1].clone(), get_meta("data/c.csv", 30, Some("1"), 0)],
+        ];
+        for changed_files in changed_files {
+            metadata_cache.put_schema("s3://bucket/data/", &files, &schema);
+            assert_eq!(metadata_cache.get_schema("s3://bucket/data/", &changed_files), None);
+            // The stale entry is removed
+            assert_eq!(metadata_cache.get_schema("s3://bucket/data/", &files), None);
+        }
+        assert_eq!(
+            metadata_cache.get_statistics().schemas,
+            SaCacheCounters {
+                hits: 1,
+                misses: 6,
+                invalidations: 3,
+            }
+        );
+    }
+
+
+    #[test]
+    fn test_parquet_metadata_version() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let metadata_cache: Arc<SaMetadataCache> = SaMetadataCache::open(dir.path()).unwrap();
+        // Without an ETag, the modification time tells the versions apart
+        let meta: ObjectMeta = get_meta("data/a.parquet", 100, None, 1_000);
+        metadata_cache.put_parquet_metadata("file:///data/a.parquet", &meta, b"footer");
+        assert_eq!(metadata_cache.get_parquet_metadata("file:///data/a.parquet", &meta), Some(b"footer".to_vec()));
+        assert_eq!(metadata_cache.get_parquet_metadata("file:///data/b.parquet", &meta), None);
+        assert_eq!(metadata_cache.get_parquet_metadata("file:///data/a.parquet", &get_meta("data/a.parquet", 100, None, 2_000)), None);
+        assert_eq!(
+            metadata_cache.get_statistics().parquet_metadata,
+            SaCacheCounters {
+                hits: 1,
+                misses: 2,
+                invalidations: 1,
+            }
+        );
+
+        // With one, the ETag does
+        let meta: ObjectMeta = get_meta("data/a.parquet", 100, Some("\"abc\""), 1_000);
+        metadata_cache.put_parquet_metadata("file:///data/a.parquet", &meta, b"footer");
+        let touched_meta: ObjectMeta = get_meta("data/a.parquet", 100, Some("\"abc\""), 2_000);
+        assert_eq!(metadata_cache.get_parquet_metadata("file:///data/a.parquet", &touched_meta), Some(b"footer".to_vec()));
+    }
+
+
+    #[test]
+    fn test_listing_ttl() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let metadata_cache: Arc<SaMetadataCache> = SaMetadataCache::open(dir.path()).unwrap();
+        let files: Vec<ObjectMeta> = vec![get_meta("data/a.csv", 10, Some("1"), 1_000), get_meta("data/b.csv", 20, None, 2_000)];
+        metadata_cache.put_listing("s3://bucket/data/", &files);
+        assert_eq!(metadata_cache.get_listing("s3://bucket/data/", Duration::from_secs(60)), Some(files));
+        assert_eq!(metadata_cache.get_listing("s3://bucket/other/", Duration::from_secs(60)), None);
+        // An expired listing is removed, whatever the TTL of the next lookup
+        assert_eq!(metadata_cache.get_listing("s3://bucket/data/", Duration::ZERO), None);
+        assert_eq!(metadata_cache.get_listing("s3://bucket/data/", Duration::from_secs(60)), None);
+        assert_eq!(
+            metadata_cache.get_statistics().listings,
+            SaCacheCounters {
+                hits: 1,
+                misses: 3,
+                invalidations: 1,
+            }
+        );
+    }
+
+
+    #[test]
+    fn test_clear() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let metadata_cache: Arc<SaMetadataCache> = SaMetadataCache::open(dir.path()).unwrap();
+        assert!(Arc::ptr_eq(&metadata_cache, &SaMetadataCache::open(dir.path()).unwrap()));
+        let meta: ObjectMeta = get_meta("data/a.parquet", 100, Some("1"), 0);
+        metadata_cache.put_parquet_metadata("file:///data/a.parquet", &meta, b"footer");
+        metadata_cache.put_listing("file:///data/", std::slice::from_ref(&meta));
+        metadata_cache.clear().unwrap();
+
+        assert_eq!(metadata_cache.get_parquet_metadata("file:///data/a.parquet", &meta), None);
+        assert_eq!(metadata_cache.get_listing("file:///data/", Duration::from_secs(60)), None);
+        let statistics: SaMetadataCacheStatistics = metadata_cache.get_statistics();
+        assert_eq!((statistics.parquet_metadata.misses, statistics.parquet_metadata.invalidations), (1, 0));
+        assert_eq!((statistics.listings.misses, statistics.listings.invalidations), (1, 0));
+    }
+}
@@ -0,0 +1,6 @@
+pub mod metadata;
+pub use metadata::{SaCacheCounters, SaMetadataCache, SaMetadataCacheStatistics, SaMetadataKind};
+pub mod parquet;
+pub use parquet::SaCachedParquetFormat;
+pub mod range;
+pub use range::{SaCachedObjectStore, SaRangeCache, SaRangeCacheStatistics};
\ No newline at end of file
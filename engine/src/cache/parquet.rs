@@ -0,0 +1,394 @@
+use std::any::Any;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{StreamExt, TryStreamExt};
+use datafusion::arrow::datatypes::{Field, Fields, Schema, SchemaRef};
+use datafusion::common::Statistics;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use datafusion::datasource::file_format::parquet::{statistics_from_parquet_meta_calc, ParquetFormat};
+use datafusion::datasource::file_format::{transform_binary_to_string, transform_schema_to_view, FilePushdownSupport, FileFormat};
+use datafusion::datasource::physical_plan::parquet::{ParquetExecBuilder, ParquetFileMetrics, ParquetFileReaderFactory};
+use datafusion::datasource::physical_plan::{FileMeta, FileScanConfig, FileSinkConfig};
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::object_store::ObjectStoreUrl;
+use datafusion::execution::SessionState;
+use datafusion::logical_expr::Expr;
+use datafusion::parquet::arrow::async_reader::{AsyncFileReader, ParquetObjectReader};
+use datafusion::parquet::arrow::parquet_to_arrow_schema;
+use datafusion::parquet::errors::ParquetError;
+use datafusion::parquet::file::metadata::{ParquetMetaData, ParquetMetaDataReader};
+use datafusion::physical_expr::{LexRequirement, PhysicalExpr};
+use datafusion::physical_plan::metrics::ExecutionPlanMetricsSet;
+use datafusion::physical_plan::ExecutionPlan;
+use object_store::{ObjectMeta, ObjectStore};
+use crate::cache::SaMetadataCache;
+
+
+/// Length of the end of a Parquet file: the metadata length and the `PAR1` magic.
+const FOOTER_SIZE: usize = 8;
+
+
+/// Wraps `file_format` in a [`SaCachedParquetFormat`] if it reads Parquet, returns it as is otherwise.
+pub fn with_metadata_cache(
+    file_format: Arc<dyn FileFormat>,
+    metadata_cache: Arc<SaMetadataCache>,
+    object_store_url: ObjectStoreUrl,
+) -> Arc<dyn FileFormat> {
+    match file_format.as_any().downcast_ref::<ParquetFormat>() {
+        Some(parquet_format) => {
+            let inner: ParquetFormat = ParquetFormat::default().with_options(parquet_format.options().clone());
+            Arc::new(SaCachedParquetFormat::new(inner, metadata_cache, object_store_url))
+        },
+        None => file_format,
+    }
+}
+
+
+/// [`ParquetFormat`] reading the footers of the files through a [`SaMetadataCache`], when
+/// inferring their schema and statistics and when scanning them.
+pub struct SaCachedParquetFormat {
+    inner: ParquetFormat,
+    metadata_cache: Arc<SaMetadataCache>,
+    /// Store the files are read from, keying their footers.
+    object_store_url: ObjectStoreUrl,
+}
+
+
+impl SaCachedParquetFormat {
+    pub fn new(inner: ParquetFormat, metadata_cache: Arc<SaMetadataCache>, object_store_url: ObjectStoreUrl) -> Self {
+        Self {
+            inner,
+            metadata_cache,
+            object_store_url,
+        }
+    }
+
+    pub fn get_inner(&self) -> &ParquetFormat {
+        &self.inner
+    }
+
+    async fn fetch_metadata(&self, store: &Arc<dyn ObjectStore>, meta: &ObjectMeta) -> Result<ParquetMetaData> {
+        let cache_key: String = get_cache_key(&self.object_store_url, meta);
+        fetch_cached_metadata(store, meta, &cache_key, &self.metadata_cache, self.inner.metadata_size_hint()).await
+    }
+}
+
+
+/// Leaves the cache out, as schemas are cached by the format they are read with.
+impl fmt::Debug for SaCachedParquetFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SaCachedParquetFormat")
+            .field("inner", &self.inner)
+            .field("object_store_url", &self.object_store_url)
+            .finish()
+    }
+}
+
+
+#[async_trait]
+impl FileFormat for SaCachedParquetFormat {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn get_ext(&self) -> String {
+        self.inner.get_ext()
+    }
+
+    fn get_ext_with_compression(&self, file_compression_type: &FileCompressionType) -> Result<String> {
+        self.inner.get_ext_with_compression(file_compression_type)
+    }
+
+    /// Merges the schemas of `objects` as [`ParquetFormat`] does, from their cached footers.
+    async fn infer_schema(&self, state: &SessionState, store: &Arc<dyn ObjectStore>, objects: &[ObjectMeta]) -> Result<SchemaRef> {
+        let mut schemas: Vec<(String, Schema)> = futures::stream::iter(objects)
+            .map(|meta| async move {
+                let metadata: ParquetMetaData = self.fetch_metadata(store, meta).await?;
+                let file_metadata = metadata.file_metadata();
+                let schema: Schema = parquet_to_arrow_schema(file_metadata.schema_descr(), file_metadata.key_value_metadata())?;
+                Ok::<_, DataFusionError>((meta.location.to_string(), schema))
+            })
+            .boxed()
+            .buffered(state.config_options().execution.meta_fetch_concurrency)
+            .try_collect()
+            .await?;
+        // Fields are merged in the order they are seen, so files are too for a stable schema
+        schemas.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+        let schemas = schemas.into_iter().map(|(_, schema)| match self.inner.skip_metadata() {
+            true => clear_metadata(schema),
+            false => schema,
+        });
+        let mut schema: Schema = Schema::try_merge(schemas)?;
+        if self.inner.binary_as_string() {
+            schema = transform_binary_to_string(&schema);
+        }
+        if self.inner.force_view_types() {
+            schema = transform_schema_to_view(&schema);
+        }
+        Ok(Arc::new(schema))
+    }
+
+    async fn infer_stats(&self, _state: &SessionState, store: &Arc<dyn ObjectStore>, table_schema: SchemaRef, object: &ObjectMeta) -> Result<Statistics> {
+        let metadata: ParquetMetaData = self.fetch_metadata(store, object).await?;
+        statistics_from_parquet_meta_calc(&metadata, table_schema)
+    }
+
+    /// Builds the scan as [`ParquetFormat`] does, its readers taking the footers from the cache.
+    async fn create_physical_plan(&self, state: &SessionState, conf: FileScanConfig, filters: Option<&Arc<dyn PhysicalExpr>>) -> Result<Arc<dyn ExecutionPlan>> {
+        let store: Arc<dyn ObjectStore> = state.runtime_env().object_store(&conf.object_store_url)?;
+        let reader_factory: SaCachedParquetFileReaderFactory = SaCachedParquetFileReaderFactory {
+            store,
+            metadata_cache: self.metadata_cache.clone(),
+            object_store_url: conf.object_store_url.clone(),
+        };
+        let mut builder: ParquetExecBuilder = ParquetExecBuilder::new_with_options(conf, self.inner.options().clone())
+            .with_parquet_file_reader_factory(Arc::new(reader_factory));
+        if self.inner.enable_pruning() {
+            if let Some(predicate) = filters.cloned() {
+                builder = builder.with_predicate(predicate);
+            }
+        }
+        if let Some(metadata_size_hint) = self.inner.metadata_size_hint() {
+            builder = builder.with_metadata_size_hint(metadata_size_hint);
+        }
+        Ok(builder.build_arc())
+    }
+
+    async fn create_writer_physical_plan(
+        &self,
+        input: Arc<dyn ExecutionPlan>,
+        state: &SessionState,
+        conf: FileSinkConfig,
+        order_requirements: Option<LexRequirement>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        self.inner.create_writer_physical_plan(input, state, conf, order_requirements).await
+    }
+
+    fn supports_filters_pushdown(&self, file_schema: &Schema, table_schema: &Schema, filters: &[&Expr]) -> Result<FilePushdownSupport> {
+        self.inner.supports_filters_pushdown(file_schema, table_schema, filters)
+    }
+}
+
+
+/// [`ParquetFileReaderFactory`] of the readers of a [`SaCachedParquetFormat`] scan.
+#[derive(Debug)]
+struct SaCachedParquetFileReaderFactory {
+    store: Arc<dyn ObjectStore>,
+    metadata_cache: Arc<SaMetadataCache>,
+    object_store_url: ObjectStoreUrl,
+}
+
+
+impl ParquetFileReaderFactory for SaCachedParquetFileReaderFactory {
+    fn create_reader(
+        &self,
+        partition_index: usize,
+        file_meta: FileMeta,
+        metadata_size_hint: Option<usize>,
+        metrics: &ExecutionPlanMetricsSet,
+    ) -> Result<Box<dyn AsyncFileReader + Send>> {
+        let file_metrics: ParquetFileMetrics = ParquetFileMetrics::new(partition_index, file_meta.location().as_ref(), metrics);
+        let meta: ObjectMeta = file_meta.object_meta;
+        Ok(Box::new(SaCachedParquetFileReader {
+            file_metrics,
+            inner: ParquetObjectReader::new(self.store.clone(), meta.clone()),
+            store: self.store.clone(),
+            cache_key: get_cache_key(&self.object_store_url, &meta),
+            meta,
+            metadata_cache: self.metadata_cache.clone(),
+            metadata_size_hint,
+        }))
+    }
+}
+
+
+/// Reads the data of a Parquet file from its store and its footer through the cache.
+struct SaCachedParquetFileReader {
+    file_metrics: ParquetFileMetrics,
+    inner: ParquetObjectReader,
+    store: Arc<dyn ObjectStore>,
+    meta: ObjectMeta,
+    cache_key: String,
+    metadata_cache: Arc<SaMetadataCache>,
+    metadata_size_hint: Option<usize>,
+}
+
+
+impl AsyncFileReader for SaCachedParquetFileReader {
+    fn get_bytes(&mut self, range: Range<usize>) -> BoxFuture<'_, datafusion::parquet::errors::Result<Bytes>> {
+        self.file_metrics.bytes_scanned.add(range.end - range.start);
+        self.inner.get_bytes(range)
+    }
+
+    fn get_byte_ranges(&mut self, ranges: Vec<Range<usize>>) -> BoxFuture<'_, datafusion::parquet::errors::Result<Vec<Bytes>>> {
+        self.file_metrics.bytes_scanned.add(ranges.iter().map(|range| range.end - range.start).sum());
+        self.inner.get_byte_ranges(ranges)
+    }
+
+    fn get_metadata(&mut self) -> BoxFuture<'_, datafusion::parquet::errors::Result<Arc<ParquetMetaData>>> {
+        async move {
+            fetch_cached_metadata(&self.store, &self.meta, &self.cache_key, &self.metadata_cache, self.metadata_size_hint)
+                .await
+                .map(Arc::new)
+                .map_err(|e| ParquetError::External(Box::new(e)))
+        }
+        .boxed()
+    }
+}
+
+
+/// Key of the footer of `meta` in the cache.
+fn get_cache_key(object_store_url: &ObjectStoreUrl, meta: &ObjectMeta) -> String {
+    format!("{}{}", object_store_url.as_str(), meta.location)
+}
+
+
+/// Metadata of the Parquet file `meta`, from the footer cached for `cache_key` unless the file changed since.
+async fn fetch_cached_metadata(
+    store: &Arc<dyn ObjectStore>,
+    meta: &ObjectMeta,
+    cache_key: &str,
+    metadata_cache: &SaMetadataCache,
+    metadata_size_hint: Option<usize>,
+) -> Result<ParquetMetaData> {
+    if let Some(footer) = metadata_cache.get_parquet_metadata(cache_key, meta) {
+        if let Ok(metadata) = ParquetMetaDataReader::decode_metadata(&footer) {
+            return Ok(metadata);
+        }
+    }
+    let footer: Bytes = fetch_footer(store, meta, metadata_size_hint).await?;
+    let metadata: ParquetMetaData = ParquetMetaDataReader::decode_metadata(&footer)?;
+    metadata_cache.put_parquet_metadata(cache_key, meta, &footer);
+    Ok(metadata)
+}
+
+
+/// Reads the encoded metadata of the Parquet file `meta`, in one request when the last
+/// `metadata_size_hint` bytes hold it, in two otherwise.
+async fn fetch_footer(store: &Arc<dyn ObjectStore>, meta: &ObjectMeta, metadata_size_hint: Option<usize>) -> Result<Bytes> {
+    let file_size: usize = meta.size;
+    if file_size < FOOTER_SIZE {
+        return Err(ParquetError::EOF(format!("{} is too small to be a Parquet file", meta.location)).into());
+    }
+    let prefetch_size: usize = metadata_size_hint.unwrap_or(FOOTER_SIZE).clamp(FOOTER_SIZE, file_size);
+    let tail: Bytes = store.get_range(&meta.location, file_size - prefetch_size..file_size).await?;
+    let footer: &[u8; FOOTER_SIZE] = tail[prefetch_size - FOOTER_SIZE..]
+        .try_into()
+        .expect("tail ends with the footer");
+    let metadata_length: usize = ParquetMetaDataReader::decode_footer(footer)?;
+    if metadata_length + FOOTER_SIZE > file_size {
+        return Err(ParquetError::EOF(format!("{} has a metadata length larger than the file", meta.location)).into());
+    }
+    match metadata_length + FOOTER_SIZE <= prefetch_size {
+        true => Ok(tail.slice(prefetch_size - FOOTER_SIZE - metadata_length..prefetch_size - FOOTER_SIZE)),
+        false => {
+            let metadata_end: usize = file_size - FOOTER_SIZE;
+            Ok(store.get_range(&meta.location, metadata_end - metadata_length..metadata_end).await?)
+        },
+    }
+}
+
+
+/// `schema` without the metadata of its fields, as [`ParquetFormat`] merges them with `skip_metadata`.
+fn clear_metadata(schema: Schema) -> Schema {
+    let fields: Fields = schema
+        .fields()
+        .iter()
+        .map(|field| Field::clone(field).with_metadata(Default::default()))
+        .collect();
+    Schema::new(fields)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::{Int64Array, RecordBatch};
+    use datafusion::arrow::datatypes::DataType;
+    use datafusion::datasource::file_format::csv::CsvFormat;
+    use datafusion::parquet::arrow::ArrowWriter;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+
+
+    /// Parquet file of an `id` column with `rows` rows.
+    fn get_parquet_file(rows: i64) -> Bytes {
+        let schema: SchemaRef = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        let batch: RecordBatch = RecordBatch::try_new(schema.clone(), vec![Arc::new(Int64Array::from_iter_values(0..rows))]).unwrap();
+        let mut writer: ArrowWriter<Vec<u8>> = ArrowWriter::try_new(Vec::new(), schema, None).unwrap();
+        writer.write(&batch).unwrap();
+        Bytes::from(writer.into_inner().unwrap())
+    }
+
+
+    async fn put(store: &Arc<dyn ObjectStore>, location: &Path, data: Bytes) -> ObjectMeta {
+        store.put(location, data.into()).await.unwrap();
+        store.head(location).await.unwrap()
+    }
+
+
+    #[tokio::test]
+    async fn test_fetch_cached_metadata() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let metadata_cache: Arc<SaMetadataCache> = SaMetadataCache::open(dir.path()).unwrap();
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let location: Path = Path::from("data/a.parquet");
+        let meta: ObjectMeta = put(&store, &location, get_parquet_file(10)).await;
+
+        let metadata: ParquetMetaData = fetch_cached_metadata(&store, &meta, "memory:///data/a.parquet", &metadata_cache, None).await.unwrap();
+        assert_eq!(metadata.file_metadata().num_rows(), 10);
+        // Read from the cache, even once the file is gone
+        store.delete(&location).await.unwrap();
+        let metadata: ParquetMetaData = fetch_cached_metadata(&store, &meta, "memory:///data/a.parquet", &metadata_cache, None).await.unwrap();
+        assert_eq!(metadata.file_metadata().num_rows(), 10);
+
+        // A rewritten file has another ETag, its footer is read again
+        let meta: ObjectMeta = put(&store, &location, get_parquet_file(20)).await;
+        let metadata: ParquetMetaData = fetch_cached_metadata(&store, &meta, "memory:///data/a.parquet", &metadata_cache, None).await.unwrap();
+        assert_eq!(metadata.file_metadata().num_rows(), 20);
+        assert_eq!(
+            metadata_cache.get_statistics().parquet_metadata,
+            crate::cache::metadata::SaCacheCounters {
+                hits: 1,
+                misses: 2,
+                invalidations: 1,
+            }
+        );
+    }
+
+
+    #[tokio::test]
+    async fn test_fetch_footer() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let data: Bytes = get_parquet_file(10);
+        let meta: ObjectMeta = put(&store, &Path::from("a.parquet"), data.clone()).await;
+        let metadata_end: usize = data.len() - FOOTER_SIZE;
+        let metadata_length: usize = ParquetMetaDataReader::decode_footer(data[metadata_end..].try_into().unwrap()).unwrap();
+        let expected: Bytes = data.slice(metadata_end - metadata_length..metadata_end);
+        // Whether the size hint covers the metadata or not, and beyond the file
+        for metadata_size_hint in [None, Some(16), Some(data.len()), Some(data.len() * 2)] {
+            assert_eq!(fetch_footer(&store, &meta, metadata_size_hint).await.unwrap(), expected, "hint {:?}", metadata_size_hint);
+        }
+
+        let meta: ObjectMeta = put(&store, &Path::from("small.parquet"), Bytes::from_static(b"PAR1")).await;
+        assert!(fetch_footer(&store, &meta, None).await.is_err());
+    }
+
+
+    #[test]
+    fn test_with_metadata_cache() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let metadata_cache: Arc<SaMetadataCache> = SaMetadataCache::open(dir.path()).unwrap();
+        let object_store_url: ObjectStoreUrl = ObjectStoreUrl::parse("memory://").unwrap();
+
+        let file_format: Arc<dyn FileFormat> = with_metadata_cache(Arc::new(ParquetFormat::default()), metadata_cache.clone(), object_store_url.clone());
+        assert!(file_format.as_any().is::<SaCachedParquetFormat>());
+        let file_format: Arc<dyn FileFormat> = with_metadata_cache(Arc::new(CsvFormat::default()), metadata_cache, object_store_url);
+        assert!(file_format.as_any().is::<CsvFormat>());
+    }
+}
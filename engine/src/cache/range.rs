@@ -0,0 +1,620 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use object_store::path::Path as ObjectPath;
+use object_store::{
+    GetOptions,
+    GetRange,
+    GetResult,
+    GetResultPayload,
+    ListResult,
+    MultipartUpload,
+    ObjectMeta,
+    ObjectStore,
+    PutMultipartOpts,
+    PutOptions,
+    PutPayload,
+    PutResult
+};
+use crate::cache::metadata::{fnv1a_hash, get_cache_home, get_object_version, write_entry_file};
+use crate::error::Result;
+
+
+/// Caches opened so far, by directory, so every session of the process shares their size bound.
+static RANGE_CACHES: OnceLock<Mutex<HashMap<PathBuf, Arc<SaRangeCache>>>> = OnceLock::new();
+
+/// Extension of the entry files.
+const ENTRY_EXTENSION: &str = "range";
+
+/// Largest range cached, as a range is held in memory before being written.
+const MAX_ENTRY_SIZE: usize = 64 << 20;
+
+/// Most object metadata a [`SaCachedObjectStore`] remembers, the objects read the longest ago
+/// are forgotten first and looked up with `head` again.
+const MAX_REMEMBERED_OBJECTS: usize = 10_000;
+
+
+/// Reads of a [`SaRangeCache`] since the process started, and its current size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SaRangeCacheStatistics {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries removed to keep the cache under its size bound.
+    pub evictions: u64,
+    /// Bytes read from the cache instead of the store.
+    pub bytes_read: u64,
+    /// Bytes fetched from the store on misses.
+    pub bytes_fetched: u64,
+    pub size: u64,
+    pub max_size: u64,
+}
+
+
+impl fmt::Display for SaRangeCacheStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ranges: {} hit(s), {} miss(es), {} eviction(s)", self.hits, self.misses, self.evictions)?;
+        write!(
+            f,
+            "range bytes: {} read from the cache, {} fetched, {} of {} cached",
+            self.bytes_read,
+            self.bytes_fetched,
+            self.size,
+            self.max_size
+        )
+    }
+}
+
+
+/// First line of an entry file, the bytes of the range following it.
+#[derive(Debug, Serialize, Deserialize)]
+struct SaRangeHeader {
+    /// URI of the object, as the file name is only a hash of it.
+    key: String,
+    /// Version of the object the bytes were read from, see [`get_object_version`].
+    version: String,
+    start: usize,
+    end: usize,
+}
+
+
+/// Size and last use of the entries, to evict the least recently used ones.
+#[derive(Debug, Default)]
+struct SaRangeIndex {
+    entries: HashMap<PathBuf, (u64, u64)>,
+    /// Entries by last use, the least recently used first.
+    order: BTreeMap<u64, PathBuf>,
+    size: u64,
+    /// Incremented on every use, ordering the entries.
+    clock: u64,
+}
+
+
+impl SaRangeIndex {
+    fn touch(&mut self, entry_path: &Path, entry_size: u64) {
+        self.clock += 1;
+        match self.entries.insert(entry_path.to_path_buf(), (entry_size, self.clock)) {
+            Some((previous_size, last_used)) => {
+                self.order.remove(&last_used);
+                self.size = self.size - previous_size + entry_size;
+            },
+            None => self.size += entry_size,
+        }
+        self.order.insert(self.clock, entry_path.to_path_buf());
+    }
+
+    /// Removes the least recently used entry, returns its path.
+    fn pop_least_recently_used(&mut self) -> Option<PathBuf> {
+        let (_, entry_path) = self.order.pop_first()?;
+        if let Some((entry_size, _)) = self.entries.remove(&entry_path) {
+            self.size -= entry_size;
+        }
+        Some(entry_path)
+    }
+}
+
+
+/// On-disk cache of the byte ranges read from remote object stores, bounded in size by
+/// evicting the least recently used ranges.
+///
+/// Ranges are cached as requested, keyed by object URI and version (ETag or modification
+/// time, and size): a changed object is fetched again and its stale ranges are evicted in time.
+/// The use order is kept in the modification time of the entries, across processes.
+///
+/// The cache is best effort: entries that cannot be read or written are misses, never errors.
+#[derive(Debug)]
+pub struct SaRangeCache {
+    dir: PathBuf,
+    max_size: AtomicU64,
+    index: Mutex<SaRangeIndex>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_fetched: AtomicU64,
+}
+
+
+impl SaRangeCache {
+    /// Opens the cache stored in `dir`, created if needed, holding up to `max_size` bytes; a
+    /// directory opened twice in the process returns the same cache, with the last `max_size`.
+    pub fn open(dir: impl AsRef<Path>, max_size: u64) -> Result<Arc<Self>> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        let dir: PathBuf = std::fs::canonicalize(dir.as_ref())?;
+        let mut caches = RANGE_CACHES
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .expect("range caches lock poisoned");
+        let cache: Arc<Self> = match caches.get(&dir) {
+            Some(cache) => cache.clone(),
+            None => {
+                let cache: Arc<Self> = Arc::new(Self {
+                    index: Mutex::new(load_index(&dir)),
+                    dir: dir.clone(),
+                    max_size: AtomicU64::new(max_size),
+                    hits: AtomicU64::new(0),
+                    misses: AtomicU64::new(0),
+                    evictions: AtomicU64::new(0),
+                    bytes_read: AtomicU64::new(0),
+                    bytes_fetched: AtomicU64::new(0),
+                });
+                caches.insert(dir, cache.clone());
+                cache
+            },
+        };
+        cache.max_size.store(max_size, Ordering::Relaxed);
+        cache.evict();
+        Ok(cache)
+    }
+
+    /// `ranges` in the [`get_cache_home`] directory.
+    pub fn default_dir() -> PathBuf {
+        get_cache_home().join("ranges")
+    }
+
+    pub fn get_dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn get_max_size(&self) -> u64 {
+        self.max_size.load(Ordering::Relaxed)
+    }
+
+    pub fn get_statistics(&self) -> SaRangeCacheStatistics {
+        SaRangeCacheStatistics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_fetched: self.bytes_fetched.load(Ordering::Relaxed),
+            size: self.lock_index().size,
+            max_size: self.get_max_size(),
+        }
+    }
+
+    /// Removes every entry; the statistics are kept.
+    pub fn clear(&self) -> Result<()> {
+        *self.lock_index() = SaRangeIndex::default();
+        // Entries written by other processes are not all in the index
+        for entry_path in load_index(&self.dir).entries.into_keys() {
+            match std::fs::remove_file(entry_path) {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => return Err(error.into()),
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    /// Bytes of `range` of the object `key`, if cached from the same `version` of it.
+    ///
+    /// The entry is read from disk, so async callers run this on a blocking thread.
+    pub fn get(&self, key: &str, version: &str, range: &Range<usize>) -> Option<Bytes> {
+        let entry_path: PathBuf = self.get_entry_path(key, version, range);
+        let entry: Option<(SaRangeHeader, Bytes)> = std::fs::read(&entry_path)
+            .ok()
+            .and_then(|content| parse_entry(content.into()))
+            .filter(|(header, bytes)| {
+                header.key == key && header.version == version
+                    && header.start == range.start && header.end == range.end
+                    && bytes.len() == range.len()
+            });
+        let Some((_, bytes)) = entry else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        // Entries written by other processes join the index when they are used
+        let entry_size: u64 = std::fs::metadata(&entry_path).map(|metadata| metadata.len()).unwrap_or_default();
+        self.lock_index().touch(&entry_path, entry_size);
+        let _ = std::fs::File::options()
+            .write(true)
+            .open(&entry_path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        self.bytes_read.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        Some(bytes)
+    }
+
+    /// Caches `bytes`, `range` of the object `key` at `version`, evicting older ranges if the
+    /// cache outgrows its size bound.
+    ///
+    /// The entry is written to disk, so async callers run this on a blocking thread.
+    pub fn put(&self, key: &str, version: &str, range: &Range<usize>, bytes: &Bytes) {
+        self.bytes_fetched.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        let header: SaRangeHeader = SaRangeHeader {
+            key: key.to_string(),
+            version: version.to_string(),
+            start: range.start,
+            end: range.end,
+        };
+        let Ok(mut content) = serde_json::to_vec(&header) else {
+            return;
+        };
+        content.push(b'\n');
+        content.extend_from_slice(bytes);
+        if content.len() as u64 > self.get_max_size() {
+            return;
+        }
+        let entry_path: PathBuf = self.get_entry_path(key, version, range);
+        if write_entry_file(&entry_path, &content) {
+            self.lock_index().touch(&entry_path, content.len() as u64);
+            self.evict();
+        }
+    }
+
+    fn get_entry_path(&self, key: &str, version: &str, range: &Range<usize>) -> PathBuf {
+        let entry_name: String = format!("{}\n{}\n{}-{}", key, version, range.start, range.end);
+        self.dir.join(format!("{:016x}.{}", fnv1a_hash(entry_name.as_bytes()), ENTRY_EXTENSION))
+    }
+
+    fn lock_index(&self) -> std::sync::MutexGuard<'_, SaRangeIndex> {
+        self.index.lock().expect("range cache index lock poisoned")
+    }
+
+    /// Removes the least recently used entries until the cache fits in its size bound.
+    fn evict(&self) {
+        let max_size: u64 = self.get_max_size();
+        let mut evicted_paths: Vec<PathBuf> = Vec::new();
+        {
+            let mut index = self.lock_index();
+            while index.size > max_size {
+                let Some(entry_path) = index.pop_least_recently_used() else {
+                    break;
+                };
+                evicted_paths.push(entry_path);
+            }
+        }
+        // Files are removed once the index is unlocked, an entry written again meanwhile is a miss
+        for entry_path in evicted_paths {
+            let _ = std::fs::remove_file(entry_path);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+
+/// Index of the entries of `dir`, ordered by modification time.
+fn load_index(dir: &Path) -> SaRangeIndex {
+    let mut entries: Vec<(SystemTime, PathBuf, u64)> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|extension| extension == ENTRY_EXTENSION))
+        .filter_map(|entry| {
+            let metadata: std::fs::Metadata = entry.metadata().ok()?;
+            Some((metadata.modified().ok()?, entry.path(), metadata.len()))
+        })
+        .collect();
+    entries.sort();
+    let mut index: SaRangeIndex = SaRangeIndex::default();
+    for (_, entry_path, entry_size) in entries {
+        index.touch(&entry_path, entry_size);
+    }
+    index
+}
+
+
+fn parse_entry(content: Bytes) -> Option<(SaRangeHeader, Bytes)> {
+    let header_end: usize = content.iter().position(|byte| *byte == b'\n')?;
+    let header: SaRangeHeader = serde_json::from_slice(&content[..header_end]).ok()?;
+    Some((header, content.slice(header_end + 1..)))
+}
+
+
+/// [`ObjectStore`] reading the byte ranges of `inner` through a [`SaRangeCache`].
+///
+/// Ranges are looked up with the version of their object, known from the last listing or
+/// `head` of it through this store, which planning a scan does; reads with conditions or of a
+/// specific object version go to `inner`, as do writes.
+#[derive(Debug)]
+pub struct SaCachedObjectStore {
+    inner: Arc<dyn ObjectStore>,
+    range_cache: Arc<SaRangeCache>,
+    /// URL of `inner`, keying its objects in the cache.
+    store_url: String,
+    /// Last known metadata of the objects read, up to [`MAX_REMEMBERED_OBJECTS`].
+    metas: Mutex<SaObjectMetas>,
+}
+
+
+/// Metadata of objects, forgetting the earliest remembered ones past a maximum count.
+#[derive(Debug, Default)]
+struct SaObjectMetas {
+    /// Metadata by location, with the number of the object in the remembering order.
+    metas: HashMap<ObjectPath, (ObjectMeta, u64)>,
+    /// Locations in the order they were remembered, those forgotten since included.
+    order: VecDeque<(u64, ObjectPath)>,
+    count: u64,
+}
+
+
+impl SaObjectMetas {
+    fn get(&self, location: &ObjectPath) -> Option<&ObjectMeta> {
+        self.metas.get(location).map(|(meta, _)| meta)
+    }
+
+    fn insert(&mut self, meta: &ObjectMeta, max_count: usize) {
+        match self.metas.get_mut(&meta.location) {
+            Some((remembered_meta, _)) => *remembered_meta = meta.clone(),
+            None => {
+                self.count += 1;
+                self.metas.insert(meta.location.clone(), (meta.clone(), self.count));
+                self.order.push_back((self.count, meta.location.clone()));
+            },
+        }
+        while self.order.len() > max_count {
+            let Some((count, location)) = self.order.pop_front() else {
+                break;
+            };
+            // The object may have been forgotten and remembered again since
+            if self.metas.get(&location).is_some_and(|(_, remembered_count)| *remembered_count == count) {
+                self.metas.remove(&location);
+            }
+        }
+    }
+
+    fn remove(&mut self, location: &ObjectPath) {
+        self.metas.remove(location);
+    }
+}
+
+
+impl SaCachedObjectStore {
+    pub fn new(inner: Arc<dyn ObjectStore>, range_cache: Arc<SaRangeCache>, store_url: &str) -> Self {
+        Self {
+            inner,
+            range_cache,
+            store_url: store_url.trim_end_matches('/').to_string(),
+            metas: Mutex::new(SaObjectMetas::default()),
+        }
+    }
+
+    fn remember(&self, meta: &ObjectMeta) {
+        self.lock_metas().insert(meta, MAX_REMEMBERED_OBJECTS);
+    }
+
+    fn forget(&self, location: &ObjectPath) {
+        self.lock_metas().remove(location);
+    }
+
+    fn lock_metas(&self) -> std::sync::MutexGuard<'_, SaObjectMetas> {
+        self.metas.lock().expect("object metadata lock poisoned")
+    }
+
+    async fn get_meta(&self, location: &ObjectPath) -> object_store::Result<ObjectMeta> {
+        if let Some(meta) = self.lock_metas().get(location) {
+            return Ok(meta.clone());
+        }
+        self.head(location).await
+    }
+
+    /// Reads `range` of `meta` from the cache, or from `inner` and caches it; the cache files are
+    /// read and written on blocking threads.
+    async fn get_cached_range(&self, meta: ObjectMeta, range: Range<usize>) -> object_store::Result<GetResult> {
+        let key: String = format!("{}/{}", self.store_url, meta.location);
+        let cached_bytes: Option<Bytes> = {
+            let (range_cache, key, version, range) = (self.range_cache.clone(), key.clone(), get_object_version(&meta), range.clone());
+            tokio::task::spawn_blocking(move || range_cache.get(&key, &version, &range))
+                .await
+                .ok()
+                .flatten()
+        };
+        if let Some(bytes) = cached_bytes {
+            return Ok(GetResult {
+                payload: GetResultPayload::Stream(stream::once(async { Ok(bytes) }).boxed()),
+                meta,
+                range,
+                attributes: Default::default(),
+            });
+        }
+
+        let options: GetOptions = GetOptions {
+            range: Some(GetRange::Bounded(range.clone())),
+            ..GetOptions::default()
+        };
+        let result: GetResult = self.inner.get_opts(&meta.location, options).await?;
+        let (meta, result_range, attributes) = (result.meta.clone(), result.range.clone(), result.attributes.clone());
+        let bytes: Bytes = result.bytes().await?;
+        // The object may have changed since it was listed
+        self.remember(&meta);
+        if result_range == range && bytes.len() == range.len() {
+            let (range_cache, version, range, bytes) = (self.range_cache.clone(), get_object_version(&meta), range.clone(), bytes.clone());
+            let _ = tokio::task::spawn_blocking(move || range_cache.put(&key, &version, &range, &bytes)).await;
+        }
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(stream::once(async { Ok(bytes) }).boxed()),
+            meta,
+            range: result_range,
+            attributes,
+        })
+    }
+}
+
+
+impl fmt::Display for SaCachedObjectStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SaCachedObjectStore({}, {})", self.inner, self.range_cache.get_dir().display())
+    }
+}
+
+
+#[async_trait]
+impl ObjectStore for SaCachedObjectStore {
+    async fn put_opts(&self, location: &ObjectPath, payload: PutPayload, opts: PutOptions) -> object_store::Result<PutResult> {
+        self.forget(location);
+        self.inner.put_opts(location, payload, opts).await
+    }
+
+    async fn put_multipart_opts(&self, location: &ObjectPath, opts: PutMultipartOpts) -> object_store::Result<Box<dyn MultipartUpload>> {
+        self.forget(location);
+        self.inner.put_multipart_opts(location, opts).await
+    }
+
+    /// Reads plain ranges or whole objects through the cache, when they are not too large to be held in memory.
+    async fn get_opts(&self, location: &ObjectPath, options: GetOptions) -> object_store::Result<GetResult> {
+        let is_cacheable: bool = options.if_match.is_none()
+            && options.if_none_match.is_none()
+            && options.if_modified_since.is_none()
+            && options.if_unmodified_since.is_none()
+            && options.version.is_none()
+            && !options.head;
+        if !is_cacheable {
+            return self.inner.get_opts(location, options).await;
+        }
+        let meta: ObjectMeta = self.get_meta(location).await?;
+        match resolve_range(options.range.as_ref(), meta.size) {
+            Some(range) if !range.is_empty() && range.len() <= MAX_ENTRY_SIZE => self.get_cached_range(meta, range).await,
+            // Invalid ranges fail in `inner`
+            _ => self.inner.get_opts(location, options).await,
+        }
+    }
+
+    async fn head(&self, location: &ObjectPath) -> object_store::Result<ObjectMeta> {
+        let meta: ObjectMeta = self.inner.head(location).await?;
+        self.remember(&meta);
+        Ok(meta)
+    }
+
+    async fn delete(&self, location: &ObjectPath) -> object_store::Result<()> {
+        self.forget(location);
+        self.inner.delete(location).await
+    }
+
+    fn list(&self, prefix: Option<&ObjectPath>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        self.inner
+            .list(prefix)
+            .inspect_ok(|meta| self.remember(meta))
+            .boxed()
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&ObjectPath>) -> object_store::Result<ListResult> {
+        let list_result: ListResult = self.inner.list_with_delimiter(prefix).await?;
+        for meta in &list_result.objects {
+            self.remember(meta);
+        }
+        Ok(list_result)
+    }
+
+    async fn copy(&self, from: &ObjectPath, to: &ObjectPath) -> object_store::Result<()> {
+        self.forget(to);
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &ObjectPath, to: &ObjectPath) -> object_store::Result<()> {
+        self.forget(to);
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
+
+
+/// Bytes `range` asks for in an object of `size` bytes, the whole object without one;
+/// `None` if it starts after the end.
+fn resolve_range(range: Option<&GetRange>, size: usize) -> Option<Range<usize>> {
+    match range {
+        None => Some(0..size),
+        Some(GetRange::Bounded(range)) if range.start < size => Some(range.start..range.end.min(size)),
+        Some(GetRange::Bounded(_)) => None,
+        Some(GetRange::Offset(offset)) if *offset < size => Some(*offset..size),
+        Some(GetRange::Offset(_)) => None,
+        Some(GetRange::Suffix(length)) => Some(size.saturating_sub(*length)..size),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn get_meta(location: &str) -> ObjectMeta {
+        ObjectMeta {
+            location: ObjectPath::from(location),
+            last_modified: Default::default(),
+            size: 10,
+            e_tag: None,
+            version: None,
+        }
+    }
+
+
+    #[test]
+    fn test_index_eviction_order() {
+        let mut index: SaRangeIndex = SaRangeIndex::default();
+        index.touch(Path::new("a"), 10);
+        index.touch(Path::new("b"), 20);
+        index.touch(Path::new("c"), 30);
+        index.touch(Path::new("a"), 15);
+        assert_eq!(index.size, 65);
+
+        assert_eq!(index.pop_least_recently_used(), Some(PathBuf::from("b")));
+        assert_eq!(index.pop_least_recently_used(), Some(PathBuf::from("c")));
+        assert_eq!(index.size, 15);
+        assert_eq!(index.pop_least_recently_used(), Some(PathBuf::from("a")));
+        assert_eq!(index.pop_least_recently_used(), None);
+        assert_eq!(index.size, 0);
+    }
+
+
+    #[test]
+    fn test_evict() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let range_cache: Arc<SaRangeCache> = SaRangeCache::open(dir.path(), 1 << 20).unwrap();
+        let bytes: Bytes = Bytes::from(vec![7; 1000]);
+        range_cache.put("s3://bucket/a", "1", &(0..1000), &bytes);
+        range_cache.put("s3://bucket/b", "1", &(0..1000), &bytes);
+        assert_eq!(range_cache.get("s3://bucket/a", "1", &(0..1000)), Some(bytes.clone()));
+
+        // `b` is the least recently used entry
+        range_cache.max_size.store(range_cache.get_statistics().size - 1, Ordering::Relaxed);
+        range_cache.evict();
+        assert_eq!(range_cache.get("s3://bucket/b", "1", &(0..1000)), None);
+        assert_eq!(range_cache.get("s3://bucket/a", "1", &(0..1000)), Some(bytes));
+        assert_eq!(range_cache.get_statistics().evictions, 1);
+    }
+
+
+    #[test]
+    fn test_remembered_objects_limit() {
+        let mut metas: SaObjectMetas = SaObjectMetas::default();
+        for location in ["a", "b", "a", "c"] {
+            metas.insert(&get_meta(location), 2);
+        }
+        assert!(metas.get(&ObjectPath::from("a")).is_none());
+        assert_eq!(metas.metas.len(), 2);
+
+        // `b` remembered again is not forgotten with its first entry
+        metas.remove(&ObjectPath::from("b"));
+        metas.insert(&get_meta("b"), 2);
+        assert_eq!(metas.order.len(), 2);
+        assert!(metas.get(&ObjectPath::from("b")).is_some());
+        assert!(metas.get(&ObjectPath::from("c")).is_some());
+        metas.insert(&get_meta("d"), 2);
+        assert!(metas.get(&ObjectPath::from("c")).is_none());
+    }
+}
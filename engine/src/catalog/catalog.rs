@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use serde::Deserialize;
+use serde_json::Value;
+use datafusion::common::{ResolvedTableReference, TableReference};
+use crate::error::{Result, SaError};
+use crate::helper;
+
+
+/// A table of a catalog file: where its data is and how to read it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SaCatalogTable {
+    /// URI of the source, or a local path relative to the catalog file.
+    pub uri: String,
+    /// Format name or extension, see `sa.format`.
+    pub format: Option<String>,
+    /// Schema as `name: Type` pairs, see `sa.schema`.
+    pub schema: Option<String>,
+    /// Types overriding the schema of some columns, see `sa.schema_hints`.
+    pub schema_hints: Option<String>,
+    /// Credentials profile of the catalog whose options apply to the table.
+    pub profile: Option<String>,
+    /// Other `sa.*` options of the table, e.g. `compression` or `json_array`.
+    #[serde(default)]
+    pub options: HashMap<String, Value>,
+}
+
+
+/// Content of a catalog file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SaCatalogFile {
+    #[serde(default)]
+    profiles: HashMap<String, HashMap<String, Value>>,
+    #[serde(default)]
+    tables: HashMap<String, SaCatalogTable>,
+}
+
+
+/// Logical table names mapped to sources, read from a TOML or YAML file such as:
+///
+/// ```toml
+/// [profiles.minio]
+/// s3_endpoint = "http://localhost:9000"
+/// s3_allow_http = true
+///
+/// [tables."sales.orders"]
+/// uri = "s3://bucket/orders/"
+/// format = "parquet"
+/// schema_hints = "amount: Float64"
+/// profile = "minio"
+/// ```
+///
+/// Table names are `table`, `schema.table` or `catalog.schema.table`, resolved against the
+/// default catalog and schema of the session as names in SQL are. Profiles are named sets of
+/// `sa.*` options, such as an S3 endpoint and the AWS profile holding its credentials.
+#[derive(Debug, Clone)]
+pub struct SaCatalog {
+    path: String,
+    profiles: HashMap<String, HashMap<String, String>>,
+    tables: Vec<(TableReference, SaCatalogTable)>,
+}
+
+
+impl SaCatalog {
+    /// Reads the catalog at `path`, a `.toml`, `.yaml` or `.yml` file.
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content: String = std::fs::read_to_string(path).map_err(|e| SaError::catalog(path, e))?;
+        let extension: String = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let catalog_file: SaCatalogFile = match extension.as_str() {
+            "toml" => toml::from_str(&content).map_err(|e| SaError::catalog(path, e))?,
+            "yaml" | "yml" => serde_yaml::from_str(&content).map_err(|e| SaError::catalog(path, e))?,
+            _ => return Err(SaError::catalog(path, "expected a .toml, .yaml or .yml file")),
+        };
+        // Local paths of the tables are relative to the catalog file
+        let base_dir: PathBuf = std::fs::canonicalize(path)
+            .map_err(|e| SaError::catalog(path, e))?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let profiles: HashMap<String, HashMap<String, String>> = catalog_file.profiles
+            .into_iter()
+            .map(|(name, options)| (name, to_option_values(options)))
+            .collect();
+        let mut tables: Vec<(TableReference, SaCatalogTable)> = Vec::with_capacity(catalog_file.tables.len());
+        for (name, mut table) in catalog_file.tables {
+            let table_name: TableReference = TableReference::from(name.as_str());
+            if table_name.table().is_empty() {
+                return Err(SaError::catalog(path, format!("invalid table name '{}'", name)));
+            }
+            if table.uri.trim().is_empty() {
+                return Err(SaError::catalog(path, format!("table '{}' has no uri", name)));
+            }
+            if helper::uri_scheme(&table.uri).is_none() {
+                table.uri = format!("file://{}", resolve_local_path(&base_dir, &table.uri));
+            }
+            if let Some(profile) = table.profile.as_deref().filter(|profile| !profiles.contains_key(*profile)) {
+                return Err(SaError::catalog(path, format!("table '{}' uses the unknown profile '{}'", name, profile)));
+            }
+            tables.push((table_name, table));
+        }
+        tables.sort_by_key(|(table_name, _)| table_name.to_string());
+        Ok(Self {
+            path: path.to_string(),
+            profiles,
+            tables,
+        })
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    /// Names of the tables, sorted.
+    pub fn get_table_names(&self) -> Vec<String> {
+        self.tables.iter().map(|(table_name, _)| table_name.to_string()).collect()
+    }
+
+    /// Finds the table `table_name` refers to, partial names being resolved against
+    /// `default_catalog` and `default_schema`.
+    pub fn get_table(&self, table_name: &TableReference, default_catalog: &str, default_schema: &str) -> Option<&SaCatalogTable> {
+        let table_name: ResolvedTableReference = table_name.clone().resolve(default_catalog, default_schema);
+        self.tables
+            .iter()
+            .find(|(name, _)| name.clone().resolve(default_catalog, default_schema) == table_name)
+            .map(|(_, table)| table)
+    }
+
+    /// `sa.*` options `table` is read with: those of its profile, then its own.
+    pub fn get_options(&self, table: &SaCatalogTable) -> HashMap<String, String> {
+        let mut options: HashMap<String, String> = table.profile
+            .as_ref()
+            .and_then(|profile| self.profiles.get(profile))
+            .cloned()
+            .unwrap_or_default();
+        options.extend(to_option_values(table.options.clone()));
+        if let Some(schema) = &table.schema {
+            options.insert("schema".to_string(), schema.clone());
+        }
+        if let Some(schema_hints) = &table.schema_hints {
+            options.insert("schema_hints".to_string(), schema_hints.clone());
+        }
+        options
+    }
+}
+
+
+/// Option values as `SET` takes them, e.g. `true` for a TOML boolean.
+fn to_option_values(options: HashMap<String, Value>) -> HashMap<String, String> {
+    options
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(value) => (key, value),
+            value => (key, value.to_string()),
+        })
+        .collect()
+}
+
+
+/// `path` relative to `base_dir` without `.` and `..`, keeping the trailing `/` of a directory.
+fn resolve_local_path(base_dir: &Path, path: &str) -> String {
+    let mut resolved: PathBuf = PathBuf::new();
+    for component in base_dir.join(path).components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            },
+            Component::CurDir => {},
+            component => resolved.push(component),
+        }
+    }
+    let mut resolved: String = resolved.display().to_string();
+    if path.ends_with('/') && !resolved.ends_with('/') {
+        resolved.push('/');
+    }
+    resolved
+}
@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod catalog;
+pub use catalog::{SaCatalog, SaCatalogTable};
\ No newline at end of file
@@ -0,0 +1,83 @@
+use std::sync::{Arc, RwLock};
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper};
+
+
+/// Dot-commands of the REPL, completed like table names.
+pub const DOT_COMMANDS: [&str; 7] = [".help", ".tables", ".schema", ".format", ".output", ".cache", ".quit"];
+
+/// Characters ending the word being completed.
+const WORD_SEPARATORS: [char; 6] = [' ', '\t', '\n', '(', ',', '='];
+
+
+/// Whether `input` can run: a dot-command, or statements ended by `;`.
+pub fn is_complete_input(input: &str) -> bool {
+    let input: &str = input.trim();
+    input.is_empty() || input.starts_with('.') || input.ends_with(';')
+}
+
+
+/// Line editor helper of the REPL: completes table and column names and dot-commands,
+/// and continues the input on a new line until the statement ends with `;`.
+pub struct SaReplHelper {
+    /// Table and column names of the session, refreshed after every command.
+    names: Arc<RwLock<Vec<String>>>,
+}
+
+
+impl SaReplHelper {
+    pub fn new(names: Arc<RwLock<Vec<String>>>) -> Self {
+        Self { names }
+    }
+}
+
+
+impl Completer for SaReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start: usize = line[..pos].rfind(WORD_SEPARATORS).map_or(0, |index| index + 1);
+        let word: &str = &line[start..pos];
+        let names = self.names.read().expect("completion names lock poisoned");
+        let candidates: Vec<Pair> = match start == 0 && word.starts_with('.') {
+            true => DOT_COMMANDS.iter().map(|command| command.to_string()).collect::<Vec<String>>(),
+            // URIs are quoted identifiers
+            false => names.iter().map(|name| match name.contains("://") {
+                true => format!("\"{}\"", name),
+                false => name.clone(),
+            }).collect(),
+        }
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word) && candidate != word)
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+
+impl Validator for SaReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        match is_complete_input(ctx.input()) {
+            true => Ok(ValidationResult::Valid(None)),
+            false => Ok(ValidationResult::Incomplete),
+        }
+    }
+}
+
+
+impl Hinter for SaReplHelper {
+    type Hint = String;
+}
+
+
+impl Highlighter for SaReplHelper {}
+
+
+impl Helper for SaReplHelper {}
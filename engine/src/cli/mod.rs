@@ -0,0 +1,5 @@
+pub mod print;
+pub use print::SaPrintFormat;
+pub mod helper;
+pub mod repl;
+pub use repl::SaRepl;
\ No newline at end of file
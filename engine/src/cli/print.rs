@@ -0,0 +1,93 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use datafusion::arrow::csv::writer::{Writer as CsvWriter, WriterBuilder as CsvWriterBuilder};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::json::ArrayWriter;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::arrow::util::pretty;
+use datafusion::parquet::arrow::ArrowWriter;
+use crate::error::Result;
+
+
+/// Output format of the query results printed by the CLI.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SaPrintFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+    Parquet,
+}
+
+
+impl SaPrintFormat {
+    pub const NAMES: [&'static str; 4] = ["table", "csv", "json", "parquet"];
+
+    /// Whether the output is binary, which is not written to a terminal.
+    pub fn is_binary(&self) -> bool {
+        *self == SaPrintFormat::Parquet
+    }
+
+    /// Writes `batches`, of schema `schema`, to `writer` in this format.
+    pub fn write_batches<W: Write + Send>(&self, schema: SchemaRef, batches: &[RecordBatch], mut writer: W) -> Result<()> {
+        match self {
+            SaPrintFormat::Table => {
+                // Without batches, the header is still printed
+                let empty_batch: [RecordBatch; 1] = [RecordBatch::new_empty(schema)];
+                let batches: &[RecordBatch] = if batches.is_empty() { &empty_batch } else { batches };
+                writeln!(writer, "{}", pretty::pretty_format_batches(batches)?)?;
+            },
+            SaPrintFormat::Csv => {
+                let mut csv_writer: CsvWriter<W> = CsvWriterBuilder::new().with_header(true).build(writer);
+                for batch in batches {
+                    csv_writer.write(batch)?;
+                }
+            },
+            SaPrintFormat::Json => {
+                let mut json_writer: ArrayWriter<W> = ArrayWriter::new(writer);
+                for batch in batches {
+                    json_writer.write(batch)?;
+                }
+                json_writer.finish()?;
+                writeln!(json_writer.into_inner())?;
+            },
+            SaPrintFormat::Parquet => {
+                let mut parquet_writer: ArrowWriter<W> = ArrowWriter::try_new(writer, schema, None)?;
+                for batch in batches {
+                    parquet_writer.write(batch)?;
+                }
+                parquet_writer.close()?;
+            },
+        }
+        Ok(())
+    }
+}
+
+
+impl FromStr for SaPrintFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "table" => Ok(SaPrintFormat::Table),
+            "csv" => Ok(SaPrintFormat::Csv),
+            "json" => Ok(SaPrintFormat::Json),
+            "parquet" => Ok(SaPrintFormat::Parquet),
+            _ => Err(format!("Unknown format '{}', expected one of: {}", name, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+
+impl fmt::Display for SaPrintFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name: &str = match self {
+            SaPrintFormat::Table => "table",
+            SaPrintFormat::Csv => "csv",
+            SaPrintFormat::Json => "json",
+            SaPrintFormat::Parquet => "parquet",
+        };
+        write!(f, "{}", name)
+    }
+}
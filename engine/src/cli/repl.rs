@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, ErrorKind, IsTerminal};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::common::DFSchema;
+use datafusion::prelude::DataFrame;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+use crate::builder::pipelines;
+use crate::cache::{SaMetadataCache, SaRangeCache};
+use crate::cli::helper::{self, SaReplHelper, DOT_COMMANDS};
+use crate::cli::print::SaPrintFormat;
+use crate::datafusion::SaDataFusion;
+use crate::error::{Result, SaError};
+use crate::helper::uri_scheme;
+
+
+const PROMPT: &str = "sqlanywhere> ";
+const CONTINUATION_PROMPT: &str = "         ...> ";
+
+
+/// Interactive SQL shell over a [`SaDataFusion`] session: sources are registered the
+/// first time a statement reads their URI, as in `sa_query`, and stay registered.
+pub struct SaRepl {
+    sa_datafusion: SaDataFusion,
+    format: SaPrintFormat,
+    /// File the results are written to, stdout if `None`.
+    output: Option<PathBuf>,
+    /// Table and column names completed by the line editor.
+    names: Arc<RwLock<Vec<String>>>,
+}
+
+
+impl SaRepl {
+    pub fn new(sa_datafusion: SaDataFusion) -> Self {
+        Self {
+            sa_datafusion,
+            format: SaPrintFormat::default(),
+            output: None,
+            names: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub fn with_format(mut self, format: SaPrintFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_output(mut self, output: Option<PathBuf>) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Reads and runs commands until `.quit` or end of input, keeping the history in `history_path`.
+    pub async fn run(&mut self, history_path: Option<&Path>) -> Result<()> {
+        let mut editor: Editor<SaReplHelper, DefaultHistory> = Editor::new().map_err(readline_error)?;
+        editor.set_helper(Some(SaReplHelper::new(self.names.clone())));
+        if let Some(history_path) = history_path {
+            // No history yet on the first run
+            let _ = editor.load_history(history_path);
+        }
+        println!("SQLAnyWhere shell, statements end with ';', .help lists the commands.");
+
+        // Lines are only joined here when the input is not a terminal, the editor does it otherwise
+        let mut input: String = String::new();
+        loop {
+            let prompt: &str = if input.is_empty() { PROMPT } else { CONTINUATION_PROMPT };
+            match editor.readline(prompt) {
+                Ok(line) => {
+                    input.push_str(&line);
+                    input.push('\n');
+                    if !helper::is_complete_input(&input) {
+                        continue;
+                    }
+                    let command: String = mem::take(&mut input);
+                    if command.trim().is_empty() {
+                        continue;
+                    }
+                    editor.add_history_entry(command.trim()).map_err(readline_error)?;
+                    match self.run_command(&command).await {
+                        Ok(true) => {},
+                        Ok(false) => break,
+                        Err(error) => eprintln!("Error: {}", error),
+                    }
+                },
+                // Ctrl-C drops the current input, Ctrl-D exits
+                Err(ReadlineError::Interrupted) => input.clear(),
+                Err(ReadlineError::Eof) => break,
+                Err(error) => return Err(readline_error(error)),
+            }
+        }
+
+        if let Some(history_path) = history_path {
+            editor.save_history(history_path).map_err(readline_error)?;
+        }
+        Ok(())
+    }
+
+    /// Runs a dot-command or SQL statements; returns `false` when the shell should exit.
+    pub async fn run_command(&mut self, input: &str) -> Result<bool> {
+        let input: &str = input.trim();
+        let is_running: bool = match input.starts_with('.') {
+            true => self.run_dot_command(input).await?,
+            false => {
+                self.run_sql(input).await?;
+                true
+            },
+        };
+        self.refresh_names().await;
+        Ok(is_running)
+    }
+
+    async fn run_sql(&self, stm: &str) -> Result<()> {
+        let start: Instant = Instant::now();
+        let df: DataFrame = pipelines::sa_query(&self.sa_datafusion, stm).await?;
+        let schema: SchemaRef = Arc::new(df.schema().as_arrow().clone());
+        let batches: Vec<RecordBatch> = df.collect().await?;
+        self.print_batches(schema, &batches)?;
+        let row_count: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+        eprintln!("{} row(s) in {:.3}s", row_count, start.elapsed().as_secs_f64());
+        Ok(())
+    }
+
+    fn print_batches(&self, schema: SchemaRef, batches: &[RecordBatch]) -> Result<()> {
+        match &self.output {
+            Some(path) => self.format.write_batches(schema, batches, BufWriter::new(File::create(path)?)),
+            None if self.format.is_binary() && io::stdout().is_terminal() => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} output is binary, choose a file with .output <path>", self.format),
+            ).into()),
+            None => self.format.write_batches(schema, batches, BufWriter::new(io::stdout())),
+        }
+    }
+
+    async fn run_dot_command(&mut self, input: &str) -> Result<bool> {
+        let (command, argument) = match input.split_once(char::is_whitespace) {
+            Some((command, argument)) => (command, Some(argument.trim()).filter(|argument| !argument.is_empty())),
+            None => (input, None),
+        };
+        match (command, argument) {
+            (".quit" | ".exit", _) => return Ok(false),
+            (".help", _) => {
+                println!(".tables                           List the registered and catalog tables");
+                println!(".schema <table|uri>               Show the columns of a table or source");
+                println!(".format [table|csv|json|parquet]  Show or set the output format");
+                println!(".output [path]                    Write the results to a file, or back to stdout");
+                println!(".cache [clear]                    Show the metadata and range cache statistics, or empty them");
+                println!(".quit                             Exit");
+            },
+            (".tables", _) => {
+                for table_name in self.sa_datafusion.get_table_names() {
+                    println!("{}", table_name);
+                }
+            },
+            (".schema", Some(table_name)) => {
+                let table_name: &str = table_name.trim_matches('"');
+                let is_catalog_table: bool = pipelines::sa_register_catalog_table_pipeline(&self.sa_datafusion, table_name).await?;
+                if !is_catalog_table && !self.sa_datafusion.ctx.table_exist(table_name)? && uri_scheme(table_name).is_some() {
+                    pipelines::sa_register_source_pipeline(
+                        &self.sa_datafusion,
+                        table_name,
+                        table_name,
+                        None,
+                        None,
+                        &HashMap::new()
+                    ).await?;
+                }
+                self.sa_datafusion.display_schema(table_name).await?;
+            },
+            (".format", None) => println!("{}", self.format),
+            (".format", Some(format)) => match format.parse::<SaPrintFormat>() {
+                Ok(format) => self.format = format,
+                Err(message) => eprintln!("{}", message),
+            },
+            (".output", output) => self.output = output.map(PathBuf::from),
+            (".cache", argument) => self.run_cache_command(argument)?,
+            _ => eprintln!("Unknown command or missing argument: {}, commands: {}", input, DOT_COMMANDS.join(", ")),
+        }
+        Ok(true)
+    }
+
+    /// Shows the statistics of the metadata and range caches, or empties them with `clear`.
+    fn run_cache_command(&self, argument: Option<&str>) -> Result<()> {
+        let metadata_cache: Option<Arc<SaMetadataCache>> = self.sa_datafusion.get_metadata_cache()?;
+        let range_cache: Option<Arc<SaRangeCache>> = self.sa_datafusion.get_range_cache()?;
+        match argument {
+            _ if metadata_cache.is_none() && range_cache.is_none() => eprintln!(
+                "The caches are disabled, enable them with SET sa.metadata_cache = true or SET sa.range_cache = true"
+            ),
+            Some("clear") => {
+                if let Some(metadata_cache) = metadata_cache {
+                    metadata_cache.clear()?;
+                }
+                if let Some(range_cache) = range_cache {
+                    range_cache.clear()?;
+                }
+            },
+            None => {
+                if let Some(metadata_cache) = metadata_cache {
+                    println!("metadata cache: {}", metadata_cache.get_dir().display());
+                    println!("{}", metadata_cache.get_statistics());
+                }
+                if let Some(range_cache) = range_cache {
+                    println!("range cache: {}", range_cache.get_dir().display());
+                    println!("{}", range_cache.get_statistics());
+                }
+            },
+            Some(argument) => eprintln!("Unknown .cache argument: {}, expected clear", argument),
+        }
+        Ok(())
+    }
+
+    /// Reloads the table and column names completed by the line editor.
+    async fn refresh_names(&self) {
+        let mut names: Vec<String> = Vec::new();
+        for table_name in self.sa_datafusion.get_table_names() {
+            if let Ok(schema) = self.sa_datafusion.get_schema(&table_name).await {
+                names.extend(schema_column_names(&schema));
+            }
+            names.push(table_name);
+        }
+        names.sort();
+        names.dedup();
+        *self.names.write().expect("completion names lock poisoned") = names;
+    }
+}
+
+
+fn schema_column_names(schema: &DFSchema) -> impl Iterator<Item = String> + '_ {
+    schema.fields().iter().map(|field| field.name().clone())
+}
+
+
+fn readline_error(error: ReadlineError) -> SaError {
+    io::Error::other(error.to_string()).into()
+}
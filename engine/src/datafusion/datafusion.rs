@@ -1,42 +1,378 @@
 use datafusion::execution::context::SessionContext;
+use datafusion::execution::object_store::ObjectStoreUrl;
+use datafusion::datasource::file_format::{FileFormat, FileFormatFactory};
+use datafusion::prelude::SessionConfig;
 use datafusion::prelude::DataFrame;
-use datafusion::error::Result;
-use object_store::ObjectStore;
+use crate::error::{Result, SaError};
+use datafusion::datasource::listing::ListingTableUrl;
+use object_store::{ObjectMeta, ObjectStore};
+use std::collections::HashMap;
 use std::sync::Arc;
 use datafusion::common::DFSchema;
+use datafusion::config::CatalogOptions;
+use std::sync::{RwLock, RwLockWriteGuard};
 use crate::object_storage::storage::SaStorage;
+use crate::object_storage::registry::{SaStorageFactory, SaStorageRegistry};
+use crate::object_storage::discovery;
+use crate::format::{SaFormatRegistry, SaFormatSpec};
+use crate::datafusion::{SaOptions, SaReaderPartition};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::record_batch::{RecordBatch, RecordBatchReader};
+use datafusion::datasource::MemTable;
+use datafusion::datasource::streaming::StreamingTable;
+use datafusion::physical_plan::streaming::PartitionStream;
 use datafusion::execution::SessionState;
+use datafusion::catalog::CatalogProvider;
+use datafusion::catalog_common::{MemoryCatalogProvider, MemorySchemaProvider};
+use datafusion::common::{ResolvedTableReference, TableReference};
+use crate::catalog::SaCatalog;
+use crate::cache::{SaCachedObjectStore, SaMetadataCache, SaRangeCache};
 use url::Url;
 
 
 #[derive(Clone)]
 pub struct SaDataFusion {
     pub ctx: SessionContext,
+    storage_registry: Arc<RwLock<SaStorageRegistry>>,
+    format_registry: Arc<RwLock<SaFormatRegistry>>,
+    /// Catalog of the `sa.catalog` file, once read.
+    source_catalog: Arc<RwLock<Option<Arc<SaCatalog>>>>,
+    /// Settings the object stores were built with, by object store URL, see
+    /// [`Self::register_configured_object_store`].
+    object_store_settings: Arc<RwLock<HashMap<String, String>>>,
+}
+
+
+impl Default for SaDataFusion {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 
 impl SaDataFusion {
     pub fn new() -> Self {
-        Self {ctx: SessionContext::new()}
+        Self::new_with_storage_registry(SaStorageRegistry::default())
+    }
+
+    pub fn new_with_storage_registry(storage_registry: SaStorageRegistry) -> Self {
+        let session_config: SessionConfig = SessionConfig::new()
+            .with_option_extension(SaOptions::default());
+        Self {
+            ctx: SessionContext::new_with_config(session_config),
+            storage_registry: Arc::new(RwLock::new(storage_registry)),
+            format_registry: Arc::new(RwLock::new(SaFormatRegistry::default())),
+            source_catalog: Arc::new(RwLock::new(None)),
+            object_store_settings: Arc::new(RwLock::new(HashMap::new())),
+        }
     }
 
     pub fn get_session_state(&self) -> SessionState {
         self.ctx.state()
     }
 
+    /// New session sharing the tables, object stores and registries of this one, with a copy
+    /// of its configuration, so `SET` statements run on either do not change the other.
+    pub fn new_session(&self) -> Self {
+        Self {
+            ctx: SessionContext::new_with_state(self.get_session_state()),
+            storage_registry: self.storage_registry.clone(),
+            format_registry: self.format_registry.clone(),
+            source_catalog: self.source_catalog.clone(),
+            object_store_settings: self.object_store_settings.clone(),
+        }
+    }
+
+    /// New session as [`Self::new_session`], with `options` as its `sa.*` options.
+    pub fn with_options(&self, options: SaOptions) -> Self {
+        let sa_datafusion: SaDataFusion = self.new_session();
+        sa_datafusion.set_options(options);
+        sa_datafusion
+    }
+
+    /// Current `sa.*` options of the session.
+    pub fn get_options(&self) -> SaOptions {
+        self.get_session_state()
+            .config_options()
+            .extensions
+            .get::<SaOptions>()
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Sets the `sa.<key>` option of the session, like `SET sa.<key> = '<value>'`.
+    pub fn set_option(&self, key: &str, value: &str) -> Result<()> {
+        let key: String = match key.starts_with("sa.") {
+            true => key.to_string(),
+            false => format!("sa.{}", key),
+        };
+        self.ctx
+            .state_ref()
+            .write()
+            .config_mut()
+            .options_mut()
+            .set(&key, value)?;
+        Ok(())
+    }
+
+    /// Replaces every `sa.*` option of the session with `options`.
+    pub fn set_options(&self, options: SaOptions) {
+        self.ctx
+            .state_ref()
+            .write()
+            .config_mut()
+            .options_mut()
+            .extensions
+            .insert(options);
+    }
+
+    /// Catalog of the `sa.catalog` file, read again when the option names another file.
+    pub fn get_catalog(&self) -> Result<Option<Arc<SaCatalog>>> {
+        let sa_options: SaOptions = self.get_options();
+        let Some(path) = sa_options.get_catalog() else {
+            return Ok(None);
+        };
+        let mut source_catalog = self.source_catalog.write().expect("catalog lock poisoned");
+        if let Some(catalog) = source_catalog.as_ref().filter(|catalog| catalog.get_path() == path) {
+            return Ok(Some(catalog.clone()));
+        }
+        let catalog: Arc<SaCatalog> = Arc::new(SaCatalog::from_file(path)?);
+        *source_catalog = Some(catalog.clone());
+        Ok(Some(catalog))
+    }
+
+    /// Reads the catalog file at `path` and sets `sa.catalog`, so the following queries read its
+    /// tables by name, see [`SaCatalog`].
+    pub fn load_catalog(&self, path: &str) -> Result<Arc<SaCatalog>> {
+        self.set_option("catalog", path)?;
+        self.get_catalog()?.ok_or_else(|| SaError::catalog(path, "no catalog file given"))
+    }
+
+    /// Metadata cache of the session, `None` unless the `sa.metadata_cache` option is set.
+    ///
+    /// Sessions using the same `sa.metadata_cache_dir` share one cache and its statistics.
+    pub fn get_metadata_cache(&self) -> Result<Option<Arc<SaMetadataCache>>> {
+        let sa_options: SaOptions = self.get_options();
+        if !sa_options.metadata_cache {
+            return Ok(None);
+        }
+        let metadata_cache: Arc<SaMetadataCache> = match sa_options.get_metadata_cache_dir() {
+            Some(dir) => SaMetadataCache::open(dir)?,
+            None => SaMetadataCache::open(SaMetadataCache::default_dir())?,
+        };
+        Ok(Some(metadata_cache))
+    }
+
+    /// Range cache of the session, `None` unless the `sa.range_cache` option is set.
+    pub fn get_range_cache(&self) -> Result<Option<Arc<SaRangeCache>>> {
+        let sa_options: SaOptions = self.get_options();
+        if !sa_options.range_cache {
+            return Ok(None);
+        }
+        let range_cache: Arc<SaRangeCache> = match sa_options.get_range_cache_dir() {
+            Some(dir) => SaRangeCache::open(dir, sa_options.range_cache_max_size)?,
+            None => SaRangeCache::open(SaRangeCache::default_dir(), sa_options.range_cache_max_size)?,
+        };
+        Ok(Some(range_cache))
+    }
+
+    /// Resolves `table_name` against the default catalog and schema of the session.
+    pub fn resolve_table_name(&self, table_name: TableReference) -> ResolvedTableReference {
+        let session_state: SessionState = self.get_session_state();
+        let catalog_options: &CatalogOptions = &session_state.config_options().catalog;
+        table_name.resolve(&catalog_options.default_catalog, &catalog_options.default_schema)
+    }
+
+    /// Creates the catalog and schema of `table_name` when they do not exist, e.g. `sales` for
+    /// `sales.orders`.
+    pub fn create_schema(&self, table_name: &TableReference) -> Result<()> {
+        let resolved: ResolvedTableReference = self.resolve_table_name(table_name.clone());
+        let catalog: Arc<dyn CatalogProvider> = match self.ctx.catalog(&resolved.catalog) {
+            Some(catalog) => catalog,
+            None => {
+                let catalog: Arc<dyn CatalogProvider> = Arc::new(MemoryCatalogProvider::new());
+                self.ctx.register_catalog(resolved.catalog.as_ref(), catalog.clone());
+                catalog
+            },
+        };
+        if catalog.schema(&resolved.schema).is_none() {
+            catalog.register_schema(&resolved.schema, Arc::new(MemorySchemaProvider::new()))?;
+        }
+        Ok(())
+    }
+
     pub async fn execute_sql(&self, stm:&str ) -> Result<DataFrame> {
-        self.ctx.sql(stm).await
+        Ok(self.ctx.sql(stm).await?)
+    }
+
+    /// Registers `object_store` for the scheme and authority of `url` and returns it, read through
+    /// the range cache if `sa.range_cache` is set and the store is remote, see [`SaCachedObjectStore`].
+    pub fn register_object_store(&self, url: &Url, object_store: Arc<dyn ObjectStore>) -> Result<Arc<dyn ObjectStore>> {
+        let is_remote: bool = !matches!(url.scheme(), "file" | discovery::GLOB_SCHEME);
+        let object_store: Arc<dyn ObjectStore> = match self.get_range_cache()? {
+            Some(range_cache) if is_remote => {
+                let store_url: String = ListingTableUrl::parse(url.as_str())?.object_store().as_str().to_string();
+                Arc::new(SaCachedObjectStore::new(object_store, range_cache, &store_url))
+            },
+            _ => object_store,
+        };
+        self.ctx.runtime_env().register_object_store(url, object_store.clone());
+        Ok(object_store)
+    }
+
+    /// Registers `object_store` as [`Self::register_object_store`] does, unless another store built
+    /// with different `settings` (e.g. an S3 endpoint and profile) is registered for the scheme and
+    /// host of `url`: its tables would read through this one, so it is rejected.
+    ///
+    /// Stores are told apart as DataFusion registers them, without the user of the URL, so the
+    /// containers of `abfss://<container>@<account>...` URIs of one account share one entry.
+    pub fn register_configured_object_store(&self, url: &Url, object_store: Arc<dyn ObjectStore>, settings: &str) -> Result<Arc<dyn ObjectStore>> {
+        let store_url: String = format!("{}://{}/", url.scheme(), &url[url::Position::BeforeHost..url::Position::AfterPort]);
+        let mut object_store_settings: RwLockWriteGuard<HashMap<String, String>> = self.object_store_settings
+            .write()
+            .expect("object store settings lock poisoned");
+        match object_store_settings.get(&store_url) {
+            Some(registered_settings) if registered_settings != settings => {
+                return Err(SaError::object_store_conflict(&store_url, registered_settings));
+            },
+            Some(_) => {},
+            None => {
+                object_store_settings.insert(store_url, settings.to_string());
+            },
+        }
+        self.register_object_store(url, object_store)
+    }
+
+    /// Registers `factory` for URIs of `scheme`, so `sa_query` can resolve them.
+    pub fn register_storage_factory(&self, scheme: &str, factory: Arc<dyn SaStorageFactory>) {
+        self.storage_registry
+            .write()
+            .expect("storage registry lock poisoned")
+            .register(scheme, factory);
+    }
+
+    pub fn get_storage_factory(&self, scheme: &str) -> Result<Arc<dyn SaStorageFactory>> {
+        self.storage_registry
+            .read()
+            .expect("storage registry lock poisoned")
+            .get_factory(scheme)
+    }
+
+    pub fn get_storage_schemes(&self) -> Vec<String> {
+        self.storage_registry
+            .read()
+            .expect("storage registry lock poisoned")
+            .get_schemes()
+    }
+
+    /// Object store registered for the scheme and authority of `uri`.
+    pub fn get_object_store(&self, uri: &str) -> Result<Arc<dyn ObjectStore>> {
+        let object_store_url: ObjectStoreUrl = ListingTableUrl::parse(uri)?.object_store();
+        Ok(self.ctx.runtime_env().object_store(object_store_url)?)
+    }
+
+    /// Registers `spec` so its extensions, MIME types and magic bytes resolve to its format.
+    pub fn register_format(&self, spec: SaFormatSpec) {
+        self.format_registry
+            .write()
+            .expect("format registry lock poisoned")
+            .register(spec);
     }
 
-    pub fn register_object_store(&self, url: &Url, object_store: Arc<dyn ObjectStore>) {
-        self.ctx.runtime_env().register_object_store(url, object_store);
+    /// Resolves the [`FileFormat`] of `uri`, see [`SaFormatRegistry::resolve`].
+    ///
+    /// A directory or glob without a known extension is resolved from its first data file, which
+    /// also tells its compression unless the `sa.compression` option is set.
+    pub async fn resolve_file_format(&self, uri: &str, format_override: Option<&str>) -> Result<Arc<dyn FileFormat>> {
+        let format_registry: SaFormatRegistry = self.format_registry
+            .read()
+            .expect("format registry lock poisoned")
+            .clone();
+        let mut sample_uri: String = uri.to_string();
+        if format_registry.get_spec_by_extension(uri).is_none() {
+            let listing_table_url: ListingTableUrl = discovery::get_listing_table_url(self, uri).await?;
+            if listing_table_url.is_collection() {
+                let files: Vec<ObjectMeta> = discovery::list_files(self, &listing_table_url).await?;
+                let sample_file: &ObjectMeta = files.first().ok_or_else(|| object_store::Error::NotFound {
+                    path: uri.to_string(),
+                    source: "no data file found".into(),
+                })?;
+                sample_uri = format!("{}{}", listing_table_url.object_store().as_str(), sample_file.location);
+            }
+        }
+        let object_store: Arc<dyn ObjectStore> = self.get_object_store(&sample_uri)?;
+        let compression_override: Option<String> = self.get_options().get_compression().map(str::to_string);
+        format_registry
+            .resolve(&sample_uri, format_override, compression_override.as_deref(), object_store, &self.get_session_state())
+            .await
+    }
+
+    /// Resolves the format `uri` is written with, see [`SaFormatRegistry::resolve_factory`].
+    pub fn resolve_write_format(&self, uri: &str, format_override: Option<&str>) -> Result<Arc<dyn FileFormatFactory>> {
+        self.format_registry
+            .read()
+            .expect("format registry lock poisoned")
+            .resolve_factory(uri, format_override)
     }
 
     pub async fn register_sa_storage(&self, sa_storage: Arc<dyn SaStorage>) -> Result<()>{
-        self.ctx.register_table(sa_storage.get_file_url(), sa_storage.get_table_provider())?;
+        self.ctx.register_table(sa_storage.get_file_url(), sa_storage.get_table_provider()?)?;
+        Ok(())
+    }
+
+    /// Registers `sa_storage` as the table `table_name`, replacing any table of that name; the
+    /// schema of a name such as `sales.orders` is created if needed.
+    pub async fn register_sa_storage_as(&self, table_name: impl Into<TableReference>, sa_storage: Arc<dyn SaStorage>) -> Result<()> {
+        let table_name: TableReference = table_name.into();
+        self.create_schema(&table_name)?;
+        self.ctx.deregister_table(table_name.clone())?;
+        self.ctx.register_table(table_name, sa_storage.get_table_provider()?)?;
+        Ok(())
+    }
+
+    /// Registers `batches` as the in-memory table `table_name`, replacing any table of that name.
+    pub fn register_batches(&self, table_name: &str, schema: SchemaRef, batches: Vec<RecordBatch>) -> Result<()> {
+        let mem_table: MemTable = MemTable::try_new(schema, vec![batches])?;
+        self.ctx.deregister_table(table_name)?;
+        self.ctx.register_table(table_name, Arc::new(mem_table))?;
         Ok(())
     }
 
+    /// Registers `reader` as the streaming table `table_name`, replacing any table of that name.
+    ///
+    /// Batches are read by the first query scanning the table, without being held in memory;
+    /// the table cannot be scanned again.
+    pub fn register_record_batch_reader(&self, table_name: &str, reader: Box<dyn RecordBatchReader + Send>) -> Result<()> {
+        let partition: Arc<dyn PartitionStream> = Arc::new(SaReaderPartition::new(reader));
+        let streaming_table: StreamingTable = StreamingTable::try_new(partition.schema().clone(), vec![partition])?;
+        self.ctx.deregister_table(table_name)?;
+        self.ctx.register_table(table_name, Arc::new(streaming_table))?;
+        Ok(())
+    }
+
+    /// Removes the table `table_name`, returns whether it was registered.
+    pub fn deregister_table(&self, table_name: &str) -> Result<bool> {
+        Ok(self.ctx.deregister_table(table_name)?.is_some())
+    }
+
+    /// Names of the tables of the default schema, sources queried by URI included, and of the
+    /// tables of the `sa.catalog` file.
+    pub fn get_table_names(&self) -> Vec<String> {
+        let session_state: SessionState = self.get_session_state();
+        let catalog_options: &CatalogOptions = &session_state.config_options().catalog;
+        let mut table_names: Vec<String> = self.ctx
+            .catalog(&catalog_options.default_catalog)
+            .and_then(|catalog| catalog.schema(&catalog_options.default_schema))
+            .map(|schema| schema.table_names())
+            .unwrap_or_default();
+        if let Ok(Some(catalog)) = self.get_catalog() {
+            table_names.extend(catalog.get_table_names());
+        }
+        table_names.sort();
+        table_names.dedup();
+        table_names
+    }
+
     pub async fn get_schema(&self, table_name: &str) -> Result<DFSchema> {
         Ok(
             self.ctx
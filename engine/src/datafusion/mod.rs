@@ -1,2 +1,7 @@
+#[allow(clippy::module_inception)]
 pub mod datafusion;
-pub use datafusion::SaDataFusion;
\ No newline at end of file
+pub use datafusion::SaDataFusion;
+pub mod options;
+pub use options::SaOptions;
+pub mod reader_table;
+pub use reader_table::SaReaderPartition;
\ No newline at end of file
@@ -0,0 +1,108 @@
+use datafusion::common::config::ConfigExtension;
+use datafusion::common::extensions_options;
+
+
+extensions_options! {
+    /// SQLAnyWhere session options, settable from SQL with `SET sa.<option> = '<value>'`.
+    pub struct SaOptions {
+        /// Format name or extension used for every source instead of auto detection
+        /// (e.g. `csv`), empty to detect it from the source.
+        pub format: String, default = String::new()
+        /// Compression of every text source (`gzip`, `zstd`, `bzip2`, `xz` or `none`), empty to
+        /// detect it from the extension or the first bytes of the source.
+        pub compression: String, default = String::new()
+        /// Region of `s3://` sources, empty for the storage factory's one.
+        pub s3_region: String, default = String::new()
+        /// Endpoint of an S3-compatible server (e.g. `http://localhost:9000`), empty for AWS.
+        pub s3_endpoint: String, default = String::new()
+        /// Forces path-style requests (`<endpoint>/<bucket>/<key>`), as MinIO expects.
+        pub s3_path_style: bool, default = false
+        /// Allows a plain HTTP `s3_endpoint`.
+        pub s3_allow_http: bool, default = false
+        /// Named AWS profile to read the credentials and endpoint from, empty for the environment.
+        pub s3_profile: String, default = String::new()
+        /// Headers sent with the requests of `http://` and `https://` sources as `Name: value`
+        /// pairs separated by `;`, e.g. `Authorization: Bearer <token>`, empty for none.
+        pub http_headers: String, default = String::new()
+        /// Schema of the following sources as `name: Type` pairs (e.g. `id: Int64, name: Utf8`),
+        /// empty to infer it from the files.
+        pub schema: String, default = String::new()
+        /// Types overriding the schema of some columns, e.g. `score: Float64, id: Utf8`.
+        pub schema_hints: String, default = String::new()
+        /// Records read to infer CSV and JSON schemas, 0 for the format's default.
+        pub schema_sample_size: usize, default = 0
+        /// Reads every column of the files as `Utf8`, before `schema_hints` apply.
+        pub schema_all_utf8: bool, default = false
+        /// Catalog file (`.toml`, `.yaml`) mapping table names such as `sales.orders` to sources,
+        /// empty for none.
+        pub catalog: String, default = String::new()
+        /// Reads JSON sources as documents holding an array of objects instead of JSON lines.
+        pub json_array: bool, default = false
+        /// Caches inferred schemas, file listings and Parquet footers on disk, so later sessions
+        /// reading unchanged sources skip those requests.
+        pub metadata_cache: bool, default = false
+        /// Directory of the metadata cache, empty for `~/.cache/sqlanywhere/metadata`.
+        pub metadata_cache_dir: String, default = String::new()
+        /// Seconds a cached listing of a directory or glob is used for, 0 to always list again.
+        pub metadata_cache_listing_ttl: u64, default = 60
+        /// Caches the byte ranges read from remote stores (S3, GCS, Azure, HTTP) on disk, for the
+        /// stores registered while it is set.
+        pub range_cache: bool, default = false
+        /// Directory of the range cache, empty for `~/.cache/sqlanywhere/ranges`.
+        pub range_cache_dir: String, default = String::new()
+        /// Size bound of the range cache in bytes, the least recently used ranges being evicted.
+        pub range_cache_max_size: u64, default = 1 << 30
+    }
+}
+
+
+impl ConfigExtension for SaOptions {
+    const PREFIX: &'static str = "sa";
+}
+
+
+impl SaOptions {
+    pub fn get_format(&self) -> Option<&str> {
+        Some(self.format.as_str()).filter(|format| !format.is_empty())
+    }
+
+    pub fn get_compression(&self) -> Option<&str> {
+        Some(self.compression.as_str()).filter(|compression| !compression.trim().is_empty())
+    }
+
+    pub fn get_catalog(&self) -> Option<&str> {
+        Some(self.catalog.as_str()).filter(|catalog| !catalog.trim().is_empty())
+    }
+
+    pub fn get_metadata_cache_dir(&self) -> Option<&str> {
+        Some(self.metadata_cache_dir.as_str()).filter(|metadata_cache_dir| !metadata_cache_dir.trim().is_empty())
+    }
+
+    pub fn get_range_cache_dir(&self) -> Option<&str> {
+        Some(self.range_cache_dir.as_str()).filter(|range_cache_dir| !range_cache_dir.trim().is_empty())
+    }
+
+    /// `(name, value)` pairs of `http_headers`.
+    pub fn get_http_headers(&self) -> Vec<(String, String)> {
+        self.http_headers
+            .split(';')
+            .filter(|header| !header.trim().is_empty())
+            .map(|header| match header.split_once(':') {
+                Some((name, value)) => (name.trim().to_string(), value.trim().to_string()),
+                None => (header.trim().to_string(), String::new()),
+            })
+            .collect()
+    }
+
+    pub fn get_schema(&self) -> Option<&str> {
+        Some(self.schema.as_str()).filter(|schema| !schema.trim().is_empty())
+    }
+
+    pub fn get_schema_hints(&self) -> Option<&str> {
+        Some(self.schema_hints.as_str()).filter(|schema_hints| !schema_hints.trim().is_empty())
+    }
+
+    pub fn get_schema_sample_size(&self) -> Option<usize> {
+        Some(self.schema_sample_size).filter(|sample_size| *sample_size > 0)
+    }
+}
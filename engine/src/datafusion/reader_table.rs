@@ -0,0 +1,68 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use futures::stream::{self, StreamExt};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::record_batch::RecordBatchReader;
+use datafusion::error::DataFusionError;
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::streaming::PartitionStream;
+
+
+type SaBatchReader = Box<dyn RecordBatchReader + Send>;
+
+
+/// Single partition of a streaming table, reading the batches of a [`RecordBatchReader`].
+///
+/// A reader can only be read once: scanning the table again fails.
+pub struct SaReaderPartition {
+    schema: SchemaRef,
+    reader: Mutex<Option<SaBatchReader>>,
+}
+
+
+impl SaReaderPartition {
+    pub fn new(reader: SaBatchReader) -> Self {
+        Self {
+            schema: reader.schema(),
+            reader: Mutex::new(Some(reader)),
+        }
+    }
+}
+
+
+impl fmt::Debug for SaReaderPartition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SaReaderPartition").field("schema", &self.schema).finish()
+    }
+}
+
+
+impl PartitionStream for SaReaderPartition {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        let reader: Option<SaBatchReader> = self.reader
+            .lock()
+            .expect("reader partition lock poisoned")
+            .take();
+        let Some(reader) = reader else {
+            let error: DataFusionError = DataFusionError::Execution(
+                "The record batch reader of this table has already been read".to_string()
+            );
+            return Box::pin(RecordBatchStreamAdapter::new(self.schema.clone(), stream::once(async { Err(error) })));
+        };
+
+        // Readers block (e.g. on Python), so batches are pulled off the runtime threads
+        let batches = stream::unfold(Some(reader), |reader| async move {
+            let mut reader: SaBatchReader = reader?;
+            let (batch, reader) = tokio::task::spawn_blocking(move || (reader.next(), reader))
+                .await
+                .ok()?;
+            batch.map(|batch| (batch.map_err(DataFusionError::from), Some(reader)))
+        });
+        Box::pin(RecordBatchStreamAdapter::new(self.schema.clone(), batches.boxed()))
+    }
+}
@@ -0,0 +1,247 @@
+use std::io;
+use datafusion::arrow::error::ArrowError;
+use datafusion::error::DataFusionError;
+use datafusion::parquet::errors::ParquetError;
+use datafusion::sql::sqlparser::parser::ParserError;
+use thiserror::Error;
+
+
+/// Result type returned by every public function of the engine.
+pub type Result<T, E = SaError> = std::result::Result<T, E>;
+
+
+/// Errors raised by SQLAnyWhere.
+#[derive(Debug, Error)]
+pub enum SaError {
+    /// A source URI could not be parsed.
+    #[error("Could not parse the URI '{uri}': {reason}")]
+    UriParse {
+        uri: String,
+        reason: String,
+    },
+
+    /// No registered format matches a source, or the detected format cannot be read.
+    #[error("{}", unsupported_format_message(uri, format.as_deref(), registered_formats))]
+    UnsupportedFormat {
+        uri: String,
+        /// Format that was detected or requested, if any.
+        format: Option<String>,
+        registered_formats: Vec<String>,
+    },
+
+    /// No storage factory is registered for the URI scheme.
+    #[error("Unsupported storage scheme '{scheme}', registered schemes: [{}]", registered_schemes.join(", "))]
+    UnsupportedScheme {
+        scheme: String,
+        registered_schemes: Vec<String>,
+    },
+
+    /// Credentials are missing or rejected by the storage.
+    #[error("Invalid or missing credentials: {0}")]
+    Credentials(String),
+
+    /// A storage was used as a table before `init_table_provider` was called.
+    #[error("The table provider of '{uri}' is not initialised, call init_table_provider first")]
+    ProviderNotInitialised {
+        uri: String,
+    },
+
+    /// The metadata of a table format (Iceberg, Delta) is missing, invalid or not supported.
+    #[error("Invalid table metadata for '{uri}': {reason}")]
+    TableMetadata {
+        uri: String,
+        reason: String,
+    },
+
+    /// A catalog file cannot be read, is invalid or refers to an unknown credentials profile.
+    #[error("Invalid catalog '{path}': {reason}")]
+    Catalog {
+        path: String,
+        reason: String,
+    },
+
+    /// A bucket, or an Azure account, is read with settings other than those of its registered object store.
+    #[error("The object store of '{url}' is registered with other settings ({registered_settings}), the tables of one bucket must use the same profile and endpoint, and those of one Azure account the same container")]
+    ObjectStoreConflict {
+        url: String,
+        registered_settings: String,
+    },
+
+    /// Boxed, as are DataFusion errors, to keep `Result`s small.
+    #[error("Object store error: {0}")]
+    ObjectStore(Box<object_store::Error>),
+
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    DataFusion(Box<DataFusionError>),
+}
+
+
+fn unsupported_format_message(uri: &str, format: Option<&str>, registered_formats: &[String]) -> String {
+    match format {
+        Some(format) => format!(
+            "Unsupported file format '{}' for {}, readable formats: [{}]",
+            format,
+            uri,
+            registered_formats.join(", ")
+        ),
+        None => format!(
+            "Could not detect the file format of {} from its extension, MIME type or content, readable formats: [{}]",
+            uri,
+            registered_formats.join(", ")
+        ),
+    }
+}
+
+
+impl SaError {
+    pub fn uri_parse(uri: &str, reason: impl ToString) -> Self {
+        SaError::UriParse {
+            uri: uri.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn table_metadata(uri: &str, reason: impl ToString) -> Self {
+        SaError::TableMetadata {
+            uri: uri.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn catalog(path: &str, reason: impl ToString) -> Self {
+        SaError::Catalog {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn object_store_conflict(url: &str, registered_settings: &str) -> Self {
+        SaError::ObjectStoreConflict {
+            url: url.to_string(),
+            registered_settings: registered_settings.to_string(),
+        }
+    }
+
+    pub fn provider_not_initialised(uri: &str) -> Self {
+        SaError::ProviderNotInitialised {
+            uri: uri.to_string(),
+        }
+    }
+}
+
+
+impl From<DataFusionError> for SaError {
+    fn from(error: DataFusionError) -> Self {
+        match error {
+            // Our own errors come back wrapped when they went through DataFusion
+            DataFusionError::External(error) => match error.downcast::<SaError>() {
+                Ok(error) => *error,
+                Err(error) => SaError::DataFusion(Box::new(DataFusionError::External(error))),
+            },
+            DataFusionError::ObjectStore(error) => SaError::from(error),
+            DataFusionError::IoError(error) => SaError::Io(error),
+            DataFusionError::Context(context, error) => match SaError::from(*error) {
+                SaError::DataFusion(error) => SaError::DataFusion(Box::new(DataFusionError::Context(context, error))),
+                error => error,
+            },
+            error => SaError::DataFusion(Box::new(error)),
+        }
+    }
+}
+
+
+impl From<SaError> for DataFusionError {
+    fn from(error: SaError) -> Self {
+        match error {
+            SaError::DataFusion(error) => *error,
+            error => DataFusionError::External(Box::new(error)),
+        }
+    }
+}
+
+
+impl From<object_store::Error> for SaError {
+    fn from(error: object_store::Error) -> Self {
+        match error {
+            object_store::Error::PermissionDenied { .. } | object_store::Error::Unauthenticated { .. } => {
+                SaError::Credentials(error.to_string())
+            },
+            error => SaError::ObjectStore(Box::new(error)),
+        }
+    }
+}
+
+
+impl From<ArrowError> for SaError {
+    fn from(error: ArrowError) -> Self {
+        SaError::DataFusion(Box::new(DataFusionError::ArrowError(error, None)))
+    }
+}
+
+
+impl From<ParquetError> for SaError {
+    fn from(error: ParquetError) -> Self {
+        SaError::DataFusion(Box::new(DataFusionError::ParquetError(error)))
+    }
+}
+
+
+impl From<ParserError> for SaError {
+    fn from(error: ParserError) -> Self {
+        SaError::DataFusion(Box::new(DataFusionError::SQL(error, None)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_from_datafusion_error() {
+        // Our errors wrapped by DataFusion are unwrapped, even under a context
+        let error: DataFusionError = SaError::uri_parse("s3://", "missing bucket").into();
+        assert!(matches!(SaError::from(error), SaError::UriParse { .. }));
+        let error: DataFusionError = DataFusionError::Context(
+            "scan".to_string(),
+            Box::new(SaError::table_metadata("s3://bucket/table", "no snapshot").into()),
+        );
+        assert!(matches!(SaError::from(error), SaError::TableMetadata { .. }));
+
+        let error: DataFusionError = DataFusionError::ObjectStore(object_store::Error::NotFound {
+            path: "data.csv".to_string(),
+            source: "missing".into(),
+        });
+        assert!(matches!(SaError::from(error), SaError::ObjectStore(error) if matches!(*error, object_store::Error::NotFound { .. })));
+        let error: DataFusionError = DataFusionError::ObjectStore(object_store::Error::PermissionDenied {
+            path: "data.csv".to_string(),
+            source: "denied".into(),
+        });
+        assert!(matches!(SaError::from(error), SaError::Credentials(_)));
+        let error: DataFusionError = DataFusionError::IoError(io::Error::other("disk"));
+        assert!(matches!(SaError::from(error), SaError::Io(_)));
+
+        // Other errors stay DataFusion errors, with their context
+        let error: DataFusionError = DataFusionError::Context("scan".to_string(), Box::new(DataFusionError::Plan("bad".to_string())));
+        let error: SaError = SaError::from(error);
+        assert!(matches!(&error, SaError::DataFusion(error) if matches!(error.as_ref(), DataFusionError::Context(..))));
+        assert_eq!(error.to_string(), "scan\ncaused by\nError during planning: bad");
+        let error: DataFusionError = DataFusionError::External(Box::new(io::Error::other("external")));
+        assert!(matches!(SaError::from(error), SaError::DataFusion(error) if matches!(*error, DataFusionError::External(_))));
+    }
+
+
+    #[test]
+    fn test_into_datafusion_error() {
+        // DataFusion errors are given back as they came
+        let error: DataFusionError = SaError::from(DataFusionError::Plan("bad".to_string())).into();
+        assert!(matches!(error, DataFusionError::Plan(_)));
+        let error: DataFusionError = SaError::catalog("catalog.toml", "unknown profile").into();
+        assert!(matches!(error, DataFusionError::External(_)));
+        assert!(matches!(SaError::from(error), SaError::Catalog { .. }));
+    }
+}
@@ -0,0 +1,156 @@
+use std::io::{Cursor, Read};
+use datafusion::common::parsers::CompressionTypeVariant;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use datafusion::error::DataFusionError;
+use crate::error::Result;
+
+
+/// Compressions text sources can be read with, their extensions and magic bytes.
+const COMPRESSIONS: [(CompressionTypeVariant, &[&str], &[u8]); 4] = [
+    (CompressionTypeVariant::GZIP, &["gz", "gzip"], b"\x1f\x8b"),
+    (CompressionTypeVariant::ZSTD, &["zst", "zstd"], b"\x28\xb5\x2f\xfd"),
+    (CompressionTypeVariant::BZIP2, &["bz2"], b"BZh"),
+    (CompressionTypeVariant::XZ, &["xz"], b"\xfd7zXZ\x00"),
+];
+
+
+/// Parses a compression name: `gzip`, `zstd`, `bzip2`, `xz`, one of their extensions, or `none`.
+pub fn parse_compression(name: &str) -> Result<FileCompressionType> {
+    let name: &str = name.trim();
+    if name.eq_ignore_ascii_case("none") {
+        return Ok(FileCompressionType::UNCOMPRESSED);
+    }
+    let variant: CompressionTypeVariant = name.parse().map_err(|_| {
+        DataFusionError::Configuration(format!(
+            "Unsupported compression '{}', expected gzip, zstd, bzip2, xz or none",
+            name
+        ))
+    })?;
+    Ok(FileCompressionType::from(variant))
+}
+
+
+/// Splits a compression extension off `uri`, e.g. `data.csv` and gzip for `data.csv.gz`.
+pub fn split_compression_extension(uri: &str) -> (&str, Option<FileCompressionType>) {
+    let path: &str = uri.split(['?', '#']).next().unwrap_or(uri);
+    let Some((stem, extension)) = path.rsplit_once('.').filter(|(_, extension)| !extension.contains('/')) else {
+        return (uri, None);
+    };
+    COMPRESSIONS
+        .iter()
+        .find(|(_, extensions, _)| extensions.iter().any(|ext| ext.eq_ignore_ascii_case(extension)))
+        .map_or((uri, None), |(variant, _, _)| (stem, Some(FileCompressionType::from(*variant))))
+}
+
+
+/// Finds the compression whose magic bytes start `head`.
+pub fn get_compression_by_content(head: &[u8]) -> Option<FileCompressionType> {
+    COMPRESSIONS
+        .iter()
+        .find(|(_, _, magic)| head.starts_with(magic))
+        .map(|(variant, _, _)| FileCompressionType::from(*variant))
+}
+
+
+/// Decompresses up to `length` bytes from `head`, the first bytes of a file compressed with
+/// `compression`. Less is returned when `head` ends before a whole block could be decoded.
+pub fn decompress_head(head: &[u8], compression: FileCompressionType, length: usize) -> Vec<u8> {
+    let mut decompressed: Vec<u8> = Vec::with_capacity(length);
+    let Ok(mut reader) = compression.convert_read(Cursor::new(head.to_vec())) else {
+        return decompressed;
+    };
+    let mut buffer: [u8; 4096] = [0; 4096];
+    // A truncated input fails once the bytes it holds are decoded
+    while decompressed.len() < length {
+        match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => decompressed.extend_from_slice(&buffer[..read]),
+        }
+    }
+    decompressed.truncate(length);
+    decompressed
+}
+
+
+/// Name of `compression` as the `sa.compression` option and error messages spell it.
+pub fn get_compression_name(compression: &FileCompressionType) -> String {
+    match compression.get_variant() {
+        CompressionTypeVariant::UNCOMPRESSED => "none".to_string(),
+        variant => variant.to_string().to_lowercase(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_parse_compression() {
+        for (name, variant) in [
+            ("gzip", CompressionTypeVariant::GZIP),
+            ("GZ", CompressionTypeVariant::GZIP),
+            (" zstd ", CompressionTypeVariant::ZSTD),
+            ("bzip2", CompressionTypeVariant::BZIP2),
+            ("xz", CompressionTypeVariant::XZ),
+            ("none", CompressionTypeVariant::UNCOMPRESSED),
+        ] {
+            assert_eq!(parse_compression(name).unwrap().get_variant(), &variant, "{}", name);
+        }
+        assert!(parse_compression("lz4").is_err());
+    }
+
+
+    #[test]
+    fn test_split_compression_extension() {
+        for (uri, stem, variant) in [
+            ("s3://bucket/data.csv.gz", "s3://bucket/data.csv", CompressionTypeVariant::GZIP),
+            ("file:///data/events.jsonl.ZST", "file:///data/events.jsonl", CompressionTypeVariant::ZSTD),
+            ("https://host/data.csv.bz2?token=abc", "https://host/data.csv", CompressionTypeVariant::BZIP2),
+            ("data.tsv.xz", "data.tsv", CompressionTypeVariant::XZ),
+        ] {
+            let (split_stem, compression) = split_compression_extension(uri);
+            assert_eq!(split_stem, stem);
+            assert_eq!(compression.as_ref().map(FileCompressionType::get_variant), Some(&variant), "{}", uri);
+        }
+        for uri in ["s3://bucket/data.csv", "s3://bucket.gz/data", "data"] {
+            assert_eq!(split_compression_extension(uri).0, uri);
+            assert!(split_compression_extension(uri).1.is_none(), "{}", uri);
+        }
+    }
+
+
+    #[test]
+    fn test_get_compression_by_content() {
+        for (head, variant) in [
+            (&b"\x1f\x8b\x08\x00"[..], CompressionTypeVariant::GZIP),
+            (b"\x28\xb5\x2f\xfd\x04", CompressionTypeVariant::ZSTD),
+            (b"BZh91AY&SY", CompressionTypeVariant::BZIP2),
+            (b"\xfd7zXZ\x00\x00\x04", CompressionTypeVariant::XZ),
+        ] {
+            assert_eq!(get_compression_by_content(head).as_ref().map(FileCompressionType::get_variant), Some(&variant));
+        }
+        assert!(get_compression_by_content(b"id,name\n1,ada\n").is_none());
+        assert!(get_compression_by_content(b"").is_none());
+    }
+
+
+    #[test]
+    fn test_decompress_head() {
+        let path: String = format!("{}/.data/bin/ex-compressed-application/scores.csv.gz", env!("CARGO_MANIFEST_DIR"));
+        let compressed: Vec<u8> = std::fs::read(path).unwrap();
+        let gzip: FileCompressionType = FileCompressionType::from(CompressionTypeVariant::GZIP);
+        let mut data: Vec<u8> = Vec::new();
+        gzip.convert_read(Cursor::new(compressed.clone())).unwrap().read_to_end(&mut data).unwrap();
+
+        assert_eq!(decompress_head(&compressed, gzip, 10), data[..10]);
+        assert_eq!(decompress_head(&compressed, gzip, data.len() + 100), data);
+        // A truncated file gives what its bytes decode to
+        let head: Vec<u8> = decompress_head(&compressed[..compressed.len() - 10], gzip, data.len());
+        assert!(data.starts_with(&head));
+        assert!(decompress_head(b"not gzip", gzip, 10).is_empty());
+        assert_eq!(get_compression_name(&gzip), "gzip");
+        assert_eq!(get_compression_name(&FileCompressionType::UNCOMPRESSED), "none");
+    }
+}
@@ -0,0 +1,398 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufReader, Cursor, Read};
+use std::sync::Arc;
+use async_trait::async_trait;
+use bytes::{Buf, Bytes};
+use futures::stream::{self, StreamExt};
+use datafusion::arrow::datatypes::{Field, Schema, SchemaRef};
+use datafusion::arrow::json::reader::{infer_json_schema, ReaderBuilder};
+use datafusion::common::config::{ConfigFileType, JsonOptions};
+use datafusion::common::{GetExt, Statistics};
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use datafusion::datasource::file_format::json::JsonFormat;
+use datafusion::datasource::file_format::{FileFormat, FileFormatFactory};
+use datafusion::datasource::physical_plan::{
+    FileMeta,
+    FileOpenFuture,
+    FileOpener,
+    FileScanConfig,
+    FileSinkConfig,
+    FileStream
+};
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::{SendableRecordBatchStream, SessionState, TaskContext};
+use datafusion::physical_expr::{EquivalenceProperties, LexOrdering, LexRequirement, PhysicalExpr};
+use datafusion::physical_plan::execution_plan::{Boundedness, EmissionType};
+use datafusion::physical_plan::metrics::{ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::{DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, PlanProperties};
+use object_store::path::Path;
+use object_store::{ObjectMeta, ObjectStore};
+
+
+/// Records read to infer a schema when the format options do not set it, as DataFusion does.
+const DEFAULT_SCHEMA_INFER_MAX_RECORD: usize = 1000;
+
+
+/// Rewrites a JSON array document (`[{...}, {...}]`) as JSON lines, one element per line.
+///
+/// A document not starting with `[` is returned as is, so JSON lines files read the same.
+pub fn json_array_to_lines(document: &[u8]) -> crate::error::Result<Vec<u8>> {
+    let document: &[u8] = document.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(document);
+    let start: Option<usize> = document.iter().position(|byte| !byte.is_ascii_whitespace());
+    let Some(start) = start.filter(|start| document[*start] == b'[') else {
+        return Ok(document.to_vec());
+    };
+
+    let mut lines: Vec<u8> = Vec::with_capacity(document.len());
+    let mut depth: usize = 0;
+    let mut is_in_string: bool = false;
+    let mut is_escaped: bool = false;
+    for (index, byte) in document.iter().enumerate().skip(start) {
+        if is_in_string {
+            is_in_string = is_escaped || *byte != b'"';
+            is_escaped = !is_escaped && *byte == b'\\';
+            lines.push(*byte);
+            continue;
+        }
+        match byte {
+            b'"' => is_in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {},
+        }
+        match (depth, byte) {
+            // The brackets of the document itself
+            (0, b']') => {
+                if document[index + 1..].iter().any(|byte| !byte.is_ascii_whitespace()) {
+                    return Err(DataFusionError::Execution("Unexpected content after the JSON array".to_string()).into());
+                }
+                return Ok(lines);
+            },
+            (1, b'[') if index == start => {},
+            (1, b',') => lines.push(b'\n'),
+            _ => lines.push(*byte),
+        }
+    }
+    Err(DataFusionError::Execution("Unterminated JSON array".to_string()).into())
+}
+
+
+/// Reads the whole object at `location`, decompressed, as JSON lines.
+async fn read_json_lines(
+    store: &Arc<dyn ObjectStore>,
+    location: &Path,
+    file_compression_type: FileCompressionType,
+) -> Result<Vec<u8>> {
+    let data: Bytes = store.get(location).await?.bytes().await?;
+    let mut document: Vec<u8> = Vec::new();
+    file_compression_type.convert_read(data.reader())?.read_to_end(&mut document)?;
+    Ok(json_array_to_lines(&document)?)
+}
+
+
+/// Factory of [`SaJsonArrayFormat`].
+#[derive(Debug, Default)]
+pub struct SaJsonArrayFormatFactory;
+
+
+impl SaJsonArrayFormatFactory {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+
+impl GetExt for SaJsonArrayFormatFactory {
+    fn get_ext(&self) -> String {
+        "json".to_string()
+    }
+}
+
+
+impl FileFormatFactory for SaJsonArrayFormatFactory {
+    fn create(&self, state: &SessionState, format_options: &HashMap<String, String>) -> Result<Arc<dyn FileFormat>> {
+        let mut table_options = state.default_table_options();
+        table_options.set_config_format(ConfigFileType::JSON);
+        table_options.alter_with_string_hash_map(format_options)?;
+        Ok(Arc::new(SaJsonArrayFormat::new(table_options.json)))
+    }
+
+    fn default(&self) -> Arc<dyn FileFormat> {
+        Arc::new(SaJsonArrayFormat::default())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+
+/// JSON documents holding an array of objects, e.g. `[{"id": 1}, {"id": 2}]`.
+///
+/// Arrays cannot be split, so each file is read whole by a single partition; files holding
+/// JSON lines are read too. Results are written as JSON lines, like [`JsonFormat`].
+#[derive(Debug, Default)]
+pub struct SaJsonArrayFormat {
+    options: JsonOptions,
+}
+
+
+impl SaJsonArrayFormat {
+    pub fn new(options: JsonOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn options(&self) -> &JsonOptions {
+        &self.options
+    }
+
+    /// Reads `max_rec` records to infer the schema.
+    pub fn with_schema_infer_max_rec(mut self, max_rec: usize) -> Self {
+        self.options.schema_infer_max_rec = Some(max_rec);
+        self
+    }
+
+    fn get_compression_type(&self) -> FileCompressionType {
+        FileCompressionType::from(self.options.compression)
+    }
+
+    fn get_json_format(&self) -> JsonFormat {
+        JsonFormat::default().with_options(self.options.clone())
+    }
+}
+
+
+impl From<&JsonFormat> for SaJsonArrayFormat {
+    fn from(json_format: &JsonFormat) -> Self {
+        Self::new(json_format.options().clone())
+    }
+}
+
+
+#[async_trait]
+impl FileFormat for SaJsonArrayFormat {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn get_ext(&self) -> String {
+        self.get_json_format().get_ext()
+    }
+
+    fn get_ext_with_compression(&self, file_compression_type: &FileCompressionType) -> Result<String> {
+        self.get_json_format().get_ext_with_compression(file_compression_type)
+    }
+
+    async fn infer_schema(
+        &self,
+        _state: &SessionState,
+        store: &Arc<dyn ObjectStore>,
+        objects: &[ObjectMeta],
+    ) -> Result<SchemaRef> {
+        let mut records_to_read: usize = self.options.schema_infer_max_rec.unwrap_or(DEFAULT_SCHEMA_INFER_MAX_RECORD);
+        let mut schemas: Vec<Schema> = Vec::new();
+        for object in objects {
+            let lines: Vec<u8> = read_json_lines(store, &object.location, self.get_compression_type()).await?;
+            let (schema, records_read) = infer_json_schema(BufReader::new(Cursor::new(lines)), Some(records_to_read))?;
+            schemas.push(schema);
+            records_to_read = records_to_read.saturating_sub(records_read);
+            if records_to_read == 0 {
+                break;
+            }
+        }
+        Ok(Arc::new(Schema::try_merge(schemas)?))
+    }
+
+    async fn infer_stats(
+        &self,
+        _state: &SessionState,
+        _store: &Arc<dyn ObjectStore>,
+        table_schema: SchemaRef,
+        _object: &ObjectMeta,
+    ) -> Result<Statistics> {
+        Ok(Statistics::new_unknown(&table_schema))
+    }
+
+    async fn create_physical_plan(
+        &self,
+        _state: &SessionState,
+        conf: FileScanConfig,
+        _filters: Option<&Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(SaJsonArrayExec::new(conf, self.get_compression_type())))
+    }
+
+    async fn create_writer_physical_plan(
+        &self,
+        input: Arc<dyn ExecutionPlan>,
+        state: &SessionState,
+        conf: FileSinkConfig,
+        order_requirements: Option<LexRequirement>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        self.get_json_format()
+            .create_writer_physical_plan(input, state, conf, order_requirements)
+            .await
+    }
+}
+
+
+/// Scan of JSON array documents, one file after the other in each file group.
+#[derive(Debug, Clone)]
+pub struct SaJsonArrayExec {
+    base_config: FileScanConfig,
+    projected_statistics: Statistics,
+    metrics: ExecutionPlanMetricsSet,
+    file_compression_type: FileCompressionType,
+    properties: PlanProperties,
+}
+
+
+impl SaJsonArrayExec {
+    pub fn new(base_config: FileScanConfig, file_compression_type: FileCompressionType) -> Self {
+        let (projected_schema, projected_statistics, projected_orderings): (SchemaRef, Statistics, Vec<LexOrdering>) = base_config.project();
+        let properties: PlanProperties = PlanProperties::new(
+            EquivalenceProperties::new_with_orderings(projected_schema, &projected_orderings),
+            Partitioning::UnknownPartitioning(base_config.file_groups.len()),
+            EmissionType::Incremental,
+            Boundedness::Bounded,
+        );
+        Self {
+            base_config,
+            projected_statistics,
+            metrics: ExecutionPlanMetricsSet::new(),
+            file_compression_type,
+            properties,
+        }
+    }
+}
+
+
+impl DisplayAs for SaJsonArrayExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SaJsonArrayExec: ")?;
+        self.base_config.fmt_as(t, f)
+    }
+}
+
+
+impl ExecutionPlan for SaJsonArrayExec {
+    fn name(&self) -> &'static str {
+        "SaJsonArrayExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        Vec::new()
+    }
+
+    fn with_new_children(self: Arc<Self>, _: Vec<Arc<dyn ExecutionPlan>>) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(&self, partition: usize, context: Arc<TaskContext>) -> Result<SendableRecordBatchStream> {
+        let opener: SaJsonArrayOpener = SaJsonArrayOpener {
+            batch_size: context.session_config().batch_size(),
+            projected_schema: get_projected_file_schema(&self.base_config),
+            file_compression_type: self.file_compression_type,
+            object_store: context.runtime_env().object_store(&self.base_config.object_store_url)?,
+        };
+        let file_stream: FileStream<SaJsonArrayOpener> = FileStream::new(&self.base_config, partition, opener, &self.metrics)?;
+        Ok(Box::pin(file_stream))
+    }
+
+    fn statistics(&self) -> Result<Statistics> {
+        Ok(self.projected_statistics.clone())
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+}
+
+
+/// Schema of the projected columns read from the files, without the partition columns.
+fn get_projected_file_schema(base_config: &FileScanConfig) -> SchemaRef {
+    let file_schema: &SchemaRef = &base_config.file_schema;
+    let Some(projection) = &base_config.projection else {
+        return file_schema.clone();
+    };
+    let fields: Vec<Field> = projection
+        .iter()
+        .filter(|index| **index < file_schema.fields().len())
+        .map(|index| file_schema.field(*index).clone())
+        .collect();
+    Arc::new(Schema::new_with_metadata(fields, file_schema.metadata().clone()))
+}
+
+
+/// Opens a JSON array document as a stream of batches of `projected_schema`.
+struct SaJsonArrayOpener {
+    batch_size: usize,
+    projected_schema: SchemaRef,
+    file_compression_type: FileCompressionType,
+    object_store: Arc<dyn ObjectStore>,
+}
+
+
+impl FileOpener for SaJsonArrayOpener {
+    fn open(&self, file_meta: FileMeta) -> Result<FileOpenFuture> {
+        let object_store: Arc<dyn ObjectStore> = self.object_store.clone();
+        let projected_schema: SchemaRef = self.projected_schema.clone();
+        let batch_size: usize = self.batch_size;
+        let file_compression_type: FileCompressionType = self.file_compression_type;
+        Ok(Box::pin(async move {
+            let lines: Vec<u8> = read_json_lines(&object_store, file_meta.location(), file_compression_type).await?;
+            let reader = ReaderBuilder::new(projected_schema)
+                .with_batch_size(batch_size)
+                .build(Cursor::new(lines))?;
+            Ok(stream::iter(reader).boxed())
+        }))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn to_lines(document: &str) -> crate::error::Result<String> {
+        json_array_to_lines(document.as_bytes()).map(|lines| String::from_utf8(lines).unwrap())
+    }
+
+
+    #[test]
+    fn test_json_array_to_lines() {
+        assert_eq!(to_lines("[{\"id\": 1}, {\"id\": 2}]").unwrap(), "{\"id\": 1}\n {\"id\": 2}");
+        // Brackets and commas of nested values and strings are kept
+        assert_eq!(
+            to_lines("\u{feff}\n  [{\"tags\": [1, 2], \"addr\": {\"city\": \"a]b,\\\"c\"}}]\n").unwrap(),
+            "{\"tags\": [1, 2], \"addr\": {\"city\": \"a]b,\\\"c\"}}"
+        );
+        assert_eq!(to_lines("[]").unwrap(), "");
+        // JSON lines are given back as they are
+        assert_eq!(to_lines("{\"id\": 1}\n{\"id\": 2}\n").unwrap(), "{\"id\": 1}\n{\"id\": 2}\n");
+
+        assert!(to_lines("[{\"id\": 1}").unwrap_err().to_string().contains("Unterminated JSON array"));
+        assert!(to_lines("[{\"id\": 1}] {}").unwrap_err().to_string().contains("Unexpected content after the JSON array"));
+    }
+
+
+    #[test]
+    fn test_schema_infer_max_rec() {
+        let json_array_format: SaJsonArrayFormat = SaJsonArrayFormat::new(JsonOptions::default());
+        assert_eq!(json_array_format.options().schema_infer_max_rec, None);
+        let json_array_format: SaJsonArrayFormat = json_array_format.with_schema_infer_max_rec(10);
+        assert_eq!(json_array_format.options().schema_infer_max_rec, Some(10));
+        assert_eq!(json_array_format.get_ext(), "json");
+    }
+}
@@ -0,0 +1,5 @@
+pub mod compression;
+pub mod json;
+pub mod registry;
+pub use json::{SaJsonArrayFormat, SaJsonArrayFormatFactory};
+pub use registry::{SaFormatRegistry, SaFormatSpec};
\ No newline at end of file
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use datafusion::datasource::file_format::{
+    FileFormat,
+    FileFormatFactory,
+    arrow::ArrowFormatFactory,
+    avro::AvroFormatFactory,
+    csv::CsvFormatFactory,
+    json::JsonFormatFactory,
+    parquet::ParquetFormatFactory
+};
+use datafusion::datasource::listing::ListingTableUrl;
+use datafusion::execution::SessionState;
+use object_store::{
+    Attribute,
+    GetOptions,
+    GetRange,
+    GetResult,
+    ObjectMeta,
+    ObjectStore
+};
+use object_store::path::Path;
+use crate::error::{Result, SaError};
+use crate::format::compression;
+use crate::format::json::SaJsonArrayFormatFactory;
+
+
+/// Bytes expected at `offset` of a file of a given format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaMagicBytes {
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+}
+
+
+impl SaMagicBytes {
+    pub fn new(offset: usize, bytes: &[u8]) -> Self {
+        Self {
+            offset,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    pub fn matches(&self, head: &[u8]) -> bool {
+        head.get(self.offset..self.offset + self.bytes.len()) == Some(self.bytes.as_slice())
+    }
+}
+
+
+/// Reads format options, e.g. `format.delimiter`, from the first bytes of a file.
+pub type SaSniffOptions = fn(&[u8]) -> HashMap<String, String>;
+
+
+/// Everything the registry knows about a file format.
+#[derive(Debug, Clone)]
+pub struct SaFormatSpec {
+    /// Name used for explicit overrides, e.g. `parquet`.
+    pub name: String,
+    /// Extensions without the leading dot, matched case-insensitively.
+    pub extensions: Vec<String>,
+    pub mime_types: Vec<String>,
+    pub magic_bytes: Vec<SaMagicBytes>,
+    /// Heuristic for formats without magic bytes, run on the first bytes of the file.
+    pub sniff: Option<fn(&[u8]) -> bool>,
+    /// Format options read from the first bytes of a file whose format was not named, e.g. the CSV delimiter.
+    pub sniff_options: Option<SaSniffOptions>,
+    /// `None` when the format can be detected but cannot be read yet.
+    pub factory: Option<Arc<dyn FileFormatFactory>>,
+}
+
+
+impl SaFormatSpec {
+    pub fn new(name: &str, factory: Option<Arc<dyn FileFormatFactory>>) -> Self {
+        Self {
+            name: name.to_string(),
+            extensions: Vec::new(),
+            mime_types: Vec::new(),
+            magic_bytes: Vec::new(),
+            sniff: None,
+            sniff_options: None,
+            factory,
+        }
+    }
+
+    pub fn with_extensions(mut self, extensions: &[&str]) -> Self {
+        self.extensions = extensions.iter().map(|ext| ext.to_ascii_lowercase()).collect();
+        self
+    }
+
+    pub fn with_mime_types(mut self, mime_types: &[&str]) -> Self {
+        self.mime_types = mime_types.iter().map(|mime| mime.to_ascii_lowercase()).collect();
+        self
+    }
+
+    pub fn with_magic_bytes(mut self, offset: usize, bytes: &[u8]) -> Self {
+        self.magic_bytes.push(SaMagicBytes::new(offset, bytes));
+        self
+    }
+
+    pub fn with_sniff(mut self, sniff: fn(&[u8]) -> bool) -> Self {
+        self.sniff = Some(sniff);
+        self
+    }
+
+    pub fn with_sniff_options(mut self, sniff_options: SaSniffOptions) -> Self {
+        self.sniff_options = Some(sniff_options);
+        self
+    }
+
+    fn matches_head(&self, head: &[u8]) -> bool {
+        self.magic_bytes.iter().any(|magic| magic.matches(head))
+    }
+}
+
+
+/// Maps extensions, MIME types and magic bytes to DataFusion [`FileFormat`]s.
+#[derive(Debug, Clone)]
+pub struct SaFormatRegistry {
+    formats: Vec<SaFormatSpec>,
+}
+
+
+impl Default for SaFormatRegistry {
+    fn default() -> Self {
+        let mut registry: SaFormatRegistry = Self::empty();
+        registry.register(
+            SaFormatSpec::new("parquet", Some(Arc::new(ParquetFormatFactory::new())))
+                .with_extensions(&["parquet", "parq", "pqt"])
+                .with_mime_types(&["application/vnd.apache.parquet", "application/x-parquet"])
+                .with_magic_bytes(0, b"PAR1")
+        );
+        registry.register(
+            SaFormatSpec::new("arrow", Some(Arc::new(ArrowFormatFactory::new())))
+                .with_extensions(&["arrow", "arrows", "feather", "ipc"])
+                .with_mime_types(&["application/vnd.apache.arrow.file", "application/vnd.apache.arrow.stream"])
+                .with_magic_bytes(0, b"ARROW1")
+        );
+        registry.register(
+            SaFormatSpec::new("avro", Some(Arc::new(AvroFormatFactory::new())))
+                .with_extensions(&["avro"])
+                .with_mime_types(&["application/avro", "avro/binary"])
+                .with_magic_bytes(0, b"Obj\x01")
+        );
+        registry.register(
+            SaFormatSpec::new("orc", None)
+                .with_extensions(&["orc"])
+                .with_mime_types(&["application/vnd.apache.orc"])
+                .with_magic_bytes(0, b"ORC")
+        );
+        registry.register(
+            SaFormatSpec::new("csv", Some(Arc::new(CsvFormatFactory::new())))
+                .with_extensions(&["csv"])
+                .with_mime_types(&["text/csv", "application/csv"])
+                .with_sniff(is_delimited_text)
+                .with_sniff_options(get_delimited_text_options)
+        );
+        registry.register(
+            SaFormatSpec::new("json", Some(Arc::new(JsonFormatFactory::new())))
+                .with_extensions(&["json", "jsonl", "ndjson"])
+                .with_mime_types(&["application/json", "application/x-ndjson", "application/jsonl"])
+                .with_sniff(is_json_lines)
+        );
+        registry.register(
+            SaFormatSpec::new("json_array", Some(Arc::new(SaJsonArrayFormatFactory::new())))
+                .with_sniff(is_json_array)
+        );
+        registry
+    }
+}
+
+
+impl SaFormatRegistry {
+    /// Number of bytes read from a source when its format has to be sniffed.
+    pub const SNIFF_LENGTH: usize = 64;
+
+    /// Number of bytes read from a compressed source to decompress its first bytes.
+    pub const COMPRESSED_SNIFF_LENGTH: usize = 1 << 20;
+
+    /// Registry with the built-in formats.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            formats: Vec::new(),
+        }
+    }
+
+    /// Registers `spec`, replacing a format with the same name. Formats registered
+    /// later take precedence when several of them match.
+    pub fn register(&mut self, spec: SaFormatSpec) {
+        self.formats.retain(|format| format.name != spec.name);
+        self.formats.push(spec);
+    }
+
+    pub fn deregister(&mut self, name: &str) -> Option<SaFormatSpec> {
+        let index: usize = self.formats.iter().position(|format| format.name == name)?;
+        Some(self.formats.remove(index))
+    }
+
+    /// Names of the formats that can be read, sorted.
+    pub fn get_format_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.formats
+            .iter()
+            .filter(|format| format.factory.is_some())
+            .map(|format| format.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Finds a format by name or by one of its extensions.
+    pub fn get_spec(&self, name: &str) -> Option<&SaFormatSpec> {
+        let name: String = name.trim_start_matches('.').to_ascii_lowercase();
+        self.formats
+            .iter()
+            .rev()
+            .find(|format| format.name == name || format.extensions.contains(&name))
+    }
+
+    /// Finds the format whose extension matches the end of `uri`, preferring the longest one.
+    pub fn get_spec_by_extension(&self, uri: &str) -> Option<&SaFormatSpec> {
+        let file_name: String = uri
+            .split(['?', '#'])
+            .next()
+            .unwrap_or(uri)
+            .rsplit('/')
+            .next()
+            .unwrap_or(uri)
+            .to_ascii_lowercase();
+
+        self.formats
+            .iter()
+            .rev()
+            .flat_map(|format| format.extensions.iter().map(move |ext| (format, ext)))
+            .filter(|(_, ext)| file_name.ends_with(&format!(".{}", ext)))
+            .max_by_key(|(_, ext)| ext.len())
+            .map(|(format, _)| format)
+    }
+
+    pub fn get_spec_by_mime_type(&self, mime_type: &str) -> Option<&SaFormatSpec> {
+        // Drop parameters such as "; charset=utf-8"
+        let mime_type: String = mime_type
+            .split(';')
+            .next()
+            .unwrap_or(mime_type)
+            .trim()
+            .to_ascii_lowercase();
+        self.formats
+            .iter()
+            .rev()
+            .find(|format| format.mime_types.contains(&mime_type))
+    }
+
+    /// Finds the format matching the first bytes of a file, magic bytes first, then heuristics.
+    pub fn get_spec_by_content(&self, head: &[u8]) -> Option<&SaFormatSpec> {
+        self.formats
+            .iter()
+            .rev()
+            .find(|format| format.matches_head(head))
+            .or_else(|| {
+                self.formats
+                    .iter()
+                    .rev()
+                    .find(|format| format.sniff.is_some_and(|sniff| sniff(head)))
+            })
+    }
+
+    /// Builds the [`FileFormat`] for `spec`, configured with the session defaults overridden by
+    /// `format_options` and reading files compressed with `compression`, which only text formats support.
+    pub fn create_file_format(
+        &self,
+        spec: &SaFormatSpec,
+        uri: &str,
+        compression: FileCompressionType,
+        mut format_options: HashMap<String, String>,
+        state: &SessionState,
+    ) -> Result<Arc<dyn FileFormat>> {
+        let Some(factory) = &spec.factory else {
+            return Err(self.unsupported_format_error(uri, Some(&spec.name)));
+        };
+        if compression.is_compressed() {
+            if factory.default().get_ext_with_compression(&compression).is_err() {
+                let format: String = format!("{} ({})", spec.name, compression::get_compression_name(&compression));
+                return Err(self.unsupported_format_error(uri, Some(&format)));
+            }
+            format_options.insert("format.compression".to_string(), compression.get_variant().to_string());
+        }
+        Ok(factory.create(state, &format_options)?)
+    }
+
+    /// Factory of the format written to `uri`: `format_override` if set, its extension otherwise.
+    pub fn resolve_factory(&self, uri: &str, format_override: Option<&str>) -> Result<Arc<dyn FileFormatFactory>> {
+        let spec: Option<&SaFormatSpec> = match format_override {
+            Some(name) => self.get_spec(name),
+            None => self.get_spec_by_extension(uri),
+        };
+        let format_name: Option<&str> = spec.map(|spec| spec.name.as_str()).or(format_override);
+        spec.and_then(|spec| spec.factory.clone())
+            .ok_or_else(|| self.unsupported_format_error(uri, format_name))
+    }
+
+    /// Resolves the [`FileFormat`] of the source at `uri`.
+    ///
+    /// An explicit `format_override` wins, then the file extension, then the
+    /// MIME type and the first bytes of the object read from `object_store`.
+    ///
+    /// The compression is `compression_override` if set, else the last extension (e.g. `.gz` of
+    /// `data.csv.gz`), else the magic bytes of the object; the format of a compressed object is
+    /// then sniffed from its decompressed first bytes. A sniffed format also reads its options from
+    /// those bytes, e.g. the delimiter of CSV.
+    pub async fn resolve(
+        &self,
+        uri: &str,
+        format_override: Option<&str>,
+        compression_override: Option<&str>,
+        object_store: Arc<dyn ObjectStore>,
+        state: &SessionState,
+    ) -> Result<Arc<dyn FileFormat>> {
+        let (inner_uri, extension_compression) = compression::split_compression_extension(uri);
+        let mut compression: Option<FileCompressionType> = match compression_override {
+            Some(name) => Some(compression::parse_compression(name)?),
+            None => extension_compression,
+        };
+        let mut spec: Option<&SaFormatSpec> = match format_override {
+            Some(name) => Some(self.get_spec(name).ok_or_else(|| self.unsupported_format_error(uri, Some(name)))?),
+            None => self.get_spec_by_extension(inner_uri),
+        };
+
+        let location: Path = ListingTableUrl::parse(uri)?.prefix().clone();
+        let mut format_options: HashMap<String, String> = HashMap::new();
+        if spec.is_none() {
+            let (mime_type, mut head) = Self::read_head(object_store.clone(), &location, Self::SNIFF_LENGTH).await?;
+            compression = compression.or_else(|| compression::get_compression_by_content(&head));
+            spec = match compression.filter(FileCompressionType::is_compressed) {
+                Some(compression) => {
+                    let (_, compressed_head) = Self::read_head(object_store, &location, Self::COMPRESSED_SNIFF_LENGTH).await?;
+                    head = compression::decompress_head(&compressed_head, compression, Self::SNIFF_LENGTH);
+                    self.get_spec_by_content(&head)
+                },
+                None => mime_type
+                    .as_deref()
+                    .and_then(|mime_type| self.get_spec_by_mime_type(mime_type))
+                    .or_else(|| self.get_spec_by_content(&head)),
+            };
+            if let Some(sniff_options) = spec.and_then(|spec| spec.sniff_options) {
+                format_options = sniff_options(&head);
+            }
+        } else if format_override.is_some() && compression.is_none() {
+            // Directories and globs have no first bytes, they are read uncompressed
+            if let Ok((_, head)) = Self::read_head(object_store, &location, Self::SNIFF_LENGTH).await {
+                compression = compression::get_compression_by_content(&head);
+            }
+        }
+
+        match spec {
+            Some(spec) => {
+                let compression: FileCompressionType = compression.unwrap_or(FileCompressionType::UNCOMPRESSED);
+                self.create_file_format(spec, uri, compression, format_options, state)
+            },
+            None => Err(self.unsupported_format_error(uri, None)),
+        }
+    }
+
+    /// Reads the content type and the first `length` bytes at `location`.
+    async fn read_head(object_store: Arc<dyn ObjectStore>, location: &Path, length: usize) -> Result<(Option<String>, Vec<u8>)> {
+        let meta: ObjectMeta = object_store.head(location).await?;
+        let length: usize = meta.size.min(length);
+        let options: GetOptions = GetOptions {
+            range: Some(GetRange::Bounded(0..length)),
+            ..Default::default()
+        };
+        let result: GetResult = object_store.get_opts(location, options).await?;
+        let mime_type: Option<String> = result.attributes
+            .get(&Attribute::ContentType)
+            .map(|value| value.to_string());
+        let head: Vec<u8> = result.bytes().await?.to_vec();
+        Ok((mime_type, head))
+    }
+
+    fn unsupported_format_error(&self, uri: &str, format: Option<&str>) -> SaError {
+        SaError::UnsupportedFormat {
+            uri: uri.to_string(),
+            format: format.map(|format| format.to_string()),
+            registered_formats: self.get_format_names(),
+        }
+    }
+}
+
+
+/// Newline-delimited JSON starts with an object.
+fn is_json_lines(head: &[u8]) -> bool {
+    head.iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|byte| *byte == b'{')
+}
+
+
+/// A JSON document holding an array of objects.
+fn is_json_array(head: &[u8]) -> bool {
+    let mut bytes = head.iter().filter(|byte| !byte.is_ascii_whitespace());
+    bytes.next() == Some(&b'[') && bytes.next().is_none_or(|byte| *byte == b'{')
+}
+
+
+/// Text whose first line contains a delimiter.
+fn is_delimited_text(head: &[u8]) -> bool {
+    get_delimiter(head).is_some()
+}
+
+
+/// Sets the delimiter of text that is not comma-separated.
+fn get_delimited_text_options(head: &[u8]) -> HashMap<String, String> {
+    match get_delimiter(head) {
+        Some(delimiter) if delimiter != ',' => HashMap::from([
+            ("format.delimiter".to_string(), delimiter.to_string()),
+        ]),
+        _ => HashMap::new(),
+    }
+}
+
+
+/// The delimiter found most often on the first line, commas first on a tie.
+fn get_delimiter(head: &[u8]) -> Option<char> {
+    // A multi-byte character may be cut at the end of the sniffed range
+    let text: &str = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).unwrap_or_default(),
+        Err(_) => return None,
+    };
+    if text.contains('\0') {
+        return None;
+    }
+    let first_line: &str = text.lines().next().unwrap_or_default();
+    [',', '\t', ';', '|']
+        .into_iter()
+        .map(|delimiter| (delimiter, first_line.matches(delimiter).count()))
+        .filter(|(_, count)| *count > 0)
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(delimiter, _)| delimiter)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_get_delimiter() {
+        assert_eq!(get_delimiter(b"id,name,score\n1,ada,9.5\n"), Some(','));
+        assert_eq!(get_delimiter(b"id\tname\tscore\n1\tada\t9.5\n"), Some('\t'));
+        assert_eq!(get_delimiter(b"id;name;note\n1;ada;a,b\n"), Some(';'));
+        assert_eq!(get_delimiter(b"id|name\n1|ada\n"), Some('|'));
+        assert_eq!(get_delimiter(b"a,b;c\n"), Some(','));
+        assert_eq!(get_delimiter(b"plain text\n"), None);
+        assert_eq!(get_delimiter(b"a,b\0"), None);
+    }
+
+
+    #[test]
+    fn test_delimited_text_options() {
+        let registry: SaFormatRegistry = SaFormatRegistry::new();
+        let head: &[u8] = b"id\tname\n1\tada\n";
+        let spec: &SaFormatSpec = registry.get_spec_by_content(head).unwrap();
+        assert_eq!(spec.name, "csv");
+        let options: HashMap<String, String> = spec.sniff_options.unwrap()(head);
+        assert_eq!(options.get("format.delimiter").map(String::as_str), Some("\t"));
+        assert!(spec.sniff_options.unwrap()(b"id,name\n").is_empty());
+    }
+}
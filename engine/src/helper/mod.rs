@@ -1,2 +1,2 @@
 pub mod sql;
-pub use sql::sql_parser;
\ No newline at end of file
+pub use sql::{sql_parser, parse_statements, get_table_references, uri_scheme, SaTableReference, SaTableAccess, SaTableVersion};
\ No newline at end of file
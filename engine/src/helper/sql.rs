@@ -1,10 +1,467 @@
-use regex::Regex;
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+use datafusion::sql::parser::{
+    CopyToSource,
+    DFParser,
+    Statement as DFStatement
+};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::keywords::Keyword;
+use datafusion::sql::sqlparser::parser::{
+    Parser,
+    ParserError
+};
+use datafusion::sql::sqlparser::tokenizer::{
+    Token,
+    TokenWithSpan,
+    Tokenizer
+};
+use crate::error::{Result, SaError};
+use datafusion::sql::sqlparser::ast::{
+    Ident,
+    ObjectName,
+    Query,
+    Statement,
+    TableFactor,
+    Visit,
+    Visitor
+};
 
-pub fn sql_parser(stm: &str) -> Vec<&str> {
-    let pattern: &str = r#""(s3://[^"]+|file://[^"]+)""#;
 
-    let re = Regex::new(pattern).unwrap();
-    re.captures_iter(stm)
-        .filter_map(|cap| cap.get(1).map(|m| m.as_str()))
-        .collect()
-}
\ No newline at end of file
+/// How a statement uses a table reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaTableAccess {
+    /// The reference is read from (`FROM`, `JOIN`, `COPY <table> TO`, ...).
+    Read,
+    /// The reference is written to (`INSERT INTO`, `COPY ... TO '<uri>'`).
+    Write,
+}
+
+
+/// A storage URI referenced as a table inside a SQL statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaTableReference {
+    /// Full URI as written in the query, e.g. `s3://bucket/key/file.csv`.
+    pub uri: String,
+    /// URI scheme without `://`, e.g. `s3` or `file`.
+    pub scheme: String,
+    /// Alias given to the reference (`... AS st`), if any.
+    pub alias: Option<String>,
+    /// 1-based line of the reference in the statement (0 when unknown).
+    pub line: u64,
+    /// 1-based column of the reference in the statement (0 when unknown).
+    pub column: u64,
+    pub access: SaTableAccess,
+}
+
+
+impl SaTableReference {
+    fn new(uri: &str, scheme: &str, alias: Option<String>, line: u64, column: u64, access: SaTableAccess) -> Self {
+        Self {
+            uri: uri.to_string(),
+            scheme: scheme.to_string(),
+            alias,
+            line,
+            column,
+            access,
+        }
+    }
+
+    fn from_ident(ident: &Ident, alias: Option<String>, access: SaTableAccess) -> Option<Self> {
+        let scheme: &str = uri_scheme(&ident.value)?;
+        Some(Self::new(&ident.value, scheme, alias, ident.span.start.line, ident.span.start.column, access))
+    }
+
+    fn from_object_name(name: &ObjectName, alias: Option<String>, access: SaTableAccess) -> Option<Self> {
+        // A URI is always written as a single quoted identifier: "s3://bucket/key/file.csv"
+        match name.0.as_slice() {
+            [ident] => Self::from_ident(ident, alias, access),
+            _ => None,
+        }
+    }
+}
+
+
+/// Version of a table read with time travel: `"<uri>" FOR VERSION AS OF <id>` or
+/// `"<uri>" FOR TIMESTAMP AS OF '<timestamp>'`.
+///
+/// The version is kept at the end of the URI (`<uri>#version=<id>`, `<uri>#timestamp=<timestamp>`),
+/// so each version of a table is registered under its own name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaTableVersion {
+    /// Snapshot id or version number.
+    Version(i64),
+    /// Timestamp such as `2024-01-31 12:00:00` or `2024-01-31T12:00:00Z`.
+    Timestamp(String),
+}
+
+
+impl SaTableVersion {
+    const VERSION_SUFFIX: &'static str = "#version=";
+    const TIMESTAMP_SUFFIX: &'static str = "#timestamp=";
+
+    /// Splits the version suffix off `uri`, if any.
+    pub fn split_uri(uri: &str) -> Result<(&str, Option<Self>)> {
+        if let Some((base_uri, version)) = uri.rsplit_once(Self::VERSION_SUFFIX) {
+            let version: i64 = version.parse().map_err(|_| SaError::uri_parse(uri, "expected an integer version"))?;
+            return Ok((base_uri, Some(SaTableVersion::Version(version))));
+        }
+        if let Some((base_uri, timestamp)) = uri.rsplit_once(Self::TIMESTAMP_SUFFIX) {
+            return Ok((base_uri, Some(SaTableVersion::Timestamp(timestamp.to_string()))));
+        }
+        Ok((uri, None))
+    }
+
+    pub fn to_uri_suffix(&self) -> String {
+        match self {
+            SaTableVersion::Version(version) => format!("{}{}", Self::VERSION_SUFFIX, version),
+            SaTableVersion::Timestamp(timestamp) => format!("{}{}", Self::TIMESTAMP_SUFFIX, timestamp),
+        }
+    }
+}
+
+
+/// Returns the scheme of `value` if it looks like a storage URI (`<scheme>://...`).
+pub fn uri_scheme(value: &str) -> Option<&str> {
+    let (scheme, rest) = value.split_once("://")?;
+    let is_valid_scheme: bool = !scheme.is_empty()
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if is_valid_scheme && !rest.is_empty() {
+        Some(scheme)
+    } else {
+        None
+    }
+}
+
+
+#[derive(Default)]
+struct SaTableReferenceVisitor {
+    references: Vec<SaTableReference>,
+    cte_names: HashSet<String>,
+}
+
+
+impl SaTableReferenceVisitor {
+    fn push(&mut self, reference: Option<SaTableReference>) {
+        if let Some(reference) = reference {
+            if !self.cte_names.contains(&reference.uri) {
+                self.references.push(reference);
+            }
+        }
+    }
+
+    fn visit_df_statement(&mut self, statement: &DFStatement) {
+        match statement {
+            DFStatement::Statement(statement) => {
+                let _ = statement.visit(self);
+            },
+            DFStatement::CopyTo(copy_to) => {
+                match &copy_to.source {
+                    CopyToSource::Relation(name) => {
+                        self.push(SaTableReference::from_object_name(name, None, SaTableAccess::Read));
+                    },
+                    CopyToSource::Query(query) => {
+                        let _ = query.visit(self);
+                    },
+                }
+                if let Some(scheme) = uri_scheme(&copy_to.target) {
+                    // The target is a string literal, sqlparser does not keep its location
+                    self.push(Some(SaTableReference::new(&copy_to.target, scheme, None, 0, 0, SaTableAccess::Write)));
+                }
+            },
+            DFStatement::Explain(explain) => self.visit_df_statement(&explain.statement),
+            // `CREATE EXTERNAL TABLE` declares its own location, nothing to register
+            DFStatement::CreateExternalTable(_) => {},
+        }
+    }
+}
+
+
+impl Visitor for SaTableReferenceVisitor {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                self.cte_names.insert(cte.alias.name.value.clone());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, table_factor: &TableFactor) -> ControlFlow<Self::Break> {
+        if let TableFactor::Table { name, alias, .. } = table_factor {
+            let alias: Option<String> = alias.as_ref().map(|alias| alias.name.value.clone());
+            self.push(SaTableReference::from_object_name(name, alias, SaTableAccess::Read));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<Self::Break> {
+        if let Statement::Insert(insert) = statement {
+            let alias: Option<String> = insert.table_alias.as_ref().map(|alias| alias.value.clone());
+            self.push(SaTableReference::from_object_name(&insert.table_name, alias, SaTableAccess::Write));
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+
+/// Folds the time travel clause following a URI into the URI, see [`SaTableVersion`]: the
+/// SQL dialect has no such clause.
+fn fold_table_versions(tokens: Vec<TokenWithSpan>) -> Vec<TokenWithSpan> {
+    let mut folded: Vec<TokenWithSpan> = Vec::with_capacity(tokens.len());
+    let mut index: usize = 0;
+    while index < tokens.len() {
+        folded.push(tokens[index].clone());
+        index += 1;
+        let Some(Token::Word(word)) = folded.last().map(|token| &token.token) else {
+            continue;
+        };
+        if word.quote_style.is_none() || uri_scheme(&word.value).is_none() {
+            continue;
+        }
+
+        let clause: Vec<(usize, &Token)> = tokens[index..]
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| !matches!(token.token, Token::Whitespace(_)))
+            .take(5)
+            .map(|(offset, token)| (index + offset, &token.token))
+            .collect();
+        let keywords: Vec<Keyword> = clause
+            .iter()
+            .take(4)
+            .map(|(_, token)| match token {
+                Token::Word(word) if word.quote_style.is_none() => word.keyword,
+                _ => Keyword::NoKeyword,
+            })
+            .collect();
+        let version: Option<SaTableVersion> = match (keywords.as_slice(), clause.get(4)) {
+            ([Keyword::FOR, Keyword::VERSION, Keyword::AS, Keyword::OF], Some((_, Token::Number(version, _)))) => {
+                version.parse().ok().map(SaTableVersion::Version)
+            },
+            (
+                [Keyword::FOR, Keyword::TIMESTAMP | Keyword::SYSTEM_TIME, Keyword::AS, Keyword::OF],
+                Some((_, Token::SingleQuotedString(timestamp)))
+            ) => Some(SaTableVersion::Timestamp(timestamp.clone())),
+            _ => None,
+        };
+        if let (Some(version), Some(TokenWithSpan { token: Token::Word(word), .. })) = (version, folded.last_mut()) {
+            word.value.push_str(&version.to_uri_suffix());
+            index = clause[4].0 + 1;
+        }
+    }
+    folded
+}
+
+
+/// Parses `stm` into DataFusion statements, keeping token locations so that
+/// identifiers carry their line/column (`DFParser::parse_sql` drops them).
+///
+/// Time travel clauses are folded into the URI they follow, see [`SaTableVersion`].
+pub fn parse_statements(stm: &str) -> Result<Vec<DFStatement>> {
+    let dialect: GenericDialect = GenericDialect {};
+    let tokens = Tokenizer::new(&dialect, stm).tokenize_with_location().map_err(ParserError::from)?;
+    let tokens: Vec<TokenWithSpan> = fold_table_versions(tokens);
+    let mut parser: DFParser = DFParser {
+        parser: Parser::new(&dialect).with_tokens_with_locations(tokens),
+    };
+
+    let mut statements: Vec<DFStatement> = Vec::new();
+    let mut expecting_statement_delimiter: bool = false;
+    loop {
+        // Ignore empty statements between successive delimiters
+        while parser.parser.consume_token(&Token::SemiColon) {
+            expecting_statement_delimiter = false;
+        }
+        if parser.parser.peek_token() == Token::EOF {
+            break;
+        }
+        if expecting_statement_delimiter {
+            let found = parser.parser.peek_token();
+            return Err(ParserError::ParserError(format!("Expected end of statement, found: {found}")).into());
+        }
+        statements.push(parser.parse_statement()?);
+        expecting_statement_delimiter = true;
+    }
+    Ok(statements)
+}
+
+
+/// Parses `stm` and returns every storage URI used as a table, in query order.
+///
+/// References are resolved from the SQL AST, so URIs inside string literals or
+/// comments are ignored while CTEs, subqueries, joins, set operations,
+/// `INSERT` targets and `COPY` sources/targets are all covered.
+pub fn sql_parser(stm: &str) -> Result<Vec<SaTableReference>> {
+    let statements: Vec<DFStatement> = parse_statements(stm)?;
+
+    let mut visitor: SaTableReferenceVisitor = SaTableReferenceVisitor::default();
+    for statement in statements.iter() {
+        visitor.visit_df_statement(statement);
+    }
+    Ok(visitor.references)
+}
+
+
+/// Storage URIs used as tables by a single parsed statement, in query order.
+pub fn get_table_references(statement: &DFStatement) -> Vec<SaTableReference> {
+    let mut visitor: SaTableReferenceVisitor = SaTableReferenceVisitor::default();
+    visitor.visit_df_statement(statement);
+    visitor.references
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// URI, alias, line and column of the references of `stm`.
+    fn get_references(stm: &str) -> Vec<(String, Option<String>, u64, u64)> {
+        sql_parser(stm)
+            .unwrap()
+            .into_iter()
+            .map(|reference| (reference.uri, reference.alias, reference.line, reference.column))
+            .collect()
+    }
+
+
+    fn get_uris(stm: &str) -> Vec<String> {
+        sql_parser(stm).unwrap().into_iter().map(|reference| reference.uri).collect()
+    }
+
+
+    #[test]
+    fn test_literals_and_comments() {
+        let stm: &str = r#"
+            -- SELECT * FROM "s3://bucket/commented.csv"
+            SELECT 's3://bucket/literal.csv' AS uri, "s3://bucket/read.csv".id
+            /* JOIN "file:///tmp/commented.csv" */
+            FROM "s3://bucket/read.csv"
+            WHERE name LIKE 'file:///tmp/%'
+        "#;
+        assert_eq!(get_uris(stm), ["s3://bucket/read.csv"]);
+    }
+
+
+    #[test]
+    fn test_quoted_and_unquoted_names() {
+        // Only quoted identifiers can hold a URI, table names and quoted names without a scheme are not references
+        let stm: &str = r#"SELECT * FROM school.scores AS s
+            JOIN "students" AS st ON st.id = s.student_id
+            JOIN `gs://bucket/grades.parquet` AS g ON g.id = s.id
+            JOIN "az://container/notes.json" ON true"#;
+        assert_eq!(
+            sql_parser(stm).unwrap(),
+            [
+                SaTableReference::new("gs://bucket/grades.parquet", "gs", Some("g".to_string()), 3, 18, SaTableAccess::Read),
+                SaTableReference::new("az://container/notes.json", "az", None, 4, 18, SaTableAccess::Read),
+            ]
+        );
+    }
+
+
+    #[test]
+    fn test_joins_subqueries_and_ctes() {
+        let stm: &str = r#"
+            WITH recent AS (SELECT * FROM "s3://bucket/events/" WHERE day > '2024-01-01')
+            SELECT * FROM recent
+            JOIN (SELECT id FROM "file:///data/users.csv") AS u ON u.id = recent.user_id
+            LEFT JOIN "https://example.com/countries.parquet" AS c ON c.code = u.country
+            WHERE recent.id IN (SELECT id FROM "gs://bucket/flagged.csv")
+            UNION ALL SELECT * FROM "s3://bucket/archive.csv"
+        "#;
+        assert_eq!(
+            get_uris(stm),
+            [
+                "s3://bucket/events/",
+                "file:///data/users.csv",
+                "https://example.com/countries.parquet",
+                "gs://bucket/flagged.csv",
+                "s3://bucket/archive.csv",
+            ]
+        );
+        // A CTE named like a URI is not a source
+        let stm: &str = r#"WITH "s3://bucket/cte" AS (SELECT 1 AS id) SELECT * FROM "s3://bucket/cte""#;
+        assert!(get_uris(stm).is_empty());
+    }
+
+
+    #[test]
+    fn test_aliases_and_positions() {
+        let stm: &str = "SELECT st.name, s.score\nFROM \"file:///data/scores.csv\" AS s\n  JOIN \"file:///data/students.csv\" st ON st.id = s.student_id";
+        assert_eq!(
+            get_references(stm),
+            [
+                ("file:///data/scores.csv".to_string(), Some("s".to_string()), 2, 6),
+                ("file:///data/students.csv".to_string(), Some("st".to_string()), 3, 8),
+            ]
+        );
+    }
+
+
+    #[test]
+    fn test_write_targets() {
+        let stm: &str = r#"INSERT INTO "s3://bucket/out.csv" SELECT * FROM "s3://bucket/in.csv""#;
+        let references: Vec<SaTableReference> = sql_parser(stm).unwrap();
+        let accesses: Vec<(&str, SaTableAccess)> = references.iter().map(|reference| (reference.uri.as_str(), reference.access)).collect();
+        assert_eq!(accesses, [("s3://bucket/out.csv", SaTableAccess::Write), ("s3://bucket/in.csv", SaTableAccess::Read)]);
+
+        let references: Vec<SaTableReference> = [
+            r#"COPY (SELECT * FROM "s3://bucket/in.csv") TO 'file:///tmp/out.parquet'"#,
+            r#"COPY "s3://bucket/other.csv" TO 'file:///tmp/other.csv'"#,
+        ]
+            .iter()
+            .flat_map(|stm| sql_parser(stm).unwrap())
+            .collect();
+        let accesses: Vec<(&str, SaTableAccess)> = references.iter().map(|reference| (reference.uri.as_str(), reference.access)).collect();
+        assert_eq!(
+            accesses,
+            [
+                ("s3://bucket/in.csv", SaTableAccess::Read),
+                ("file:///tmp/out.parquet", SaTableAccess::Write),
+                ("s3://bucket/other.csv", SaTableAccess::Read),
+                ("file:///tmp/other.csv", SaTableAccess::Write),
+            ]
+        );
+        // The target of `COPY` is a literal, whose position is unknown
+        assert_eq!((references[1].line, references[1].column), (0, 0));
+    }
+
+
+    #[test]
+    fn test_table_versions() {
+        let stm: &str = r#"SELECT * FROM "s3://bucket/table" FOR VERSION AS OF 42 AS t
+            JOIN "s3://bucket/other" for timestamp as of '2024-01-31 12:00:00' o ON o.id = t.id
+            JOIN "s3://bucket/third" FOR SYSTEM_TIME AS OF '2024-01-31T12:00:00Z' ON true"#;
+        assert_eq!(
+            get_references(stm),
+            [
+                ("s3://bucket/table#version=42".to_string(), Some("t".to_string()), 1, 15),
+                ("s3://bucket/other#timestamp=2024-01-31 12:00:00".to_string(), Some("o".to_string()), 2, 18),
+                ("s3://bucket/third#timestamp=2024-01-31T12:00:00Z".to_string(), None, 3, 18),
+            ]
+        );
+
+        // Clauses that do not follow a quoted URI, or are incomplete, are left to the parser
+        assert!(parse_statements(r#"SELECT * FROM t FOR VERSION AS OF 42"#).is_err());
+        assert!(parse_statements(r#"SELECT * FROM "s3://bucket/table" FOR VERSION AS OF 'latest'"#).is_err());
+
+        let (uri, version) = SaTableVersion::split_uri("s3://bucket/table#version=42").unwrap();
+        assert_eq!((uri, version), ("s3://bucket/table", Some(SaTableVersion::Version(42))));
+        assert!(SaTableVersion::split_uri("s3://bucket/table#version=latest").is_err());
+    }
+
+
+    #[test]
+    fn test_uri_scheme() {
+        assert_eq!(uri_scheme("s3://bucket/key"), Some("s3"));
+        assert_eq!(uri_scheme("git+ssh://host/repo"), Some("git+ssh"));
+        assert_eq!(uri_scheme("s3://"), None);
+        assert_eq!(uri_scheme("3s://bucket"), None);
+        assert_eq!(uri_scheme("school.scores"), None);
+    }
+}
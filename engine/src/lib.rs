@@ -1,4 +1,12 @@
 pub mod datafusion;
 pub mod object_storage;
 pub mod helper;
-pub mod builder;
\ No newline at end of file
+pub mod builder;
+pub mod format;
+pub mod table;
+pub mod catalog;
+pub mod cache;
+pub mod cli;
+pub mod server;
+pub mod error;
+pub use error::{SaError, Result};
\ No newline at end of file
@@ -0,0 +1,350 @@
+use std::any::Any;
+use std::sync::Arc;
+use async_trait::async_trait;
+use url::Url;
+use datafusion::catalog::Session;
+use datafusion::datasource::TableProvider;
+use datafusion::datasource::file_format::FileFormat;
+use datafusion::common::Result as DFResult;
+use datafusion::arrow::datatypes::{
+    Schema,
+    SchemaRef
+};
+use datafusion_expr::{
+    TableType,
+    Expr
+};
+use datafusion::physical_plan::ExecutionPlan;
+use object_store::azure::{AzureConfigKey, MicrosoftAzure, MicrosoftAzureBuilder};
+use object_store::ObjectStore;
+use crate::object_storage::schema::SaSchemaOptions;
+use crate::object_storage::storage::SaStorage;
+use crate::object_storage::registry::SaStorageFactory;
+use crate::datafusion::SaDataFusion;
+use crate::object_storage::utils;
+use crate::error::{Result, SaError};
+
+
+/// How [`SaAzure`] authenticates against Azure Blob Storage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SaAzureCredentials {
+    /// `AZURE_STORAGE_*` variables, Azure CLI or managed identity, as resolved by `object_store`.
+    /// `AZURE_STORAGE_USE_EMULATOR=true` switches to the emulator.
+    #[default]
+    Env,
+    /// Shared key of the storage account.
+    AccountKey(String),
+    /// SAS token, with or without the leading `?`.
+    SasToken(String),
+    /// Azurite or a compatible emulator, at `AZURITE_BLOB_STORAGE_URL` (default `http://127.0.0.1:10000`)
+    /// with the well-known `devstoreaccount1` account unless one is given.
+    Emulator,
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct SaAzure {
+    azure_account: Option<String>,
+    azure_container: String,
+    azure_blob_path: String,
+    file_url: String,
+    credentials: SaAzureCredentials,
+    table_provider: Option<Arc<dyn TableProvider>>,
+    object_store: Option<Arc<dyn ObjectStore>>
+}
+
+
+impl SaAzure {
+    pub const PROTOCAL: &str = "az";
+    /// ADLS Gen2 schemes, `abfs[s]://<container>@<account>.dfs.core.windows.net/<path>`.
+    pub const ADLS_PROTOCALS: [&str; 2] = ["abfs", "abfss"];
+    const ADLS_HOST_SUFFIX: &str = ".dfs.core.windows.net";
+
+    pub fn get_azure_account(&self) -> Option<String> {
+        self.azure_account.clone()
+    }
+
+    pub fn get_azure_container(&self) -> String {
+        self.azure_container.clone()
+    }
+
+    pub fn get_azure_blob_path(&self) -> String {
+        self.azure_blob_path.clone()
+    }
+
+    pub fn new(azure_container: &str, azure_blob_path: &str) -> Self {
+        let file_url: String = format!("{}://{}/{}", Self::PROTOCAL, azure_container, azure_blob_path);
+
+        Self {
+            azure_container: azure_container.to_string(),
+            azure_blob_path: azure_blob_path.to_string(),
+            file_url,
+            ..Default::default()
+        }
+    }
+
+    /// Parses `az://<container>/<path>` and `abfss://<container>@<account>.dfs.core.windows.net/<path>`.
+    ///
+    /// `az://` URIs carry no account, it comes from the credentials (e.g. `AZURE_STORAGE_ACCOUNT_NAME`).
+    pub fn new_from_azure_uri(azure_uri: &str) -> Result<Self> {
+        let url: Url = Url::parse(azure_uri).map_err(|e| SaError::uri_parse(azure_uri, e))?;
+        let host: &str = url.host_str()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| SaError::uri_parse(azure_uri, "missing container"))?;
+
+        let (azure_container, azure_account): (&str, Option<&str>) = match url.scheme() {
+            Self::PROTOCAL => (host, None),
+            scheme if Self::ADLS_PROTOCALS.contains(&scheme) => {
+                let azure_account: &str = host.strip_suffix(Self::ADLS_HOST_SUFFIX)
+                    .filter(|account| !account.is_empty() && !url.username().is_empty())
+                    .ok_or_else(|| SaError::uri_parse(
+                        azure_uri,
+                        "expected abfss://<container>@<account>.dfs.core.windows.net/<path>"
+                    ))?;
+                (url.username(), Some(azure_account))
+            },
+            _ => return Err(SaError::uri_parse(azure_uri, "expected the az://, abfs:// or abfss:// scheme")),
+        };
+        // Directories of hierarchical-namespace accounts are plain prefixes of the blob API
+        let azure_blob_path: &str = url.path().trim_start_matches('/');
+        if azure_blob_path.is_empty() {
+            return Err(SaError::uri_parse(azure_uri, "missing path inside the container"));
+        }
+
+        Ok(Self {
+            azure_account: azure_account.map(str::to_string),
+            azure_container: azure_container.to_string(),
+            azure_blob_path: azure_blob_path.to_string(),
+            file_url: azure_uri.to_string(),
+            ..Default::default()
+        })
+    }
+
+    pub fn with_credentials(mut self, credentials: SaAzureCredentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    pub fn build_object_store(&self) -> Result<Arc<dyn ObjectStore>> {
+        let mut builder: MicrosoftAzureBuilder = match &self.credentials {
+            SaAzureCredentials::Env => MicrosoftAzureBuilder::from_env(),
+            SaAzureCredentials::AccountKey(key) => MicrosoftAzureBuilder::from_env()
+                .with_access_key(key),
+            SaAzureCredentials::SasToken(token) => MicrosoftAzureBuilder::from_env()
+                .with_config(AzureConfigKey::SasKey, token.trim_start_matches('?')),
+            SaAzureCredentials::Emulator => MicrosoftAzureBuilder::from_env()
+                .with_use_emulator(true),
+        };
+        if let Some(azure_account) = &self.azure_account {
+            builder = builder.with_account(azure_account);
+        }
+
+        // The container is always set, so building only fails on missing account or credentials
+        let azure: MicrosoftAzure = builder
+            .with_container_name(self.azure_container.clone())
+            .build()
+            .map_err(|e| SaError::Credentials(e.to_string()))?;
+        Ok(Arc::new(azure))
+    }
+
+    /// Builds the container's object store and registers it on `sa_datafusion`.
+    ///
+    /// DataFusion keys object stores by scheme and host, so two `abfss://` containers of the
+    /// same account would share one entry: the second one is rejected rather than read through
+    /// the store of the first, see [`SaDataFusion::register_configured_object_store`].
+    pub fn register_object_store(&self, sa_datafusion: &SaDataFusion) -> Result<Arc<dyn ObjectStore>> {
+        let object_store: Arc<dyn ObjectStore> = self.build_object_store()?;
+        let url: Url = Url::parse(&self.file_url).map_err(|e| SaError::uri_parse(&self.file_url, e))?;
+        let settings: String = format!("container {}", self.azure_container);
+        sa_datafusion.register_configured_object_store(&url, object_store, &settings)
+    }
+
+    pub async fn init_table_provider(
+        mut self,
+        sa_datafusion: &SaDataFusion,
+        file_format: Arc<dyn FileFormat>,
+        schema_options: &SaSchemaOptions,
+    ) -> Result<Self>  {
+        let object_store: Arc<dyn ObjectStore> = self.register_object_store(sa_datafusion)?;
+        self.object_store = Some(object_store);
+        let table_provider: Arc<dyn TableProvider> = utils::init_listing_table(
+            sa_datafusion,
+            &self.file_url,
+            file_format,
+            schema_options
+        ).await?;
+        self.table_provider = Some(table_provider);
+        Ok(self)
+    }
+}
+
+
+/// Creates [`SaAzure`] for `az://`, `abfs://` and `abfss://` URIs.
+#[derive(Debug, Clone, Default)]
+pub struct SaAzureFactory {
+    pub credentials: SaAzureCredentials,
+}
+
+
+impl SaAzureFactory {
+    pub fn new(credentials: SaAzureCredentials) -> Self {
+        Self {
+            credentials,
+        }
+    }
+
+    pub fn get_credentials(&self) -> SaAzureCredentials {
+        self.credentials.clone()
+    }
+}
+
+
+#[async_trait]
+impl SaStorageFactory for SaAzureFactory {
+    async fn register_object_store(&self, uri: &str, sa_datafusion: &SaDataFusion) -> Result<()> {
+        SaAzure::new_from_azure_uri(uri)?
+            .with_credentials(self.get_credentials())
+            .register_object_store(sa_datafusion)?;
+        Ok(())
+    }
+
+    async fn create_storage(
+        &self,
+        uri: &str,
+        sa_datafusion: &SaDataFusion,
+        file_format: Arc<dyn FileFormat>,
+        schema_options: &SaSchemaOptions,
+    ) -> Result<Arc<dyn SaStorage>> {
+        let azure_storage: SaAzure = SaAzure::new_from_azure_uri(uri)?
+            .with_credentials(self.get_credentials())
+            .init_table_provider(
+                sa_datafusion,
+                file_format,
+                schema_options
+            ).await?;
+        Ok(Arc::new(azure_storage))
+    }
+}
+
+
+impl SaStorage for SaAzure {
+    fn get_table_provider(&self) -> Result<Arc<dyn TableProvider>> {
+        self.table_provider
+            .clone()
+            .ok_or_else(|| SaError::provider_not_initialised(&self.file_url))
+    }
+
+    fn get_file_url(&self) -> String {
+        self.file_url.clone()
+    }
+
+    fn get_protocal(&self) -> String {
+        Self::PROTOCAL.to_string()
+    }
+
+    fn get_object_store(&self) -> Option<Arc<dyn ObjectStore>>{
+        self.object_store.clone()
+    }
+}
+
+
+#[async_trait]
+impl TableProvider for SaAzure {
+    fn as_any(&self) -> &dyn Any {
+        &self.table_provider
+    }
+
+    /// Returns the schema of the table, empty until the provider is initialised
+    fn schema(&self) -> SchemaRef {
+        match &self.table_provider {
+            Some(table_provider) => table_provider.schema(),
+            None => Arc::new(Schema::empty()),
+        }
+    }
+
+    /// Returns the type of the table (e.g., Base or View)
+    fn table_type(&self) -> TableType {
+        match &self.table_provider {
+            Some(table_provider) => table_provider.table_type(),
+            None => TableType::Base,
+        }
+    }
+
+    /// Creates a logical plan for scanning the table
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DFResult<Arc<dyn ExecutionPlan>> {
+        self.get_table_provider()?.scan(state, projection, filters, limit).await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_new_from_az_uri() {
+        let azure: SaAzure = SaAzure::new_from_azure_uri("az://sql-anywhere/school/students.csv").unwrap();
+        assert_eq!(azure.get_azure_account(), None);
+        assert_eq!(azure.get_azure_container(), "sql-anywhere");
+        assert_eq!(azure.get_azure_blob_path(), "school/students.csv");
+        assert_eq!(azure.get_file_url(), "az://sql-anywhere/school/students.csv");
+    }
+
+
+    #[test]
+    fn test_new_from_abfss_uri() {
+        for scheme in SaAzure::ADLS_PROTOCALS {
+            let azure_uri: String = format!("{}://sql-anywhere@sqlanywhere.dfs.core.windows.net/school/scores/", scheme);
+            let azure: SaAzure = SaAzure::new_from_azure_uri(&azure_uri).unwrap();
+            assert_eq!(azure.get_azure_account().as_deref(), Some("sqlanywhere"));
+            assert_eq!(azure.get_azure_container(), "sql-anywhere");
+            assert_eq!(azure.get_azure_blob_path(), "school/scores/");
+            assert_eq!(azure.get_file_url(), azure_uri);
+        }
+    }
+
+
+    #[test]
+    fn test_register_containers_of_one_account() {
+        let sa_datafusion: SaDataFusion = SaDataFusion::new();
+        let register = |azure_uri: &str| SaAzure::new_from_azure_uri(azure_uri)
+            .unwrap()
+            .with_credentials(SaAzureCredentials::Emulator)
+            .register_object_store(&sa_datafusion);
+
+        assert!(register("abfss://scores@sqlanywhere.dfs.core.windows.net/2024/scores.csv").is_ok());
+        assert!(register("abfss://scores@sqlanywhere.dfs.core.windows.net/2025/scores.csv").is_ok());
+        // Another container of the account cannot share the registered store
+        let result: Result<Arc<dyn ObjectStore>> = register("abfss://students@sqlanywhere.dfs.core.windows.net/students.csv");
+        assert!(matches!(result, Err(SaError::ObjectStoreConflict { .. })), "registered as {:?}", result);
+        // Containers of other accounts, or read with az://, have stores of their own
+        assert!(register("abfss://students@sqlanywhere2.dfs.core.windows.net/students.csv").is_ok());
+        assert!(register("az://students/students.csv").is_ok());
+        assert!(register("az://scores/scores.csv").is_ok());
+    }
+
+
+    #[test]
+    fn test_new_from_azure_uri_errors() {
+        for azure_uri in [
+            "sql-anywhere/students.csv",
+            "gs://sql-anywhere/students.csv",
+            "az://sql-anywhere",
+            "az://sql-anywhere/",
+            "abfss://sqlanywhere.dfs.core.windows.net/students.csv",
+            "abfss://sql-anywhere@sqlanywhere.blob.core.windows.net/students.csv",
+            "abfss://sql-anywhere@.dfs.core.windows.net/students.csv",
+            "abfss://sql-anywhere@sqlanywhere.dfs.core.windows.net/",
+        ] {
+            let result: Result<SaAzure> = SaAzure::new_from_azure_uri(azure_uri);
+            assert!(matches!(result, Err(SaError::UriParse { .. })), "{} parsed as {:?}", azure_uri, result);
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use futures::stream::{BoxStream, StreamExt, TryStreamExt};
+use glob::{MatchOptions, Pattern};
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use url::Url;
+use datafusion::arrow::datatypes::DataType;
+use datafusion::datasource::listing::ListingTableUrl;
+use datafusion::execution::object_store::ObjectStoreUrl;
+use datafusion::execution::SessionState;
+use object_store::path::Path;
+use object_store::{
+    GetOptions,
+    GetResult,
+    ListResult,
+    MultipartUpload,
+    ObjectMeta,
+    ObjectStore,
+    PutMultipartOpts,
+    PutOptions,
+    PutPayload,
+    PutResult
+};
+use crate::cache::SaMetadataCache;
+use crate::datafusion::SaDataFusion;
+use crate::error::{Result, SaError};
+
+
+/// Scheme of the listing URLs of glob sources, served by a [`SaGlobObjectStore`].
+pub const GLOB_SCHEME: &str = "saglob";
+
+/// Characters starting a glob expression in a source URI.
+const GLOB_CHARACTERS: [char; 3] = ['*', '?', '['];
+
+/// Value Hive writes for null partitions.
+const HIVE_DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+const GLOB_MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+
+/// Splits `file_url` into the directory before its first glob character and the glob
+/// expression after it, e.g. `s3://bucket/events/` and `year=*/*.parquet`.
+///
+/// HTTP sources cannot be listed and their `?` starts a query, so they are never globs.
+pub fn split_glob(file_url: &str) -> Option<(&str, &str)> {
+    if file_url.starts_with("http://") || file_url.starts_with("https://") {
+        return None;
+    }
+    let (_, path) = file_url.split_once("://")?;
+    let path_start: usize = file_url.len() - path.len();
+    let glob_start: usize = path_start + path.find(GLOB_CHARACTERS)?;
+    let base_end: usize = file_url[..glob_start].rfind('/')? + 1;
+    Some(file_url.split_at(base_end))
+}
+
+
+/// [`ObjectStore`] only listing the objects of `inner` under `prefix` that match `pattern`,
+/// registered under a [`GLOB_SCHEME`] URL so DataFusion lists glob sources like directories.
+#[derive(Debug)]
+pub struct SaGlobObjectStore {
+    inner: Arc<dyn ObjectStore>,
+    prefix: Path,
+    pattern: Pattern,
+}
+
+
+impl SaGlobObjectStore {
+    pub fn new(inner: Arc<dyn ObjectStore>, prefix: Path, pattern: Pattern) -> Self {
+        Self {
+            inner,
+            prefix,
+            pattern,
+        }
+    }
+
+    fn is_match(&self, location: &Path) -> bool {
+        let relative_path: Option<&str> = match self.prefix.as_ref() {
+            "" => Some(location.as_ref()),
+            prefix => location.as_ref().strip_prefix(prefix).and_then(|path| path.strip_prefix('/')),
+        };
+        relative_path.is_some_and(|path| self.pattern.matches_with(path, GLOB_MATCH_OPTIONS))
+    }
+}
+
+
+impl fmt::Display for SaGlobObjectStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SaGlobObjectStore({}, {}/{})", self.inner, self.prefix, self.pattern)
+    }
+}
+
+
+#[async_trait]
+impl ObjectStore for SaGlobObjectStore {
+    async fn put_opts(&self, location: &Path, payload: PutPayload, opts: PutOptions) -> object_store::Result<PutResult> {
+        self.inner.put_opts(location, payload, opts).await
+    }
+
+    async fn put_multipart_opts(&self, location: &Path, opts: PutMultipartOpts) -> object_store::Result<Box<dyn MultipartUpload>> {
+        self.inner.put_multipart_opts(location, opts).await
+    }
+
+    async fn get_opts(&self, location: &Path, options: GetOptions) -> object_store::Result<GetResult> {
+        self.inner.get_opts(location, options).await
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.inner.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(location).await
+    }
+
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        self.inner
+            .list(prefix)
+            .try_filter(|meta| futures::future::ready(self.is_match(&meta.location)))
+            .boxed()
+    }
+
+    /// Lists like `inner`, without the objects not matching the pattern; partitioned tables are listed this way.
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        let mut list_result: ListResult = self.inner.list_with_delimiter(prefix).await?;
+        list_result.objects.retain(|meta| self.is_match(&meta.location));
+        Ok(list_result)
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
+
+
+/// Returns the [`ListingTableUrl`] to read `file_url` with:
+/// - a glob (`s3://bucket/events/*.parquet`) lists the matching files, through a [`SaGlobObjectStore`];
+/// - a prefix with a trailing `/` (`s3://bucket/events/`) lists every file below it;
+/// - a path without any object (`file:///data/events`) is read as a directory if it has files;
+/// - anything else is a single file.
+///
+/// The object store of `file_url` must be registered on `sa_datafusion`.
+pub async fn get_listing_table_url(sa_datafusion: &SaDataFusion, file_url: &str) -> Result<ListingTableUrl> {
+    if let Some((base_url, glob_expression)) = split_glob(file_url) {
+        return register_glob(sa_datafusion, file_url, base_url, glob_expression);
+    }
+
+    let listing_table_url: ListingTableUrl = ListingTableUrl::parse(file_url)?;
+    if listing_table_url.is_collection() {
+        return Ok(listing_table_url);
+    }
+    let object_store: Arc<dyn ObjectStore> = sa_datafusion.get_object_store(file_url)?;
+    match object_store.head(listing_table_url.prefix()).await {
+        Err(error @ object_store::Error::NotFound { .. }) => {
+            let directory_url: ListingTableUrl = ListingTableUrl::parse(format!("{}/", file_url))?;
+            match list_files(sa_datafusion, &directory_url).await {
+                Ok(files) if !files.is_empty() => Ok(directory_url),
+                _ => Err(error.into()),
+            }
+        },
+        _ => Ok(listing_table_url),
+    }
+}
+
+
+/// Registers a [`SaGlobObjectStore`] for `file_url` and returns its listing URL.
+fn register_glob(
+    sa_datafusion: &SaDataFusion,
+    file_url: &str,
+    base_url: &str,
+    glob_expression: &str,
+) -> Result<ListingTableUrl> {
+    let pattern: Pattern = Pattern::new(glob_expression).map_err(|e| SaError::uri_parse(file_url, e))?;
+    let base_listing_url: ListingTableUrl = ListingTableUrl::parse(base_url)?;
+    let inner: Arc<dyn ObjectStore> = sa_datafusion.get_object_store(base_url)?;
+
+    // One store per source, so the URL host is derived from it
+    let mut hasher: DefaultHasher = DefaultHasher::new();
+    file_url.hash(&mut hasher);
+    let glob_store_url: String = format!("{}://{:x}/", GLOB_SCHEME, hasher.finish());
+    let glob_object_store: SaGlobObjectStore = SaGlobObjectStore::new(
+        inner,
+        base_listing_url.prefix().clone(),
+        pattern
+    );
+    let url: Url = Url::parse(&glob_store_url).map_err(|e| SaError::uri_parse(&glob_store_url, e))?;
+    sa_datafusion.register_object_store(&url, Arc::new(glob_object_store))?;
+
+    let listing_url: String = match base_listing_url.prefix().as_ref() {
+        "" => glob_store_url,
+        prefix => format!("{}{}/", glob_store_url, prefix),
+    };
+    Ok(ListingTableUrl::parse(listing_url)?)
+}
+
+
+/// Lists the data files of `listing_table_url`, skipping hidden files such as
+/// `_SUCCESS` or `.part-0000.crc`.
+///
+/// With the metadata cache, a listing is reused for `sa.metadata_cache_listing_ttl` seconds;
+/// empty ones are not cached, so a directory being written is found as soon as it has files.
+pub async fn list_files(sa_datafusion: &SaDataFusion, listing_table_url: &ListingTableUrl) -> Result<Vec<ObjectMeta>> {
+    let listing_ttl: Duration = Duration::from_secs(sa_datafusion.get_options().metadata_cache_listing_ttl);
+    let metadata_cache: Option<Arc<SaMetadataCache>> = match listing_ttl.is_zero() {
+        true => None,
+        false => sa_datafusion.get_metadata_cache()?,
+    };
+    if let Some(files) = metadata_cache.as_ref().and_then(|cache| cache.get_listing(listing_table_url.as_str(), listing_ttl)) {
+        return Ok(files);
+    }
+
+    let session_state: SessionState = sa_datafusion.get_session_state();
+    let object_store_url: ObjectStoreUrl = listing_table_url.object_store();
+    let object_store: Arc<dyn ObjectStore> = session_state.runtime_env().object_store(object_store_url)?;
+    let mut files: Vec<ObjectMeta> = listing_table_url
+        .list_all_files(&session_state, object_store.as_ref(), "")
+        .await?
+        .try_filter(|meta| futures::future::ready(!is_hidden_file(&meta.location)))
+        .try_collect()
+        .await?;
+    files.sort_by(|left, right| left.location.cmp(&right.location));
+    if let Some(metadata_cache) = metadata_cache.filter(|_| !files.is_empty()) {
+        metadata_cache.put_listing(listing_table_url.as_str(), &files);
+    }
+    Ok(files)
+}
+
+
+fn is_hidden_file(location: &Path) -> bool {
+    location
+        .filename()
+        .is_some_and(|file_name| file_name.starts_with('_') || file_name.starts_with('.'))
+}
+
+
+/// Infers the Hive partition columns (`year=2024/month=01/...`) of `files` below
+/// `listing_table_url`, typed from their values.
+///
+/// Files must all be partitioned by the same columns in the same order, otherwise
+/// none is returned.
+pub fn infer_partition_columns(listing_table_url: &ListingTableUrl, files: &[ObjectMeta]) -> Vec<(String, DataType)> {
+    let mut partition_names: Option<Vec<String>> = None;
+    let mut partition_values: Vec<Vec<String>> = Vec::new();
+    for file in files {
+        let Some(partitions) = parse_partitions(listing_table_url.prefix(), &file.location) else {
+            return Vec::new();
+        };
+        let names: Vec<String> = partitions.iter().map(|(name, _)| name.clone()).collect();
+        if partition_names.get_or_insert_with(|| names.clone()) != &names {
+            return Vec::new();
+        }
+        partition_values.resize(names.len(), Vec::new());
+        for (values, (_, value)) in partition_values.iter_mut().zip(partitions) {
+            values.push(value);
+        }
+    }
+
+    partition_names
+        .unwrap_or_default()
+        .into_iter()
+        .zip(partition_values)
+        .map(|(name, values)| (name, infer_partition_type(&values)))
+        .collect()
+}
+
+
+/// `key=value` directories between `prefix` and the file name of `location`, with percent-decoded
+/// values (`city=New%20York`), `None` if one of them is not a partition.
+fn parse_partitions(prefix: &Path, location: &Path) -> Option<Vec<(String, String)>> {
+    let relative_path: &str = match prefix.as_ref() {
+        "" => location.as_ref(),
+        prefix => location.as_ref().strip_prefix(prefix)?.strip_prefix('/')?,
+    };
+    let mut segments: Vec<&str> = relative_path.split('/').collect();
+    segments.pop();
+    segments
+        .into_iter()
+        .map(|segment| segment
+            .split_once('=')
+            .filter(|(name, _)| !name.is_empty())
+            .map(|(name, value)| (name.to_string(), percent_decode_str(value).decode_utf8_lossy().into_owned()))
+        )
+        .collect()
+}
+
+
+/// `Int64` for integers (`year=2024`), `Date32` for valid dates (`day=2024-01-31`), `Utf8` otherwise.
+///
+/// DataFusion lists `<column>=<value>` directories for equality filters with the value
+/// formatted back from its type, so zero-padded integers (`month=01`) stay strings to be
+/// found by `month = '01'`; Hive's null partition, not read as null, keeps the column a string too.
+fn infer_partition_type(values: &[String]) -> DataType {
+    let date_pattern: Regex = Regex::new(r"^\d{4}-\d{2}-\d{2}$").expect("valid date pattern");
+    let is_integer = |value: &String| value.parse::<i64>().is_ok_and(|integer| integer.to_string() == *value);
+    if values.is_empty() || values.iter().any(|value| value == HIVE_DEFAULT_PARTITION) {
+        DataType::Utf8
+    } else if values.iter().all(is_integer) {
+        DataType::Int64
+    } else if values.iter().all(|value| date_pattern.is_match(value) && NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok()) {
+        DataType::Date32
+    } else {
+        DataType::Utf8
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn to_values(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+
+    #[test]
+    fn test_infer_partition_type() {
+        assert_eq!(infer_partition_type(&to_values(&["2023", "2024"])), DataType::Int64);
+        assert_eq!(infer_partition_type(&to_values(&["01", "02"])), DataType::Utf8);
+        assert_eq!(infer_partition_type(&to_values(&["2024-01-31", "2024-02-29"])), DataType::Date32);
+        assert_eq!(infer_partition_type(&to_values(&["2024-01-31", "2024-13-45"])), DataType::Utf8);
+        assert_eq!(infer_partition_type(&to_values(&["2024-02-30"])), DataType::Utf8);
+        assert_eq!(infer_partition_type(&to_values(&["2023-02-29"])), DataType::Utf8);
+        assert_eq!(infer_partition_type(&to_values(&["2024", HIVE_DEFAULT_PARTITION])), DataType::Utf8);
+        assert_eq!(infer_partition_type(&[]), DataType::Utf8);
+    }
+
+
+    #[test]
+    fn test_parse_partitions() {
+        let prefix: Path = Path::from("sales");
+        let location: Path = Path::parse("sales/city=New%20York/day=2024-01-31/part-0.parquet").unwrap();
+        let partitions: Vec<(String, String)> = parse_partitions(&prefix, &location).unwrap();
+        assert_eq!(partitions, vec![
+            ("city".to_string(), "New York".to_string()),
+            ("day".to_string(), "2024-01-31".to_string()),
+        ]);
+        let values: Vec<String> = partitions.into_iter().map(|(_, value)| value).collect();
+        assert_eq!(infer_partition_type(&values[1..]), DataType::Date32);
+
+        assert_eq!(parse_partitions(&prefix, &Path::from("sales/2024/part-0.parquet")), None);
+        assert_eq!(parse_partitions(&prefix, &Path::from("sales/part-0.parquet")), Some(Vec::new()));
+    }
+}
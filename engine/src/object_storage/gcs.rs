@@ -0,0 +1,321 @@
+use std::any::Any;
+use std::sync::Arc;
+use async_trait::async_trait;
+use url::Url;
+use std::env;
+use datafusion::catalog::Session;
+use datafusion::datasource::TableProvider;
+use datafusion::datasource::file_format::FileFormat;
+use datafusion::common::Result as DFResult;
+use datafusion::arrow::datatypes::{
+    Schema,
+    SchemaRef
+};
+use datafusion_expr::{
+    TableType,
+    Expr
+};
+use datafusion::physical_plan::ExecutionPlan;
+use object_store::gcp::{GoogleCloudStorage, GoogleCloudStorageBuilder};
+use object_store::{ClientOptions, ObjectStore};
+use crate::object_storage::schema::SaSchemaOptions;
+use crate::object_storage::storage::SaStorage;
+use crate::object_storage::registry::SaStorageFactory;
+use crate::datafusion::SaDataFusion;
+use crate::object_storage::utils;
+use crate::error::{Result, SaError};
+
+
+/// How [`SaGcs`] authenticates against Google Cloud Storage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SaGcsCredentials {
+    /// `GOOGLE_SERVICE_ACCOUNT`, `GOOGLE_SERVICE_ACCOUNT_KEY`, `GOOGLE_APPLICATION_CREDENTIALS`
+    /// or the metadata server, as resolved by `object_store`.
+    #[default]
+    Env,
+    /// Path to a service-account JSON file.
+    ServiceAccountPath(String),
+    /// Content of a service-account JSON file.
+    ServiceAccountKey(String),
+    /// Unauthenticated access to a local GCS emulator such as fake-gcs-server.
+    Emulator {
+        base_url: String,
+    },
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct SaGcs {
+    gcs_bucket: String,
+    gcs_object_path: String,
+    file_url: String,
+    credentials: SaGcsCredentials,
+    table_provider: Option<Arc<dyn TableProvider>>,
+    object_store: Option<Arc<dyn ObjectStore>>
+}
+
+
+impl SaGcs {
+    pub const PROTOCAL: &str = "gs";
+
+    pub fn get_gcs_bucket(&self) -> String {
+        self.gcs_bucket.clone()
+    }
+
+    pub fn get_gcs_object_path(&self) -> String {
+        self.gcs_object_path.clone()
+    }
+
+    pub fn new(gcs_bucket: &str, gcs_object_path: &str) -> Self {
+        let file_url: String = format!("{}://{}/{}", Self::PROTOCAL, gcs_bucket, gcs_object_path);
+
+        Self {
+            gcs_bucket: gcs_bucket.to_string(),
+            gcs_object_path: gcs_object_path.to_string(),
+            file_url,
+            ..Default::default()
+        }
+    }
+
+    pub fn new_from_gcs_uri(gcs_uri: &str) -> Result<Self> {
+        let url: Url = Url::parse(gcs_uri).map_err(|e| SaError::uri_parse(gcs_uri, e))?;
+        if url.scheme() != Self::PROTOCAL {
+            return Err(SaError::uri_parse(gcs_uri, "expected the gs:// scheme"));
+        }
+        let gcs_bucket: &str = url.host_str()
+            .filter(|bucket| !bucket.is_empty())
+            .ok_or_else(|| SaError::uri_parse(gcs_uri, "expected gs://<bucket>/<path>"))?;
+        let gcs_object_path: &str = url.path().trim_start_matches('/');
+        if gcs_object_path.is_empty() {
+            return Err(SaError::uri_parse(gcs_uri, "expected gs://<bucket>/<path>"));
+        }
+
+        Ok(Self {
+            gcs_bucket: gcs_bucket.to_string(),
+            gcs_object_path: gcs_object_path.to_string(),
+            file_url: gcs_uri.to_string(),
+            ..Default::default()
+        })
+    }
+
+    pub fn with_credentials(mut self, credentials: SaGcsCredentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    pub fn build_object_store(&self) -> Result<Arc<dyn ObjectStore>> {
+        let builder: GoogleCloudStorageBuilder = match &self.credentials {
+            SaGcsCredentials::Env => GoogleCloudStorageBuilder::from_env(),
+            SaGcsCredentials::ServiceAccountPath(path) => GoogleCloudStorageBuilder::new()
+                .with_service_account_path(path),
+            SaGcsCredentials::ServiceAccountKey(key) => GoogleCloudStorageBuilder::new()
+                .with_service_account_key(key),
+            SaGcsCredentials::Emulator { base_url } => {
+                // object_store reads the endpoint from the service account, and skips OAuth with `disable_oauth`
+                let service_account_key: String = format!(
+                    r#"{{"gcs_base_url": "{}", "disable_oauth": true, "client_email": "", "private_key": "", "private_key_id": ""}}"#,
+                    base_url.trim_end_matches('/')
+                );
+                GoogleCloudStorageBuilder::new()
+                    .with_service_account_key(service_account_key)
+                    .with_client_options(ClientOptions::new().with_allow_http(true))
+            },
+        };
+
+        // The bucket is always set, so building only fails on unreadable credentials
+        let gcs: GoogleCloudStorage = builder
+            .with_bucket_name(self.gcs_bucket.clone())
+            .build()
+            .map_err(|e| SaError::Credentials(e.to_string()))?;
+        Ok(Arc::new(gcs))
+    }
+
+    /// Builds the bucket's object store and registers it on `sa_datafusion`.
+    pub fn register_object_store(&self, sa_datafusion: &SaDataFusion) -> Result<Arc<dyn ObjectStore>> {
+        let object_store: Arc<dyn ObjectStore> = self.build_object_store()?;
+        let url: Url = Url::parse(&self.file_url).map_err(|e| SaError::uri_parse(&self.file_url, e))?;
+        sa_datafusion.register_object_store(&url, object_store)
+    }
+
+    pub async fn init_table_provider(
+        mut self,
+        sa_datafusion: &SaDataFusion,
+        file_format: Arc<dyn FileFormat>,
+        schema_options: &SaSchemaOptions,
+    ) -> Result<Self>  {
+        let object_store: Arc<dyn ObjectStore> = self.register_object_store(sa_datafusion)?;
+        self.object_store = Some(object_store);
+        let table_provider: Arc<dyn TableProvider> = utils::init_listing_table(
+            sa_datafusion,
+            &self.file_url,
+            file_format,
+            schema_options
+        ).await?;
+        self.table_provider = Some(table_provider);
+        Ok(self)
+    }
+}
+
+
+/// Creates [`SaGcs`] for `gs://` URIs.
+///
+/// With [`SaGcsCredentials::Env`], setting `STORAGE_EMULATOR_HOST` points it at a local emulator.
+#[derive(Debug, Clone, Default)]
+pub struct SaGcsFactory {
+    pub credentials: SaGcsCredentials,
+}
+
+
+impl SaGcsFactory {
+    pub fn new(credentials: SaGcsCredentials) -> Self {
+        Self {
+            credentials,
+        }
+    }
+
+    pub fn get_credentials(&self) -> SaGcsCredentials {
+        self.get_credentials_with_emulator(env::var("STORAGE_EMULATOR_HOST").ok())
+    }
+
+    /// Credentials to use when `emulator_host` is the base URL of a local emulator, if any.
+    fn get_credentials_with_emulator(&self, emulator_host: Option<String>) -> SaGcsCredentials {
+        match (&self.credentials, emulator_host) {
+            (SaGcsCredentials::Env, Some(base_url)) => SaGcsCredentials::Emulator { base_url },
+            (credentials, _) => credentials.clone(),
+        }
+    }
+}
+
+
+#[async_trait]
+impl SaStorageFactory for SaGcsFactory {
+    async fn register_object_store(&self, uri: &str, sa_datafusion: &SaDataFusion) -> Result<()> {
+        SaGcs::new_from_gcs_uri(uri)?
+            .with_credentials(self.get_credentials())
+            .register_object_store(sa_datafusion)?;
+        Ok(())
+    }
+
+    async fn create_storage(
+        &self,
+        uri: &str,
+        sa_datafusion: &SaDataFusion,
+        file_format: Arc<dyn FileFormat>,
+        schema_options: &SaSchemaOptions,
+    ) -> Result<Arc<dyn SaStorage>> {
+        let gcs_storage: SaGcs = SaGcs::new_from_gcs_uri(uri)?
+            .with_credentials(self.get_credentials())
+            .init_table_provider(
+                sa_datafusion,
+                file_format,
+                schema_options
+            ).await?;
+        Ok(Arc::new(gcs_storage))
+    }
+}
+
+
+impl SaStorage for SaGcs {
+    fn get_table_provider(&self) -> Result<Arc<dyn TableProvider>> {
+        self.table_provider
+            .clone()
+            .ok_or_else(|| SaError::provider_not_initialised(&self.file_url))
+    }
+
+    fn get_file_url(&self) -> String {
+        self.file_url.clone()
+    }
+
+    fn get_protocal(&self) -> String {
+        Self::PROTOCAL.to_string()
+    }
+
+    fn get_object_store(&self) -> Option<Arc<dyn ObjectStore>>{
+        self.object_store.clone()
+    }
+}
+
+
+#[async_trait]
+impl TableProvider for SaGcs {
+    fn as_any(&self) -> &dyn Any {
+        &self.table_provider
+    }
+
+    /// Returns the schema of the table, empty until the provider is initialised
+    fn schema(&self) -> SchemaRef {
+        match &self.table_provider {
+            Some(table_provider) => table_provider.schema(),
+            None => Arc::new(Schema::empty()),
+        }
+    }
+
+    /// Returns the type of the table (e.g., Base or View)
+    fn table_type(&self) -> TableType {
+        match &self.table_provider {
+            Some(table_provider) => table_provider.table_type(),
+            None => TableType::Base,
+        }
+    }
+
+    /// Creates a logical plan for scanning the table
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DFResult<Arc<dyn ExecutionPlan>> {
+        self.get_table_provider()?.scan(state, projection, filters, limit).await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_new_from_gcs_uri() {
+        let gcs: SaGcs = SaGcs::new_from_gcs_uri("gs://sql-anywhere/school/students.csv").unwrap();
+        assert_eq!(gcs.get_gcs_bucket(), "sql-anywhere");
+        assert_eq!(gcs.get_gcs_object_path(), "school/students.csv");
+        assert_eq!(gcs.get_file_url(), "gs://sql-anywhere/school/students.csv");
+
+        for gcs_uri in [
+            "sql-anywhere/students.csv",
+            "s3://sql-anywhere/students.csv",
+            "gs:///students.csv",
+            "gs://sql-anywhere",
+            "gs://sql-anywhere/",
+        ] {
+            let result: Result<SaGcs> = SaGcs::new_from_gcs_uri(gcs_uri);
+            assert!(matches!(result, Err(SaError::UriParse { .. })), "{} parsed as {:?}", gcs_uri, result);
+        }
+    }
+
+
+    #[test]
+    fn test_get_credentials() {
+        let service_account: SaGcsCredentials = SaGcsCredentials::ServiceAccountPath("key.json".to_string());
+
+        let emulator_host: Option<String> = Some("http://127.0.0.1:4443".to_string());
+        assert_eq!(
+            SaGcsFactory::default().get_credentials_with_emulator(emulator_host.clone()),
+            SaGcsCredentials::Emulator { base_url: "http://127.0.0.1:4443".to_string() }
+        );
+        // Explicit credentials are not replaced by the emulator
+        assert_eq!(SaGcsFactory::new(service_account.clone()).get_credentials_with_emulator(emulator_host), service_account);
+
+        assert_eq!(SaGcsFactory::default().get_credentials_with_emulator(None), SaGcsCredentials::Env);
+    }
+
+
+    #[test]
+    fn test_build_emulator_object_store() {
+        let gcs: SaGcs = SaGcs::new("sql-anywhere", "students.csv")
+            .with_credentials(SaGcsCredentials::Emulator { base_url: "http://127.0.0.1:4443/".to_string() });
+        assert!(gcs.build_object_store().is_ok());
+    }
+}
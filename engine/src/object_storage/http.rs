@@ -0,0 +1,512 @@
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::{self, BoxStream};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use url::Url;
+use datafusion::catalog::Session;
+use datafusion::datasource::TableProvider;
+use datafusion::datasource::file_format::FileFormat;
+use datafusion::common::Result as DFResult;
+use datafusion::error::DataFusionError;
+use datafusion::arrow::datatypes::{
+    Schema,
+    SchemaRef
+};
+use datafusion_expr::{
+    TableType,
+    Expr
+};
+use datafusion::physical_plan::ExecutionPlan;
+use object_store::http::{HttpBuilder, HttpStore};
+use object_store::path::Path;
+use object_store::{
+    ClientOptions,
+    GetOptions,
+    GetRange,
+    GetResult,
+    GetResultPayload,
+    ListResult,
+    MultipartUpload,
+    ObjectMeta,
+    ObjectStore,
+    PutMultipartOpts,
+    PutOptions,
+    PutPayload,
+    PutResult
+};
+use crate::object_storage::schema::SaSchemaOptions;
+use crate::object_storage::storage::SaStorage;
+use crate::object_storage::registry::SaStorageFactory;
+use crate::datafusion::SaDataFusion;
+use crate::object_storage::utils;
+use crate::cache::metadata::fnv1a_hash;
+use crate::error::{Result, SaError};
+
+
+/// Most bytes of the objects downloaded whole that a [`SaHttpObjectStore`] keeps in memory.
+const MAX_FULL_OBJECTS_SIZE: usize = 256 << 20;
+
+/// Messages of the `object_store` errors of a range request answered with the whole object,
+/// by the HTTP client (`200 OK` instead of `206 Partial Content`) and by its response check.
+const RANGE_NOT_SUPPORTED_MESSAGE: &str = "Range request not supported by ";
+const NOT_PARTIAL_MESSAGE: &str = "Received non-partial response when range requested";
+
+
+#[derive(Debug, Clone, Default)]
+pub struct SaHttp {
+    http_host: String,
+    http_path: String,
+    file_url: String,
+    headers: Vec<(String, String)>,
+    table_provider: Option<Arc<dyn TableProvider>>,
+    object_store: Option<Arc<dyn ObjectStore>>
+}
+
+
+impl SaHttp {
+    pub const PROTOCAL: &str = "https";
+    pub const INSECURE_PROTOCAL: &str = "http";
+
+    pub fn get_http_host(&self) -> String {
+        self.http_host.clone()
+    }
+
+    pub fn get_http_path(&self) -> String {
+        self.http_path.clone()
+    }
+
+    pub fn get_headers(&self) -> Vec<(String, String)> {
+        self.headers.clone()
+    }
+
+    pub fn new_from_http_uri(http_uri: &str) -> Result<Self> {
+        let url: Url = Url::parse(http_uri).map_err(|e| SaError::uri_parse(http_uri, e))?;
+        if url.scheme() != Self::PROTOCAL && url.scheme() != Self::INSECURE_PROTOCAL {
+            return Err(SaError::uri_parse(http_uri, "expected the http:// or https:// scheme"));
+        }
+        let http_host: &str = url.host_str()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| SaError::uri_parse(http_uri, "missing host"))?;
+        let http_path: &str = url.path().trim_start_matches('/');
+        if http_path.is_empty() {
+            return Err(SaError::uri_parse(http_uri, "missing path"));
+        }
+
+        Ok(Self {
+            http_host: http_host.to_string(),
+            http_path: http_path.to_string(),
+            file_url: http_uri.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Headers sent with every request, e.g. `("Authorization", "Bearer <token>")`.
+    pub fn with_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn build_header_map(&self) -> Result<HeaderMap> {
+        let mut header_map: HeaderMap = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let header_name: HeaderName = HeaderName::try_from(name.as_str())
+                .map_err(|e| DataFusionError::Configuration(format!("Invalid HTTP header name '{}': {}", name, e)))?;
+            let mut header_value: HeaderValue = HeaderValue::try_from(value.as_str())
+                .map_err(|e| DataFusionError::Configuration(format!("Invalid value for HTTP header '{}': {}", name, e)))?;
+            header_value.set_sensitive(true);
+            header_map.append(header_name, header_value);
+        }
+        Ok(header_map)
+    }
+
+    pub fn build_object_store(&self) -> Result<Arc<dyn ObjectStore>> {
+        let url: Url = Url::parse(&self.file_url).map_err(|e| SaError::uri_parse(&self.file_url, e))?;
+        let client_options: ClientOptions = ClientOptions::new()
+            .with_allow_http(url.scheme() == Self::INSECURE_PROTOCAL)
+            .with_default_headers(self.build_header_map()?);
+
+        // Object paths are relative to the server root, like the other storages' buckets
+        let http_store: HttpStore = HttpBuilder::new()
+            .with_url(&url[..url::Position::BeforePath])
+            .with_client_options(client_options)
+            .build()?;
+        Ok(Arc::new(SaHttpObjectStore::new(http_store)))
+    }
+
+    /// Names of the headers, and a hash of their values, the server's object store is built with.
+    pub fn get_settings(&self) -> String {
+        let names: Vec<&str> = self.headers.iter().map(|(name, _)| name.as_str()).collect();
+        let values: Vec<&str> = self.headers.iter().map(|(_, value)| value.as_str()).collect();
+        format!("headers [{}] {:016x}", names.join(", "), fnv1a_hash(values.join("\n").as_bytes()))
+    }
+
+    /// Builds the server's object store and registers it on `sa_datafusion`, unless the server
+    /// is registered with other headers, see [`SaDataFusion::register_configured_object_store`].
+    pub fn register_object_store(&self, sa_datafusion: &SaDataFusion) -> Result<Arc<dyn ObjectStore>> {
+        let object_store: Arc<dyn ObjectStore> = self.build_object_store()?;
+        let url: Url = Url::parse(&self.file_url).map_err(|e| SaError::uri_parse(&self.file_url, e))?;
+        sa_datafusion.register_configured_object_store(&url, object_store, &self.get_settings())
+    }
+
+    pub async fn init_table_provider(
+        mut self,
+        sa_datafusion: &SaDataFusion,
+        file_format: Arc<dyn FileFormat>,
+        schema_options: &SaSchemaOptions,
+    ) -> Result<Self>  {
+        let object_store: Arc<dyn ObjectStore> = self.register_object_store(sa_datafusion)?;
+        self.object_store = Some(object_store);
+        let table_provider: Arc<dyn TableProvider> = utils::init_listing_table(
+            sa_datafusion,
+            &self.file_url,
+            file_format,
+            schema_options
+        ).await?;
+        self.table_provider = Some(table_provider);
+        Ok(self)
+    }
+}
+
+
+/// Objects downloaded whole, the first downloaded being dropped once they hold more than
+/// `max_size` bytes.
+#[derive(Debug, Default)]
+struct SaFullObjects {
+    objects: HashMap<Path, (ObjectMeta, Bytes)>,
+    order: VecDeque<Path>,
+    size: usize,
+    max_size: usize,
+}
+
+
+impl SaFullObjects {
+    fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            ..Default::default()
+        }
+    }
+
+    fn get(&self, location: &Path) -> Option<(ObjectMeta, Bytes)> {
+        self.objects.get(location).cloned()
+    }
+
+    /// Keeps `data`, unless it is larger than the bound by itself.
+    fn insert(&mut self, location: &Path, meta: ObjectMeta, data: Bytes) {
+        if data.len() > self.max_size || self.objects.contains_key(location) {
+            return;
+        }
+        while self.size + data.len() > self.max_size {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Some((_, oldest_data)) = self.objects.remove(&oldest) {
+                self.size -= oldest_data.len();
+            }
+        }
+        self.size += data.len();
+        self.order.push_back(location.clone());
+        self.objects.insert(location.clone(), (meta, data));
+    }
+}
+
+
+/// [`HttpStore`] that falls back to downloading whole objects when the server
+/// answers range requests with `200 OK` and the whole object instead of `206 Partial Content`.
+///
+/// Downloaded objects are kept in memory, up to [`MAX_FULL_OBJECTS_SIZE`] bytes, so the
+/// following reads of the same file (e.g. the Parquet footer, then its row groups) do not
+/// download it again.
+#[derive(Debug)]
+pub struct SaHttpObjectStore {
+    inner: HttpStore,
+    is_range_supported: AtomicBool,
+    full_objects: Mutex<SaFullObjects>,
+}
+
+
+impl SaHttpObjectStore {
+    pub fn new(inner: HttpStore) -> Self {
+        Self {
+            inner,
+            is_range_supported: AtomicBool::new(true),
+            full_objects: Mutex::new(SaFullObjects::new(MAX_FULL_OBJECTS_SIZE)),
+        }
+    }
+
+    pub fn is_range_supported(&self) -> bool {
+        self.is_range_supported.load(Ordering::Relaxed)
+    }
+
+    async fn get_full_object(&self, location: &Path, options: GetOptions) -> object_store::Result<(ObjectMeta, Bytes)> {
+        if let Some(full_object) = self.full_objects.lock().expect("HTTP object cache lock poisoned").get(location) {
+            return Ok(full_object);
+        }
+        let result: GetResult = self.inner.get_opts(location, GetOptions { range: None, ..options }).await?;
+        let meta: ObjectMeta = result.meta.clone();
+        let data: Bytes = result.bytes().await?;
+        self.full_objects
+            .lock()
+            .expect("HTTP object cache lock poisoned")
+            .insert(location, meta.clone(), data.clone());
+        Ok((meta, data))
+    }
+
+    async fn get_range_from_full_object(
+        &self,
+        location: &Path,
+        range: &GetRange,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        let (meta, data): (ObjectMeta, Bytes) = self.get_full_object(location, options).await?;
+        let range: Range<usize> = resolve_range(range, data.len()).ok_or_else(|| object_store::Error::Generic {
+            store: "HTTP",
+            source: format!("Range {} is out of bounds for {} ({} bytes)", range, location, data.len()).into(),
+        })?;
+        let payload: Bytes = data.slice(range.clone());
+
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(Box::pin(stream::once(async move { Ok(payload) }))),
+            meta,
+            range,
+            attributes: Default::default(),
+        })
+    }
+}
+
+
+/// Whether `error` tells that a range request was answered with the whole object.
+fn is_not_partial_error(error: &object_store::Error) -> bool {
+    match error {
+        object_store::Error::NotSupported { source } => source.to_string().starts_with(RANGE_NOT_SUPPORTED_MESSAGE),
+        object_store::Error::Generic { source, .. } => source.to_string() == NOT_PARTIAL_MESSAGE,
+        _ => false,
+    }
+}
+
+
+/// Resolves `range` against an object of `len` bytes, `None` when it is empty or out of bounds.
+fn resolve_range(range: &GetRange, len: usize) -> Option<Range<usize>> {
+    let range: Range<usize> = match range {
+        GetRange::Bounded(range) => range.start..range.end.min(len),
+        GetRange::Offset(offset) => *offset..len,
+        GetRange::Suffix(suffix) => len.saturating_sub(*suffix)..len,
+    };
+    Some(range).filter(|range| range.start < range.end)
+}
+
+
+impl fmt::Display for SaHttpObjectStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SaHttpObjectStore({})", self.inner)
+    }
+}
+
+
+#[async_trait]
+impl ObjectStore for SaHttpObjectStore {
+    async fn put_opts(&self, location: &Path, payload: PutPayload, opts: PutOptions) -> object_store::Result<PutResult> {
+        self.inner.put_opts(location, payload, opts).await
+    }
+
+    async fn put_multipart_opts(&self, location: &Path, opts: PutMultipartOpts) -> object_store::Result<Box<dyn MultipartUpload>> {
+        self.inner.put_multipart_opts(location, opts).await
+    }
+
+    async fn get_opts(&self, location: &Path, options: GetOptions) -> object_store::Result<GetResult> {
+        let range: GetRange = match (&options.range, options.head) {
+            (Some(range), false) => range.clone(),
+            _ => return self.inner.get_opts(location, options).await,
+        };
+        if !self.is_range_supported() {
+            return self.get_range_from_full_object(location, &range, options).await;
+        }
+
+        match self.inner.get_opts(location, options.clone()).await {
+            // The server ignored the range, download the whole object and remember it
+            Err(error) if is_not_partial_error(&error) => {
+                self.is_range_supported.store(false, Ordering::Relaxed);
+                self.get_range_from_full_object(location, &range, options).await
+            },
+            result => result,
+        }
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.inner.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(location).await
+    }
+
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        self.inner.list(prefix)
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
+
+
+/// Creates [`SaHttp`] for `http://` and `https://` URIs, sending `headers` with every request,
+/// then those of the `sa.http_headers` option.
+#[derive(Debug, Clone, Default)]
+pub struct SaHttpFactory {
+    pub headers: Vec<(String, String)>,
+}
+
+
+impl SaHttpFactory {
+    pub fn new(headers: Vec<(String, String)>) -> Self {
+        Self {
+            headers,
+        }
+    }
+
+    /// Headers of the factory and of the session.
+    pub fn get_headers(&self, sa_datafusion: &SaDataFusion) -> Vec<(String, String)> {
+        let mut headers: Vec<(String, String)> = self.headers.clone();
+        headers.extend(sa_datafusion.get_options().get_http_headers());
+        headers
+    }
+}
+
+
+#[async_trait]
+impl SaStorageFactory for SaHttpFactory {
+    async fn register_object_store(&self, uri: &str, sa_datafusion: &SaDataFusion) -> Result<()> {
+        SaHttp::new_from_http_uri(uri)?
+            .with_headers(self.get_headers(sa_datafusion))
+            .register_object_store(sa_datafusion)?;
+        Ok(())
+    }
+
+    async fn create_storage(
+        &self,
+        uri: &str,
+        sa_datafusion: &SaDataFusion,
+        file_format: Arc<dyn FileFormat>,
+        schema_options: &SaSchemaOptions,
+    ) -> Result<Arc<dyn SaStorage>> {
+        let http_storage: SaHttp = SaHttp::new_from_http_uri(uri)?
+            .with_headers(self.get_headers(sa_datafusion))
+            .init_table_provider(
+                sa_datafusion,
+                file_format,
+                schema_options
+            ).await?;
+        Ok(Arc::new(http_storage))
+    }
+}
+
+
+impl SaStorage for SaHttp {
+    fn get_table_provider(&self) -> Result<Arc<dyn TableProvider>> {
+        self.table_provider
+            .clone()
+            .ok_or_else(|| SaError::provider_not_initialised(&self.file_url))
+    }
+
+    fn get_file_url(&self) -> String {
+        self.file_url.clone()
+    }
+
+    fn get_protocal(&self) -> String {
+        match self.file_url.starts_with(Self::INSECURE_PROTOCAL) && !self.file_url.starts_with(Self::PROTOCAL) {
+            true => Self::INSECURE_PROTOCAL.to_string(),
+            false => Self::PROTOCAL.to_string(),
+        }
+    }
+
+    fn get_object_store(&self) -> Option<Arc<dyn ObjectStore>>{
+        self.object_store.clone()
+    }
+}
+
+
+#[async_trait]
+impl TableProvider for SaHttp {
+    fn as_any(&self) -> &dyn Any {
+        &self.table_provider
+    }
+
+    /// Returns the schema of the table, empty until the provider is initialised
+    fn schema(&self) -> SchemaRef {
+        match &self.table_provider {
+            Some(table_provider) => table_provider.schema(),
+            None => Arc::new(Schema::empty()),
+        }
+    }
+
+    /// Returns the type of the table (e.g., Base or View)
+    fn table_type(&self) -> TableType {
+        match &self.table_provider {
+            Some(table_provider) => table_provider.table_type(),
+            None => TableType::Base,
+        }
+    }
+
+    /// Creates a logical plan for scanning the table
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DFResult<Arc<dyn ExecutionPlan>> {
+        self.get_table_provider()?.scan(state, projection, filters, limit).await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn get_meta(location: &str, size: usize) -> ObjectMeta {
+        ObjectMeta {
+            location: Path::from(location),
+            last_modified: Default::default(),
+            size,
+            e_tag: None,
+            version: None,
+        }
+    }
+
+
+    #[test]
+    fn test_full_objects_limit() {
+        let mut full_objects: SaFullObjects = SaFullObjects::new(100);
+        for (location, size) in [("a", 40), ("b", 40), ("c", 40), ("large", 101)] {
+            full_objects.insert(&Path::from(location), get_meta(location, size), Bytes::from(vec![0; size]));
+        }
+        // `a` is dropped for `c`, and an object larger than the bound is not kept
+        assert!(full_objects.get(&Path::from("a")).is_none());
+        assert!(full_objects.get(&Path::from("b")).is_some());
+        assert!(full_objects.get(&Path::from("c")).is_some());
+        assert!(full_objects.get(&Path::from("large")).is_none());
+        assert_eq!(full_objects.size, 80);
+    }
+}
@@ -2,77 +2,61 @@ use std::any::Any;
 use std::sync::Arc;
 use async_trait::async_trait;
 use std::path::Path;
-use std::io;
 use std::fs;
 use datafusion::catalog::Session;
 use datafusion::datasource::TableProvider;
-use datafusion::datasource::listing::{
-    ListingOptions,
-    ListingTable,
-    ListingTableConfig,
-    ListingTableUrl
-};
 use datafusion::datasource::file_format::FileFormat;
-use datafusion::common::Result;
+use datafusion::common::Result as DFResult;
 use datafusion::arrow::datatypes::{
     Schema,
-    SchemaRef,
-    Field,
-    DataType
+    SchemaRef
 };
 use datafusion_expr::{
     TableType,
     Expr
 };
 use datafusion::physical_plan::ExecutionPlan;
+use crate::object_storage::schema::SaSchemaOptions;
 use crate::object_storage::storage::SaStorage;
+use crate::object_storage::registry::SaStorageFactory;
 use crate::datafusion::SaDataFusion;
 use crate::object_storage::utils;
+use crate::error::{Result, SaError};
 use object_store::ObjectStore;
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct SaLocalStorage {
     file_url: String,
     table_provider: Option<Arc<dyn TableProvider>>,
 }
 
 
-impl Default for SaLocalStorage {
-    fn default() -> Self {
-        SaLocalStorage {
-            file_url: String::new(),
-            table_provider: None,
-        }
-    }
-}
-
-
 impl SaLocalStorage {
-    const PROTOCAL: &str = "file";
+    pub const PROTOCAL: &str = "file";
 
-    pub fn copy_file<P: AsRef<Path>>(src: P, dest: P) -> io::Result<u64> {
-        fs::copy(src, dest)
+    pub fn copy_file<P: AsRef<Path>>(src: P, dest: P) -> Result<u64> {
+        Ok(fs::copy(src, dest)?)
     }
 
-    pub fn move_file<P: AsRef<Path>>(src: P, dest: P) -> io::Result<()> {
+    pub fn move_file<P: AsRef<Path>>(src: P, dest: P) -> Result<()> {
         fs::copy(&src, &dest)?;
-        fs::remove_file(src)
+        Ok(fs::remove_file(src)?)
     }
 
-    pub fn delete_file<P: AsRef<Path>>(path: P) -> io::Result<()> {
-        fs::remove_file(path)
+    pub fn delete_file<P: AsRef<Path>>(path: P) -> Result<()> {
+        Ok(fs::remove_file(path)?)
     }
 
-    pub fn get_file_name<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    pub fn get_file_name<P: AsRef<Path>>(path: P) -> Result<String> {
         utils::extract_path(path, Path::file_name, "file name")
     }
 
-    pub fn get_file_stem<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    pub fn get_file_stem<P: AsRef<Path>>(path: P) -> Result<String> {
         utils::extract_path(path, Path::file_stem, "file stem")
     }
 
-    pub fn get_file_extension<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    pub fn get_file_extension<P: AsRef<Path>>(path: P) -> Result<String> {
         utils::extract_path(path, Path::extension, "extension")
     }
 
@@ -80,7 +64,7 @@ impl SaLocalStorage {
         let file_url: String = format!("{}://{}", Self::PROTOCAL, file_path);
 
         Self {
-            file_url: file_url,
+            file_url,
             ..Default::default()
         }
     }
@@ -92,44 +76,46 @@ impl SaLocalStorage {
         }
     }
 
-    pub async fn init_table_provider(mut self, sa_datafusion: &SaDataFusion, file_format: Arc<dyn FileFormat>, is_infer_schema: Option<bool>) -> Result<Self> {
-        let is_infer_schema: bool = is_infer_schema.unwrap_or(true);
-        let listing_options: ListingOptions = ListingOptions::new(file_format);
-        let listing_table_url: ListingTableUrl = ListingTableUrl::parse(self.file_url.clone())?;
-        let schema: Arc<Schema> = if is_infer_schema {
-            // Auto inference
-            listing_options
-                .infer_schema(&sa_datafusion.get_session_state(), &listing_table_url)
-                .await?
-        } else {
-            // Create a schema with all columns as DataType::Utf8 (string)
-            let inferred_schema = listing_options
-                .infer_schema(&sa_datafusion.get_session_state(), &listing_table_url)
-                .await?;
-
-            let fields_as_string: Vec<Field> = inferred_schema
-                .fields()
-                .iter()
-                .map(|field| Field::new(field.name(), DataType::Utf8, field.is_nullable()))
-                .collect();
-
-            Arc::new(Schema::new(fields_as_string))
-        };
-
-        let listing_table_config: ListingTableConfig = ListingTableConfig::new(listing_table_url)
-            .with_listing_options(listing_options)
-            .with_schema(schema);
-
-        let table_provider: Arc<ListingTable> = Arc::new(ListingTable::try_new(listing_table_config)?);
+    pub async fn init_table_provider(mut self, sa_datafusion: &SaDataFusion, file_format: Arc<dyn FileFormat>, schema_options: &SaSchemaOptions) -> Result<Self> {
+        let table_provider: Arc<dyn TableProvider> = utils::init_listing_table(
+            sa_datafusion,
+            &self.file_url,
+            file_format,
+            schema_options
+        ).await?;
         self.table_provider = Some(table_provider);
         Ok(self)
     }
 }
 
 
+/// Creates [`SaLocalStorage`] for `file://` URIs.
+#[derive(Debug, Clone, Default)]
+pub struct SaLocalStorageFactory;
+
+
+#[async_trait]
+impl SaStorageFactory for SaLocalStorageFactory {
+    async fn create_storage(
+        &self,
+        uri: &str,
+        sa_datafusion: &SaDataFusion,
+        file_format: Arc<dyn FileFormat>,
+        schema_options: &SaSchemaOptions,
+    ) -> Result<Arc<dyn SaStorage>> {
+        let local_storage: SaLocalStorage = SaLocalStorage::new_with_file_uri(uri)
+            .init_table_provider(sa_datafusion, file_format, schema_options)
+            .await?;
+        Ok(Arc::new(local_storage))
+    }
+}
+
+
 impl SaStorage for SaLocalStorage {
-    fn get_table_provider(&self) -> Arc<dyn TableProvider> {
-        self.table_provider.clone().unwrap()
+    fn get_table_provider(&self) -> Result<Arc<dyn TableProvider>> {
+        self.table_provider
+            .clone()
+            .ok_or_else(|| SaError::provider_not_initialised(&self.file_url))
     }
 
     fn get_file_url(&self) -> String {
@@ -152,14 +138,20 @@ impl TableProvider for SaLocalStorage {
         &self.table_provider
     }
 
-    /// Returns the schema of the table
+    /// Returns the schema of the table, empty until the provider is initialised
     fn schema(&self) -> SchemaRef {
-        self.table_provider.clone().unwrap().schema()
+        match &self.table_provider {
+            Some(table_provider) => table_provider.schema(),
+            None => Arc::new(Schema::empty()),
+        }
     }
 
     /// Returns the type of the table (e.g., Base or View)
     fn table_type(&self) -> TableType {
-        self.table_provider.clone().unwrap().table_type()
+        match &self.table_provider {
+            Some(table_provider) => table_provider.table_type(),
+            None => TableType::Base,
+        }
     }
 
     /// Creates a logical plan for scanning the table
@@ -169,8 +161,8 @@ impl TableProvider for SaLocalStorage {
         projection: Option<&Vec<usize>>,
         filters: &[Expr],
         limit: Option<usize>,
-    ) -> Result<Arc<dyn ExecutionPlan>> {
-        self.table_provider.clone().unwrap().scan(state, projection, filters, limit).await
+    ) -> DFResult<Arc<dyn ExecutionPlan>> {
+        self.get_table_provider()?.scan(state, projection, filters, limit).await
     }
 }
 
@@ -3,4 +3,15 @@ pub mod local_storage;
 pub use local_storage::SaLocalStorage;
 pub mod s3;
 pub use s3::SaS3;
-pub mod utils;
\ No newline at end of file
+pub mod gcs;
+pub use gcs::SaGcs;
+pub mod azure;
+pub use azure::SaAzure;
+pub mod http;
+pub use http::SaHttp;
+pub mod schema;
+pub use schema::SaSchemaOptions;
+pub mod utils;
+pub mod discovery;
+pub mod registry;
+pub use registry::{SaStorageFactory, SaStorageRegistry};
\ No newline at end of file
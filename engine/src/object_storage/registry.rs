@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use async_trait::async_trait;
+use datafusion::datasource::file_format::FileFormat;
+use crate::object_storage::schema::SaSchemaOptions;
+use crate::datafusion::SaDataFusion;
+use crate::error::{Result, SaError};
+use crate::object_storage::storage::SaStorage;
+use crate::object_storage::{SaLocalStorage, SaS3, SaGcs, SaAzure, SaHttp};
+use crate::object_storage::local_storage::SaLocalStorageFactory;
+use crate::object_storage::s3::SaS3Factory;
+use crate::object_storage::gcs::SaGcsFactory;
+use crate::object_storage::azure::SaAzureFactory;
+use crate::object_storage::http::SaHttpFactory;
+
+
+/// Builds an initialised [`SaStorage`] for a URI of the scheme it is registered under.
+#[async_trait]
+pub trait SaStorageFactory: Send + Sync {
+    /// Registers the object store serving `uri` on `sa_datafusion`, so the source
+    /// can be read (e.g. sniffed) before its storage is created.
+    async fn register_object_store(&self, _uri: &str, _sa_datafusion: &SaDataFusion) -> Result<()> {
+        Ok(())
+    }
+
+    async fn create_storage(
+        &self,
+        uri: &str,
+        sa_datafusion: &SaDataFusion,
+        file_format: Arc<dyn FileFormat>,
+        schema_options: &SaSchemaOptions,
+    ) -> Result<Arc<dyn SaStorage>>;
+}
+
+
+/// Storage factories keyed by URI scheme (`file`, `s3`, ...).
+#[derive(Clone)]
+pub struct SaStorageRegistry {
+    factories: HashMap<String, Arc<dyn SaStorageFactory>>,
+}
+
+
+impl Default for SaStorageRegistry {
+    fn default() -> Self {
+        let mut registry: SaStorageRegistry = Self::empty();
+        registry.register(SaLocalStorage::PROTOCAL, Arc::new(SaLocalStorageFactory));
+        registry.register(SaS3::PROTOCAL, Arc::new(SaS3Factory::default()));
+        registry.register(SaGcs::PROTOCAL, Arc::new(SaGcsFactory::default()));
+        let azure_factory: Arc<dyn SaStorageFactory> = Arc::new(SaAzureFactory::default());
+        registry.register(SaAzure::PROTOCAL, azure_factory.clone());
+        for scheme in SaAzure::ADLS_PROTOCALS {
+            registry.register(scheme, azure_factory.clone());
+        }
+        let http_factory: Arc<dyn SaStorageFactory> = Arc::new(SaHttpFactory::default());
+        registry.register(SaHttp::PROTOCAL, http_factory.clone());
+        registry.register(SaHttp::INSECURE_PROTOCAL, http_factory);
+        registry
+    }
+}
+
+
+impl SaStorageRegistry {
+    /// Registry with the built-in storages (`file`, `s3`, `gs`, `az`, `abfs`, `abfss`, `http`, `https`).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry without any storage.
+    pub fn empty() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Registers `factory` for `scheme`, replacing the previous one if any.
+    pub fn register(&mut self, scheme: &str, factory: Arc<dyn SaStorageFactory>) -> Option<Arc<dyn SaStorageFactory>> {
+        self.factories.insert(scheme.to_ascii_lowercase(), factory)
+    }
+
+    pub fn deregister(&mut self, scheme: &str) -> Option<Arc<dyn SaStorageFactory>> {
+        self.factories.remove(&scheme.to_ascii_lowercase())
+    }
+
+    /// Registered schemes, sorted.
+    pub fn get_schemes(&self) -> Vec<String> {
+        let mut schemes: Vec<String> = self.factories.keys().cloned().collect();
+        schemes.sort();
+        schemes
+    }
+
+    pub fn get_factory(&self, scheme: &str) -> Result<Arc<dyn SaStorageFactory>> {
+        self.factories
+            .get(&scheme.to_ascii_lowercase())
+            .cloned()
+            .ok_or_else(|| SaError::UnsupportedScheme {
+                scheme: scheme.to_string(),
+                registered_schemes: self.get_schemes(),
+            })
+    }
+}
@@ -4,19 +4,11 @@ use async_trait::async_trait;
 use url::Url;
 use datafusion::catalog::Session;
 use datafusion::datasource::TableProvider;
-use datafusion::datasource::listing::{
-    ListingOptions,
-    ListingTable,
-    ListingTableConfig,
-    ListingTableUrl
-};
 use datafusion::datasource::file_format::FileFormat;
-use datafusion::common::Result;
+use datafusion::common::Result as DFResult;
 use datafusion::arrow::datatypes::{
     Schema,
-    SchemaRef,
-    Field,
-    DataType
+    SchemaRef
 };
 use datafusion_expr::{
     TableType,
@@ -26,35 +18,67 @@ use datafusion::physical_plan::ExecutionPlan;
 use object_store::aws::{AmazonS3Builder, AmazonS3};
 use object_store::ObjectStore;
 use regex::Regex;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use crate::object_storage::schema::SaSchemaOptions;
 use crate::object_storage::storage::SaStorage;
-use crate::datafusion::SaDataFusion;
+use crate::object_storage::registry::SaStorageFactory;
+use crate::datafusion::{SaDataFusion, SaOptions};
+use crate::object_storage::utils;
+use crate::error::{Result, SaError};
+
+
+/// Connection options of [`SaS3`] for S3-compatible servers, unset ones come from the `AWS_*` environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaS3Options {
+    /// Custom endpoint such as `http://localhost:9000` for MinIO.
+    pub endpoint: Option<String>,
+    /// `true` for path-style requests (`<endpoint>/<bucket>/<key>`), `false` for virtual-hosted style (`<bucket>.<endpoint>/<key>`).
+    pub is_path_style: Option<bool>,
+    /// Allows plain HTTP endpoints.
+    pub allow_http: Option<bool>,
+    /// Named profile of `~/.aws/credentials` and `~/.aws/config`, providing the keys and `endpoint_url`.
+    pub profile: Option<String>,
+}
 
 
-#[derive(Debug, Clone)]
+impl SaS3Options {
+    /// Options set with `SET sa.s3_<option> = '<value>'`.
+    pub fn from_sa_options(sa_options: &SaOptions) -> Self {
+        Self {
+            endpoint: Some(sa_options.s3_endpoint.clone()).filter(|endpoint| !endpoint.is_empty()),
+            is_path_style: sa_options.s3_path_style.then_some(true),
+            allow_http: sa_options.s3_allow_http.then_some(true),
+            profile: Some(sa_options.s3_profile.clone()).filter(|profile| !profile.is_empty()),
+        }
+    }
+
+    /// Returns these options with the unset ones taken from `other`.
+    pub fn or(self, other: SaS3Options) -> Self {
+        Self {
+            endpoint: self.endpoint.or(other.endpoint),
+            is_path_style: self.is_path_style.or(other.is_path_style),
+            allow_http: self.allow_http.or(other.allow_http),
+            profile: self.profile.or(other.profile),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Default)]
 pub struct SaS3 {
     s3_bucket: String,
     s3_src_key: String,
     s3_file: String,
     file_url: String,
+    options: SaS3Options,
     table_provider: Option<Arc<dyn TableProvider>>,
     object_store: Option<Arc<dyn ObjectStore>>
 }
 
 
-impl Default for SaS3 {
-    fn default() -> Self {
-        SaS3 {
-            s3_bucket: String::new(),
-            s3_src_key: String::new(),
-            s3_file: String::new(),
-            file_url: String::new(),
-            table_provider: None,
-            object_store: None
-        }
-    }
-}
-
-
 impl SaS3 {
     pub const PROTOCAL: &str = "s3";
 
@@ -86,9 +110,11 @@ impl SaS3 {
         }
     }
 
-    pub fn new_from_s3_uri(s3_uri: &str) -> Self {
-        let pattern: &str = r"s3://([^/]+)/(.+)/([^/]+)$";
-        let re: Regex = Regex::new(pattern).unwrap();
+    /// Parses `s3://<bucket>/<key>/<file>`; the key and file can be empty, for a prefix
+    /// (`s3://<bucket>/events/`), or contain a glob (`s3://<bucket>/events/*.parquet`).
+    pub fn new_from_s3_uri(s3_uri: &str) -> Result<Self> {
+        let pattern: &str = r"^s3://([^/]+)/?(?:(.*)/)?([^/]*)$";
+        let re: Regex = Regex::new(pattern).expect("valid S3 URI pattern");
 
         match re.captures(s3_uri) {
             Some(captures) => {
@@ -96,18 +122,91 @@ impl SaS3 {
                 let s3_bucket: &str = captures.get(1).map_or(default_value, |m| m.as_str());
                 let s3_src_key: &str = captures.get(2).map_or(default_value, |m| m.as_str());
                 let s3_file: &str = captures.get(3).map_or(default_value, |m| m.as_str());
-                Self {
+                Ok(Self {
                     s3_bucket: s3_bucket.to_string(),
                     s3_src_key: s3_src_key.to_string(),
                     s3_file: s3_file.to_string(),
                     file_url: s3_uri.to_string(),
                     ..Default::default()
-                }
+                })
             },
-            _ => {
-                panic!("[new_from_s3_uri][Exception]: Could not parse the S3 URI: {}", s3_uri);
+            _ => Err(SaError::uri_parse(s3_uri, "expected s3://<bucket>/<key>")),
+        }
+    }
+
+    pub fn with_options(mut self, options: SaS3Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn get_options(&self) -> SaS3Options {
+        self.options.clone()
+    }
+
+    /// Builder of the bucket's object store: the options first, then their profile, then the `AWS_*` environment.
+    pub fn get_object_store_builder(&self, s3_region: &str) -> Result<AmazonS3Builder> {
+        let mut builder: AmazonS3Builder = AmazonS3Builder::from_env() // extract credential inforation by OS env
+            .with_region(s3_region)
+            .with_bucket_name(self.s3_bucket.clone());
+        let mut endpoint: Option<String> = self.options.endpoint.clone();
+
+        if let Some(profile) = &self.options.profile {
+            let profile_values: HashMap<String, String> = read_aws_profile(profile)?;
+            let get_value = |key: &str| profile_values.get(key).cloned();
+            match (get_value("aws_access_key_id"), get_value("aws_secret_access_key")) {
+                (Some(access_key_id), Some(secret_access_key)) => {
+                    builder = builder
+                        .with_access_key_id(access_key_id)
+                        .with_secret_access_key(secret_access_key);
+                },
+                _ => return Err(SaError::Credentials(format!("AWS profile '{}' has no access key", profile))),
             }
+            if let Some(session_token) = get_value("aws_session_token") {
+                builder = builder.with_token(session_token);
+            }
+            endpoint = endpoint.or(get_value("endpoint_url"));
+        }
+        if let Some(endpoint) = endpoint {
+            builder = builder.with_endpoint(endpoint);
+        }
+        if let Some(is_path_style) = self.options.is_path_style {
+            builder = builder.with_virtual_hosted_style_request(!is_path_style);
         }
+        if let Some(allow_http) = self.options.allow_http {
+            builder = builder.with_allow_http(allow_http);
+        }
+        Ok(builder)
+    }
+
+    pub fn build_object_store(&self, s3_region: &str) -> Result<Arc<dyn ObjectStore>> {
+        let s3: AmazonS3 = self.get_object_store_builder(s3_region)?.build()?;
+        Ok(Arc::new(s3))
+    }
+
+    /// Region and options the bucket's object store is built with, e.g. `region us-east-1, endpoint http://localhost:9000`.
+    pub fn get_settings(&self, s3_region: &str) -> String {
+        let mut settings: Vec<String> = vec![format!("region {}", s3_region)];
+        if let Some(endpoint) = &self.options.endpoint {
+            settings.push(format!("endpoint {}", endpoint));
+        }
+        if let Some(profile) = &self.options.profile {
+            settings.push(format!("profile {}", profile));
+        }
+        if let Some(is_path_style) = self.options.is_path_style {
+            settings.push(format!("path style {}", is_path_style));
+        }
+        if let Some(allow_http) = self.options.allow_http {
+            settings.push(format!("allow http {}", allow_http));
+        }
+        settings.join(", ")
+    }
+
+    /// Builds the bucket's object store and registers it on `sa_datafusion`, unless the bucket
+    /// is registered with other settings, see [`SaDataFusion::register_configured_object_store`].
+    pub fn register_object_store(&self, s3_region: &str, sa_datafusion: &SaDataFusion) -> Result<Arc<dyn ObjectStore>> {
+        let object_store: Arc<dyn ObjectStore> = self.build_object_store(s3_region)?;
+        let url: Url = Url::parse(&self.file_url).map_err(|e| SaError::uri_parse(&self.file_url, e))?;
+        sa_datafusion.register_configured_object_store(&url, object_store, &self.get_settings(s3_region))
     }
 
     pub async fn init_table_provider(
@@ -115,51 +214,163 @@ impl SaS3 {
         s3_region: &str,
         sa_datafusion: &SaDataFusion,
         file_format: Arc<dyn FileFormat>,
-        infer_schema: Option<bool>,
+        schema_options: &SaSchemaOptions,
     ) -> Result<Self>  {
-        let is_infer_schema: bool = infer_schema.unwrap_or(true);
-        let s3: AmazonS3 = AmazonS3Builder::from_env() // extract credential inforation by OS env
-            .with_region(s3_region) // Replace with your AWS region
-            .with_bucket_name(self.s3_bucket.clone()) // Replace with your bucket name
-            .build()?;
-
-        let object_store: Arc<dyn ObjectStore> = Arc::new(s3);
-        sa_datafusion.register_object_store(&Url::parse(&self.file_url).unwrap(), object_store);
-        let listing_table_url: ListingTableUrl = ListingTableUrl::parse(self.file_url.clone())?;
-        let listing_options: ListingOptions = ListingOptions::new(file_format);
-
-        let schema: Arc<Schema> = if is_infer_schema {
-            // Auto inference
-            listing_options
-                .infer_schema(&sa_datafusion.get_session_state(), &listing_table_url)
-                .await?
-        } else {
-            // Create a schema with all columns as DataType::Utf8 (string)
-            let inferred_schema = listing_options
-                .infer_schema(&sa_datafusion.get_session_state(), &listing_table_url)
-                .await?;
-            let fields_as_string: Vec<Field> = inferred_schema
-                .fields()
-                .iter()
-                .map(|field| Field::new(field.name(), DataType::Utf8, field.is_nullable()))
-                .collect();
-
-            Arc::new(Schema::new(fields_as_string))
-        };
-
-        let listing_table_config: ListingTableConfig = ListingTableConfig::new(listing_table_url)
-            .with_listing_options(listing_options)
-            .with_schema(schema);
-        let table_provider: Arc<ListingTable> = Arc::new(ListingTable::try_new(listing_table_config)?);
+        let object_store: Arc<dyn ObjectStore> = self.register_object_store(s3_region, sa_datafusion)?;
+        self.object_store = Some(object_store);
+        let table_provider: Arc<dyn TableProvider> = utils::init_listing_table(
+            sa_datafusion,
+            &self.file_url,
+            file_format,
+            schema_options
+        ).await?;
         self.table_provider = Some(table_provider);
         Ok(self)
     }
 }
 
 
+/// Reads the keys of `profile` from the AWS shared config and credentials files,
+/// the credentials file taking precedence.
+fn read_aws_profile(profile: &str) -> Result<HashMap<String, String>> {
+    let aws_dir: PathBuf = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".aws"))
+        .unwrap_or_default();
+    let config_file: PathBuf = env::var_os("AWS_CONFIG_FILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| aws_dir.join("config"));
+    let credentials_file: PathBuf = env::var_os("AWS_SHARED_CREDENTIALS_FILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| aws_dir.join("credentials"));
+
+    // The config file names its sections `[profile <name>]`, except `[default]`
+    let config_section: String = match profile {
+        "default" => "default".to_string(),
+        profile => format!("profile {}", profile),
+    };
+    let mut profile_values: Option<HashMap<String, String>> = None;
+    for (file, section) in [(&config_file, config_section.as_str()), (&credentials_file, profile)] {
+        let Ok(content) = fs::read_to_string(file) else {
+            continue;
+        };
+        if let Some(values) = read_ini_section(&content, section) {
+            profile_values.get_or_insert_with(HashMap::new).extend(values);
+        }
+    }
+    profile_values.ok_or_else(|| SaError::Credentials(format!(
+        "AWS profile '{}' not found in {} or {}",
+        profile,
+        config_file.display(),
+        credentials_file.display()
+    )))
+}
+
+
+/// Returns the `key = value` pairs of `[section]`, `None` if there is no such section.
+fn read_ini_section(content: &str, section: &str) -> Option<HashMap<String, String>> {
+    let mut values: Option<HashMap<String, String>> = None;
+    let mut is_in_section: bool = false;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            is_in_section = name.trim() == section;
+            if is_in_section {
+                values.get_or_insert_with(HashMap::new);
+            }
+            continue;
+        }
+        if let (true, Some((key, value))) = (is_in_section, line.split_once('=')) {
+            values.get_or_insert_with(HashMap::new).insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    values
+}
+
+
+/// Creates [`SaS3`] for `s3://` URIs.
+///
+/// The region falls back to `sa.s3_region`, the `AWS_S3_REGION` environment variable, then `us-east-1`.
+/// Options set on the session with `SET sa.s3_<option>` take precedence over the factory ones.
+#[derive(Debug, Clone, Default)]
+pub struct SaS3Factory {
+    pub s3_region: Option<String>,
+    pub options: SaS3Options,
+}
+
+
+impl SaS3Factory {
+    pub const DEFAULT_REGION: &str = "us-east-1";
+
+    pub fn new(s3_region: &str) -> Self {
+        Self {
+            s3_region: Some(s3_region.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_options(mut self, options: SaS3Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn get_s3_region(&self) -> String {
+        self.s3_region
+            .clone()
+            .or_else(|| env::var("AWS_S3_REGION").ok())
+            .unwrap_or(Self::DEFAULT_REGION.to_string())
+    }
+
+    /// Region and options of the storages created for `sa_datafusion`'s session.
+    fn get_session_settings(&self, sa_datafusion: &SaDataFusion) -> (String, SaS3Options) {
+        let sa_options: SaOptions = sa_datafusion.get_options();
+        let s3_region: String = match sa_options.s3_region.is_empty() {
+            true => self.get_s3_region(),
+            false => sa_options.s3_region.clone(),
+        };
+        (s3_region, SaS3Options::from_sa_options(&sa_options).or(self.options.clone()))
+    }
+}
+
+
+#[async_trait]
+impl SaStorageFactory for SaS3Factory {
+    async fn register_object_store(&self, uri: &str, sa_datafusion: &SaDataFusion) -> Result<()> {
+        let (s3_region, options): (String, SaS3Options) = self.get_session_settings(sa_datafusion);
+        SaS3::new_from_s3_uri(uri)?
+            .with_options(options)
+            .register_object_store(s3_region.as_str(), sa_datafusion)?;
+        Ok(())
+    }
+
+    async fn create_storage(
+        &self,
+        uri: &str,
+        sa_datafusion: &SaDataFusion,
+        file_format: Arc<dyn FileFormat>,
+        schema_options: &SaSchemaOptions,
+    ) -> Result<Arc<dyn SaStorage>> {
+        let (s3_region, options): (String, SaS3Options) = self.get_session_settings(sa_datafusion);
+        let s3_storage: SaS3 = SaS3::new_from_s3_uri(uri)?
+            .with_options(options)
+            .init_table_provider(
+                s3_region.as_str(),
+                sa_datafusion,
+                file_format,
+                schema_options
+            ).await?;
+        Ok(Arc::new(s3_storage))
+    }
+}
+
+
 impl SaStorage for SaS3 {
-    fn get_table_provider(&self) -> Arc<dyn TableProvider> {
-        self.table_provider.clone().unwrap()
+    fn get_table_provider(&self) -> Result<Arc<dyn TableProvider>> {
+        self.table_provider
+            .clone()
+            .ok_or_else(|| SaError::provider_not_initialised(&self.file_url))
     }
 
     fn get_file_url(&self) -> String {
@@ -171,7 +382,7 @@ impl SaStorage for SaS3 {
     }
 
     fn get_object_store(&self) -> Option<Arc<dyn ObjectStore>>{
-        Some(self.object_store.clone().unwrap())
+        self.object_store.clone()
     }
 }
 
@@ -182,14 +393,20 @@ impl TableProvider for SaS3 {
         &self.table_provider
     }
 
-    /// Returns the schema of the table
+    /// Returns the schema of the table, empty until the provider is initialised
     fn schema(&self) -> SchemaRef {
-        self.table_provider.clone().unwrap().schema()
+        match &self.table_provider {
+            Some(table_provider) => table_provider.schema(),
+            None => Arc::new(Schema::empty()),
+        }
     }
 
     /// Returns the type of the table (e.g., Base or View)
     fn table_type(&self) -> TableType {
-        self.table_provider.clone().unwrap().table_type()
+        match &self.table_provider {
+            Some(table_provider) => table_provider.table_type(),
+            None => TableType::Base,
+        }
     }
 
     /// Creates a logical plan for scanning the table
@@ -199,7 +416,124 @@ impl TableProvider for SaS3 {
         projection: Option<&Vec<usize>>,
         filters: &[Expr],
         limit: Option<usize>,
-    ) -> Result<Arc<dyn ExecutionPlan>> {
-        self.table_provider.clone().unwrap().scan(state, projection, filters, limit).await
+    ) -> DFResult<Arc<dyn ExecutionPlan>> {
+        self.get_table_provider()?.scan(state, projection, filters, limit).await
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use object_store::aws::AmazonS3ConfigKey;
+    use tempfile::TempDir;
+
+
+    /// Tests pointing the `AWS_*` file variables at their own files run one at a time.
+    static AWS_FILES_LOCK: Mutex<()> = Mutex::new(());
+
+    const AWS_CONFIG: &str = "
+        [default]
+        region = eu-west-1
+
+        # MinIO running locally
+        [profile minio]
+        endpoint_url = http://profile:9000
+        aws_access_key_id = config-key
+    ";
+
+    const AWS_CREDENTIALS: &str = "
+        [default]
+        aws_access_key_id = default-key
+        aws_secret_access_key = default-secret
+
+        [minio]
+        AWS_Access_Key_Id = minio-key
+        aws_secret_access_key = minio-secret
+        ; temporary credentials
+        aws_session_token = minio-token
+    ";
+
+
+    /// Writes the AWS config and credentials files and points `AWS_CONFIG_FILE` and
+    /// `AWS_SHARED_CREDENTIALS_FILE` at them.
+    fn write_aws_files() -> TempDir {
+        let aws_dir: TempDir = tempfile::tempdir().unwrap();
+        fs::write(aws_dir.path().join("config"), AWS_CONFIG).unwrap();
+        fs::write(aws_dir.path().join("credentials"), AWS_CREDENTIALS).unwrap();
+        env::set_var("AWS_CONFIG_FILE", aws_dir.path().join("config"));
+        env::set_var("AWS_SHARED_CREDENTIALS_FILE", aws_dir.path().join("credentials"));
+        aws_dir
+    }
+
+
+    #[test]
+    fn test_read_ini_section() {
+        let values: HashMap<String, String> = read_ini_section(AWS_CREDENTIALS, "minio").unwrap();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values["aws_access_key_id"], "minio-key");
+        assert_eq!(values["aws_secret_access_key"], "minio-secret");
+        assert_eq!(values["aws_session_token"], "minio-token");
+
+        assert_eq!(read_ini_section("[empty]\n", "empty"), Some(HashMap::new()));
+        assert_eq!(read_ini_section(AWS_CREDENTIALS, "profile minio"), None);
+    }
+
+
+    #[test]
+    fn test_read_aws_profile() {
+        let _lock = AWS_FILES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let _aws_dir: TempDir = write_aws_files();
+
+        // Keys of the credentials file win over the `[profile <name>]` section of the config file
+        let values: HashMap<String, String> = read_aws_profile("minio").unwrap();
+        assert_eq!(values["aws_access_key_id"], "minio-key");
+        assert_eq!(values["endpoint_url"], "http://profile:9000");
+
+        let values: HashMap<String, String> = read_aws_profile("default").unwrap();
+        assert_eq!(values["aws_access_key_id"], "default-key");
+        assert_eq!(values["region"], "eu-west-1");
+
+        assert!(matches!(read_aws_profile("missing"), Err(SaError::Credentials(_))));
+    }
+
+
+    #[test]
+    fn test_session_options_precedence() {
+        let _lock = AWS_FILES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let _aws_dir: TempDir = write_aws_files();
+        let get_builder = |factory: &SaS3Factory, sa_datafusion: &SaDataFusion| {
+            let (s3_region, options): (String, SaS3Options) = factory.get_session_settings(sa_datafusion);
+            SaS3::new_from_s3_uri("s3://sql-anywhere/school/")
+                .unwrap()
+                .with_options(options)
+                .get_object_store_builder(&s3_region)
+                .unwrap()
+        };
+        let factory: SaS3Factory = SaS3Factory::new("us-west-2").with_options(SaS3Options {
+            profile: Some("minio".to_string()),
+            ..Default::default()
+        });
+        let sa_datafusion: SaDataFusion = SaDataFusion::new();
+
+        // The factory's profile provides the keys and the endpoint
+        let builder: AmazonS3Builder = get_builder(&factory, &sa_datafusion);
+        assert_eq!(builder.get_config_value(&AmazonS3ConfigKey::Region).as_deref(), Some("us-west-2"));
+        assert_eq!(builder.get_config_value(&AmazonS3ConfigKey::Endpoint).as_deref(), Some("http://profile:9000"));
+        assert_eq!(builder.get_config_value(&AmazonS3ConfigKey::AccessKeyId).as_deref(), Some("minio-key"));
+        assert_eq!(builder.get_config_value(&AmazonS3ConfigKey::Token).as_deref(), Some("minio-token"));
+
+        // Session options win over the profile's endpoint and the factory's region and profile
+        sa_datafusion.set_option("s3_endpoint", "http://session:9000").unwrap();
+        sa_datafusion.set_option("s3_region", "eu-central-1").unwrap();
+        let builder: AmazonS3Builder = get_builder(&factory, &sa_datafusion);
+        assert_eq!(builder.get_config_value(&AmazonS3ConfigKey::Region).as_deref(), Some("eu-central-1"));
+        assert_eq!(builder.get_config_value(&AmazonS3ConfigKey::Endpoint).as_deref(), Some("http://session:9000"));
+        assert_eq!(builder.get_config_value(&AmazonS3ConfigKey::AccessKeyId).as_deref(), Some("minio-key"));
+
+        sa_datafusion.set_option("s3_profile", "default").unwrap();
+        let builder: AmazonS3Builder = get_builder(&factory, &sa_datafusion);
+        assert_eq!(builder.get_config_value(&AmazonS3ConfigKey::AccessKeyId).as_deref(), Some("default-key"));
+        assert_eq!(builder.get_config_value(&AmazonS3ConfigKey::Endpoint).as_deref(), Some("http://session:9000"));
+    }
+}
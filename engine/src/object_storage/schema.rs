@@ -0,0 +1,286 @@
+use std::sync::Arc;
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::datasource::file_format::FileFormat;
+use datafusion::datasource::file_format::csv::CsvFormat;
+use datafusion::datasource::file_format::json::JsonFormat;
+use datafusion::error::DataFusionError;
+use crate::datafusion::SaOptions;
+use crate::error::Result;
+use crate::format::SaJsonArrayFormat;
+
+
+/// How the schema of a source is read.
+///
+/// The schema is inferred from the files unless an explicit `schema` is given; `type_hints`
+/// then override the type of some columns, e.g. `score: Float64`. With `is_all_utf8` every
+/// column of the files is read as `DataType::Utf8` before the hints apply.
+#[derive(Clone, Debug, Default)]
+pub struct SaSchemaOptions {
+    schema: Option<SchemaRef>,
+    type_hints: Vec<(String, DataType)>,
+    /// Records read to infer CSV and JSON schemas, the format's default if `None`.
+    sample_size: Option<usize>,
+    is_all_utf8: bool,
+}
+
+
+impl SaSchemaOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads every column of the files as `DataType::Utf8`.
+    pub fn all_utf8() -> Self {
+        Self::default().with_all_utf8(true)
+    }
+
+    /// Schema options of the `sa.schema*` session options.
+    pub fn from_sa_options(sa_options: &SaOptions) -> Result<Self> {
+        let mut schema_options: SaSchemaOptions = Self::default().with_all_utf8(sa_options.schema_all_utf8);
+        if let Some(schema) = sa_options.get_schema() {
+            schema_options = schema_options.with_schema(Arc::new(Schema::new(parse_fields(schema)?)));
+        }
+        if let Some(type_hints) = sa_options.get_schema_hints() {
+            for field in parse_fields(type_hints)? {
+                schema_options = schema_options.with_type_hint(field.name(), field.data_type().clone());
+            }
+        }
+        if let Some(sample_size) = sa_options.get_schema_sample_size() {
+            schema_options = schema_options.with_sample_size(sample_size);
+        }
+        Ok(schema_options)
+    }
+
+    pub fn with_schema(mut self, schema: SchemaRef) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Reads the column `name` as `data_type`, replacing a previous hint of that column.
+    pub fn with_type_hint(mut self, name: &str, data_type: DataType) -> Self {
+        self.type_hints.retain(|(hint_name, _)| hint_name != name);
+        self.type_hints.push((name.to_string(), data_type));
+        self
+    }
+
+    pub fn with_sample_size(mut self, sample_size: usize) -> Self {
+        self.sample_size = Some(sample_size);
+        self
+    }
+
+    pub fn with_all_utf8(mut self, is_all_utf8: bool) -> Self {
+        self.is_all_utf8 = is_all_utf8;
+        self
+    }
+
+    pub fn get_schema(&self) -> Option<&SchemaRef> {
+        self.schema.as_ref()
+    }
+
+    pub fn get_type_hint(&self, name: &str) -> Option<&DataType> {
+        self.type_hints
+            .iter()
+            .find(|(hint_name, _)| hint_name == name)
+            .map(|(_, data_type)| data_type)
+    }
+
+    pub fn get_sample_size(&self) -> Option<usize> {
+        self.sample_size
+    }
+
+    pub fn is_all_utf8(&self) -> bool {
+        self.is_all_utf8
+    }
+
+    /// `file_format` reading `sample_size` records to infer its schema, for CSV and JSON.
+    pub fn apply_sample_size(&self, file_format: Arc<dyn FileFormat>) -> Arc<dyn FileFormat> {
+        let Some(sample_size) = self.sample_size else {
+            return file_format;
+        };
+        if let Some(csv_format) = file_format.as_any().downcast_ref::<CsvFormat>() {
+            let csv_format: CsvFormat = CsvFormat::default()
+                .with_options(csv_format.options().clone())
+                .with_schema_infer_max_rec(sample_size);
+            Arc::new(csv_format)
+        } else if let Some(json_format) = file_format.as_any().downcast_ref::<JsonFormat>() {
+            let json_format: JsonFormat = JsonFormat::default()
+                .with_options(json_format.options().clone())
+                .with_schema_infer_max_rec(sample_size);
+            Arc::new(json_format)
+        } else if let Some(json_array_format) = file_format.as_any().downcast_ref::<SaJsonArrayFormat>() {
+            let json_array_format: SaJsonArrayFormat = SaJsonArrayFormat::new(json_array_format.options().clone())
+                .with_schema_infer_max_rec(sample_size);
+            Arc::new(json_array_format)
+        } else {
+            file_format
+        }
+    }
+
+    /// Schema of the files from the explicit or inferred `schema`, with the type overrides applied.
+    ///
+    /// A hint must name a column of `schema` or one of `partition_columns`, which are typed
+    /// apart, so that a misspelt column is not silently read with its inferred type.
+    pub fn resolve_schema(&self, schema: &Schema, partition_columns: &[String]) -> Result<SchemaRef> {
+        let unknown_column: Option<&String> = self.type_hints
+            .iter()
+            .map(|(name, _)| name)
+            .find(|name| schema.field_with_name(name).is_err() && !partition_columns.contains(name));
+        if let Some(name) = unknown_column {
+            let columns: Vec<&str> = schema
+                .fields()
+                .iter()
+                .map(|field| field.name().as_str())
+                .chain(partition_columns.iter().map(String::as_str))
+                .collect();
+            return Err(DataFusionError::Configuration(
+                format!("Type hint of unknown column '{}', columns: [{}]", name, columns.join(", "))
+            ).into());
+        }
+
+        let fields: Vec<Field> = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                let data_type: &DataType = match self.get_type_hint(field.name()) {
+                    Some(data_type) => data_type,
+                    None if self.is_all_utf8 => &DataType::Utf8,
+                    None => field.data_type(),
+                };
+                field.as_ref().clone().with_data_type(data_type.clone())
+            })
+            .collect();
+        Ok(Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone())))
+    }
+}
+
+
+/// Parses `name: Type` pairs separated by commas into nullable fields, e.g.
+/// `id: Int64, price: Decimal128(10, 2), ts: Timestamp(Millisecond, None)`.
+///
+/// Types are written as Arrow displays them; names can be double-quoted.
+pub fn parse_fields(fields: &str) -> Result<Vec<Field>> {
+    split_top_level(fields)
+        .into_iter()
+        .filter(|field| !field.trim().is_empty())
+        .map(|field| {
+            let (name, data_type) = field.split_once(':').ok_or_else(|| DataFusionError::Configuration(
+                format!("Expected '<name>: <type>' in the schema, got '{}'", field.trim())
+            ))?;
+            let name: &str = name.trim().trim_matches('"');
+            let data_type: DataType = data_type.trim().parse()?;
+            Ok(Field::new(name, data_type, true))
+        })
+        .collect()
+}
+
+
+/// Splits `fields` on the commas outside parentheses.
+fn split_top_level(fields: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut depth: usize = 0;
+    let mut start: usize = 0;
+    for (index, character) in fields.char_indices() {
+        match character {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&fields[start..index]);
+                start = index + 1;
+            },
+            _ => {},
+        }
+    }
+    parts.push(&fields[start..]);
+    parts
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::datatypes::TimeUnit;
+
+
+    #[test]
+    fn test_parse_fields() {
+        let fields: Vec<Field> = parse_fields("id: Int64, \"unit price\": Decimal128(10, 2), ts: Timestamp(Millisecond, None),").unwrap();
+        assert_eq!(
+            fields,
+            [
+                Field::new("id", DataType::Int64, true),
+                Field::new("unit price", DataType::Decimal128(10, 2), true),
+                Field::new("ts", DataType::Timestamp(TimeUnit::Millisecond, None), true),
+            ]
+        );
+        assert!(parse_fields("id Int64").is_err());
+        assert!(parse_fields("id: Integer").is_err());
+    }
+
+
+    #[test]
+    fn test_resolve_schema() {
+        let schema: Schema = Schema::new(vec![
+            Field::new("id", DataType::Int64, true),
+            Field::new("score", DataType::Int64, true),
+        ]);
+        let partition_columns: Vec<String> = vec!["year".to_string()];
+
+        let schema_options: SaSchemaOptions = SaSchemaOptions::new()
+            .with_type_hint("score", DataType::Utf8)
+            .with_type_hint("score", DataType::Float64)
+            .with_type_hint("year", DataType::Utf8);
+        let resolved_schema: SchemaRef = schema_options.resolve_schema(&schema, &partition_columns).unwrap();
+        assert_eq!(resolved_schema.field(0).data_type(), &DataType::Int64);
+        assert_eq!(resolved_schema.field(1).data_type(), &DataType::Float64);
+
+        // The hints apply after every column is read as Utf8
+        let schema_options: SaSchemaOptions = SaSchemaOptions::all_utf8().with_type_hint("score", DataType::Float64);
+        let resolved_schema: SchemaRef = schema_options.resolve_schema(&schema, &[]).unwrap();
+        assert_eq!(resolved_schema.field(0).data_type(), &DataType::Utf8);
+        assert_eq!(resolved_schema.field(1).data_type(), &DataType::Float64);
+
+        let schema_options: SaSchemaOptions = SaSchemaOptions::new().with_type_hint("scroe", DataType::Float64);
+        let error: String = schema_options.resolve_schema(&schema, &partition_columns).unwrap_err().to_string();
+        assert!(error.contains("Type hint of unknown column 'scroe', columns: [id, score, year]"), "{}", error);
+    }
+
+
+    #[test]
+    fn test_apply_sample_size() {
+        let schema_options: SaSchemaOptions = SaSchemaOptions::new().with_sample_size(10);
+        let csv_format: Arc<dyn FileFormat> = schema_options.apply_sample_size(Arc::new(CsvFormat::default().with_delimiter(b';')));
+        let csv_format: &CsvFormat = csv_format.as_any().downcast_ref::<CsvFormat>().unwrap();
+        assert_eq!(csv_format.options().schema_infer_max_rec, Some(10));
+        assert_eq!(csv_format.options().delimiter, b';');
+
+        let json_format: Arc<dyn FileFormat> = schema_options.apply_sample_size(Arc::new(JsonFormat::default()));
+        let json_format: &JsonFormat = json_format.as_any().downcast_ref::<JsonFormat>().unwrap();
+        assert_eq!(json_format.options().schema_infer_max_rec, Some(10));
+
+        // Without a sample size the format is kept as is
+        let csv_format: Arc<dyn FileFormat> = Arc::new(CsvFormat::default());
+        assert!(Arc::ptr_eq(&SaSchemaOptions::new().apply_sample_size(csv_format.clone()), &csv_format));
+    }
+
+
+    #[test]
+    fn test_from_sa_options() {
+        let sa_options: SaOptions = SaOptions {
+            schema: "id: Int64, score: Float64".to_string(),
+            schema_hints: "score: Decimal128(5, 2)".to_string(),
+            schema_sample_size: 100,
+            schema_all_utf8: true,
+            ..Default::default()
+        };
+        let schema_options: SaSchemaOptions = SaSchemaOptions::from_sa_options(&sa_options).unwrap();
+        assert_eq!(schema_options.get_schema().map(|schema| schema.fields().len()), Some(2));
+        assert_eq!(schema_options.get_type_hint("score"), Some(&DataType::Decimal128(5, 2)));
+        assert_eq!(schema_options.get_sample_size(), Some(100));
+        assert!(schema_options.is_all_utf8());
+
+        let schema_options: SaSchemaOptions = SaSchemaOptions::from_sa_options(&SaOptions::default()).unwrap();
+        assert!(schema_options.get_schema().is_none());
+        assert_eq!(schema_options.get_sample_size(), None);
+        assert!(!schema_options.is_all_utf8());
+    }
+}
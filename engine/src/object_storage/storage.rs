@@ -1,11 +1,13 @@
 use std::sync::Arc;
 use datafusion::datasource::TableProvider;
 use object_store::ObjectStore;
+use crate::error::Result;
 
 
 pub trait SaStorage: Send + Sync {
     fn get_protocal(&self) -> String;
-    fn get_table_provider(&self) -> Arc<dyn TableProvider>;
+    /// Fails with `SaError::ProviderNotInitialised` before `init_table_provider`.
+    fn get_table_provider(&self) -> Result<Arc<dyn TableProvider>>;
     fn get_file_url(&self) -> String;
     fn get_object_store(&self) -> Option<Arc<dyn ObjectStore>>;
 }
\ No newline at end of file
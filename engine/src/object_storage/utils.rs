@@ -1,17 +1,36 @@
 use std::path::Path;
 use std::io::{
-    self,
     Error,
     ErrorKind
 };
 use std::ffi::OsStr;
+use std::sync::Arc;
+use datafusion::datasource::file_format::FileFormat;
+use crate::error::Result;
+use datafusion::arrow::datatypes::{
+    DataType,
+    SchemaRef
+};
+use datafusion::datasource::TableProvider;
+use datafusion::datasource::listing::{
+    ListingOptions,
+    ListingTable,
+    ListingTableConfig,
+    ListingTableUrl
+};
+use crate::datafusion::SaDataFusion;
+use crate::object_storage::discovery;
+use crate::object_storage::schema::SaSchemaOptions;
+use crate::cache::{parquet, SaMetadataCache};
+use datafusion::execution::SessionState;
+use object_store::{ObjectMeta, ObjectStore};
 
 
 pub fn extract_path<P, F>(
     path: P,
     extract_path_fn: F,
     mode: &str
-) -> io::Result<String>
+) -> Result<String>
 where
     P: AsRef<Path>,
     F: Fn(&Path) -> Option<&OsStr>
@@ -23,9 +42,132 @@ where
             .map(|s| s.to_string())
             .ok_or_else(
                 || {
-                    Error::new(ErrorKind::InvalidData, format!("Invalid UTF-8 in {}", mode),)
+                    Error::new(ErrorKind::InvalidData, format!("Invalid UTF-8 in {}", mode),).into()
                 }
             ),
-        None => Err(Error::new(ErrorKind::InvalidData, format!("Path has no valid {}", mode)))
+        None => Err(Error::new(ErrorKind::InvalidData, format!("Path has no valid {}", mode)).into())
+    }
+}
+
+
+/// Listing options for `listing_table_url`: a single file or the files matched by
+/// a glob are read whatever their extension (`data.CSV`, `part-0000`), a directory
+/// only lists the files of `files` sharing an extension, those with the extension of
+/// `file_format` otherwise, so directories of `.jsonl` or `.parq` files are read too.
+pub fn get_listing_options(
+    file_format: Arc<dyn FileFormat>,
+    listing_table_url: &ListingTableUrl,
+    files: &[ObjectMeta],
+) -> ListingOptions {
+    let file_extension: String = file_format.get_ext();
+    let listing_options: ListingOptions = ListingOptions::new(file_format);
+    if listing_table_url.is_collection() && listing_table_url.scheme() != discovery::GLOB_SCHEME {
+        let file_extension: String = get_common_extension(files).unwrap_or(file_extension);
+        listing_options.with_file_extension(file_extension)
+    } else {
+        listing_options.with_file_extension("")
+    }
+}
+
+
+/// Extension (with its dot) of every file of `files`, `None` if they do not share one.
+fn get_common_extension(files: &[ObjectMeta]) -> Option<String> {
+    let mut extensions = files.iter().map(|file| file
+        .location
+        .filename()
+        .and_then(|file_name| file_name.rfind('.').map(|index| file_name[index..].to_string()))
+    );
+    let extension: String = extensions.next()??;
+    extensions.all(|other| other.as_ref() == Some(&extension)).then_some(extension)
+}
+
+
+/// Builds the [`ListingTable`] reading `file_url` with `file_format`.
+///
+/// `file_url` can be a file, a directory or a glob (see [`discovery::get_listing_table_url`]);
+/// the Hive partitions of a directory or glob become typed columns, pruned by the filters on them.
+/// The schema of the files is explicit or inferred, with the type overrides of `schema_options`,
+/// which apply to the partition columns too.
+///
+/// With the metadata cache, the inferred schema and Parquet footers are read from it while the
+/// files are unchanged, see [`SaMetadataCache`].
+pub async fn init_listing_table(
+    sa_datafusion: &SaDataFusion,
+    file_url: &str,
+    file_format: Arc<dyn FileFormat>,
+    schema_options: &SaSchemaOptions,
+) -> Result<Arc<dyn TableProvider>> {
+    let listing_table_url: ListingTableUrl = discovery::get_listing_table_url(sa_datafusion, file_url).await?;
+    let files: Vec<ObjectMeta> = match listing_table_url.is_collection() {
+        true => discovery::list_files(sa_datafusion, &listing_table_url).await?,
+        false => Vec::new(),
+    };
+    let metadata_cache: Option<Arc<SaMetadataCache>> = sa_datafusion.get_metadata_cache()?;
+    let mut file_format: Arc<dyn FileFormat> = schema_options.apply_sample_size(file_format);
+    if let Some(metadata_cache) = &metadata_cache {
+        file_format = parquet::with_metadata_cache(file_format, metadata_cache.clone(), listing_table_url.object_store());
+    }
+    let listing_options: ListingOptions = get_listing_options(file_format, &listing_table_url, &files);
+    let partition_columns: Vec<(String, DataType)> = discovery::infer_partition_columns(&listing_table_url, &files);
+    let partition_names: Vec<String> = partition_columns.iter().map(|(name, _)| name.clone()).collect();
+    let file_schema: SchemaRef = match schema_options.get_schema() {
+        Some(schema) => schema_options.resolve_schema(schema, &partition_names)?,
+        None => {
+            let inferred_schema: SchemaRef = infer_schema(
+                sa_datafusion,
+                metadata_cache.as_deref(),
+                &listing_options,
+                &listing_table_url,
+                &files
+            ).await?;
+            schema_options.resolve_schema(&inferred_schema, &partition_names)?
+        },
+    };
+
+    let partition_columns: Vec<(String, DataType)> = partition_columns
+        .into_iter()
+        // A column written both in the files and the path is read from the files
+        .filter(|(name, _)| file_schema.field_with_name(name).is_err())
+        .map(|(name, data_type)| match schema_options.get_type_hint(&name) {
+            Some(hint) => (name, hint.clone()),
+            None => (name, data_type),
+        })
+        .collect();
+    let listing_options: ListingOptions = listing_options.with_table_partition_cols(partition_columns);
+
+    let listing_table_config: ListingTableConfig = ListingTableConfig::new(listing_table_url)
+        .with_listing_options(listing_options)
+        .with_schema(file_schema);
+    Ok(Arc::new(ListingTable::try_new(listing_table_config)?))
+}
+
+
+/// Infers the schema of the files of `listing_table_url`, taken from `metadata_cache` while
+/// `files` (the file itself for a single file source) are unchanged since it was cached.
+async fn infer_schema(
+    sa_datafusion: &SaDataFusion,
+    metadata_cache: Option<&SaMetadataCache>,
+    listing_options: &ListingOptions,
+    listing_table_url: &ListingTableUrl,
+    files: &[ObjectMeta],
+) -> Result<SchemaRef> {
+    let session_state: SessionState = sa_datafusion.get_session_state();
+    let Some(metadata_cache) = metadata_cache else {
+        return Ok(listing_options.infer_schema(&session_state, listing_table_url).await?);
+    };
+    let files: Vec<ObjectMeta> = match listing_table_url.is_collection() {
+        true => files.to_vec(),
+        false => {
+            let object_store: Arc<dyn ObjectStore> = session_state.runtime_env().object_store(listing_table_url.object_store())?;
+            vec![object_store.head(listing_table_url.prefix()).await?]
+        },
+    };
+    // The format options (delimiter, sample size...) change the inferred schema too
+    let cache_key: String = format!("{} {:?} {}", listing_table_url, listing_options.format, listing_options.file_extension);
+    if let Some(schema) = metadata_cache.get_schema(&cache_key, &files) {
+        return Ok(schema);
     }
-}
\ No newline at end of file
+    let schema: SchemaRef = listing_options.infer_schema(&session_state, listing_table_url).await?;
+    metadata_cache.put_schema(&cache_key, &files, &schema);
+    Ok(schema)
+}
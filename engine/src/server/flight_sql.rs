@@ -0,0 +1,726 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use arrow_flight::decode::FlightRecordBatchStream;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::sql::metadata::{SqlInfoData, SqlInfoDataBuilder};
+use arrow_flight::sql::server::{FlightSqlService, PeekableFlightDataStream};
+use arrow_flight::sql::{
+    ActionClosePreparedStatementRequest, ActionCreatePreparedStatementRequest,
+    ActionCreatePreparedStatementResult, Any, CommandGetCatalogs, CommandGetDbSchemas,
+    CommandGetSqlInfo, CommandGetTableTypes, CommandGetTables, CommandPreparedStatementQuery,
+    CommandPreparedStatementUpdate, CommandStatementQuery, CommandStatementUpdate,
+    DoPutPreparedStatementResult, ProstMessageExt, SqlInfo, TicketStatementQuery,
+};
+use arrow_flight::{
+    Action, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest,
+    HandshakeResponse, IpcMessage, SchemaAsIpc, Ticket,
+};
+use bytes::Bytes;
+use datafusion::arrow::array::{Array, UInt64Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::arrow::ipc::writer::IpcWriteOptions;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::common::{DataFusionError, ParamValues, ResolvedTableReference, ScalarValue, TableReference};
+use datafusion::config::CatalogOptions;
+use datafusion::datasource::TableType;
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::prelude::DataFrame;
+use futures::{Stream, TryStreamExt};
+use prost::Message;
+use regex::Regex;
+use tokio::net::TcpListener;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tonic::metadata::MetadataValue;
+use tonic::{Request, Response, Status, Streaming};
+use crate::builder::pipelines;
+use crate::datafusion::SaDataFusion;
+use crate::error::{Result, SaError};
+
+
+/// Stream of Flight data answering a `DoGet`.
+type SaDoGetStream = Pin<Box<dyn Stream<Item = std::result::Result<FlightData, Status>> + Send>>;
+
+/// Stream of messages answering a `Handshake`.
+type SaHandshakeStream = Pin<Box<dyn Stream<Item = std::result::Result<HandshakeResponse, Status>> + Send>>;
+
+/// Most client sessions kept, and how long an unused one is kept.
+const MAX_SESSIONS: usize = 1024;
+const SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Most prepared statements kept, and how long an unused one is kept.
+const MAX_PREPARED_STATEMENTS: usize = 4096;
+const PREPARED_STATEMENT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Most results waiting for a `DoGet`, and how long one waits.
+const MAX_RESULTS: usize = 1024;
+const RESULT_TTL: Duration = Duration::from_secs(10 * 60);
+
+
+/// Session of a Flight SQL client.
+struct SaFlightSqlSession {
+    sa_datafusion: SaDataFusion,
+    /// Tables of the `sa.catalog` file that could not be registered, not tried again.
+    failed_catalog_tables: Mutex<HashSet<String>>,
+}
+
+
+/// Result planned for a Flight SQL client, waiting for its `DoGet`.
+struct SaPendingResult {
+    session: Arc<SaFlightSqlSession>,
+    df: DataFrame,
+}
+
+
+/// Statement prepared by a Flight SQL client, planned once and run in its session with the
+/// parameters it bound last.
+struct SaPreparedStatement {
+    session: Arc<SaFlightSqlSession>,
+    plan: LogicalPlan,
+    parameters: Option<ParamValues>,
+}
+
+
+/// Map whose entries expire once unused for a while, dropping the least recently used ones
+/// when it is full.
+struct SaExpiringMap<K, V> {
+    entries: HashMap<K, (V, Instant)>,
+    max_len: usize,
+    ttl: Duration,
+}
+
+
+impl<K: Eq + Hash + Clone, V> SaExpiringMap<K, V> {
+    fn new(max_len: usize, ttl: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            max_len,
+            ttl,
+        }
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if self.entries.len() >= self.max_len {
+            let now: Instant = Instant::now();
+            self.entries.retain(|_, (_, last_used)| now.duration_since(*last_used) < self.ttl);
+        }
+        if self.entries.len() >= self.max_len {
+            let least_recently_used: Option<K> = self.entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = least_recently_used {
+                self.entries.remove(&key);
+            }
+        }
+        self.entries.insert(key, (value, Instant::now()));
+    }
+
+    /// Value of `key` unless it expired, marked as used.
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let now: Instant = Instant::now();
+        if self.entries.get(key).is_some_and(|(_, last_used)| now.duration_since(*last_used) >= self.ttl) {
+            self.entries.remove(key);
+            return None;
+        }
+        let (value, last_used) = self.entries.get_mut(key)?;
+        *last_used = now;
+        Some(value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let (value, last_used) = self.entries.remove(key)?;
+        (last_used.elapsed() < self.ttl).then_some(value)
+    }
+}
+
+
+/// Flight SQL service running the statements of each client in a session of its own, so its
+/// `SET` statements only apply to it; sources read by URI are registered the first time a
+/// statement reads them, as in `sa_query`, and stay registered for every client.
+///
+/// A client is told apart by the token a `Handshake` returns, sent back as a bearer token, or
+/// else by its connection. Tokens, tickets and prepared statement handles are random, and a
+/// ticket or handle is only known to the session that asked for it. Unused sessions, prepared
+/// statements and results expire.
+///
+/// `GetFlightInfo` plans a statement and keeps its result until a `DoGet` with the returned
+/// ticket streams it, prepared statements take their positional `$1`, `$2`... parameters from
+/// the first row bound by `DoPut`.
+pub struct SaFlightSqlService {
+    sa_datafusion: SaDataFusion,
+    sql_info: SqlInfoData,
+    sessions: Mutex<SaExpiringMap<String, Arc<SaFlightSqlSession>>>,
+    prepared_statements: Mutex<SaExpiringMap<String, SaPreparedStatement>>,
+    /// Planned results not fetched by a `DoGet` yet.
+    results: Mutex<SaExpiringMap<String, SaPendingResult>>,
+}
+
+
+impl SaFlightSqlService {
+    pub fn new(sa_datafusion: SaDataFusion) -> Self {
+        let mut sql_info: SqlInfoDataBuilder = SqlInfoDataBuilder::new();
+        sql_info.append(SqlInfo::FlightSqlServerName, "SQLAnyWhere");
+        sql_info.append(SqlInfo::FlightSqlServerVersion, env!("CARGO_PKG_VERSION"));
+        // Version of the Arrow columnar format
+        sql_info.append(SqlInfo::FlightSqlServerArrowVersion, "1.3");
+        sql_info.append(SqlInfo::FlightSqlServerReadOnly, false);
+        Self {
+            sa_datafusion,
+            sql_info: sql_info.build().expect("valid SQL info"),
+            sessions: Mutex::new(SaExpiringMap::new(MAX_SESSIONS, SESSION_TTL)),
+            prepared_statements: Mutex::new(SaExpiringMap::new(MAX_PREPARED_STATEMENTS, PREPARED_STATEMENT_TTL)),
+            results: Mutex::new(SaExpiringMap::new(MAX_RESULTS, RESULT_TTL)),
+        }
+    }
+
+    pub fn get_sa_datafusion(&self) -> &SaDataFusion {
+        &self.sa_datafusion
+    }
+
+    /// Serves Flight SQL on `listener` until the server fails.
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        let incoming: TcpIncoming = TcpIncoming::from_listener(listener, true, None).map_err(io::Error::other)?;
+        Server::builder()
+            .add_service(FlightServiceServer::new(self))
+            .serve_with_incoming(incoming)
+            .await
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    fn new_session(&self) -> Arc<SaFlightSqlSession> {
+        Arc::new(SaFlightSqlSession {
+            sa_datafusion: self.sa_datafusion.new_session(),
+            failed_catalog_tables: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Session of the client sending `request`: the one of its bearer token, or else the one
+    /// of its connection, created on its first request.
+    fn get_session<T>(&self, request: &Request<T>) -> Result<Arc<SaFlightSqlSession>, Status> {
+        let token: Option<&str> = request
+            .metadata()
+            .get("authorization")
+            .and_then(|authorization| authorization.to_str().ok())
+            .and_then(|authorization| authorization.strip_prefix("Bearer "));
+        let mut sessions = self.sessions.lock().expect("sessions lock poisoned");
+        if let Some(token) = token {
+            return sessions
+                .get_mut(&token.to_string())
+                .cloned()
+                .ok_or_else(|| Status::unauthenticated("Unknown or expired session token, handshake again"));
+        }
+        let connection: String = request
+            .remote_addr()
+            .map(|address| address.to_string())
+            .unwrap_or_default();
+        if let Some(session) = sessions.get_mut(&connection) {
+            return Ok(session.clone());
+        }
+        let session: Arc<SaFlightSqlSession> = self.new_session();
+        sessions.insert(connection, session.clone());
+        Ok(session)
+    }
+
+    /// Keeps `df` until a `DoGet` of `session` fetches it and returns where to fetch it from.
+    fn get_result_flight_info(
+        &self,
+        session: Arc<SaFlightSqlSession>,
+        df: DataFrame,
+        flight_descriptor: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema: Schema = df.schema().as_arrow().clone();
+        let handle: String = new_token()?;
+        self.results
+            .lock()
+            .expect("results lock poisoned")
+            .insert(handle.clone(), SaPendingResult { session, df });
+        let ticket: TicketStatementQuery = TicketStatementQuery {
+            statement_handle: Bytes::from(handle),
+        };
+        get_flight_info(&schema, ticket.as_any(), flight_descriptor)
+    }
+
+    /// Prepared statement `handle` of `session`, unknown to every other session.
+    fn get_prepared_statement<'a>(
+        prepared_statements: &'a mut SaExpiringMap<String, SaPreparedStatement>,
+        session: &Arc<SaFlightSqlSession>,
+        handle: &Bytes,
+    ) -> Result<&'a mut SaPreparedStatement, Status> {
+        let handle: String = decode_handle(handle)?;
+        prepared_statements
+            .get_mut(&handle)
+            .filter(|prepared_statement| Arc::ptr_eq(&prepared_statement.session, session))
+            .ok_or_else(|| Status::not_found("Unknown or expired prepared statement"))
+    }
+
+    /// Plan of the prepared statement `handle` of `session`, with its parameters bound.
+    fn get_prepared_plan(&self, session: &Arc<SaFlightSqlSession>, handle: &Bytes) -> Result<LogicalPlan, Status> {
+        let mut prepared_statements = self.prepared_statements.lock().expect("prepared statements lock poisoned");
+        let prepared_statement: &SaPreparedStatement = Self::get_prepared_statement(&mut prepared_statements, session, handle)?;
+        let plan: LogicalPlan = match &prepared_statement.parameters {
+            Some(parameters) => prepared_statement.plan.clone().with_param_values(parameters.clone()).map_err(SaError::from)?,
+            None => prepared_statement.plan.clone(),
+        };
+        Ok(plan)
+    }
+}
+
+
+impl SaFlightSqlSession {
+    /// Registers the tables of the `sa.catalog` file in `catalog_name` whose schema and name
+    /// match the `LIKE` patterns, so the metadata commands list them; a table that cannot be
+    /// read is skipped, and not tried again by later commands.
+    async fn register_catalog_tables(
+        &self,
+        catalog_name: Option<&str>,
+        schema_pattern: Option<&str>,
+        table_pattern: Option<&str>,
+    ) -> Result<()> {
+        let Some(catalog) = self.sa_datafusion.get_catalog()? else {
+            return Ok(());
+        };
+        let catalog_options: CatalogOptions = self.sa_datafusion.get_session_state().config_options().catalog.clone();
+        for table_name in catalog.get_table_names() {
+            let resolved: ResolvedTableReference = TableReference::from(table_name.as_str())
+                .resolve(&catalog_options.default_catalog, &catalog_options.default_schema);
+            let is_listed: bool = catalog_name.is_none_or(|catalog_name| *catalog_name == *resolved.catalog)
+                && is_like_match(schema_pattern, &resolved.schema)
+                && is_like_match(table_pattern, &resolved.table);
+            if !is_listed || self.failed_catalog_tables.lock().expect("failed catalog tables lock poisoned").contains(&table_name) {
+                continue;
+            }
+            if let Err(error) = pipelines::sa_register_catalog_table_pipeline(&self.sa_datafusion, table_name.as_str()).await {
+                eprintln!("[sa_flight_sql]: Could not register the catalog table {}: {}", table_name, error);
+                self.failed_catalog_tables.lock().expect("failed catalog tables lock poisoned").insert(table_name);
+            }
+        }
+        Ok(())
+    }
+}
+
+
+#[tonic::async_trait]
+impl FlightSqlService for SaFlightSqlService {
+    type FlightService = SaFlightSqlService;
+
+    async fn get_flight_info_statement(
+        &self,
+        query: CommandStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let session: Arc<SaFlightSqlSession> = self.get_session(&request)?;
+        let df: DataFrame = pipelines::sa_query(&session.sa_datafusion, &query.query).await?;
+        self.get_result_flight_info(session, df, request.into_inner())
+    }
+
+    async fn get_flight_info_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let session: Arc<SaFlightSqlSession> = self.get_session(&request)?;
+        let plan: LogicalPlan = self.get_prepared_plan(&session, &query.prepared_statement_handle)?;
+        let df: DataFrame = session.sa_datafusion.ctx.execute_logical_plan(plan).await.map_err(SaError::from)?;
+        self.get_result_flight_info(session, df, request.into_inner())
+    }
+
+    async fn get_flight_info_catalogs(
+        &self,
+        query: CommandGetCatalogs,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema: SchemaRef = query.into_builder().schema();
+        get_flight_info(&schema, query.as_any(), request.into_inner())
+    }
+
+    async fn get_flight_info_schemas(
+        &self,
+        query: CommandGetDbSchemas,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema: SchemaRef = query.clone().into_builder().schema();
+        get_flight_info(&schema, query.as_any(), request.into_inner())
+    }
+
+    async fn get_flight_info_tables(
+        &self,
+        query: CommandGetTables,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema: SchemaRef = query.clone().into_builder().schema();
+        get_flight_info(&schema, query.as_any(), request.into_inner())
+    }
+
+    async fn get_flight_info_table_types(
+        &self,
+        query: CommandGetTableTypes,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema: SchemaRef = query.into_builder().schema();
+        get_flight_info(&schema, query.as_any(), request.into_inner())
+    }
+
+    async fn get_flight_info_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema: SchemaRef = query.clone().into_builder(&self.sql_info).schema();
+        get_flight_info(&schema, query.as_any(), request.into_inner())
+    }
+
+    async fn do_get_statement(
+        &self,
+        ticket: TicketStatementQuery,
+        request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let session: Arc<SaFlightSqlSession> = self.get_session(&request)?;
+        let handle: String = decode_handle(&ticket.statement_handle)?;
+        let df: DataFrame = {
+            let mut results = self.results.lock().expect("results lock poisoned");
+            // Another session's result is left in place, as if it did not exist
+            if !results.get_mut(&handle).is_some_and(|result| Arc::ptr_eq(&result.session, &session)) {
+                return Err(Status::not_found("Unknown, expired or already fetched result"));
+            }
+            results
+                .remove(&handle)
+                .ok_or_else(|| Status::not_found("Unknown, expired or already fetched result"))?
+                .df
+        };
+        let stream: SendableRecordBatchStream = df.execute_stream().await.map_err(SaError::from)?;
+        let schema: SchemaRef = stream.schema();
+        let stream = stream.map_err(|error| FlightError::ExternalError(Box::new(error)));
+        Ok(Response::new(encode_batches(schema, stream)))
+    }
+
+    async fn do_get_catalogs(
+        &self,
+        query: CommandGetCatalogs,
+        request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let session: Arc<SaFlightSqlSession> = self.get_session(&request)?;
+        let mut builder = query.into_builder();
+        for catalog_name in session.sa_datafusion.ctx.catalog_names() {
+            builder.append(catalog_name);
+        }
+        Ok(Response::new(encode_batch(builder.schema(), builder.build())))
+    }
+
+    async fn do_get_schemas(
+        &self,
+        query: CommandGetDbSchemas,
+        request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let session: Arc<SaFlightSqlSession> = self.get_session(&request)?;
+        session
+            .register_catalog_tables(query.catalog.as_deref(), query.db_schema_filter_pattern.as_deref(), None)
+            .await?;
+        let mut builder = query.into_builder();
+        for catalog_name in session.sa_datafusion.ctx.catalog_names() {
+            let Some(catalog) = session.sa_datafusion.ctx.catalog(&catalog_name) else {
+                continue;
+            };
+            for schema_name in catalog.schema_names() {
+                builder.append(&catalog_name, schema_name);
+            }
+        }
+        Ok(Response::new(encode_batch(builder.schema(), builder.build())))
+    }
+
+    async fn do_get_tables(
+        &self,
+        query: CommandGetTables,
+        request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let session: Arc<SaFlightSqlSession> = self.get_session(&request)?;
+        session
+            .register_catalog_tables(
+                query.catalog.as_deref(),
+                query.db_schema_filter_pattern.as_deref(),
+                query.table_name_filter_pattern.as_deref()
+            )
+            .await?;
+        let mut builder = query.into_builder();
+        for catalog_name in session.sa_datafusion.ctx.catalog_names() {
+            let Some(catalog) = session.sa_datafusion.ctx.catalog(&catalog_name) else {
+                continue;
+            };
+            for schema_name in catalog.schema_names() {
+                let Some(schema) = catalog.schema(&schema_name) else {
+                    continue;
+                };
+                for table_name in schema.table_names() {
+                    let Some(table) = schema.table(&table_name).await.map_err(SaError::from)? else {
+                        continue;
+                    };
+                    builder
+                        .append(&catalog_name, &schema_name, &table_name, table_type_name(table.table_type()), &table.schema())?;
+                }
+            }
+        }
+        Ok(Response::new(encode_batch(builder.schema(), builder.build())))
+    }
+
+    async fn do_get_table_types(
+        &self,
+        query: CommandGetTableTypes,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let mut builder = query.into_builder();
+        for table_type in [TableType::Base, TableType::View, TableType::Temporary] {
+            builder.append(table_type_name(table_type));
+        }
+        Ok(Response::new(encode_batch(builder.schema(), builder.build())))
+    }
+
+    async fn do_get_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let builder = query.into_builder(&self.sql_info);
+        Ok(Response::new(encode_batch(builder.schema(), builder.build())))
+    }
+
+    async fn do_put_statement_update(
+        &self,
+        query: CommandStatementUpdate,
+        request: Request<PeekableFlightDataStream>,
+    ) -> Result<i64, Status> {
+        let session: Arc<SaFlightSqlSession> = self.get_session(&request)?;
+        let df: DataFrame = pipelines::sa_query(&session.sa_datafusion, &query.query).await?;
+        Ok(get_record_count(df).await?)
+    }
+
+    async fn do_put_prepared_statement_query(
+        &self,
+        query: CommandPreparedStatementQuery,
+        request: Request<PeekableFlightDataStream>,
+    ) -> Result<DoPutPreparedStatementResult, Status> {
+        let session: Arc<SaFlightSqlSession> = self.get_session(&request)?;
+        let batches: Vec<RecordBatch> = FlightRecordBatchStream::new_from_flight_data(request.into_inner().map_err(FlightError::from))
+            .try_collect()
+            .await?;
+        let rows: Vec<&RecordBatch> = batches.iter().filter(|batch| batch.num_rows() > 0).collect();
+        let parameters: &RecordBatch = match rows[..] {
+            [batch] if batch.num_rows() == 1 => batch,
+            _ => return Err(Status::invalid_argument("Expected a single row of parameters")),
+        };
+        let parameters: Vec<ScalarValue> = parameters
+            .columns()
+            .iter()
+            .map(|column| Ok(ScalarValue::try_from_array(column, 0)?))
+            .collect::<Result<Vec<ScalarValue>>>()?;
+        let mut prepared_statements = self.prepared_statements.lock().expect("prepared statements lock poisoned");
+        Self::get_prepared_statement(&mut prepared_statements, &session, &query.prepared_statement_handle)?
+            .parameters = Some(ParamValues::List(parameters));
+        Ok(DoPutPreparedStatementResult {
+            prepared_statement_handle: Some(query.prepared_statement_handle),
+        })
+    }
+
+    async fn do_put_prepared_statement_update(
+        &self,
+        query: CommandPreparedStatementUpdate,
+        request: Request<PeekableFlightDataStream>,
+    ) -> Result<i64, Status> {
+        let session: Arc<SaFlightSqlSession> = self.get_session(&request)?;
+        let plan: LogicalPlan = self.get_prepared_plan(&session, &query.prepared_statement_handle)?;
+        let df: DataFrame = session.sa_datafusion.ctx.execute_logical_plan(plan).await.map_err(SaError::from)?;
+        Ok(get_record_count(df).await?)
+    }
+
+    async fn do_action_create_prepared_statement(
+        &self,
+        query: ActionCreatePreparedStatementRequest,
+        request: Request<Action>,
+    ) -> Result<ActionCreatePreparedStatementResult, Status> {
+        let session: Arc<SaFlightSqlSession> = self.get_session(&request)?;
+        let plan: LogicalPlan = pipelines::sa_plan(&session.sa_datafusion, &query.query).await?;
+        let dataset_schema: Schema = plan.schema().as_arrow().clone();
+        let parameter_schema: Schema = get_parameter_schema(&plan)?;
+        let handle: String = new_token()?;
+        self.prepared_statements
+            .lock()
+            .expect("prepared statements lock poisoned")
+            .insert(handle.clone(), SaPreparedStatement {
+                session,
+                plan,
+                parameters: None,
+            });
+        Ok(ActionCreatePreparedStatementResult {
+            prepared_statement_handle: Bytes::from(handle),
+            dataset_schema: encode_schema(&dataset_schema)?,
+            parameter_schema: encode_schema(&parameter_schema)?,
+        })
+    }
+
+    async fn do_action_close_prepared_statement(
+        &self,
+        query: ActionClosePreparedStatementRequest,
+        request: Request<Action>,
+    ) -> Result<(), Status> {
+        let session: Arc<SaFlightSqlSession> = self.get_session(&request)?;
+        let mut prepared_statements = self.prepared_statements.lock().expect("prepared statements lock poisoned");
+        Self::get_prepared_statement(&mut prepared_statements, &session, &query.prepared_statement_handle)?;
+        prepared_statements.remove(&decode_handle(&query.prepared_statement_handle)?);
+        Ok(())
+    }
+
+    /// Opens a session for the client, whatever its credentials, and returns its token.
+    async fn do_handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<SaHandshakeStream>, Status> {
+        let token: String = new_token()?;
+        self.sessions.lock().expect("sessions lock poisoned").insert(token.clone(), self.new_session());
+        let handshake_response: HandshakeResponse = HandshakeResponse {
+            protocol_version: 0,
+            payload: Bytes::from(token.clone()),
+        };
+        let mut response: Response<SaHandshakeStream> = Response::new(Box::pin(futures::stream::iter([Ok(handshake_response)])));
+        let authorization: MetadataValue<_> = format!("Bearer {}", token)
+            .parse()
+            .map_err(|_| Status::internal("Invalid session token"))?;
+        response.metadata_mut().insert("authorization", authorization);
+        Ok(response)
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+
+impl From<SaError> for Status {
+    fn from(error: SaError) -> Self {
+        match &error {
+            SaError::Credentials(_) => Status::unauthenticated(error.to_string()),
+            SaError::UriParse { .. }
+            | SaError::UnsupportedFormat { .. }
+            | SaError::UnsupportedScheme { .. }
+            | SaError::Catalog { .. }
+            | SaError::ObjectStoreConflict { .. } => Status::invalid_argument(error.to_string()),
+            SaError::DataFusion(datafusion_error)
+                if matches!(datafusion_error.as_ref(), DataFusionError::SQL(..) | DataFusionError::Plan(_) | DataFusionError::SchemaError(..)) =>
+            {
+                Status::invalid_argument(error.to_string())
+            },
+            SaError::ObjectStore(object_store_error) if matches!(object_store_error.as_ref(), object_store::Error::NotFound { .. }) => {
+                Status::not_found(error.to_string())
+            },
+            _ => Status::internal(error.to_string()),
+        }
+    }
+}
+
+
+/// Random session token, ticket or prepared statement handle: 128 bits from the operating
+/// system's random source, in hexadecimal.
+fn new_token() -> Result<String, Status> {
+    let mut bytes: [u8; 16] = [0; 16];
+    getrandom::getrandom(&mut bytes).map_err(|error| Status::internal(format!("Could not generate a token: {}", error)))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+
+/// Whether `value` matches the SQL `LIKE` `pattern` of a metadata command, `%` matching any
+/// characters and `_` one; no pattern matches everything.
+fn is_like_match(pattern: Option<&str>, value: &str) -> bool {
+    let Some(pattern) = pattern else {
+        return true;
+    };
+    let mut expression: String = String::from("^");
+    for character in pattern.chars() {
+        match character {
+            '%' => expression.push_str(".*"),
+            '_' => expression.push('.'),
+            character => expression.push_str(&regex::escape(&character.to_string())),
+        }
+    }
+    expression.push('$');
+    Regex::new(&expression).is_ok_and(|regex| regex.is_match(value))
+}
+
+
+fn decode_handle(handle: &Bytes) -> Result<String, Status> {
+    std::str::from_utf8(handle)
+        .map(str::to_string)
+        .map_err(|_| Status::invalid_argument("Invalid statement handle"))
+}
+
+
+fn encode_schema(schema: &Schema) -> Result<Bytes> {
+    let IpcMessage(schema) = SchemaAsIpc::new(schema, &IpcWriteOptions::default()).try_into()?;
+    Ok(schema)
+}
+
+
+/// Flight info of a result with `schema`, fetched by a `DoGet` of the `ticket` command.
+fn get_flight_info(schema: &Schema, ticket: Any, flight_descriptor: FlightDescriptor) -> Result<Response<FlightInfo>, Status> {
+    let flight_info: FlightInfo = FlightInfo::new()
+        .try_with_schema(schema)
+        .map_err(SaError::from)?
+        .with_endpoint(FlightEndpoint::new().with_ticket(Ticket::new(ticket.encode_to_vec())))
+        .with_descriptor(flight_descriptor);
+    Ok(Response::new(flight_info))
+}
+
+
+fn encode_batches(
+    schema: SchemaRef,
+    batches: impl Stream<Item = std::result::Result<RecordBatch, FlightError>> + Send + 'static,
+) -> SaDoGetStream {
+    let stream = FlightDataEncoderBuilder::new()
+        .with_schema(schema)
+        .build(batches)
+        .map_err(Status::from);
+    Box::pin(stream)
+}
+
+
+fn encode_batch(schema: SchemaRef, batch: std::result::Result<RecordBatch, FlightError>) -> SaDoGetStream {
+    encode_batches(schema, futures::stream::once(async { batch }))
+}
+
+
+/// Schema of the `$1`, `$2`... placeholders of `plan`, in order; a placeholder whose type cannot
+/// be inferred is `Null`.
+pub fn get_parameter_schema(plan: &LogicalPlan) -> Result<Schema> {
+    let mut parameters: Vec<(String, Option<DataType>)> = plan.get_parameter_types()?.into_iter().collect();
+    parameters.sort_by_key(|(name, _)| (name.trim_start_matches('$').parse::<usize>().unwrap_or(usize::MAX), name.clone()));
+    let fields: Vec<Field> = parameters
+        .into_iter()
+        .map(|(name, data_type)| Field::new(name, data_type.unwrap_or(DataType::Null), true))
+        .collect();
+    Ok(Schema::new(fields))
+}
+
+
+/// Rows changed by a statement: DML and `COPY` statements return them in a `count` column,
+/// other statements none.
+pub async fn get_record_count(df: DataFrame) -> Result<i64> {
+    let batches: Vec<RecordBatch> = df.collect().await?;
+    let count: u64 = batches
+        .iter()
+        .filter_map(|batch| batch.column_by_name("count")?.as_any().downcast_ref::<UInt64Array>().cloned())
+        .map(|counts| counts.iter().flatten().sum::<u64>())
+        .sum();
+    Ok(count as i64)
+}
+
+
+fn table_type_name(table_type: TableType) -> &'static str {
+    match table_type {
+        TableType::Base => "TABLE",
+        TableType::View => "VIEW",
+        TableType::Temporary => "LOCAL TEMPORARY",
+    }
+}
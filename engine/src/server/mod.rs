@@ -0,0 +1,7 @@
+// The Flight SQL services return tonic's `Status`, and so do their helpers
+#[allow(clippy::result_large_err)]
+pub mod flight_sql;
+pub mod pg_catalog;
+pub mod pgwire;
+pub use flight_sql::SaFlightSqlService;
+pub use pgwire::SaPgWireServer;
\ No newline at end of file
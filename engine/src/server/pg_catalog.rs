@@ -0,0 +1,559 @@
+use std::any::Any;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Weak};
+use async_trait::async_trait;
+use datafusion::arrow::array::{new_null_array, ArrayRef, AsArray, BooleanArray, Int16Array, Int32Array, Int64Array, StringArray};
+use datafusion::arrow::datatypes::{DataType, Field, Int64Type, Schema, SchemaRef};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::catalog::{CatalogProvider, CatalogProviderList, SchemaProvider, TableProvider};
+use datafusion::common::{DataFusionError, ScalarValue};
+use datafusion::config::CatalogOptions;
+use datafusion::datasource::{MemTable, TableType};
+use datafusion::execution::SessionState;
+use datafusion::logical_expr::{create_udf, ColumnarValue, ScalarUDF, Volatility};
+use pgwire::api::Type;
+use crate::cache::metadata::fnv1a_hash;
+use crate::datafusion::SaDataFusion;
+use crate::error::Result;
+use crate::server::pgwire::to_pg_type;
+
+
+/// Postgres version reported to clients, which pick their queries and features by it.
+pub const PG_SERVER_VERSION: &str = "14.0";
+
+/// Server parameters reported to clients on startup, answered by `SHOW` and listed in
+/// `pg_settings`.
+pub const PG_PARAMETERS: [(&str, &str); 11] = [
+    ("server_version", PG_SERVER_VERSION),
+    ("server_encoding", "UTF8"),
+    ("client_encoding", "UTF8"),
+    ("DateStyle", "ISO, YMD"),
+    ("IntervalStyle", "postgres"),
+    ("TimeZone", "UTC"),
+    ("integer_datetimes", "on"),
+    ("standard_conforming_strings", "on"),
+    ("search_path", "public"),
+    ("transaction_isolation", "read committed"),
+    ("max_identifier_length", "63"),
+];
+
+/// Owner of every object, as there are no users.
+const PG_USER: &str = "sqlanywhere";
+const PG_USER_OID: i64 = 10;
+const PG_CATALOG_OID: i64 = 11;
+const PUBLIC_OID: i64 = 2200;
+/// First OID of user objects in Postgres, objects of the session are numbered from there.
+const FIRST_OBJECT_OID: i64 = 16384;
+/// Access method of the tables, the only one listed in `pg_am`.
+const HEAP_OID: i64 = 2;
+
+const PG_TABLES: [&str; 10] = [
+    "pg_am",
+    "pg_attribute",
+    "pg_class",
+    "pg_database",
+    "pg_description",
+    "pg_namespace",
+    "pg_settings",
+    "pg_tables",
+    "pg_type",
+    "pg_views",
+];
+
+
+/// Postgres types the results are sent as, with the element type of the array ones.
+fn get_pg_types() -> Vec<(Type, Option<Type>)> {
+    vec![
+        (Type::BOOL, None),
+        (Type::BYTEA, None),
+        (Type::INT8, None),
+        (Type::INT2, None),
+        (Type::INT4, None),
+        (Type::TEXT, None),
+        (Type::OID, None),
+        (Type::FLOAT4, None),
+        (Type::FLOAT8, None),
+        (Type::VARCHAR, None),
+        (Type::DATE, None),
+        (Type::TIME, None),
+        (Type::TIMESTAMP, None),
+        (Type::TIMESTAMPTZ, None),
+        (Type::INTERVAL, None),
+        (Type::NUMERIC, None),
+        (Type::BOOL_ARRAY, Some(Type::BOOL)),
+        (Type::INT2_ARRAY, Some(Type::INT2)),
+        (Type::INT4_ARRAY, Some(Type::INT4)),
+        (Type::INT8_ARRAY, Some(Type::INT8)),
+        (Type::FLOAT4_ARRAY, Some(Type::FLOAT4)),
+        (Type::FLOAT8_ARRAY, Some(Type::FLOAT8)),
+        (Type::TEXT_ARRAY, Some(Type::TEXT)),
+    ]
+}
+
+
+/// `typlen` and `typcategory` of `pg_type`: the size of fixed size values (-1 otherwise) and
+/// the category letter, e.g. `N` for numbers.
+fn get_pg_type_layout(pg_type: &Type) -> (i16, &'static str) {
+    match *pg_type {
+        Type::BOOL => (1, "B"),
+        Type::INT2 => (2, "N"),
+        Type::INT4 | Type::OID | Type::FLOAT4 => (4, "N"),
+        Type::INT8 | Type::FLOAT8 => (8, "N"),
+        Type::NUMERIC => (-1, "N"),
+        Type::TEXT | Type::VARCHAR => (-1, "S"),
+        Type::DATE => (4, "D"),
+        Type::TIME | Type::TIMESTAMP | Type::TIMESTAMPTZ => (8, "D"),
+        Type::INTERVAL => (16, "T"),
+        Type::BYTEA => (-1, "U"),
+        _ => (-1, "A"),
+    }
+}
+
+
+/// OID of the object `name`, stable while the object exists.
+fn get_object_oid(name: &str) -> i64 {
+    FIRST_OBJECT_OID + (fnv1a_hash(name.as_bytes()) % (i32::MAX - FIRST_OBJECT_OID as i32) as u64) as i64
+}
+
+
+fn get_namespace_oid(schema_name: &str) -> i64 {
+    match schema_name {
+        "pg_catalog" => PG_CATALOG_OID,
+        "public" => PUBLIC_OID,
+        schema_name => get_object_oid(schema_name),
+    }
+}
+
+
+/// Schema of the `pg_catalog` table `table_name`.
+fn get_pg_table_schema(table_name: &str) -> Option<SchemaRef> {
+    let oid = |name: &str| Field::new(name, DataType::Int64, false);
+    let name = |name: &str| Field::new(name, DataType::Utf8, false);
+    let flag = |name: &str| Field::new(name, DataType::Boolean, false);
+    let fields: Vec<Field> = match table_name {
+        "pg_am" => vec![oid("oid"), name("amname"), name("amtype")],
+        "pg_attribute" => vec![
+            oid("attrelid"),
+            name("attname"),
+            oid("atttypid"),
+            Field::new("attlen", DataType::Int16, false),
+            Field::new("attnum", DataType::Int16, false),
+            Field::new("atttypmod", DataType::Int32, false),
+            flag("attnotnull"),
+            flag("atthasdef"),
+            flag("attisdropped"),
+        ],
+        "pg_class" => vec![
+            oid("oid"),
+            name("relname"),
+            oid("relnamespace"),
+            name("relkind"),
+            oid("relowner"),
+            oid("relam"),
+            name("relpersistence"),
+            flag("relhasindex"),
+            flag("relispartition"),
+        ],
+        "pg_database" => vec![
+            oid("oid"),
+            name("datname"),
+            oid("datdba"),
+            Field::new("encoding", DataType::Int32, false),
+            name("datcollate"),
+            name("datctype"),
+            flag("datistemplate"),
+            flag("datallowconn"),
+            Field::new("datacl", DataType::new_list(DataType::Utf8, true), true),
+        ],
+        "pg_description" => vec![
+            oid("objoid"),
+            oid("classoid"),
+            Field::new("objsubid", DataType::Int32, false),
+            name("description"),
+        ],
+        "pg_namespace" => vec![oid("oid"), name("nspname"), oid("nspowner")],
+        "pg_settings" => vec![name("name"), name("setting")],
+        "pg_tables" => vec![name("schemaname"), name("tablename"), name("tableowner"), flag("hasindexes")],
+        "pg_type" => vec![
+            oid("oid"),
+            name("typname"),
+            oid("typnamespace"),
+            oid("typowner"),
+            Field::new("typlen", DataType::Int16, false),
+            name("typtype"),
+            name("typcategory"),
+            oid("typrelid"),
+            oid("typelem"),
+            oid("typarray"),
+            oid("typbasetype"),
+            Field::new("typtypmod", DataType::Int32, false),
+            flag("typnotnull"),
+        ],
+        "pg_views" => vec![
+            name("schemaname"),
+            name("viewname"),
+            name("viewowner"),
+            Field::new("definition", DataType::Utf8, true),
+        ],
+        _ => return None,
+    };
+    Some(Arc::new(Schema::new(fields)))
+}
+
+
+/// Table of the default catalog, as listed in `pg_class`.
+struct SaPgRelation {
+    oid: i64,
+    schema_name: String,
+    name: String,
+    table_type: TableType,
+    schema: SchemaRef,
+}
+
+
+/// `pg_catalog` schema of the default catalog: its tables describe the schemas and tables of
+/// the session when they are read, so Postgres clients list them as they do on Postgres.
+pub struct SaPgCatalogSchemaProvider {
+    /// Catalogs of the session, which owns this schema.
+    catalog_list: Weak<dyn CatalogProviderList>,
+    default_catalog: String,
+}
+
+
+impl Debug for SaPgCatalogSchemaProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SaPgCatalogSchemaProvider")
+            .field("default_catalog", &self.default_catalog)
+            .finish()
+    }
+}
+
+
+impl SaPgCatalogSchemaProvider {
+    pub fn new(catalog_list: Weak<dyn CatalogProviderList>, default_catalog: &str) -> Self {
+        Self {
+            catalog_list,
+            default_catalog: default_catalog.to_string(),
+        }
+    }
+
+    fn get_catalog_list(&self) -> Result<Arc<dyn CatalogProviderList>> {
+        Ok(self.catalog_list.upgrade().ok_or_else(|| DataFusionError::Internal("The session of pg_catalog is closed".to_string()))?)
+    }
+
+    fn get_default_catalog(&self) -> Result<Arc<dyn CatalogProvider>> {
+        let default_catalog: Option<Arc<dyn CatalogProvider>> = self.get_catalog_list()?.catalog(&self.default_catalog);
+        Ok(default_catalog.ok_or_else(|| DataFusionError::Internal(format!("No catalog {}", self.default_catalog)))?)
+    }
+
+    /// Schemas of the default catalog, `information_schema` included, sorted by name.
+    fn get_namespaces(&self) -> Result<Vec<String>> {
+        let mut schema_names: Vec<String> = self.get_default_catalog()?.schema_names();
+        if !schema_names.iter().any(|schema_name| schema_name == "information_schema") {
+            schema_names.push("information_schema".to_string());
+        }
+        schema_names.sort();
+        Ok(schema_names)
+    }
+
+    /// Tables of the default catalog, sorted by schema and name.
+    async fn get_relations(&self) -> Result<Vec<SaPgRelation>> {
+        let catalog: Arc<dyn CatalogProvider> = self.get_default_catalog()?;
+        let mut relations: Vec<SaPgRelation> = Vec::new();
+        for schema_name in catalog.schema_names() {
+            let Some(schema) = catalog.schema(&schema_name) else {
+                continue;
+            };
+            for table_name in schema.table_names() {
+                // The tables of this schema are built from the others, not read
+                let (table_type, table_schema): (TableType, SchemaRef) = match get_pg_table_schema(&table_name) {
+                    Some(table_schema) if schema_name == "pg_catalog" => (TableType::View, table_schema),
+                    _ => match schema.table(&table_name).await? {
+                        Some(table) => (table.table_type(), table.schema()),
+                        None => continue,
+                    },
+                };
+                relations.push(SaPgRelation {
+                    oid: get_object_oid(&format!("{}.{}", schema_name, table_name)),
+                    schema_name: schema_name.clone(),
+                    name: table_name,
+                    table_type,
+                    schema: table_schema,
+                });
+            }
+        }
+        relations.sort_by(|left, right| (&left.schema_name, &left.name).cmp(&(&right.schema_name, &right.name)));
+        Ok(relations)
+    }
+
+    /// Columns of the `pg_catalog` table `table_name`, in the order of its schema.
+    async fn get_pg_table_columns(&self, table_name: &str) -> Result<Vec<ArrayRef>> {
+        let columns: Vec<ArrayRef> = match table_name {
+            "pg_am" => vec![
+                Arc::new(Int64Array::from(vec![HEAP_OID])),
+                Arc::new(StringArray::from(vec!["heap"])),
+                Arc::new(StringArray::from(vec!["t"])),
+            ],
+            "pg_attribute" => {
+                let mut attributes: Vec<(i64, String, i64, i16, i16, bool)> = Vec::new();
+                for relation in self.get_relations().await? {
+                    for (index, field) in relation.schema.fields().iter().enumerate() {
+                        let pg_type: Type = to_pg_type(field.data_type());
+                        attributes.push((
+                            relation.oid,
+                            field.name().clone(),
+                            i64::from(pg_type.oid()),
+                            get_pg_type_layout(&pg_type).0,
+                            index as i16 + 1,
+                            !field.is_nullable(),
+                        ));
+                    }
+                }
+                let rows: usize = attributes.len();
+                vec![
+                    Arc::new(Int64Array::from_iter_values(attributes.iter().map(|attribute| attribute.0))),
+                    Arc::new(StringArray::from_iter_values(attributes.iter().map(|attribute| &attribute.1))),
+                    Arc::new(Int64Array::from_iter_values(attributes.iter().map(|attribute| attribute.2))),
+                    Arc::new(Int16Array::from_iter_values(attributes.iter().map(|attribute| attribute.3))),
+                    Arc::new(Int16Array::from_iter_values(attributes.iter().map(|attribute| attribute.4))),
+                    Arc::new(Int32Array::from(vec![-1; rows])),
+                    Arc::new(BooleanArray::from_iter(attributes.iter().map(|attribute| Some(attribute.5)))),
+                    Arc::new(BooleanArray::from(vec![false; rows])),
+                    Arc::new(BooleanArray::from(vec![false; rows])),
+                ]
+            },
+            "pg_class" => {
+                let relations: Vec<SaPgRelation> = self.get_relations().await?;
+                let rows: usize = relations.len();
+                vec![
+                    Arc::new(Int64Array::from_iter_values(relations.iter().map(|relation| relation.oid))),
+                    Arc::new(StringArray::from_iter_values(relations.iter().map(|relation| &relation.name))),
+                    Arc::new(Int64Array::from_iter_values(relations.iter().map(|relation| get_namespace_oid(&relation.schema_name)))),
+                    Arc::new(StringArray::from_iter_values(relations.iter().map(|relation| match relation.table_type {
+                        TableType::View => "v",
+                        _ => "r",
+                    }))),
+                    Arc::new(Int64Array::from(vec![PG_USER_OID; rows])),
+                    Arc::new(Int64Array::from_iter_values(relations.iter().map(|relation| match relation.table_type {
+                        TableType::View => 0,
+                        _ => HEAP_OID,
+                    }))),
+                    Arc::new(StringArray::from_iter_values(relations.iter().map(|relation| match relation.table_type {
+                        TableType::Temporary => "t",
+                        _ => "p",
+                    }))),
+                    Arc::new(BooleanArray::from(vec![false; rows])),
+                    Arc::new(BooleanArray::from(vec![false; rows])),
+                ]
+            },
+            "pg_database" => {
+                let mut catalog_names: Vec<String> = self.get_catalog_list()?.catalog_names();
+                catalog_names.sort();
+                let rows: usize = catalog_names.len();
+                vec![
+                    Arc::new(Int64Array::from_iter_values(catalog_names.iter().map(|catalog_name| get_object_oid(catalog_name)))),
+                    Arc::new(StringArray::from_iter_values(&catalog_names)),
+                    Arc::new(Int64Array::from(vec![PG_USER_OID; rows])),
+                    // UTF8
+                    Arc::new(Int32Array::from(vec![6; rows])),
+                    Arc::new(StringArray::from(vec!["C"; rows])),
+                    Arc::new(StringArray::from(vec!["C"; rows])),
+                    Arc::new(BooleanArray::from(vec![false; rows])),
+                    Arc::new(BooleanArray::from(vec![true; rows])),
+                    // Default privileges
+                    new_null_array(&DataType::new_list(DataType::Utf8, true), rows),
+                ]
+            },
+            "pg_description" => vec![
+                Arc::new(Int64Array::from(Vec::<i64>::new())),
+                Arc::new(Int64Array::from(Vec::<i64>::new())),
+                Arc::new(Int32Array::from(Vec::<i32>::new())),
+                Arc::new(StringArray::from(Vec::<&str>::new())),
+            ],
+            "pg_namespace" => {
+                let namespaces: Vec<String> = self.get_namespaces()?;
+                vec![
+                    Arc::new(Int64Array::from_iter_values(namespaces.iter().map(|namespace| get_namespace_oid(namespace)))),
+                    Arc::new(StringArray::from_iter_values(&namespaces)),
+                    Arc::new(Int64Array::from(vec![PG_USER_OID; namespaces.len()])),
+                ]
+            },
+            "pg_settings" => vec![
+                Arc::new(StringArray::from_iter_values(PG_PARAMETERS.iter().map(|(name, _)| name))),
+                Arc::new(StringArray::from_iter_values(PG_PARAMETERS.iter().map(|(_, setting)| setting))),
+            ],
+            "pg_tables" | "pg_views" => {
+                let relations: Vec<SaPgRelation> = self.get_relations()
+                    .await?
+                    .into_iter()
+                    .filter(|relation| (relation.table_type == TableType::View) == (table_name == "pg_views"))
+                    .collect();
+                let rows: usize = relations.len();
+                let mut columns: Vec<ArrayRef> = vec![
+                    Arc::new(StringArray::from_iter_values(relations.iter().map(|relation| &relation.schema_name))),
+                    Arc::new(StringArray::from_iter_values(relations.iter().map(|relation| &relation.name))),
+                    Arc::new(StringArray::from(vec![PG_USER; rows])),
+                ];
+                columns.push(match table_name {
+                    "pg_views" => Arc::new(StringArray::from(vec![None::<&str>; rows])),
+                    _ => Arc::new(BooleanArray::from(vec![false; rows])),
+                });
+                columns
+            },
+            "pg_type" => {
+                let pg_types: Vec<(Type, Option<Type>)> = get_pg_types();
+                let rows: usize = pg_types.len();
+                let get_array_oid = |pg_type: &Type| {
+                    pg_types
+                        .iter()
+                        .find(|(_, element)| element.as_ref() == Some(pg_type))
+                        .map_or(0, |(array, _)| i64::from(array.oid()))
+                };
+                vec![
+                    Arc::new(Int64Array::from_iter_values(pg_types.iter().map(|(pg_type, _)| i64::from(pg_type.oid())))),
+                    Arc::new(StringArray::from_iter_values(pg_types.iter().map(|(pg_type, _)| pg_type.name()))),
+                    Arc::new(Int64Array::from(vec![PG_CATALOG_OID; rows])),
+                    Arc::new(Int64Array::from(vec![PG_USER_OID; rows])),
+                    Arc::new(Int16Array::from_iter_values(pg_types.iter().map(|(pg_type, _)| get_pg_type_layout(pg_type).0))),
+                    Arc::new(StringArray::from(vec!["b"; rows])),
+                    Arc::new(StringArray::from_iter_values(pg_types.iter().map(|(pg_type, _)| get_pg_type_layout(pg_type).1))),
+                    Arc::new(Int64Array::from(vec![0; rows])),
+                    Arc::new(Int64Array::from_iter_values(pg_types.iter().map(|(_, element)| element.as_ref().map_or(0, |element| i64::from(element.oid()))))),
+                    Arc::new(Int64Array::from_iter_values(pg_types.iter().map(|(pg_type, _)| get_array_oid(pg_type)))),
+                    Arc::new(Int64Array::from(vec![0; rows])),
+                    Arc::new(Int32Array::from(vec![-1; rows])),
+                    Arc::new(BooleanArray::from(vec![false; rows])),
+                ]
+            },
+            table_name => return Err(DataFusionError::Plan(format!("No pg_catalog table {}", table_name)).into()),
+        };
+        Ok(columns)
+    }
+}
+
+
+#[async_trait]
+impl SchemaProvider for SaPgCatalogSchemaProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn table_names(&self) -> Vec<String> {
+        PG_TABLES.iter().map(|table_name| table_name.to_string()).collect()
+    }
+
+    async fn table(&self, name: &str) -> datafusion::error::Result<Option<Arc<dyn TableProvider>>> {
+        let Some(schema) = get_pg_table_schema(name) else {
+            return Ok(None);
+        };
+        let batch: RecordBatch = RecordBatch::try_new(schema.clone(), self.get_pg_table_columns(name).await?)?;
+        Ok(Some(Arc::new(MemTable::try_new(schema, vec![vec![batch]])?)))
+    }
+
+    fn table_exist(&self, name: &str) -> bool {
+        PG_TABLES.contains(&name)
+    }
+}
+
+
+/// Function returning `value` whatever its arguments.
+// DataFusion's function implementations return its own error
+#[allow(clippy::result_large_err)]
+fn new_constant_function(
+    name: &str,
+    aliases: &[&'static str],
+    input_types: Vec<DataType>,
+    value: ScalarValue,
+) -> ScalarUDF {
+    let return_type: DataType = value.data_type();
+    create_udf(
+        name,
+        input_types,
+        return_type,
+        Volatility::Stable,
+        Arc::new(move |_| Ok(ColumnarValue::Scalar(value.clone()))),
+    ).with_aliases(aliases.iter().copied())
+}
+
+
+/// Functions of Postgres that clients call when they connect or list tables, e.g. `version()`
+/// or `pg_table_is_visible(oid)`, which they often qualify with `pg_catalog`.
+#[allow(clippy::result_large_err)]
+fn get_pg_functions(default_catalog: &str, default_schema: &str) -> Vec<ScalarUDF> {
+    let utf8 = |value: &str| ScalarValue::Utf8(Some(value.to_string()));
+    let search_path: ScalarValue = ScalarValue::List(ScalarValue::new_list_nullable(
+        &[utf8("pg_catalog"), utf8(default_schema)],
+        &DataType::Utf8,
+    ));
+    let format_type = create_udf(
+        "format_type",
+        vec![DataType::Int64, DataType::Int64],
+        DataType::Utf8,
+        Volatility::Immutable,
+        Arc::new(|args: &[ColumnarValue]| {
+            let args: Vec<ArrayRef> = ColumnarValue::values_to_arrays(args)?;
+            let pg_types: Vec<(Type, Option<Type>)> = get_pg_types();
+            let names: StringArray = args[0]
+                .as_primitive::<Int64Type>()
+                .iter()
+                .map(|oid| {
+                    let oid: i64 = oid?;
+                    pg_types.iter().find(|(pg_type, _)| i64::from(pg_type.oid()) == oid).map(|(pg_type, _)| pg_type.name())
+                })
+                .collect();
+            Ok(ColumnarValue::Array(Arc::new(names)))
+        }),
+    );
+    vec![
+        new_constant_function(
+            "version",
+            &[],
+            vec![],
+            utf8(&format!("PostgreSQL {} (SQLAnyWhere {})", PG_SERVER_VERSION, env!("CARGO_PKG_VERSION"))),
+        ),
+        new_constant_function("current_database", &["current_catalog"], vec![], utf8(default_catalog)),
+        new_constant_function("current_schema", &[], vec![], utf8(default_schema)),
+        new_constant_function("current_schemas", &[], vec![DataType::Boolean], search_path),
+        new_constant_function("current_user", &["session_user", "user"], vec![], utf8(PG_USER)),
+        new_constant_function("pg_backend_pid", &[], vec![], ScalarValue::Int32(Some(std::process::id() as i32))),
+        new_constant_function("pg_get_userbyid", &[], vec![DataType::Int64], utf8(PG_USER)),
+        new_constant_function("pg_encoding_to_char", &[], vec![DataType::Int32], utf8("UTF8")),
+        new_constant_function("pg_table_is_visible", &[], vec![DataType::Int64], ScalarValue::Boolean(Some(true))),
+        new_constant_function(
+            "has_table_privilege",
+            &[],
+            vec![DataType::Utf8, DataType::Utf8],
+            ScalarValue::Boolean(Some(true)),
+        ),
+        new_constant_function("obj_description", &[], vec![DataType::Int64, DataType::Utf8], ScalarValue::Utf8(None)),
+        new_constant_function("col_description", &[], vec![DataType::Int64, DataType::Int64], ScalarValue::Utf8(None)),
+        format_type,
+    ]
+}
+
+
+/// Adds the `pg_catalog` schema and functions of Postgres to `sa_datafusion`, and enables its
+/// `information_schema`, for the introspection queries of Postgres clients.
+pub fn register_pg_catalog(sa_datafusion: &SaDataFusion) -> Result<()> {
+    let session_state: SessionState = sa_datafusion.get_session_state();
+    let catalog_options: &CatalogOptions = &session_state.config_options().catalog;
+    let catalog: Arc<dyn CatalogProvider> = sa_datafusion.ctx
+        .catalog(&catalog_options.default_catalog)
+        .ok_or_else(|| DataFusionError::Plan(format!("No catalog {}", catalog_options.default_catalog)))?;
+    let pg_catalog: SaPgCatalogSchemaProvider = SaPgCatalogSchemaProvider::new(
+        Arc::downgrade(session_state.catalog_list()),
+        &catalog_options.default_catalog,
+    );
+    catalog.register_schema("pg_catalog", Arc::new(pg_catalog))?;
+    for function in get_pg_functions(&catalog_options.default_catalog, &catalog_options.default_schema) {
+        sa_datafusion.ctx.register_udf(function);
+    }
+    sa_datafusion.ctx
+        .state_ref()
+        .write()
+        .config_mut()
+        .options_mut()
+        .catalog
+        .information_schema = true;
+    Ok(())
+}
@@ -0,0 +1,812 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use datafusion::arrow::array::{Array, ArrayRef, AsArray, StringArray};
+use datafusion::arrow::compute::{can_cast_types, cast_with_options, CastOptions};
+use datafusion::arrow::datatypes::{
+    DataType, Date32Type, Decimal128Type, Field, Float32Type, Float64Type, Int16Type, Int32Type,
+    Int64Type, Schema, Time64MicrosecondType, TimeUnit, TimestampMicrosecondType,
+};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::arrow::util::display::{ArrayFormatter, FormatOptions};
+use datafusion::common::{DataFusionError, ParamValues, ScalarValue, TableReference};
+use datafusion::logical_expr::{DdlStatement, LogicalPlan, Statement, WriteOp};
+use datafusion::prelude::DataFrame;
+use datafusion::sql::parser::Statement as DFStatement;
+use datafusion::sql::sqlparser::ast::{visit_expressions_mut, BinaryOperator, Expr, Statement as SQLStatement};
+use futures::sink::Sink;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use pgwire::api::auth::{
+    finish_authentication, save_startup_parameters_to_metadata, ServerParameterProvider,
+    StartupHandler,
+};
+use pgwire::api::copy::NoopCopyHandler;
+use pgwire::api::portal::{Format, Portal};
+use pgwire::api::query::{ExtendedQueryHandler, SimpleQueryHandler};
+use pgwire::api::results::{
+    DataRowEncoder, DescribePortalResponse, DescribeStatementResponse, FieldInfo, QueryResponse,
+    Response, Tag,
+};
+use pgwire::api::stmt::{QueryParser, StoredStatement};
+use pgwire::api::store::PortalStore;
+use pgwire::api::{ClientInfo, ClientPortalStore, NoopErrorHandler, PgWireServerHandlers, Type};
+use pgwire::error::{ErrorInfo, PgWireError, PgWireResult};
+use pgwire::messages::data::DataRow;
+use pgwire::messages::{PgWireBackendMessage, PgWireFrontendMessage};
+use pgwire::tokio::process_socket;
+use rust_decimal::Decimal;
+use tokio::net::TcpListener;
+use crate::builder::pipelines;
+use crate::datafusion::SaDataFusion;
+use crate::error::{Result, SaError};
+use crate::helper;
+use crate::server::flight_sql::{get_parameter_schema, get_record_count};
+use crate::server::pg_catalog::{self, PG_PARAMETERS};
+
+
+/// Statement of a Postgres client, planned when it is parsed.
+#[derive(Clone, Debug)]
+pub enum SaPgStatement {
+    Empty,
+    /// Session statement answered by the server itself.
+    Command(SaPgCommand),
+    Plan(Box<LogicalPlan>),
+}
+
+
+/// Session statements of Postgres that DataFusion does not run, such as `BEGIN` or
+/// `SET application_name = 'psql'`: statements run one by one, so transactions and the
+/// settings of Postgres are only acknowledged.
+#[derive(Clone, Debug)]
+pub enum SaPgCommand {
+    /// Acknowledged with the command tag.
+    Ignore(String),
+    /// `SHOW` of a server parameter, with its value.
+    Show(String, String),
+    TransactionStart,
+    /// `COMMIT` or `ROLLBACK`.
+    TransactionEnd(String),
+}
+
+
+/// Plans the statements of the extended query protocol when clients parse them.
+pub struct SaPgQueryParser {
+    sa_datafusion: SaDataFusion,
+}
+
+
+#[async_trait]
+impl QueryParser for SaPgQueryParser {
+    type Statement = SaPgStatement;
+
+    async fn parse_sql(&self, sql: &str, _types: &[Type]) -> PgWireResult<SaPgStatement> {
+        let mut statements: Vec<DFStatement> = helper::parse_statements(sql)?;
+        match statements.len() {
+            0 => Ok(SaPgStatement::Empty),
+            1 => Ok(plan_statement(&self.sa_datafusion, statements.remove(0)).await?),
+            count => Err(SaError::from(DataFusionError::Plan(format!("[sa_pgwire]: Expected a single SQL statement to prepare, got {}", count))).into()),
+        }
+    }
+}
+
+
+/// Handles the startup, simple query and extended query messages of Postgres clients.
+pub struct SaPgWireHandler {
+    sa_datafusion: SaDataFusion,
+    query_parser: Arc<SaPgQueryParser>,
+}
+
+
+impl SaPgWireHandler {
+    pub fn new(sa_datafusion: SaDataFusion) -> Self {
+        Self {
+            query_parser: Arc::new(SaPgQueryParser {
+                sa_datafusion: sa_datafusion.clone(),
+            }),
+            sa_datafusion,
+        }
+    }
+}
+
+
+impl ServerParameterProvider for SaPgWireHandler {
+    fn server_parameters<C>(&self, _client: &C) -> Option<HashMap<String, String>>
+    where
+        C: ClientInfo,
+    {
+        Some(PG_PARAMETERS.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect())
+    }
+}
+
+
+#[async_trait]
+impl StartupHandler for SaPgWireHandler {
+    /// Accepts every client: there are no users, serve on a trusted network only.
+    async fn on_startup<C>(&self, client: &mut C, message: PgWireFrontendMessage) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if let PgWireFrontendMessage::Startup(ref startup) = message {
+            save_startup_parameters_to_metadata(client, startup);
+            finish_authentication(client, self).await?;
+        }
+        Ok(())
+    }
+}
+
+
+#[async_trait]
+impl SimpleQueryHandler for SaPgWireHandler {
+    async fn do_query<'a, 'b: 'a, C>(&'b self, _client: &mut C, query: &'a str) -> PgWireResult<Vec<Response<'a>>>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let statements: Vec<DFStatement> = helper::parse_statements(query)?;
+        let count: usize = statements.len();
+        let mut responses: Vec<Response<'a>> = Vec::new();
+        for (index, statement) in statements.into_iter().enumerate() {
+            // A statement runs to completion before the next one is planned, the last one streams
+            let fetch_all: bool = index + 1 < count;
+            let response: PgWireResult<Response<'static>> = match plan_statement(&self.sa_datafusion, statement).await {
+                Ok(statement) => run_statement(&self.sa_datafusion, &statement, None, &Format::UnifiedText, fetch_all).await,
+                Err(error) => Err(error.into()),
+            };
+            match response {
+                Ok(response) => responses.push(response),
+                // Postgres stops at the first failing statement
+                Err(error) => {
+                    responses.push(Response::Error(Box::new(to_error_info(error))));
+                    break;
+                },
+            }
+        }
+        if responses.is_empty() {
+            responses.push(Response::EmptyQuery);
+        }
+        Ok(responses)
+    }
+}
+
+
+#[async_trait]
+impl ExtendedQueryHandler for SaPgWireHandler {
+    type Statement = SaPgStatement;
+    type QueryParser = SaPgQueryParser;
+
+    fn query_parser(&self) -> Arc<SaPgQueryParser> {
+        self.query_parser.clone()
+    }
+
+    async fn do_describe_statement<C>(
+        &self,
+        _client: &mut C,
+        target: &StoredStatement<SaPgStatement>,
+    ) -> PgWireResult<DescribeStatementResponse>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = SaPgStatement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let parameter_types: Vec<Type> = match &target.statement {
+            SaPgStatement::Plan(plan) => get_parameter_types(plan, &target.parameter_types)?
+                .iter()
+                .map(to_pg_type)
+                .collect(),
+            _ => Vec::new(),
+        };
+        let fields: Vec<FieldInfo> = get_statement_fields(&target.statement, &Format::UnifiedText);
+        Ok(DescribeStatementResponse::new(parameter_types, fields))
+    }
+
+    async fn do_describe_portal<C>(&self, _client: &mut C, target: &Portal<SaPgStatement>) -> PgWireResult<DescribePortalResponse>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = SaPgStatement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        Ok(DescribePortalResponse::new(get_statement_fields(&target.statement.statement, &target.result_column_format)))
+    }
+
+    /// Runs `portal` and returns all its rows: the `max_rows` limit of `Execute` is not applied,
+    /// portals are never suspended, so clients fetching rows in chunks get them all at once.
+    async fn do_query<'a, 'b: 'a, C>(
+        &'b self,
+        _client: &mut C,
+        portal: &'a Portal<SaPgStatement>,
+        _max_rows: usize,
+    ) -> PgWireResult<Response<'a>>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = SaPgStatement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let parameters: Option<ParamValues> = match &portal.statement.statement {
+            SaPgStatement::Plan(plan) => {
+                let parameter_types: Vec<DataType> = get_parameter_types(plan, &portal.statement.parameter_types)?;
+                let values: Vec<ScalarValue> = parameter_types
+                    .iter()
+                    .enumerate()
+                    .map(|(index, data_type)| decode_parameter(portal, index, data_type))
+                    .collect::<PgWireResult<Vec<ScalarValue>>>()?;
+                Some(ParamValues::List(values))
+            },
+            _ => None,
+        };
+        run_statement(&self.sa_datafusion, &portal.statement.statement, parameters, &portal.result_column_format, false).await
+    }
+}
+
+
+/// Postgres server running the statements of each connection on a session of its own, see
+/// [`SaDataFusion::new_session`], with the `pg_catalog` tables and functions that clients query
+/// when they connect. Tables are shared by every connection, `SET` applies to its own only.
+///
+/// Clients are not authenticated.
+pub struct SaPgWireServer {
+    handler: Arc<SaPgWireHandler>,
+}
+
+
+impl SaPgWireServer {
+    pub fn try_new(sa_datafusion: SaDataFusion) -> Result<Self> {
+        pg_catalog::register_pg_catalog(&sa_datafusion)?;
+        Ok(Self {
+            handler: Arc::new(SaPgWireHandler::new(sa_datafusion)),
+        })
+    }
+
+    pub fn get_sa_datafusion(&self) -> &SaDataFusion {
+        &self.handler.sa_datafusion
+    }
+
+    /// Serves the clients connecting to `listener`, each on a new session, until accepting a
+    /// connection fails.
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (socket, address) = listener.accept().await?;
+            let server: Arc<SaPgWireServer> = Arc::new(SaPgWireServer {
+                handler: Arc::new(SaPgWireHandler::new(self.get_sa_datafusion().new_session())),
+            });
+            tokio::spawn(async move {
+                if let Err(error) = process_socket(socket, None, server).await {
+                    eprintln!("[sa_pgwire]: Connection from {} failed: {}", address, error);
+                }
+            });
+        }
+    }
+}
+
+
+impl PgWireServerHandlers for SaPgWireServer {
+    type StartupHandler = SaPgWireHandler;
+    type SimpleQueryHandler = SaPgWireHandler;
+    type ExtendedQueryHandler = SaPgWireHandler;
+    type CopyHandler = NoopCopyHandler;
+    type ErrorHandler = NoopErrorHandler;
+
+    fn simple_query_handler(&self) -> Arc<SaPgWireHandler> {
+        self.handler.clone()
+    }
+
+    fn extended_query_handler(&self) -> Arc<SaPgWireHandler> {
+        self.handler.clone()
+    }
+
+    fn startup_handler(&self) -> Arc<SaPgWireHandler> {
+        self.handler.clone()
+    }
+
+    fn copy_handler(&self) -> Arc<NoopCopyHandler> {
+        Arc::new(NoopCopyHandler)
+    }
+
+    fn error_handler(&self) -> Arc<NoopErrorHandler> {
+        Arc::new(NoopErrorHandler)
+    }
+}
+
+
+impl From<SaError> for PgWireError {
+    fn from(error: SaError) -> Self {
+        PgWireError::UserError(Box::new(ErrorInfo::new("ERROR".to_string(), get_sql_state(&error).to_string(), error.to_string())))
+    }
+}
+
+
+/// SQLSTATE code of `error`, which clients tell errors apart by.
+fn get_sql_state(error: &SaError) -> &'static str {
+    match error {
+        SaError::Credentials(_) => "28000",
+        SaError::UriParse { .. } | SaError::Catalog { .. } | SaError::ObjectStoreConflict { .. } => "22023",
+        SaError::UnsupportedFormat { .. } | SaError::UnsupportedScheme { .. } => "0A000",
+        SaError::ObjectStore(_) | SaError::Io(_) => "58030",
+        SaError::DataFusion(error) => match error.find_root() {
+            DataFusionError::SQL(..) => "42601",
+            DataFusionError::Plan(_) | DataFusionError::SchemaError(..) => "42000",
+            DataFusionError::NotImplemented(_) => "0A000",
+            DataFusionError::ArrowError(..) => "22000",
+            _ => "XX000",
+        },
+        _ => "XX000",
+    }
+}
+
+
+fn to_error_info(error: PgWireError) -> ErrorInfo {
+    match error {
+        PgWireError::UserError(error_info) => *error_info,
+        error => ErrorInfo::new("ERROR".to_string(), "XX000".to_string(), error.to_string()),
+    }
+}
+
+
+/// Postgres type a column of `data_type` is sent as; types Postgres has no counterpart for are
+/// sent as text.
+pub fn to_pg_type(data_type: &DataType) -> Type {
+    match data_type {
+        DataType::Boolean => Type::BOOL,
+        DataType::Int8 | DataType::Int16 | DataType::UInt8 => Type::INT2,
+        DataType::Int32 | DataType::UInt16 => Type::INT4,
+        DataType::Int64 | DataType::UInt32 | DataType::UInt64 => Type::INT8,
+        DataType::Float16 | DataType::Float32 => Type::FLOAT4,
+        DataType::Float64 => Type::FLOAT8,
+        // Wider decimals do not fit the decimals sent as `numeric`
+        DataType::Decimal128(precision, scale) if *precision <= 28 && *scale >= 0 => Type::NUMERIC,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => Type::TEXT,
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView | DataType::FixedSizeBinary(_) => Type::BYTEA,
+        DataType::Date32 | DataType::Date64 => Type::DATE,
+        DataType::Time32(_) | DataType::Time64(_) => Type::TIME,
+        DataType::Timestamp(_, None) => Type::TIMESTAMP,
+        DataType::Timestamp(_, Some(_)) => Type::TIMESTAMPTZ,
+        DataType::Dictionary(_, value_type) => to_pg_type(value_type),
+        DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => match to_pg_type(field.data_type()) {
+            Type::BOOL => Type::BOOL_ARRAY,
+            Type::INT2 => Type::INT2_ARRAY,
+            Type::INT4 => Type::INT4_ARRAY,
+            Type::INT8 => Type::INT8_ARRAY,
+            Type::FLOAT4 => Type::FLOAT4_ARRAY,
+            Type::FLOAT8 => Type::FLOAT8_ARRAY,
+            Type::TEXT => Type::TEXT_ARRAY,
+            _ => Type::TEXT,
+        },
+        _ => Type::TEXT,
+    }
+}
+
+
+/// Arrow type of a parameter the client sent as `pg_type`.
+fn from_pg_type(pg_type: &Type) -> DataType {
+    match *pg_type {
+        Type::BOOL => DataType::Boolean,
+        Type::INT2 => DataType::Int16,
+        Type::INT4 => DataType::Int32,
+        Type::INT8 => DataType::Int64,
+        Type::FLOAT4 => DataType::Float32,
+        Type::FLOAT8 => DataType::Float64,
+        Type::BYTEA => DataType::Binary,
+        Type::DATE => DataType::Date32,
+        Type::TIMESTAMP => DataType::Timestamp(TimeUnit::Microsecond, None),
+        Type::TIMESTAMPTZ => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        _ => DataType::Utf8,
+    }
+}
+
+
+/// Arrow type the values of a `pg_type` column are encoded from.
+fn get_encoded_data_type(pg_type: &Type, data_type: &DataType) -> DataType {
+    let list = |data_type: DataType| DataType::List(Arc::new(Field::new_list_field(data_type, true)));
+    match *pg_type {
+        Type::BOOL => DataType::Boolean,
+        Type::INT2 => DataType::Int16,
+        Type::INT4 => DataType::Int32,
+        Type::INT8 => DataType::Int64,
+        Type::FLOAT4 => DataType::Float32,
+        Type::FLOAT8 => DataType::Float64,
+        Type::NUMERIC => match data_type {
+            DataType::Dictionary(_, value_type) => value_type.as_ref().clone(),
+            data_type => data_type.clone(),
+        },
+        Type::BYTEA => DataType::Binary,
+        Type::DATE => DataType::Date32,
+        Type::TIME => DataType::Time64(TimeUnit::Microsecond),
+        Type::TIMESTAMP => DataType::Timestamp(TimeUnit::Microsecond, None),
+        // Timestamps are stored in UTC whatever their time zone
+        Type::TIMESTAMPTZ => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        Type::BOOL_ARRAY => list(DataType::Boolean),
+        Type::INT2_ARRAY => list(DataType::Int16),
+        Type::INT4_ARRAY => list(DataType::Int32),
+        Type::INT8_ARRAY => list(DataType::Int64),
+        Type::FLOAT4_ARRAY => list(DataType::Float32),
+        Type::FLOAT8_ARRAY => list(DataType::Float64),
+        Type::TEXT_ARRAY => list(DataType::Utf8),
+        _ => DataType::Utf8,
+    }
+}
+
+
+/// Fields of the rows of `schema`, sent in `format`.
+fn get_fields(schema: &Schema, format: &Format) -> Vec<FieldInfo> {
+    schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(index, field)| FieldInfo::new(field.name().clone(), None, None, to_pg_type(field.data_type()), format.format_for(index)))
+        .collect()
+}
+
+
+/// Fields of the rows `statement` returns, none if it returns a command tag.
+fn get_statement_fields(statement: &SaPgStatement, format: &Format) -> Vec<FieldInfo> {
+    match statement {
+        SaPgStatement::Plan(plan) if get_command_tag(plan).is_none() => get_fields(plan.schema().as_arrow(), format),
+        SaPgStatement::Command(SaPgCommand::Show(name, _)) => vec![FieldInfo::new(name.clone(), None, None, Type::TEXT, format.format_for(0))],
+        _ => Vec::new(),
+    }
+}
+
+
+/// Types of the `$1`, `$2`... parameters of `plan`, the ones DataFusion cannot infer are taken
+/// from the types declared by the client, or text.
+fn get_parameter_types(plan: &LogicalPlan, declared_types: &[Type]) -> Result<Vec<DataType>> {
+    let parameter_types: Vec<DataType> = get_parameter_schema(plan)?
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match (field.data_type(), declared_types.get(index)) {
+            (DataType::Null, Some(pg_type)) => from_pg_type(pg_type),
+            (DataType::Null, None) => DataType::Utf8,
+            (data_type, _) => data_type.clone(),
+        })
+        .collect();
+    Ok(parameter_types)
+}
+
+
+/// Value of the parameter `index` bound to `portal`, as a `data_type` value.
+fn decode_parameter(portal: &Portal<SaPgStatement>, index: usize, data_type: &DataType) -> PgWireResult<ScalarValue> {
+    let invalid_parameter = |error: PgWireError| {
+        PgWireError::UserError(Box::new(ErrorInfo::new(
+            "ERROR".to_string(),
+            "22P02".to_string(),
+            format!("Invalid value for the parameter ${}: {}", index + 1, error),
+        )))
+    };
+    let value: ScalarValue = match portal.parameters.get(index) {
+        Some(Some(bytes)) if portal.parameter_format.is_text(index) => {
+            let text: &str = std::str::from_utf8(bytes).map_err(|error| invalid_parameter(PgWireError::ApiError(Box::new(error))))?;
+            ScalarValue::Utf8(Some(text.to_string()))
+        },
+        Some(Some(_)) => {
+            // Binary values are encoded with the declared type, or the one the client was told
+            let pg_type: Type = match portal.statement.parameter_types.get(index) {
+                Some(pg_type) if *pg_type != Type::UNKNOWN => pg_type.clone(),
+                _ => to_pg_type(data_type),
+            };
+            decode_binary_parameter(portal, index, &pg_type).map_err(invalid_parameter)?
+        },
+        _ => ScalarValue::Null,
+    };
+    if value.data_type() == *data_type || value.is_null() {
+        return Ok(value);
+    }
+    value.cast_to(data_type).map_err(|error| invalid_parameter(SaError::from(error).into()))
+}
+
+
+fn decode_binary_parameter(portal: &Portal<SaPgStatement>, index: usize, pg_type: &Type) -> PgWireResult<ScalarValue> {
+    let value: ScalarValue = match *pg_type {
+        Type::BOOL => ScalarValue::Boolean(portal.parameter::<bool>(index, pg_type)?),
+        Type::INT2 => ScalarValue::Int16(portal.parameter::<i16>(index, pg_type)?),
+        Type::INT4 => ScalarValue::Int32(portal.parameter::<i32>(index, pg_type)?),
+        Type::INT8 => ScalarValue::Int64(portal.parameter::<i64>(index, pg_type)?),
+        Type::FLOAT4 => ScalarValue::Float32(portal.parameter::<f32>(index, pg_type)?),
+        Type::FLOAT8 => ScalarValue::Float64(portal.parameter::<f64>(index, pg_type)?),
+        Type::NUMERIC => ScalarValue::Utf8(portal.parameter::<Decimal>(index, pg_type)?.map(|value| value.to_string())),
+        Type::BYTEA => ScalarValue::Binary(portal.parameter::<Vec<u8>>(index, pg_type)?),
+        Type::DATE => ScalarValue::Date32(portal.parameter::<NaiveDate>(index, pg_type)?.map(Date32Type::from_naive_date)),
+        Type::TIMESTAMP => ScalarValue::TimestampMicrosecond(
+            portal.parameter::<NaiveDateTime>(index, pg_type)?.map(|value| value.and_utc().timestamp_micros()),
+            None,
+        ),
+        Type::TIMESTAMPTZ => ScalarValue::TimestampMicrosecond(
+            portal.parameter::<DateTime<Utc>>(index, pg_type)?.map(|value| value.timestamp_micros()),
+            Some("UTC".into()),
+        ),
+        _ => ScalarValue::Utf8(portal.parameter::<String>(index, pg_type)?),
+    };
+    Ok(value)
+}
+
+
+/// Answers the session statements of Postgres, or plans `statement`.
+async fn plan_statement(sa_datafusion: &SaDataFusion, mut statement: DFStatement) -> Result<SaPgStatement> {
+    if let Some(command) = get_command(&statement) {
+        return Ok(SaPgStatement::Command(command));
+    }
+    rewrite_pg_syntax(&mut statement);
+    register_catalog_tables(sa_datafusion, &statement).await?;
+    Ok(SaPgStatement::Plan(Box::new(pipelines::sa_plan_statement(sa_datafusion, statement).await?)))
+}
+
+
+/// Registers the tables of the `sa.catalog` file before `statement` reads `pg_catalog` or
+/// `information_schema`, so they list them; a table that cannot be read is skipped.
+async fn register_catalog_tables(sa_datafusion: &SaDataFusion, statement: &DFStatement) -> Result<()> {
+    let Some(catalog) = sa_datafusion.get_catalog()? else {
+        return Ok(());
+    };
+    let table_names: Vec<TableReference> = sa_datafusion.get_session_state().resolve_table_references(statement)?;
+    if !table_names.iter().any(|table_name| matches!(table_name.schema(), Some("pg_catalog" | "information_schema"))) {
+        return Ok(());
+    }
+    for table_name in catalog.get_table_names() {
+        if let Err(error) = pipelines::sa_register_catalog_table_pipeline(sa_datafusion, table_name.as_str()).await {
+            eprintln!("[sa_pgwire]: Could not register the catalog table {}: {}", table_name, error);
+        }
+    }
+    Ok(())
+}
+
+
+fn get_command(statement: &DFStatement) -> Option<SaPgCommand> {
+    let DFStatement::Statement(statement) = statement else {
+        return None;
+    };
+    let command: SaPgCommand = match statement.as_ref() {
+        // Options of the session are set by DataFusion, settings of Postgres are ignored
+        SQLStatement::SetVariable { variables, .. } => {
+            let is_option = |variable: &str| variable.eq_ignore_ascii_case("datafusion") || variable.eq_ignore_ascii_case("sa");
+            if variables.iter().any(|variable| variable.0.first().is_some_and(|part| is_option(&part.value)) && variable.0.len() > 1) {
+                return None;
+            }
+            SaPgCommand::Ignore("SET".to_string())
+        },
+        SQLStatement::SetNames { .. } | SQLStatement::SetNamesDefault {} | SQLStatement::SetTransaction { .. } => SaPgCommand::Ignore("SET".to_string()),
+        SQLStatement::ShowVariable { variable } => {
+            let name: String = variable.iter().map(|part| part.value.as_str()).collect::<Vec<&str>>().join("_");
+            let name: &str = match name.to_lowercase().as_str() {
+                "transaction_isolation_level" => "transaction_isolation",
+                _ => name.as_str(),
+            };
+            let (name, value) = PG_PARAMETERS.iter().find(|(parameter, _)| parameter.eq_ignore_ascii_case(name))?;
+            SaPgCommand::Show(name.to_lowercase(), value.to_string())
+        },
+        SQLStatement::StartTransaction { .. } => SaPgCommand::TransactionStart,
+        SQLStatement::Commit { .. } => SaPgCommand::TransactionEnd("COMMIT".to_string()),
+        SQLStatement::Rollback { savepoint: None, .. } => SaPgCommand::TransactionEnd("ROLLBACK".to_string()),
+        SQLStatement::Rollback { .. } => SaPgCommand::Ignore("ROLLBACK".to_string()),
+        SQLStatement::Savepoint { .. } => SaPgCommand::Ignore("SAVEPOINT".to_string()),
+        SQLStatement::ReleaseSavepoint { .. } => SaPgCommand::Ignore("RELEASE".to_string()),
+        SQLStatement::Discard { object_type } => SaPgCommand::Ignore(format!("DISCARD {}", object_type)),
+        _ => return None,
+    };
+    Some(command)
+}
+
+
+/// Rewrites the Postgres syntax psql uses in its introspection queries that DataFusion does not
+/// plan: `COLLATE` clauses are dropped, texts being compared bytewise, and functions and
+/// operators qualified by `pg_catalog`, such as `OPERATOR(pg_catalog.~)`, become the plain ones.
+fn rewrite_pg_syntax(statement: &mut DFStatement) {
+    let DFStatement::Statement(statement) = statement else {
+        return;
+    };
+    let _ = visit_expressions_mut(statement.as_mut(), |expr| {
+        match expr {
+            Expr::Collate { expr: collated, .. } => *expr = collated.as_ref().clone(),
+            Expr::Function(function) if function.name.0.len() == 2 && function.name.0[0].value.eq_ignore_ascii_case("pg_catalog") => {
+                function.name.0.remove(0);
+            },
+            Expr::BinaryOp { op, .. } => {
+                if let BinaryOperator::PGCustomBinaryOperator(parts) = op {
+                    let operator: Option<BinaryOperator> = match parts.last().map(String::as_str) {
+                        Some("=") => Some(BinaryOperator::Eq),
+                        Some("<>") | Some("!=") => Some(BinaryOperator::NotEq),
+                        Some("<") => Some(BinaryOperator::Lt),
+                        Some("<=") => Some(BinaryOperator::LtEq),
+                        Some(">") => Some(BinaryOperator::Gt),
+                        Some(">=") => Some(BinaryOperator::GtEq),
+                        Some("~") => Some(BinaryOperator::PGRegexMatch),
+                        Some("~*") => Some(BinaryOperator::PGRegexIMatch),
+                        Some("!~") => Some(BinaryOperator::PGRegexNotMatch),
+                        Some("!~*") => Some(BinaryOperator::PGRegexNotIMatch),
+                        Some("~~") => Some(BinaryOperator::PGLikeMatch),
+                        _ => None,
+                    };
+                    if let Some(operator) = operator {
+                        *op = operator;
+                    }
+                }
+            },
+            _ => (),
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
+
+
+/// Command tag of the statements that return no rows, such as `INSERT 0 1` or `CREATE TABLE`.
+fn get_command_tag(plan: &LogicalPlan) -> Option<Tag> {
+    let tag: Tag = match plan {
+        LogicalPlan::Dml(dml) => match dml.op {
+            WriteOp::Insert(_) => Tag::new("INSERT").with_oid(0),
+            WriteOp::Update => Tag::new("UPDATE"),
+            WriteOp::Delete => Tag::new("DELETE"),
+            WriteOp::Ctas => Tag::new("SELECT"),
+        },
+        LogicalPlan::Copy(_) => Tag::new("COPY"),
+        LogicalPlan::Ddl(ddl) => Tag::new(match ddl {
+            DdlStatement::CreateExternalTable(_) | DdlStatement::CreateMemoryTable(_) => "CREATE TABLE",
+            DdlStatement::CreateView(_) => "CREATE VIEW",
+            DdlStatement::CreateCatalogSchema(_) => "CREATE SCHEMA",
+            DdlStatement::CreateCatalog(_) => "CREATE DATABASE",
+            DdlStatement::CreateIndex(_) => "CREATE INDEX",
+            DdlStatement::DropTable(_) => "DROP TABLE",
+            DdlStatement::DropView(_) => "DROP VIEW",
+            DdlStatement::DropCatalogSchema(_) => "DROP SCHEMA",
+            DdlStatement::CreateFunction(_) => "CREATE FUNCTION",
+            DdlStatement::DropFunction(_) => "DROP FUNCTION",
+        }),
+        LogicalPlan::Statement(Statement::SetVariable(_)) => Tag::new("SET"),
+        LogicalPlan::Statement(Statement::Prepare(_)) => Tag::new("PREPARE"),
+        LogicalPlan::Statement(Statement::Deallocate(_)) => Tag::new("DEALLOCATE"),
+        _ => return None,
+    };
+    Some(tag)
+}
+
+
+/// Runs `statement` with its `parameters` bound; the rows it returns stream in `format`, unless
+/// `fetch_all` asks to fetch them first.
+async fn run_statement(
+    sa_datafusion: &SaDataFusion,
+    statement: &SaPgStatement,
+    parameters: Option<ParamValues>,
+    format: &Format,
+    fetch_all: bool,
+) -> PgWireResult<Response<'static>> {
+    let plan: LogicalPlan = match statement {
+        SaPgStatement::Empty => return Ok(Response::EmptyQuery),
+        SaPgStatement::Command(command) => return get_command_response(command, format),
+        SaPgStatement::Plan(plan) => match parameters {
+            Some(parameters) => plan.as_ref().clone().with_param_values(parameters).map_err(SaError::from)?,
+            None => plan.as_ref().clone(),
+        },
+    };
+    // DDL runs when the plan is executed, the tag is known before
+    let tag: Option<Tag> = get_command_tag(&plan);
+    let is_counted: bool = matches!(plan, LogicalPlan::Dml(_) | LogicalPlan::Copy(_));
+    let df: DataFrame = sa_datafusion.ctx.execute_logical_plan(plan).await.map_err(SaError::from)?;
+    match tag {
+        Some(tag) if is_counted => Ok(Response::Execution(tag.with_rows(get_record_count(df).await? as usize))),
+        Some(tag) => Ok(Response::Execution(tag)),
+        None => Ok(Response::Query(get_query_response(df, format, fetch_all).await?)),
+    }
+}
+
+
+fn get_command_response(command: &SaPgCommand, format: &Format) -> PgWireResult<Response<'static>> {
+    let response: Response<'static> = match command {
+        SaPgCommand::Ignore(tag) => Response::Execution(Tag::new(tag)),
+        SaPgCommand::Show(name, value) => {
+            let fields: Arc<Vec<FieldInfo>> = Arc::new(vec![FieldInfo::new(name.clone(), None, None, Type::TEXT, format.format_for(0))]);
+            let mut encoder: DataRowEncoder = DataRowEncoder::new(fields.clone());
+            encoder.encode_field(value)?;
+            let row: DataRow = encoder.finish()?;
+            Response::Query(QueryResponse::new(fields, stream::iter([Ok(row)])))
+        },
+        SaPgCommand::TransactionStart => Response::TransactionStart(Tag::new("BEGIN")),
+        SaPgCommand::TransactionEnd(tag) => Response::TransactionEnd(Tag::new(tag)),
+    };
+    Ok(response)
+}
+
+
+/// Rows of `df` sent in `format`, encoded batch by batch as they are computed.
+async fn get_query_response(df: DataFrame, format: &Format, fetch_all: bool) -> Result<QueryResponse<'static>> {
+    let fields: Arc<Vec<FieldInfo>> = Arc::new(get_fields(df.schema().as_arrow(), format));
+    let batches: BoxStream<'static, std::result::Result<RecordBatch, DataFusionError>> = match fetch_all {
+        true => stream::iter(df.collect().await?.into_iter().map(Ok)).boxed(),
+        false => df.execute_stream().await?.boxed(),
+    };
+    let row_fields: Arc<Vec<FieldInfo>> = fields.clone();
+    let rows = batches
+        .map_err(|error| PgWireError::from(SaError::from(error)))
+        .map(move |batch| match batch.and_then(|batch| encode_batch(&batch, &row_fields)) {
+            Ok(rows) => stream::iter(rows.into_iter().map(Ok).collect::<Vec<PgWireResult<DataRow>>>()),
+            Err(error) => stream::iter(vec![Err(error)]),
+        })
+        .flatten();
+    Ok(QueryResponse::new(fields, rows))
+}
+
+
+fn encode_batch(batch: &RecordBatch, fields: &Arc<Vec<FieldInfo>>) -> PgWireResult<Vec<DataRow>> {
+    let columns: Vec<ArrayRef> = batch
+        .columns()
+        .iter()
+        .zip(fields.iter())
+        .map(|(column, field)| to_encoded_array(column, field.datatype()))
+        .collect::<Result<Vec<ArrayRef>>>()?;
+    (0..batch.num_rows())
+        .map(|row| {
+            let mut encoder: DataRowEncoder = DataRowEncoder::new(fields.clone());
+            for (column, field) in columns.iter().zip(fields.iter()) {
+                encode_value(&mut encoder, column, field.datatype(), row)?;
+            }
+            encoder.finish()
+        })
+        .collect()
+}
+
+
+/// `column` cast to the type its `pg_type` values are encoded from, columns that cannot be cast
+/// to text are formatted as they are printed.
+fn to_encoded_array(column: &ArrayRef, pg_type: &Type) -> Result<ArrayRef> {
+    let data_type: DataType = get_encoded_data_type(pg_type, column.data_type());
+    if data_type == DataType::Utf8 && !can_cast_types(column.data_type(), &DataType::Utf8) {
+        let formatter: ArrayFormatter = ArrayFormatter::try_new(column.as_ref(), &FormatOptions::default())?;
+        let values: StringArray = (0..column.len())
+            .map(|row| column.is_valid(row).then(|| formatter.value(row).to_string()))
+            .collect();
+        return Ok(Arc::new(values));
+    }
+    let cast_options: CastOptions = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    Ok(cast_with_options(column, &data_type, &cast_options)?)
+}
+
+
+fn encode_value(encoder: &mut DataRowEncoder, column: &ArrayRef, pg_type: &Type, row: usize) -> PgWireResult<()> {
+    if column.is_null(row) {
+        return encoder.encode_field(&None::<i8>);
+    }
+    let list = |row: usize| column.as_list::<i32>().value(row);
+    match *pg_type {
+        Type::BOOL => encoder.encode_field(&column.as_boolean().value(row)),
+        Type::INT2 => encoder.encode_field(&column.as_primitive::<Int16Type>().value(row)),
+        Type::INT4 => encoder.encode_field(&column.as_primitive::<Int32Type>().value(row)),
+        Type::INT8 => encoder.encode_field(&column.as_primitive::<Int64Type>().value(row)),
+        Type::FLOAT4 => encoder.encode_field(&column.as_primitive::<Float32Type>().value(row)),
+        Type::FLOAT8 => encoder.encode_field(&column.as_primitive::<Float64Type>().value(row)),
+        Type::NUMERIC => {
+            let DataType::Decimal128(_, scale) = column.data_type() else {
+                return Err(SaError::from(DataFusionError::Internal(format!("Cannot send {} as numeric", column.data_type()))).into());
+            };
+            let value: Decimal = Decimal::try_from_i128_with_scale(column.as_primitive::<Decimal128Type>().value(row), *scale as u32)
+                .map_err(|error| PgWireError::ApiError(Box::new(error)))?;
+            encoder.encode_field(&value)
+        },
+        Type::BYTEA => encoder.encode_field(&column.as_binary::<i32>().value(row)),
+        Type::DATE => encoder.encode_field(&column.as_primitive::<Date32Type>().value_as_date(row)),
+        Type::TIME => encoder.encode_field(&column.as_primitive::<Time64MicrosecondType>().value_as_time(row)),
+        Type::TIMESTAMP => encoder.encode_field(&column.as_primitive::<TimestampMicrosecondType>().value_as_datetime(row)),
+        Type::TIMESTAMPTZ => {
+            let value: Option<NaiveDateTime> = column.as_primitive::<TimestampMicrosecondType>().value_as_datetime(row);
+            encoder.encode_field(&value.map(|value| value.and_utc()))
+        },
+        Type::BOOL_ARRAY => encoder.encode_field(&list(row).as_boolean().iter().collect::<Vec<Option<bool>>>()),
+        Type::INT2_ARRAY => encoder.encode_field(&list(row).as_primitive::<Int16Type>().iter().collect::<Vec<Option<i16>>>()),
+        Type::INT4_ARRAY => encoder.encode_field(&list(row).as_primitive::<Int32Type>().iter().collect::<Vec<Option<i32>>>()),
+        Type::INT8_ARRAY => encoder.encode_field(&list(row).as_primitive::<Int64Type>().iter().collect::<Vec<Option<i64>>>()),
+        Type::FLOAT4_ARRAY => encoder.encode_field(&list(row).as_primitive::<Float32Type>().iter().collect::<Vec<Option<f32>>>()),
+        Type::FLOAT8_ARRAY => encoder.encode_field(&list(row).as_primitive::<Float64Type>().iter().collect::<Vec<Option<f64>>>()),
+        Type::TEXT_ARRAY => {
+            let values: ArrayRef = list(row);
+            encoder.encode_field(&values.as_string::<i32>().iter().collect::<Vec<Option<&str>>>())
+        },
+        _ => encoder.encode_field(&column.as_string::<i32>().value(row)),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+use serde_json::Value;
+use datafusion::arrow::array::{ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, TimestampMillisecondArray};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use datafusion::datasource::MemTable;
+use crate::error::Result;
+use crate::table::delta::log::{SaDeltaCommit, SaDeltaLog};
+
+
+/// History of a Delta table, one row per commit still in its log from the latest, as
+/// `DESCRIBE HISTORY` shows it: `version`, `timestamp`, `operation`, `operation_parameters`,
+/// `operation_metrics` (JSON objects), `user_name`, `engine_info` and `is_blind_append`.
+pub async fn create_history_table(delta_log: &SaDeltaLog) -> Result<MemTable> {
+    let commits: Vec<SaDeltaCommit> = delta_log.read_commits().await?;
+    let schema: SchemaRef = Arc::new(Schema::new(vec![
+        Field::new("version", DataType::Int64, false),
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), false),
+        Field::new("operation", DataType::Utf8, true),
+        Field::new("operation_parameters", DataType::Utf8, true),
+        Field::new("operation_metrics", DataType::Utf8, true),
+        Field::new("user_name", DataType::Utf8, true),
+        Field::new("engine_info", DataType::Utf8, true),
+        Field::new("is_blind_append", DataType::Boolean, true),
+    ]));
+    let get_string = |key: &str| -> ArrayRef {
+        Arc::new(commits.iter().map(|commit| match commit.commit_info.get(key) {
+            Some(Value::String(value)) => Some(value.clone()),
+            Some(Value::Null) | None => None,
+            Some(value) => Some(value.to_string()),
+        }).collect::<StringArray>())
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(commits.iter().map(|commit| commit.version).collect::<Int64Array>()),
+        Arc::new(TimestampMillisecondArray::from_iter_values(commits.iter().map(|commit| commit.timestamp_ms)).with_timezone("UTC")),
+        get_string("operation"),
+        get_string("operationParameters"),
+        get_string("operationMetrics"),
+        get_string("userName"),
+        get_string("engineInfo"),
+        Arc::new(commits.iter().map(|commit| commit.commit_info.get("isBlindAppend").and_then(Value::as_bool)).collect::<BooleanArray>()),
+    ];
+    let batch: RecordBatch = RecordBatch::try_new(schema.clone(), columns)?;
+    Ok(MemTable::try_new(schema, vec![vec![batch]])?)
+}
@@ -0,0 +1,320 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use bytes::Bytes;
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::compute::kernels::cast_utils::string_to_timestamp_nanos;
+use datafusion::arrow::json::LineDelimitedWriter;
+use datafusion::parquet::arrow::async_reader::{ParquetObjectReader, ParquetRecordBatchStreamBuilder};
+use datafusion::parquet::arrow::ProjectionMask;
+use object_store::path::Path;
+use object_store::{ObjectMeta, ObjectStore};
+use crate::error::{Result, SaError};
+use crate::helper::SaTableVersion;
+
+
+const LOG_DIRECTORY: &str = "_delta_log";
+
+/// Columns of the checkpoints needed to rebuild a version, removes being only tombstones there.
+const CHECKPOINT_COLUMNS: [&str; 3] = ["add", "metaData", "protocol"];
+
+/// Reader features whose tables are read as is, or checked file by file.
+const SUPPORTED_READER_FEATURES: [&str; 4] = ["timestampNtz", "vacuumProtocolCheck", "deletionVectors", "columnMapping"];
+
+/// Number of commit files read at once.
+const CONCURRENT_READS: usize = 16;
+
+
+/// File added to the table by an `add` action.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaDeltaAdd {
+    /// Path relative to the table, URL-encoded, or absolute URI.
+    pub path: String,
+    #[serde(default)]
+    pub partition_values: HashMap<String, Option<String>>,
+    pub size: i64,
+    /// JSON statistics: `numRecords`, `minValues`, `maxValues` and `nullCount`.
+    #[serde(default)]
+    pub stats: Option<String>,
+    #[serde(default)]
+    pub deletion_vector: Option<Value>,
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaDeltaRemove {
+    pub path: String,
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaDeltaMetadata {
+    pub schema_string: String,
+    #[serde(default)]
+    pub partition_columns: Vec<String>,
+    #[serde(default)]
+    pub configuration: HashMap<String, Option<String>>,
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaDeltaProtocol {
+    pub min_reader_version: i32,
+    #[serde(default)]
+    pub reader_features: Option<Vec<String>>,
+}
+
+
+/// A line of a commit file, or a row of a checkpoint: one action.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaDeltaAction {
+    add: Option<SaDeltaAdd>,
+    remove: Option<SaDeltaRemove>,
+    meta_data: Option<SaDeltaMetadata>,
+    protocol: Option<SaDeltaProtocol>,
+    commit_info: Option<Map<String, Value>>,
+}
+
+
+/// A commit of the log with its `commitInfo`.
+#[derive(Debug, Clone)]
+pub struct SaDeltaCommit {
+    pub version: i64,
+    /// In-commit timestamp, `commitInfo.timestamp` or the modification time of the commit file.
+    pub timestamp_ms: i64,
+    pub commit_info: Map<String, Value>,
+}
+
+
+/// The table at a version: its metadata and active files.
+#[derive(Debug, Clone)]
+pub struct SaDeltaSnapshot {
+    pub version: i64,
+    pub metadata: SaDeltaMetadata,
+    pub files: Vec<SaDeltaAdd>,
+}
+
+
+/// The `_delta_log` of a Delta table: its JSON commits and Parquet checkpoints.
+#[derive(Debug, Clone)]
+pub struct SaDeltaLog {
+    uri: String,
+    object_store: Arc<dyn ObjectStore>,
+    commits: BTreeMap<i64, ObjectMeta>,
+    /// Complete checkpoints, with all their parts.
+    checkpoints: BTreeMap<i64, Vec<ObjectMeta>>,
+}
+
+
+impl SaDeltaLog {
+    /// Lists the log of the table at `table_path`, `None` if it has no `_delta_log` directory.
+    pub async fn try_new(uri: &str, object_store: Arc<dyn ObjectStore>, table_path: &Path) -> Result<Option<Self>> {
+        let log_path: Path = table_path.child(LOG_DIRECTORY);
+        let log_files: Vec<ObjectMeta> = match object_store.list_with_delimiter(Some(&log_path)).await {
+            Ok(list_result) => list_result.objects,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut commits: BTreeMap<i64, ObjectMeta> = BTreeMap::new();
+        let mut checkpoint_parts: BTreeMap<i64, (usize, Vec<ObjectMeta>)> = BTreeMap::new();
+        for meta in log_files {
+            let file_name: &str = meta.location.filename().unwrap_or_default();
+            let Some((version, kind)) = file_name.split_once('.') else {
+                continue;
+            };
+            let Ok(version) = version.parse::<i64>() else {
+                continue;
+            };
+            // `<v>.json`, `<v>.checkpoint.parquet` or `<v>.checkpoint.<part>.<parts>.parquet`;
+            // V2 checkpoints (`<v>.checkpoint.<uuid>.parquet`), checksums and compactions are skipped
+            match kind.split('.').collect::<Vec<&str>>().as_slice() {
+                ["json"] => {
+                    commits.insert(version, meta);
+                },
+                ["checkpoint", "parquet"] => {
+                    checkpoint_parts.insert(version, (1, vec![meta]));
+                },
+                ["checkpoint", _, parts, "parquet"] => {
+                    let Ok(parts) = parts.parse::<usize>() else {
+                        continue;
+                    };
+                    checkpoint_parts.entry(version).or_insert_with(|| (parts, Vec::new())).1.push(meta);
+                },
+                _ => {},
+            }
+        }
+        if commits.is_empty() && checkpoint_parts.is_empty() {
+            return Ok(None);
+        }
+
+        let checkpoints: BTreeMap<i64, Vec<ObjectMeta>> = checkpoint_parts
+            .into_iter()
+            .filter(|(_, (parts, files))| files.len() == *parts)
+            .map(|(version, (_, mut files))| {
+                files.sort_by(|a, b| a.location.cmp(&b.location));
+                (version, files)
+            })
+            .collect();
+        Ok(Some(Self {
+            uri: uri.to_string(),
+            object_store,
+            commits,
+            checkpoints,
+        }))
+    }
+
+    pub fn get_latest_version(&self) -> i64 {
+        let last_commit: Option<i64> = self.commits.keys().next_back().copied();
+        let last_checkpoint: Option<i64> = self.checkpoints.keys().next_back().copied();
+        last_commit.max(last_checkpoint).unwrap_or_default()
+    }
+
+    /// Version of the table at `version`, the latest one if `None`.
+    pub async fn resolve_version(&self, version: Option<&SaTableVersion>) -> Result<i64> {
+        match version {
+            None => Ok(self.get_latest_version()),
+            Some(SaTableVersion::Version(version)) => Ok(*version),
+            Some(SaTableVersion::Timestamp(timestamp)) => {
+                let timestamp_ms: i64 = string_to_timestamp_nanos(timestamp)? / 1_000_000;
+                self.read_commits()
+                    .await?
+                    .into_iter()
+                    .filter(|commit| commit.timestamp_ms <= timestamp_ms)
+                    .map(|commit| commit.version)
+                    .max()
+                    .ok_or_else(|| SaError::table_metadata(&self.uri, format!("no version at or before {}", timestamp)))
+            },
+        }
+    }
+
+    /// Replays the log up to `version`: the last checkpoint at or before it, then the following commits.
+    pub async fn replay(&self, version: i64) -> Result<SaDeltaSnapshot> {
+        let checkpoint: Option<(&i64, &Vec<ObjectMeta>)> = self.checkpoints.range(..=version).next_back();
+        let commit_versions: RangeInclusive<i64> = checkpoint.map_or(0, |(checkpoint_version, _)| checkpoint_version + 1)..=version;
+        if version < 0 || commit_versions.clone().any(|commit_version| !self.commits.contains_key(&commit_version)) {
+            return Err(SaError::table_metadata(&self.uri, format!("version {} is not in the log", version)));
+        }
+
+        let mut actions: Vec<SaDeltaAction> = Vec::new();
+        for part in checkpoint.map(|(_, parts)| parts.as_slice()).unwrap_or_default() {
+            actions.extend(self.read_checkpoint(part).await?);
+        }
+        let reads: Vec<_> = commit_versions.map(|commit_version| self.read(&self.commits[&commit_version])).collect();
+        let commits: Vec<Bytes> = stream::iter(reads).buffered(CONCURRENT_READS).try_collect().await?;
+        for data in commits {
+            actions.extend(parse_actions(&self.uri, &data)?);
+        }
+
+        let mut files: BTreeMap<String, SaDeltaAdd> = BTreeMap::new();
+        let mut metadata: Option<SaDeltaMetadata> = None;
+        let mut protocol: Option<SaDeltaProtocol> = None;
+        for action in actions {
+            if let Some(add) = action.add {
+                files.insert(add.path.clone(), add);
+            }
+            if let Some(remove) = action.remove {
+                files.remove(&remove.path);
+            }
+            if let Some(action_metadata) = action.meta_data {
+                metadata = Some(action_metadata);
+            }
+            if let Some(action_protocol) = action.protocol {
+                protocol = Some(action_protocol);
+            }
+        }
+        let metadata: SaDeltaMetadata = metadata.ok_or_else(|| SaError::table_metadata(&self.uri, "no metaData action"))?;
+        self.check_protocol(protocol.as_ref(), &metadata)?;
+        if files.values().any(|file| file.deletion_vector.is_some()) {
+            return Err(SaError::table_metadata(&self.uri, "deletion vectors are not supported"));
+        }
+        Ok(SaDeltaSnapshot {
+            version,
+            metadata,
+            files: files.into_values().collect(),
+        })
+    }
+
+    /// Commits still in the log, from the latest.
+    pub async fn read_commits(&self) -> Result<Vec<SaDeltaCommit>> {
+        let reads: Vec<_> = self.commits
+            .iter()
+            .rev()
+            .map(|(version, meta)| async move {
+                let data: Bytes = self.read(meta).await?;
+                let commit_info: Map<String, Value> = parse_actions(&self.uri, &data)?
+                    .into_iter()
+                    .find_map(|action| action.commit_info)
+                    .unwrap_or_default();
+                let timestamp_ms: i64 = ["inCommitTimestamp", "timestamp"]
+                    .iter()
+                    .find_map(|key| commit_info.get(*key)?.as_i64())
+                    .unwrap_or_else(|| meta.last_modified.timestamp_millis());
+                Ok(SaDeltaCommit {
+                    version: *version,
+                    timestamp_ms,
+                    commit_info,
+                })
+            })
+            .collect();
+        stream::iter(reads).buffered(CONCURRENT_READS).try_collect().await
+    }
+
+    async fn read(&self, meta: &ObjectMeta) -> Result<Bytes> {
+        Ok(self.object_store.get(&meta.location).await?.bytes().await?)
+    }
+
+    /// Reads the actions of a checkpoint part, converted to the JSON lines of the commits.
+    async fn read_checkpoint(&self, meta: &ObjectMeta) -> Result<Vec<SaDeltaAction>> {
+        let reader: ParquetObjectReader = ParquetObjectReader::new(self.object_store.clone(), meta.clone());
+        let builder = ParquetRecordBatchStreamBuilder::new(reader).await?;
+        let columns: Vec<usize> = builder.schema()
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| CHECKPOINT_COLUMNS.contains(&field.name().as_str()))
+            .map(|(index, _)| index)
+            .collect();
+        let projection: ProjectionMask = ProjectionMask::roots(builder.parquet_schema(), columns);
+        let batches: Vec<RecordBatch> = builder.with_projection(projection).build()?.try_collect().await?;
+
+        let mut writer: LineDelimitedWriter<Vec<u8>> = LineDelimitedWriter::new(Vec::new());
+        writer.write_batches(&batches.iter().collect::<Vec<&RecordBatch>>())?;
+        writer.finish()?;
+        parse_actions(&self.uri, &writer.into_inner())
+    }
+
+    fn check_protocol(&self, protocol: Option<&SaDeltaProtocol>, metadata: &SaDeltaMetadata) -> Result<()> {
+        if let Some(protocol) = protocol {
+            if protocol.min_reader_version > 3 {
+                let reason: String = format!("reader version {} is not supported", protocol.min_reader_version);
+                return Err(SaError::table_metadata(&self.uri, reason));
+            }
+            let reader_features: &[String] = protocol.reader_features.as_deref().unwrap_or_default();
+            if let Some(feature) = reader_features.iter().find(|feature| !SUPPORTED_READER_FEATURES.contains(&feature.as_str())) {
+                return Err(SaError::table_metadata(&self.uri, format!("reader feature '{}' is not supported", feature)));
+            }
+        }
+        match metadata.configuration.get("delta.columnMapping.mode").cloned().flatten() {
+            Some(mode) if mode != "none" => {
+                Err(SaError::table_metadata(&self.uri, format!("column mapping mode '{}' is not supported", mode)))
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+
+fn parse_actions(uri: &str, data: &[u8]) -> Result<Vec<SaDeltaAction>> {
+    data.split(|byte| *byte == b'\n')
+        .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+        .map(|line| serde_json::from_slice(line).map_err(|e| SaError::table_metadata(uri, e)))
+        .collect()
+}
@@ -0,0 +1,6 @@
+pub mod history;
+pub mod log;
+pub mod schema;
+pub mod table;
+pub use log::SaDeltaLog;
+pub use table::SaDeltaTable;
\ No newline at end of file
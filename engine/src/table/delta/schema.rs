@@ -0,0 +1,141 @@
+use std::sync::Arc;
+use serde::Deserialize;
+use datafusion::arrow::datatypes::{DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+use crate::error::{Result, SaError};
+
+
+/// Schema of a Delta table, the `schemaString` of its `metaData` action.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaDeltaSchema {
+    pub fields: Vec<SaDeltaField>,
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaDeltaField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: SaDeltaType,
+    #[serde(default = "default_nullable")]
+    pub nullable: bool,
+}
+
+
+/// Type of a field: a primitive such as `long` or `decimal(10,2)`, or a nested type.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SaDeltaType {
+    Primitive(String),
+    Nested(SaDeltaNestedType),
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SaDeltaNestedType {
+    Struct {
+        fields: Vec<SaDeltaField>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Array {
+        element_type: Box<SaDeltaType>,
+        contains_null: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    Map {
+        key_type: Box<SaDeltaType>,
+        value_type: Box<SaDeltaType>,
+        value_contains_null: bool,
+    },
+}
+
+
+fn default_nullable() -> bool {
+    true
+}
+
+
+impl SaDeltaSchema {
+    pub fn from_json(uri: &str, json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| SaError::table_metadata(uri, e))
+    }
+
+    /// Arrow schema of the table, with the `partition_columns` last as they are not in the data files.
+    pub fn to_arrow_schema(&self, partition_columns: &[String]) -> Result<SchemaRef> {
+        let (partition_fields, data_fields): (Vec<&SaDeltaField>, Vec<&SaDeltaField>) = self.fields
+            .iter()
+            .partition(|field| partition_columns.contains(&field.name));
+        let fields: Vec<Field> = data_fields
+            .into_iter()
+            .chain(partition_fields)
+            .map(SaDeltaField::to_arrow_field)
+            .collect::<Result<_>>()?;
+        Ok(Arc::new(Schema::new(fields)))
+    }
+}
+
+
+impl SaDeltaField {
+    pub fn to_arrow_field(&self) -> Result<Field> {
+        Ok(Field::new(&self.name, self.field_type.to_arrow_type()?, self.nullable))
+    }
+}
+
+
+impl SaDeltaType {
+    pub fn to_arrow_type(&self) -> Result<DataType> {
+        match self {
+            SaDeltaType::Primitive(name) => to_arrow_primitive(name),
+            SaDeltaType::Nested(SaDeltaNestedType::Struct { fields }) => {
+                let fields: Vec<Field> = fields.iter().map(SaDeltaField::to_arrow_field).collect::<Result<_>>()?;
+                Ok(DataType::Struct(Fields::from(fields)))
+            },
+            SaDeltaType::Nested(SaDeltaNestedType::Array { element_type, contains_null }) => {
+                let element: Field = Field::new("element", element_type.to_arrow_type()?, *contains_null);
+                Ok(DataType::List(Arc::new(element)))
+            },
+            SaDeltaType::Nested(SaDeltaNestedType::Map { key_type, value_type, value_contains_null }) => {
+                let entries: Field = Field::new("key_value", DataType::Struct(Fields::from(vec![
+                    Field::new("key", key_type.to_arrow_type()?, false),
+                    Field::new("value", value_type.to_arrow_type()?, *value_contains_null),
+                ])), false);
+                Ok(DataType::Map(Arc::new(entries), false))
+            },
+        }
+    }
+}
+
+
+/// Arrow type of the Delta primitive `name`, e.g. `decimal(10,2)`.
+fn to_arrow_primitive(name: &str) -> Result<DataType> {
+    let data_type: DataType = match name {
+        "boolean" => DataType::Boolean,
+        "byte" => DataType::Int8,
+        "short" => DataType::Int16,
+        "integer" => DataType::Int32,
+        "long" => DataType::Int64,
+        "float" => DataType::Float32,
+        "double" => DataType::Float64,
+        "date" => DataType::Date32,
+        "timestamp" => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        "timestamp_ntz" => DataType::Timestamp(TimeUnit::Microsecond, None),
+        "string" => DataType::Utf8,
+        "binary" => DataType::Binary,
+        _ => {
+            let Some(arguments) = name.strip_prefix("decimal(").and_then(|name| name.strip_suffix(')')) else {
+                return Err(unsupported_type(name));
+            };
+            let (precision, scale) = arguments.split_once(',').unwrap_or((arguments, "0"));
+            match (precision.trim().parse::<u8>(), scale.trim().parse::<i8>()) {
+                (Ok(precision), Ok(scale)) => DataType::Decimal128(precision, scale),
+                _ => return Err(unsupported_type(name)),
+            }
+        },
+    };
+    Ok(data_type)
+}
+
+
+fn unsupported_type(name: &str) -> SaError {
+    SaError::table_metadata("delta", format!("unsupported type '{}'", name))
+}
@@ -0,0 +1,292 @@
+use std::any::Any;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use datafusion::arrow::array::{ArrayRef, BooleanArray, UInt64Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::catalog::Session;
+use datafusion::common::{Column, DFSchema, ScalarValue};
+use datafusion::datasource::listing::PartitionedFile;
+use datafusion::datasource::physical_plan::{FileScanConfig, ParquetExec};
+use datafusion::datasource::{TableProvider, TableType};
+use datafusion::execution::object_store::ObjectStoreUrl;
+use datafusion::logical_expr::utils::conjunction;
+use datafusion::logical_expr::{Expr, TableProviderFilterPushDown};
+use datafusion::physical_expr::PhysicalExpr;
+use datafusion::physical_optimizer::pruning::{PruningPredicate, PruningStatistics};
+use datafusion::physical_plan::ExecutionPlan;
+use object_store::path::Path;
+use url::Url;
+use crate::error::{Result, SaError};
+use crate::helper::SaTableVersion;
+use crate::table::delta::log::{SaDeltaAdd, SaDeltaLog, SaDeltaSnapshot};
+use crate::table::delta::schema::SaDeltaSchema;
+
+
+/// Length string statistics are truncated to by most writers, making their maximum unusable.
+const MAX_STRING_STATISTICS_LENGTH: usize = 32;
+
+
+/// A Delta table read at its latest version or at a past one.
+///
+/// The active files are those of the log replayed up to the version; at each scan they are skipped
+/// with their partition values and statistics. Partition columns come after the other columns.
+/// Column mapping and deletion vectors are not supported.
+pub struct SaDeltaTable {
+    uri: String,
+    object_store_url: ObjectStoreUrl,
+    table_path: Path,
+    version: i64,
+    schema: SchemaRef,
+    /// Schema of the data files, without the partition columns.
+    file_schema: SchemaRef,
+    partition_fields: Vec<Field>,
+    files: Vec<SaDeltaAdd>,
+}
+
+
+impl fmt::Debug for SaDeltaTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SaDeltaTable")
+            .field("uri", &self.uri)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+
+impl SaDeltaTable {
+    /// Replays `delta_log` of the table at `table_path` up to `version`, the latest one if `None`.
+    pub async fn try_new(
+        uri: &str,
+        object_store_url: ObjectStoreUrl,
+        table_path: Path,
+        delta_log: &SaDeltaLog,
+        version: Option<&SaTableVersion>,
+    ) -> Result<Self> {
+        let version: i64 = delta_log.resolve_version(version).await?;
+        let snapshot: SaDeltaSnapshot = delta_log.replay(version).await?;
+        let partition_columns: &[String] = &snapshot.metadata.partition_columns;
+        let schema: SchemaRef = SaDeltaSchema::from_json(uri, &snapshot.metadata.schema_string)?
+            .to_arrow_schema(partition_columns)?;
+        let (partition_fields, file_fields): (Vec<Field>, Vec<Field>) = schema
+            .fields()
+            .iter()
+            .map(|field| field.as_ref().clone())
+            .partition(|field| partition_columns.contains(field.name()));
+        Ok(Self {
+            uri: uri.to_string(),
+            object_store_url,
+            table_path,
+            version,
+            schema,
+            file_schema: Arc::new(Schema::new(file_fields)),
+            partition_fields,
+            files: snapshot.files,
+        })
+    }
+
+    pub fn get_version(&self) -> i64 {
+        self.version
+    }
+
+    /// Object store path of the file at `path`, relative to the table unless it is a URI.
+    fn resolve_path(&self, path: &str) -> Result<Path> {
+        if path.contains("://") {
+            let url: Url = Url::parse(path).map_err(|e| SaError::uri_parse(path, e))?;
+            return Path::from_url_path(url.path()).map_err(|e| SaError::uri_parse(path, e));
+        }
+        let relative_path: Path = Path::from_url_path(path).map_err(|e| SaError::uri_parse(path, e))?;
+        Ok(Path::from_iter(self.table_path.parts().chain(relative_path.parts())))
+    }
+
+    /// Value of the partition column `field` for `file`, null when it is not set.
+    fn get_partition_value(&self, file: &SaDeltaAdd, field: &Field) -> Result<ScalarValue> {
+        match file.partition_values.get(field.name()).cloned().flatten() {
+            Some(value) => ScalarValue::try_from_string(value, field.data_type()).map_err(|e| {
+                SaError::table_metadata(&self.uri, format!("invalid partition value of '{}': {}", field.name(), e))
+            }),
+            None => Ok(ScalarValue::try_from(field.data_type())?),
+        }
+    }
+}
+
+
+#[async_trait]
+impl TableProvider for SaDeltaTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    fn supports_filters_pushdown(&self, filters: &[&Expr]) -> datafusion::error::Result<Vec<TableProviderFilterPushDown>> {
+        Ok(vec![TableProviderFilterPushDown::Inexact; filters.len()])
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> datafusion::error::Result<Arc<dyn ExecutionPlan>> {
+        let predicate: Option<Arc<dyn PhysicalExpr>> = match conjunction(filters.to_vec()) {
+            Some(filter) => {
+                let df_schema: DFSchema = DFSchema::try_from(self.schema.as_ref().clone())?;
+                Some(state.create_physical_expr(filter, &df_schema)?)
+            },
+            None => None,
+        };
+        let pruning_predicate: Option<PruningPredicate> = predicate
+            .as_ref()
+            .and_then(|predicate| PruningPredicate::try_new(predicate.clone(), self.schema.clone()).ok());
+
+        let keep: Vec<bool> = match &pruning_predicate {
+            Some(pruning_predicate) => pruning_predicate.prune(&SaDeltaFileStatistics::new(self))?,
+            None => vec![true; self.files.len()],
+        };
+        let mut partitioned_files: Vec<PartitionedFile> = Vec::new();
+        for file in self.files.iter().zip(keep).filter(|(_, keep)| *keep).map(|(file, _)| file) {
+            let path: Path = self.resolve_path(&file.path)?;
+            let mut partitioned_file: PartitionedFile = PartitionedFile::new(path.to_string(), file.size as u64);
+            partitioned_file.partition_values = self.partition_fields
+                .iter()
+                .map(|field| self.get_partition_value(file, field))
+                .collect::<Result<_>>()?;
+            partitioned_files.push(partitioned_file);
+        }
+
+        let file_scan_config: FileScanConfig = FileScanConfig::new(self.object_store_url.clone(), self.file_schema.clone())
+            .with_table_partition_cols(self.partition_fields.clone())
+            .with_file_group(partitioned_files)
+            .with_projection(projection.cloned())
+            .with_limit(limit);
+        let mut parquet_exec = ParquetExec::builder(file_scan_config);
+        if let Some(predicate) = predicate {
+            parquet_exec = parquet_exec.with_predicate(predicate);
+        }
+        Ok(parquet_exec.build_arc())
+    }
+}
+
+
+/// Statistics of an `add` action, parsed from its JSON `stats`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaDeltaFileStats {
+    num_records: Option<u64>,
+    #[serde(default)]
+    min_values: Map<String, Value>,
+    #[serde(default)]
+    max_values: Map<String, Value>,
+    #[serde(default)]
+    null_count: Map<String, Value>,
+}
+
+
+/// Statistics of the active files of a table: their partition values and column statistics.
+struct SaDeltaFileStatistics<'a> {
+    table: &'a SaDeltaTable,
+    stats: Vec<SaDeltaFileStats>,
+}
+
+
+impl<'a> SaDeltaFileStatistics<'a> {
+    fn new(table: &'a SaDeltaTable) -> Self {
+        let stats: Vec<SaDeltaFileStats> = table.files
+            .iter()
+            .map(|file| {
+                file.stats
+                    .as_deref()
+                    .and_then(|stats| serde_json::from_str(stats).ok())
+                    .unwrap_or_default()
+            })
+            .collect();
+        Self {
+            table,
+            stats,
+        }
+    }
+
+    fn get_bounds(&self, column: &Column, is_max: bool) -> Option<ArrayRef> {
+        let field: &Field = self.table.schema.field_with_name(&column.name).ok()?;
+        let null: ScalarValue = ScalarValue::try_from(field.data_type()).ok()?;
+        let is_partition: bool = self.table.partition_fields.iter().any(|partition| partition.name() == field.name());
+        let values = self.table.files.iter().zip(&self.stats).map(|(file, stats)| {
+            // A partition value is both bounds of its column
+            if is_partition {
+                return self.table.get_partition_value(file, field).unwrap_or_else(|_| null.clone());
+            }
+            let bounds: &Map<String, Value> = if is_max { &stats.max_values } else { &stats.min_values };
+            bounds.get(field.name())
+                .and_then(|value| to_bound(value, field.data_type(), is_max))
+                .unwrap_or_else(|| null.clone())
+        });
+        ScalarValue::iter_to_array(values).ok()
+    }
+}
+
+
+impl PruningStatistics for SaDeltaFileStatistics<'_> {
+    fn min_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.get_bounds(column, false)
+    }
+
+    fn max_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.get_bounds(column, true)
+    }
+
+    fn num_containers(&self) -> usize {
+        self.table.files.len()
+    }
+
+    fn null_counts(&self, column: &Column) -> Option<ArrayRef> {
+        if self.table.partition_fields.iter().any(|partition| partition.name() == &column.name) {
+            return None;
+        }
+        let null_counts: UInt64Array = self.stats
+            .iter()
+            .map(|stats| stats.null_count.get(&column.name).and_then(Value::as_u64))
+            .collect();
+        Some(Arc::new(null_counts))
+    }
+
+    fn row_counts(&self, _column: &Column) -> Option<ArrayRef> {
+        let row_counts: UInt64Array = self.stats.iter().map(|stats| stats.num_records).collect();
+        Some(Arc::new(row_counts))
+    }
+
+    fn contained(&self, _column: &Column, _values: &HashSet<ScalarValue>) -> Option<BooleanArray> {
+        None
+    }
+}
+
+
+/// Converts the statistic `value` to `data_type`, `None` if it cannot bound the column.
+fn to_bound(value: &Value, data_type: &DataType, is_max: bool) -> Option<ScalarValue> {
+    let value: String = match value {
+        Value::String(value) => value.clone(),
+        Value::Number(value) => value.to_string(),
+        Value::Bool(value) => value.to_string(),
+        _ => return None,
+    };
+    if is_max && *data_type == DataType::Utf8 && value.chars().count() >= MAX_STRING_STATISTICS_LENGTH {
+        return None;
+    }
+    let bound: ScalarValue = ScalarValue::try_from_string(value, data_type).ok()?;
+    // Timestamp statistics are written in milliseconds, the maximum may hide up to 999 microseconds
+    match (bound, is_max) {
+        (ScalarValue::TimestampMicrosecond(Some(max), tz), true) => Some(ScalarValue::TimestampMicrosecond(Some(max + 999), tz)),
+        (bound, _) => Some(bound),
+    }
+}
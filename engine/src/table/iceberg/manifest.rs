@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use apache_avro::Reader;
+use apache_avro::types::Value;
+use datafusion::arrow::datatypes::{DataType, TimeUnit};
+use datafusion::scalar::ScalarValue;
+use crate::error::{Result, SaError};
+
+
+/// Status of a manifest entry whose file was removed by its snapshot.
+const STATUS_DELETED: i32 = 2;
+
+/// Content of manifests and files holding data rows, not row-level deletes.
+const CONTENT_DATA: i32 = 0;
+
+
+/// Entry of a manifest list: one manifest and the bounds of its partition values.
+#[derive(Debug, Clone)]
+pub struct SaIcebergManifestFile {
+    pub manifest_path: String,
+    pub partition_spec_id: i32,
+    pub content: i32,
+    /// Summary of each partition field of the spec, in spec order.
+    pub partitions: Vec<SaIcebergFieldSummary>,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct SaIcebergFieldSummary {
+    pub contains_null: bool,
+    pub lower_bound: Option<Vec<u8>>,
+    pub upper_bound: Option<Vec<u8>>,
+}
+
+
+/// Data file of a manifest with its statistics, keyed by field id.
+#[derive(Debug, Clone)]
+pub struct SaIcebergDataFile {
+    pub file_path: String,
+    pub file_format: String,
+    /// Partition values by partition field name.
+    pub partition: HashMap<String, Value>,
+    pub record_count: i64,
+    pub file_size_in_bytes: i64,
+    pub null_value_counts: HashMap<i32, i64>,
+    pub lower_bounds: HashMap<i32, Vec<u8>>,
+    pub upper_bounds: HashMap<i32, Vec<u8>>,
+}
+
+
+impl SaIcebergManifestFile {
+    pub fn is_data(&self) -> bool {
+        self.content == CONTENT_DATA
+    }
+}
+
+
+/// Reads the manifests listed by the Avro manifest list `data`.
+pub fn read_manifest_list(uri: &str, data: &[u8]) -> Result<Vec<SaIcebergManifestFile>> {
+    read_records(uri, data)?
+        .into_iter()
+        .map(|record| {
+            let partitions: Vec<SaIcebergFieldSummary> = get_list(&record, "partitions")
+                .into_iter()
+                .map(|summary| {
+                    let summary: HashMap<String, Value> = to_record(summary);
+                    SaIcebergFieldSummary {
+                        contains_null: get_bool(&summary, "contains_null").unwrap_or(true),
+                        lower_bound: get_bytes(&summary, "lower_bound"),
+                        upper_bound: get_bytes(&summary, "upper_bound"),
+                    }
+                })
+                .collect();
+            Ok(SaIcebergManifestFile {
+                manifest_path: get_string(&record, "manifest_path")
+                    .ok_or_else(|| SaError::table_metadata(uri, "manifest list entry without manifest_path"))?,
+                partition_spec_id: get_long(&record, "partition_spec_id").unwrap_or(0) as i32,
+                content: get_long(&record, "content").unwrap_or(CONTENT_DATA as i64) as i32,
+                partitions,
+            })
+        })
+        .collect()
+}
+
+
+/// Reads the live data files of the Avro manifest `data`, without the deleted entries.
+pub fn read_manifest(uri: &str, data: &[u8]) -> Result<Vec<SaIcebergDataFile>> {
+    let mut data_files: Vec<SaIcebergDataFile> = Vec::new();
+    for entry in read_records(uri, data)? {
+        if get_long(&entry, "status") == Some(STATUS_DELETED as i64) {
+            continue;
+        }
+        let data_file: HashMap<String, Value> = entry
+            .get("data_file")
+            .cloned()
+            .map(to_record)
+            .ok_or_else(|| SaError::table_metadata(uri, "manifest entry without data_file"))?;
+        if get_long(&data_file, "content").unwrap_or(CONTENT_DATA as i64) != CONTENT_DATA as i64 {
+            return Err(SaError::table_metadata(uri, "row-level delete files are not supported"));
+        }
+        data_files.push(SaIcebergDataFile {
+            file_path: get_string(&data_file, "file_path")
+                .ok_or_else(|| SaError::table_metadata(uri, "data file without file_path"))?,
+            file_format: get_string(&data_file, "file_format").unwrap_or_default(),
+            partition: data_file.get("partition").cloned().map(to_record).unwrap_or_default(),
+            record_count: get_long(&data_file, "record_count").unwrap_or_default(),
+            file_size_in_bytes: get_long(&data_file, "file_size_in_bytes").unwrap_or_default(),
+            null_value_counts: get_int_map(&data_file, "null_value_counts")
+                .into_iter()
+                .filter_map(|(id, value)| Some((id, to_long(&value)?)))
+                .collect(),
+            lower_bounds: get_int_map(&data_file, "lower_bounds")
+                .into_iter()
+                .filter_map(|(id, value)| Some((id, to_bytes(&value)?)))
+                .collect(),
+            upper_bounds: get_int_map(&data_file, "upper_bounds")
+                .into_iter()
+                .filter_map(|(id, value)| Some((id, to_bytes(&value)?)))
+                .collect(),
+        });
+    }
+    Ok(data_files)
+}
+
+
+/// Decodes a lower or upper bound, serialized as Iceberg's single-value binary format.
+///
+/// Returns `None` for the types whose bounds cannot be compared, such as binary.
+pub fn decode_bound(bytes: &[u8], data_type: &DataType) -> Option<ScalarValue> {
+    let value: ScalarValue = match data_type {
+        DataType::Boolean => ScalarValue::Boolean(Some(*bytes.first()? != 0)),
+        DataType::Int32 => ScalarValue::Int32(Some(i32::from_le_bytes(bytes.try_into().ok()?))),
+        DataType::Int64 => ScalarValue::Int64(Some(read_long(bytes)?)),
+        DataType::Float32 => ScalarValue::Float32(Some(f32::from_le_bytes(bytes.try_into().ok()?))),
+        DataType::Float64 => ScalarValue::Float64(Some(f64::from_le_bytes(bytes.try_into().ok()?))),
+        DataType::Date32 => ScalarValue::Date32(Some(i32::from_le_bytes(bytes.try_into().ok()?))),
+        DataType::Time64(TimeUnit::Microsecond) => ScalarValue::Time64Microsecond(Some(read_long(bytes)?)),
+        DataType::Timestamp(TimeUnit::Microsecond, tz) => ScalarValue::TimestampMicrosecond(Some(read_long(bytes)?), tz.clone()),
+        DataType::Timestamp(TimeUnit::Nanosecond, tz) => ScalarValue::TimestampNanosecond(Some(read_long(bytes)?), tz.clone()),
+        DataType::Utf8 => ScalarValue::Utf8(Some(String::from_utf8(bytes.to_vec()).ok()?)),
+        DataType::Decimal128(precision, scale) => {
+            // Unscaled value, big-endian two's complement on the minimum number of bytes
+            if bytes.is_empty() || bytes.len() > 16 {
+                return None;
+            }
+            let fill: u8 = if bytes[0] & 0x80 != 0 { 0xFF } else { 0 };
+            let mut be_bytes: [u8; 16] = [fill; 16];
+            be_bytes[16 - bytes.len()..].copy_from_slice(bytes);
+            ScalarValue::Decimal128(Some(i128::from_be_bytes(be_bytes)), *precision, *scale)
+        },
+        _ => return None,
+    };
+    Some(value)
+}
+
+
+/// Converts the partition value `value` to `data_type`, the type of its identity partition.
+pub fn to_scalar(value: &Value, data_type: &DataType) -> Option<ScalarValue> {
+    let value: &Value = match value {
+        Value::Union(_, value) => value,
+        value => value,
+    };
+    if matches!(value, Value::Null) {
+        return ScalarValue::try_from(data_type).ok();
+    }
+    let scalar: ScalarValue = match (value, data_type) {
+        (Value::Boolean(value), DataType::Boolean) => ScalarValue::Boolean(Some(*value)),
+        (Value::Int(value), DataType::Int32) => ScalarValue::Int32(Some(*value)),
+        (Value::Long(value), DataType::Int64) => ScalarValue::Int64(Some(*value)),
+        (Value::Float(value), DataType::Float32) => ScalarValue::Float32(Some(*value)),
+        (Value::Double(value), DataType::Float64) => ScalarValue::Float64(Some(*value)),
+        (Value::Date(value) | Value::Int(value), DataType::Date32) => ScalarValue::Date32(Some(*value)),
+        (Value::String(value), DataType::Utf8) => ScalarValue::Utf8(Some(value.clone())),
+        (Value::TimestampMicros(value) | Value::Long(value), DataType::Timestamp(TimeUnit::Microsecond, tz)) => {
+            ScalarValue::TimestampMicrosecond(Some(*value), tz.clone())
+        },
+        _ => return None,
+    };
+    Some(scalar)
+}
+
+
+fn read_records(uri: &str, data: &[u8]) -> Result<Vec<HashMap<String, Value>>> {
+    let reader: Reader<&[u8]> = Reader::new(data).map_err(|e| SaError::table_metadata(uri, e))?;
+    reader
+        .map(|value| value.map(to_record).map_err(|e| SaError::table_metadata(uri, e)))
+        .collect()
+}
+
+
+/// Fields of an Avro record by name, empty for any other value.
+fn to_record(value: Value) -> HashMap<String, Value> {
+    match value {
+        Value::Record(fields) => fields.into_iter().collect(),
+        Value::Union(_, value) => to_record(*value),
+        _ => HashMap::new(),
+    }
+}
+
+
+fn get_value<'a>(record: &'a HashMap<String, Value>, name: &str) -> Option<&'a Value> {
+    match record.get(name)? {
+        Value::Union(_, value) => Some(value),
+        value => Some(value),
+    }
+}
+
+
+fn get_string(record: &HashMap<String, Value>, name: &str) -> Option<String> {
+    match get_value(record, name)? {
+        Value::String(value) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+
+fn get_long(record: &HashMap<String, Value>, name: &str) -> Option<i64> {
+    to_long(get_value(record, name)?)
+}
+
+
+fn get_bool(record: &HashMap<String, Value>, name: &str) -> Option<bool> {
+    match get_value(record, name)? {
+        Value::Boolean(value) => Some(*value),
+        _ => None,
+    }
+}
+
+
+fn get_bytes(record: &HashMap<String, Value>, name: &str) -> Option<Vec<u8>> {
+    to_bytes(get_value(record, name)?)
+}
+
+
+fn get_list(record: &HashMap<String, Value>, name: &str) -> Vec<Value> {
+    match get_value(record, name) {
+        Some(Value::Array(values)) => values.clone(),
+        _ => Vec::new(),
+    }
+}
+
+
+/// Map with int keys, written by Iceberg as an array of `key`/`value` records.
+fn get_int_map(record: &HashMap<String, Value>, name: &str) -> Vec<(i32, Value)> {
+    get_list(record, name)
+        .into_iter()
+        .filter_map(|entry| {
+            let mut entry: HashMap<String, Value> = to_record(entry);
+            let key: i64 = to_long(entry.get("key")?)?;
+            Some((key as i32, entry.remove("value")?))
+        })
+        .collect()
+}
+
+
+fn to_long(value: &Value) -> Option<i64> {
+    match value {
+        Value::Int(value) => Some(*value as i64),
+        Value::Long(value) => Some(*value),
+        Value::Union(_, value) => to_long(value),
+        _ => None,
+    }
+}
+
+
+fn to_bytes(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::Bytes(value) | Value::Fixed(_, value) => Some(value.clone()),
+        Value::Union(_, value) => to_bytes(value),
+        _ => None,
+    }
+}
+
+
+/// Long bounds; ints promoted to longs by schema evolution keep their 4-byte bounds.
+fn read_long(bytes: &[u8]) -> Option<i64> {
+    match bytes.len() {
+        4 => Some(i32::from_le_bytes(bytes.try_into().ok()?) as i64),
+        8 => Some(i64::from_le_bytes(bytes.try_into().ok()?)),
+        _ => None,
+    }
+}
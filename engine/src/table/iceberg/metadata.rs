@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use serde::Deserialize;
+use datafusion::arrow::datatypes::{DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+use crate::error::{Result, SaError};
+
+
+/// Table metadata of an Iceberg table, as written in its `*.metadata.json` files (v1 and v2).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SaIcebergMetadata {
+    pub format_version: u8,
+    /// Location the table was written at, prefix of the paths of its files.
+    pub location: String,
+    #[serde(default)]
+    pub current_snapshot_id: Option<i64>,
+    #[serde(default)]
+    pub snapshots: Vec<SaIcebergSnapshot>,
+    #[serde(default)]
+    pub schemas: Vec<SaIcebergSchema>,
+    #[serde(default)]
+    pub current_schema_id: Option<i32>,
+    /// Only schema of v1 tables written without `schemas`.
+    #[serde(default)]
+    pub schema: Option<SaIcebergSchema>,
+    #[serde(default)]
+    pub partition_specs: Vec<SaIcebergPartitionSpec>,
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SaIcebergSnapshot {
+    pub snapshot_id: i64,
+    pub timestamp_ms: i64,
+    #[serde(default)]
+    pub manifest_list: Option<String>,
+    /// Manifests of v1 snapshots written without a manifest list.
+    #[serde(default)]
+    pub manifests: Vec<String>,
+    #[serde(default)]
+    pub schema_id: Option<i32>,
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SaIcebergSchema {
+    #[serde(default)]
+    pub schema_id: i32,
+    pub fields: Vec<SaIcebergField>,
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaIcebergField {
+    pub id: i32,
+    pub name: String,
+    pub required: bool,
+    #[serde(rename = "type")]
+    pub field_type: SaIcebergType,
+}
+
+
+/// Type of a field: a primitive such as `long` or `decimal(10, 2)`, or a nested type.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SaIcebergType {
+    Primitive(String),
+    Nested(SaIcebergNestedType),
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SaIcebergNestedType {
+    Struct {
+        fields: Vec<SaIcebergField>,
+    },
+    #[serde(rename_all = "kebab-case")]
+    List {
+        element_id: i32,
+        element: Box<SaIcebergType>,
+        element_required: bool,
+    },
+    #[serde(rename_all = "kebab-case")]
+    Map {
+        key_id: i32,
+        key: Box<SaIcebergType>,
+        value_id: i32,
+        value: Box<SaIcebergType>,
+        value_required: bool,
+    },
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SaIcebergPartitionSpec {
+    pub spec_id: i32,
+    pub fields: Vec<SaIcebergPartitionField>,
+}
+
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SaIcebergPartitionField {
+    pub source_id: i32,
+    pub name: String,
+    pub transform: String,
+}
+
+
+impl SaIcebergMetadata {
+    pub fn from_json(uri: &str, json: &[u8]) -> Result<Self> {
+        serde_json::from_slice(json).map_err(|e| SaError::table_metadata(uri, e))
+    }
+
+    /// The current snapshot, `None` for a table without data yet.
+    pub fn get_current_snapshot(&self) -> Option<&SaIcebergSnapshot> {
+        let snapshot_id: i64 = self.current_snapshot_id.filter(|snapshot_id| *snapshot_id != -1)?;
+        self.get_snapshot(snapshot_id)
+    }
+
+    pub fn get_snapshot(&self, snapshot_id: i64) -> Option<&SaIcebergSnapshot> {
+        self.snapshots.iter().find(|snapshot| snapshot.snapshot_id == snapshot_id)
+    }
+
+    /// The last snapshot committed at or before `timestamp_ms`.
+    pub fn get_snapshot_as_of(&self, timestamp_ms: i64) -> Option<&SaIcebergSnapshot> {
+        self.snapshots
+            .iter()
+            .filter(|snapshot| snapshot.timestamp_ms <= timestamp_ms)
+            .max_by_key(|snapshot| snapshot.timestamp_ms)
+    }
+
+    /// Schema `snapshot` was written with, the current one if it does not say.
+    pub fn get_schema(&self, snapshot: Option<&SaIcebergSnapshot>) -> Option<&SaIcebergSchema> {
+        let schema_id: Option<i32> = snapshot.and_then(|snapshot| snapshot.schema_id).or(self.current_schema_id);
+        schema_id
+            .and_then(|schema_id| self.schemas.iter().find(|schema| schema.schema_id == schema_id))
+            .or(self.schema.as_ref())
+            .or(self.schemas.last())
+    }
+
+    pub fn get_partition_spec(&self, spec_id: i32) -> Option<&SaIcebergPartitionSpec> {
+        self.partition_specs.iter().find(|spec| spec.spec_id == spec_id)
+    }
+}
+
+
+impl SaIcebergSchema {
+    pub fn to_arrow_schema(&self) -> Result<SchemaRef> {
+        let fields: Vec<Field> = self.fields
+            .iter()
+            .map(SaIcebergField::to_arrow_field)
+            .collect::<Result<_>>()?;
+        Ok(Arc::new(Schema::new(fields)))
+    }
+
+    /// Top-level fields by id.
+    pub fn get_fields_by_id(&self) -> HashMap<i32, &SaIcebergField> {
+        self.fields.iter().map(|field| (field.id, field)).collect()
+    }
+}
+
+
+impl SaIcebergField {
+    pub fn to_arrow_field(&self) -> Result<Field> {
+        Ok(Field::new(&self.name, self.field_type.to_arrow_type()?, !self.required))
+    }
+}
+
+
+impl SaIcebergType {
+    pub fn to_arrow_type(&self) -> Result<DataType> {
+        match self {
+            SaIcebergType::Primitive(name) => to_arrow_primitive(name),
+            SaIcebergType::Nested(SaIcebergNestedType::Struct { fields }) => {
+                let fields: Vec<Field> = fields.iter().map(SaIcebergField::to_arrow_field).collect::<Result<_>>()?;
+                Ok(DataType::Struct(Fields::from(fields)))
+            },
+            SaIcebergType::Nested(SaIcebergNestedType::List { element, element_required, .. }) => {
+                let element: Field = Field::new("element", element.to_arrow_type()?, !element_required);
+                Ok(DataType::List(Arc::new(element)))
+            },
+            SaIcebergType::Nested(SaIcebergNestedType::Map { key, value, value_required, .. }) => {
+                let entries: Field = Field::new("key_value", DataType::Struct(Fields::from(vec![
+                    Field::new("key", key.to_arrow_type()?, false),
+                    Field::new("value", value.to_arrow_type()?, !value_required),
+                ])), false);
+                Ok(DataType::Map(Arc::new(entries), false))
+            },
+        }
+    }
+}
+
+
+/// Arrow type of the Iceberg primitive `name`, e.g. `decimal(10, 2)` or `fixed[16]`.
+fn to_arrow_primitive(name: &str) -> Result<DataType> {
+    let data_type: DataType = match name {
+        "boolean" => DataType::Boolean,
+        "int" => DataType::Int32,
+        "long" => DataType::Int64,
+        "float" => DataType::Float32,
+        "double" => DataType::Float64,
+        "date" => DataType::Date32,
+        "time" => DataType::Time64(TimeUnit::Microsecond),
+        "timestamp" => DataType::Timestamp(TimeUnit::Microsecond, None),
+        "timestamptz" => DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into())),
+        "timestamp_ns" => DataType::Timestamp(TimeUnit::Nanosecond, None),
+        "timestamptz_ns" => DataType::Timestamp(TimeUnit::Nanosecond, Some("+00:00".into())),
+        "string" => DataType::Utf8,
+        "uuid" => DataType::FixedSizeBinary(16),
+        "binary" => DataType::Binary,
+        _ => {
+            if let Some(arguments) = name.strip_prefix("decimal(").and_then(|name| name.strip_suffix(')')) {
+                let (precision, scale) = arguments.split_once(',').unwrap_or((arguments, "0"));
+                match (precision.trim().parse::<u8>(), scale.trim().parse::<i8>()) {
+                    (Ok(precision), Ok(scale)) => DataType::Decimal128(precision, scale),
+                    _ => return Err(unsupported_type(name)),
+                }
+            } else if let Some(length) = name.strip_prefix("fixed[").and_then(|name| name.strip_suffix(']')) {
+                DataType::FixedSizeBinary(length.trim().parse().map_err(|_| unsupported_type(name))?)
+            } else {
+                return Err(unsupported_type(name));
+            }
+        },
+    };
+    Ok(data_type)
+}
+
+
+fn unsupported_type(name: &str) -> SaError {
+    SaError::table_metadata("iceberg", format!("unsupported type '{}'", name))
+}
@@ -0,0 +1,5 @@
+pub mod manifest;
+pub mod metadata;
+pub mod schema;
+pub mod table;
+pub use table::{find_metadata_location, SaIcebergTable};
\ No newline at end of file
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use datafusion::arrow::datatypes::{Field, Schema, SchemaRef};
+use datafusion::common::Result as DFResult;
+use datafusion::datasource::schema_adapter::{
+    DefaultSchemaAdapterFactory,
+    SchemaAdapter,
+    SchemaAdapterFactory,
+    SchemaMapper
+};
+use datafusion::parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+use crate::error::{Result, SaError};
+use crate::table::iceberg::metadata::{SaIcebergMetadata, SaIcebergSchema};
+
+
+/// Prefix of the names given to the columns of a data file that are not columns of the table.
+const UNMATCHED_COLUMN_PREFIX: &str = "__sa_unmatched_";
+
+
+/// Matches the top-level columns of data files with the columns of an Iceberg table by field id.
+///
+/// Columns of files written without field ids are matched by the names their field had in the
+/// schemas of the table, as the default name mapping of Iceberg does; a name several fields had
+/// cannot be matched and fails the scan.
+#[derive(Debug, Clone)]
+pub struct SaIcebergSchemaAdapterFactory {
+    uri: String,
+    /// Name of each column of the schema read, by field id.
+    names_by_id: HashMap<i32, String>,
+    /// Field id of every name of a top-level field in the schemas of the table, `None` for the
+    /// names of several fields.
+    ids_by_name: HashMap<String, Option<i32>>,
+}
+
+
+impl SaIcebergSchemaAdapterFactory {
+    pub fn new(uri: &str, metadata: &SaIcebergMetadata, schema: &SaIcebergSchema) -> Self {
+        let mut ids_by_name: HashMap<String, Option<i32>> = HashMap::new();
+        let fields = metadata.schemas
+            .iter()
+            .chain(&metadata.schema)
+            .chain([schema])
+            .flat_map(|schema| &schema.fields);
+        for field in fields {
+            ids_by_name
+                .entry(field.name.clone())
+                .and_modify(|field_id| *field_id = field_id.filter(|field_id| *field_id == field.id))
+                .or_insert(Some(field.id));
+        }
+        Self {
+            uri: uri.to_string(),
+            names_by_id: schema.fields.iter().map(|field| (field.id, field.name.clone())).collect(),
+            ids_by_name,
+        }
+    }
+
+    /// Whether a column of the table was renamed, or a name given to another field, so the
+    /// columns of a data file do not all have the names of the table.
+    pub fn has_renamed_columns(&self) -> bool {
+        self.ids_by_name.iter().any(|(name, field_id)| match field_id {
+            Some(field_id) => self.names_by_id.get(field_id).is_some_and(|column| column != name),
+            None => true,
+        })
+    }
+
+    /// `file_schema` with the columns named as the columns of the table with their field id.
+    fn rename_file_schema(&self, file_schema: &Schema) -> Result<Schema> {
+        let fields: Vec<Field> = file_schema
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let field_id: Option<i32> = match field.metadata().get(PARQUET_FIELD_ID_META_KEY) {
+                    Some(field_id) => field_id.parse().ok(),
+                    None => match self.ids_by_name.get(field.name()) {
+                        Some(None) => {
+                            let reason: String = format!("data file column '{}' has no field id and several fields had this name", field.name());
+                            return Err(SaError::table_metadata(&self.uri, reason));
+                        },
+                        Some(field_id) => *field_id,
+                        None => None,
+                    },
+                };
+                // Columns of dropped fields are not read, even if a column has their name now
+                let name: String = field_id
+                    .and_then(|field_id| self.names_by_id.get(&field_id).cloned())
+                    .unwrap_or_else(|| format!("{}{}", UNMATCHED_COLUMN_PREFIX, index));
+                Ok(field.as_ref().clone().with_name(name))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Schema::new_with_metadata(fields, file_schema.metadata().clone()))
+    }
+}
+
+
+impl SchemaAdapterFactory for SaIcebergSchemaAdapterFactory {
+    fn create(&self, projected_table_schema: SchemaRef, table_schema: SchemaRef) -> Box<dyn SchemaAdapter> {
+        Box::new(SaIcebergSchemaAdapter {
+            factory: self.clone(),
+            schema_adapter: DefaultSchemaAdapterFactory.create(projected_table_schema, table_schema),
+        })
+    }
+}
+
+
+/// Maps data files to the table once their columns are renamed by [`SaIcebergSchemaAdapterFactory`].
+struct SaIcebergSchemaAdapter {
+    factory: SaIcebergSchemaAdapterFactory,
+    schema_adapter: Box<dyn SchemaAdapter>,
+}
+
+
+impl SchemaAdapter for SaIcebergSchemaAdapter {
+    fn map_column_index(&self, index: usize, file_schema: &Schema) -> Option<usize> {
+        let file_schema: Schema = self.factory.rename_file_schema(file_schema).ok()?;
+        self.schema_adapter.map_column_index(index, &file_schema)
+    }
+
+    fn map_schema(&self, file_schema: &Schema) -> DFResult<(Arc<dyn SchemaMapper>, Vec<usize>)> {
+        self.schema_adapter.map_schema(&self.factory.rename_file_schema(file_schema)?)
+    }
+}
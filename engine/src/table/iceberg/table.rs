@@ -0,0 +1,491 @@
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::future;
+use datafusion::arrow::array::{ArrayRef, BooleanArray, UInt64Array};
+use datafusion::arrow::compute::kernels::cast_utils::string_to_timestamp_nanos;
+use datafusion::arrow::datatypes::{DataType, SchemaRef};
+use datafusion::catalog::Session;
+use datafusion::common::{Column, DFSchema, ScalarValue};
+use datafusion::datasource::listing::PartitionedFile;
+use datafusion::datasource::physical_plan::{FileScanConfig, ParquetExec};
+use datafusion::datasource::{TableProvider, TableType};
+use datafusion::execution::object_store::ObjectStoreUrl;
+use datafusion::logical_expr::utils::conjunction;
+use datafusion::logical_expr::{Expr, TableProviderFilterPushDown};
+use datafusion::physical_expr::PhysicalExpr;
+use datafusion::physical_optimizer::pruning::{PruningPredicate, PruningStatistics};
+use datafusion::physical_plan::empty::EmptyExec;
+use datafusion::physical_plan::ExecutionPlan;
+use object_store::path::Path;
+use object_store::{ObjectMeta, ObjectStore};
+use url::Url;
+use crate::error::{Result, SaError};
+use crate::helper::SaTableVersion;
+use crate::table::iceberg::manifest::{self, SaIcebergDataFile, SaIcebergManifestFile};
+use crate::table::iceberg::metadata::{SaIcebergMetadata, SaIcebergPartitionSpec, SaIcebergSchema, SaIcebergSnapshot};
+use crate::table::iceberg::schema::SaIcebergSchemaAdapterFactory;
+
+
+const METADATA_DIRECTORY: &str = "metadata";
+const METADATA_SUFFIX: &str = ".metadata.json";
+const VERSION_HINT_FILE: &str = "version-hint.text";
+
+
+/// Finds the metadata file of the Iceberg table at `table_path`: `table_path` itself if it is a
+/// `*.metadata.json` file, then the version of `metadata/version-hint.text`, then the latest
+/// `metadata/*.metadata.json`. `None` if `table_path` is not an Iceberg table.
+pub async fn find_metadata_location(object_store: &Arc<dyn ObjectStore>, table_path: &Path) -> Result<Option<Path>> {
+    if table_path.as_ref().ends_with(METADATA_SUFFIX) {
+        return Ok(Some(table_path.clone()));
+    }
+    let metadata_path: Path = table_path.child(METADATA_DIRECTORY);
+    let metadata_files: Vec<ObjectMeta> = match object_store.list_with_delimiter(Some(&metadata_path)).await {
+        Ok(list_result) => list_result.objects,
+        Err(object_store::Error::NotFound { .. }) => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+
+    if let Some(version_hint) = metadata_files.iter().find(|meta| meta.location.filename() == Some(VERSION_HINT_FILE)) {
+        let version: Bytes = object_store.get(&version_hint.location).await?.bytes().await?;
+        let version: String = String::from_utf8_lossy(&version).trim().to_string();
+        let hinted_file: String = format!("v{}{}", version, METADATA_SUFFIX);
+        if let Some(meta) = metadata_files.iter().find(|meta| meta.location.filename() == Some(hinted_file.as_str())) {
+            return Ok(Some(meta.location.clone()));
+        }
+    }
+    Ok(metadata_files
+        .into_iter()
+        .filter(|meta| meta.location.as_ref().ends_with(METADATA_SUFFIX))
+        .max_by_key(|meta| (get_metadata_version(&meta.location), meta.last_modified))
+        .map(|meta| meta.location))
+}
+
+
+/// Version of a metadata file named `v3.metadata.json` or `00003-<uuid>.metadata.json`.
+fn get_metadata_version(location: &Path) -> i64 {
+    let file_name: &str = location.filename().unwrap_or_default();
+    file_name
+        .trim_start_matches('v')
+        .split(['-', '.'])
+        .next()
+        .and_then(|version| version.parse().ok())
+        .unwrap_or(-1)
+}
+
+
+/// An Iceberg table read at its current snapshot or at a past one.
+///
+/// Manifests are skipped with the partition bounds of the manifest list, data files with their
+/// partition values and column bounds; both are read again at each scan. Data files must be Parquet,
+/// their top-level columns are matched by field id (see [`SaIcebergSchemaAdapterFactory`]) and
+/// row-level deletes (v2 merge-on-read) are not supported.
+pub struct SaIcebergTable {
+    uri: String,
+    object_store: Arc<dyn ObjectStore>,
+    object_store_url: ObjectStoreUrl,
+    /// Location of the table in its metadata, replaced by `table_path` in file paths.
+    table_location: String,
+    table_path: Path,
+    metadata: SaIcebergMetadata,
+    snapshot: Option<SaIcebergSnapshot>,
+    schema: SchemaRef,
+    iceberg_schema: SaIcebergSchema,
+    schema_adapter_factory: Arc<SaIcebergSchemaAdapterFactory>,
+}
+
+
+impl fmt::Debug for SaIcebergTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SaIcebergTable")
+            .field("uri", &self.uri)
+            .field("snapshot_id", &self.snapshot.as_ref().map(|snapshot| snapshot.snapshot_id))
+            .finish()
+    }
+}
+
+
+impl SaIcebergTable {
+    /// Reads the metadata at `metadata_location` of the table at `table_path` (or of its
+    /// `metadata/*.metadata.json` file) in `object_store`, at the snapshot of `version` if set.
+    ///
+    /// The current snapshot is read with the current schema, so renamed columns have their new
+    /// name, and a past snapshot with the schema it was written with.
+    pub async fn try_new(
+        uri: &str,
+        object_store: Arc<dyn ObjectStore>,
+        object_store_url: ObjectStoreUrl,
+        table_path: Path,
+        metadata_location: &Path,
+        version: Option<&SaTableVersion>,
+    ) -> Result<Self> {
+        let metadata_json: Bytes = object_store.get(metadata_location).await?.bytes().await?;
+        let metadata: SaIcebergMetadata = SaIcebergMetadata::from_json(uri, &metadata_json)?;
+        let snapshot: Option<SaIcebergSnapshot> = match version {
+            None => metadata.get_current_snapshot().cloned(),
+            Some(SaTableVersion::Version(snapshot_id)) => Some(
+                metadata.get_snapshot(*snapshot_id)
+                    .cloned()
+                    .ok_or_else(|| SaError::table_metadata(uri, format!("no snapshot {}", snapshot_id)))?
+            ),
+            Some(SaTableVersion::Timestamp(timestamp)) => {
+                let timestamp_ms: i64 = string_to_timestamp_nanos(timestamp)? / 1_000_000;
+                Some(
+                    metadata.get_snapshot_as_of(timestamp_ms)
+                        .cloned()
+                        .ok_or_else(|| SaError::table_metadata(uri, format!("no snapshot at or before {}", timestamp)))?
+                )
+            },
+        };
+        let schema_snapshot: Option<&SaIcebergSnapshot> = version.and(snapshot.as_ref());
+        let iceberg_schema: SaIcebergSchema = metadata
+            .get_schema(schema_snapshot)
+            .cloned()
+            .ok_or_else(|| SaError::table_metadata(uri, "no schema"))?;
+        let table_path: Path = match table_path.as_ref().ends_with(METADATA_SUFFIX) {
+            true => get_table_path(&table_path),
+            false => table_path,
+        };
+        let schema_adapter_factory: SaIcebergSchemaAdapterFactory = SaIcebergSchemaAdapterFactory::new(uri, &metadata, &iceberg_schema);
+        Ok(Self {
+            uri: uri.to_string(),
+            object_store,
+            object_store_url,
+            table_location: normalize_location(&metadata.location),
+            table_path,
+            schema: iceberg_schema.to_arrow_schema()?,
+            iceberg_schema,
+            schema_adapter_factory: Arc::new(schema_adapter_factory),
+            metadata,
+            snapshot,
+        })
+    }
+
+    pub fn get_snapshot_id(&self) -> Option<i64> {
+        self.snapshot.as_ref().map(|snapshot| snapshot.snapshot_id)
+    }
+
+    /// Object store path of the file at `location`, relative to the table if it is inside it.
+    fn resolve_path(&self, location: &str) -> Result<Path> {
+        // A table copied or moved after being written still points to its first location
+        let location: String = normalize_location(location);
+        if let Some(relative_path) = location.strip_prefix(&self.table_location) {
+            let relative_path: &str = relative_path.trim_start_matches('/');
+            return Ok(Path::from_iter(self.table_path.parts().chain(Path::from(relative_path).parts())));
+        }
+        let url: Url = Url::parse(&location).map_err(|e| SaError::uri_parse(&location, e))?;
+        Path::from_url_path(url.path()).map_err(|e| SaError::uri_parse(&location, e))
+    }
+
+    async fn read_file(&self, location: &str) -> Result<Bytes> {
+        let path: Path = self.resolve_path(location)?;
+        Ok(self.object_store.get(&path).await?.bytes().await?)
+    }
+
+    async fn read_manifest_files(&self, snapshot: &SaIcebergSnapshot) -> Result<Vec<SaIcebergManifestFile>> {
+        let Some(manifest_list) = &snapshot.manifest_list else {
+            // v1 snapshots may list their manifests directly, without partition bounds
+            return Ok(snapshot.manifests
+                .iter()
+                .map(|manifest_path| SaIcebergManifestFile {
+                    manifest_path: manifest_path.clone(),
+                    partition_spec_id: 0,
+                    content: 0,
+                    partitions: Vec::new(),
+                })
+                .collect());
+        };
+        let data: Bytes = self.read_file(manifest_list).await?;
+        manifest::read_manifest_list(&self.uri, &data)
+    }
+
+    /// Data files of `snapshot` whose statistics may match `pruning_predicate`.
+    async fn get_data_files(
+        &self,
+        snapshot: &SaIcebergSnapshot,
+        pruning_predicate: Option<&PruningPredicate>,
+    ) -> Result<Vec<SaIcebergDataFile>> {
+        let mut manifest_files: Vec<SaIcebergManifestFile> = self.read_manifest_files(snapshot).await?;
+        if manifest_files.iter().any(|manifest_file| !manifest_file.is_data()) {
+            return Err(SaError::table_metadata(&self.uri, "row-level delete files are not supported"));
+        }
+        if let Some(pruning_predicate) = pruning_predicate {
+            let statistics: SaManifestStatistics = SaManifestStatistics::new(self, &manifest_files);
+            let keep: Vec<bool> = pruning_predicate.prune(&statistics)?;
+            manifest_files = manifest_files.into_iter().zip(keep).filter(|(_, keep)| *keep).map(|(file, _)| file).collect();
+        }
+
+        let manifests: Vec<Bytes> = future::try_join_all(
+            manifest_files.iter().map(|manifest_file| self.read_file(&manifest_file.manifest_path))
+        ).await?;
+        let mut data_files: Vec<SaIcebergDataFile> = Vec::new();
+        for (manifest_file, data) in manifest_files.iter().zip(manifests) {
+            let mut manifest_data_files: Vec<SaIcebergDataFile> = manifest::read_manifest(&self.uri, &data)?;
+            if let Some(pruning_predicate) = pruning_predicate {
+                let spec: Option<&SaIcebergPartitionSpec> = self.metadata.get_partition_spec(manifest_file.partition_spec_id);
+                let statistics: SaDataFileStatistics = SaDataFileStatistics::new(self, spec, &manifest_data_files);
+                let keep: Vec<bool> = pruning_predicate.prune(&statistics)?;
+                manifest_data_files = manifest_data_files.into_iter().zip(keep).filter(|(_, keep)| *keep).map(|(file, _)| file).collect();
+            }
+            data_files.extend(manifest_data_files);
+        }
+        Ok(data_files)
+    }
+
+    /// Columns of the identity partitions of `spec`: (partition field index, name, column, type).
+    fn get_identity_partitions(&self, spec: Option<&SaIcebergPartitionSpec>) -> Vec<(usize, String, String, DataType)> {
+        let fields_by_id = self.iceberg_schema.get_fields_by_id();
+        spec.map(|spec| spec.fields.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.transform == "identity")
+            .filter_map(|(index, field)| {
+                let source: &_ = fields_by_id.get(&field.source_id)?;
+                let data_type: DataType = source.field_type.to_arrow_type().ok()?;
+                Some((index, field.name.clone(), source.name.clone(), data_type))
+            })
+            .collect()
+    }
+}
+
+
+#[async_trait]
+impl TableProvider for SaIcebergTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    fn supports_filters_pushdown(&self, filters: &[&Expr]) -> datafusion::error::Result<Vec<TableProviderFilterPushDown>> {
+        Ok(vec![TableProviderFilterPushDown::Inexact; filters.len()])
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> datafusion::error::Result<Arc<dyn ExecutionPlan>> {
+        let Some(snapshot) = &self.snapshot else {
+            let projected_schema: SchemaRef = match projection {
+                Some(projection) => Arc::new(self.schema.project(projection)?),
+                None => self.schema.clone(),
+            };
+            return Ok(Arc::new(EmptyExec::new(projected_schema)));
+        };
+
+        let predicate: Option<Arc<dyn PhysicalExpr>> = match conjunction(filters.to_vec()) {
+            Some(filter) => {
+                let df_schema: DFSchema = DFSchema::try_from(self.schema.as_ref().clone())?;
+                Some(state.create_physical_expr(filter, &df_schema)?)
+            },
+            None => None,
+        };
+        let pruning_predicate: Option<PruningPredicate> = predicate
+            .as_ref()
+            .and_then(|predicate| PruningPredicate::try_new(predicate.clone(), self.schema.clone()).ok());
+
+        let data_files: Vec<SaIcebergDataFile> = self.get_data_files(snapshot, pruning_predicate.as_ref()).await?;
+        let mut partitioned_files: Vec<PartitionedFile> = Vec::with_capacity(data_files.len());
+        for data_file in data_files {
+            if !data_file.file_format.eq_ignore_ascii_case("parquet") {
+                let reason: String = format!("unsupported data file format '{}'", data_file.file_format);
+                return Err(SaError::table_metadata(&self.uri, reason).into());
+            }
+            let path: Path = self.resolve_path(&data_file.file_path)?;
+            partitioned_files.push(PartitionedFile::new(path.to_string(), data_file.file_size_in_bytes as u64));
+        }
+
+        let file_scan_config: FileScanConfig = FileScanConfig::new(self.object_store_url.clone(), self.schema.clone())
+            .with_file_group(partitioned_files)
+            .with_projection(projection.cloned())
+            .with_limit(limit);
+        let mut parquet_exec = ParquetExec::builder(file_scan_config)
+            .with_schema_adapter_factory(self.schema_adapter_factory.clone());
+        // Row groups are pruned by column name, which renamed columns do not match
+        if let Some(predicate) = predicate.filter(|_| !self.schema_adapter_factory.has_renamed_columns()) {
+            parquet_exec = parquet_exec.with_predicate(predicate);
+        }
+        Ok(parquet_exec.build_arc())
+    }
+}
+
+
+/// `location` with `file:/path` written as `file:///path` and without a trailing `/`.
+fn normalize_location(location: &str) -> String {
+    let location: String = match location.strip_prefix("file:") {
+        Some(path) if !path.starts_with("//") => format!("file://{}", path),
+        _ => location.to_string(),
+    };
+    location.trim_end_matches('/').to_string()
+}
+
+
+/// Path of the table of the metadata file at `metadata_location`, e.g. `db/events` for
+/// `db/events/metadata/v3.metadata.json`.
+fn get_table_path(metadata_location: &Path) -> Path {
+    let parts: Vec<_> = metadata_location.parts().collect();
+    let table_parts: usize = parts.len().saturating_sub(2);
+    Path::from_iter(parts.into_iter().take(table_parts))
+}
+
+
+/// Statistics of the manifests of a snapshot: the bounds of their identity partitions.
+struct SaManifestStatistics {
+    /// Bounds by column name, one value per manifest.
+    bounds: HashMap<String, (Vec<ScalarValue>, Vec<ScalarValue>)>,
+    num_manifests: usize,
+}
+
+
+impl SaManifestStatistics {
+    fn new(table: &SaIcebergTable, manifest_files: &[SaIcebergManifestFile]) -> Self {
+        let mut bounds: HashMap<String, (Vec<ScalarValue>, Vec<ScalarValue>)> = HashMap::new();
+        for (manifest_index, manifest_file) in manifest_files.iter().enumerate() {
+            let spec: Option<&SaIcebergPartitionSpec> = table.metadata.get_partition_spec(manifest_file.partition_spec_id);
+            for (field_index, _, column, data_type) in table.get_identity_partitions(spec) {
+                let Ok(null) = ScalarValue::try_from(&data_type) else {
+                    continue;
+                };
+                let (mins, maxes) = bounds
+                    .entry(column)
+                    .or_insert_with(|| (vec![null.clone(); manifest_files.len()], vec![null.clone(); manifest_files.len()]));
+                let summary = manifest_file.partitions.get(field_index);
+                let decode = |bound: Option<&Vec<u8>>| bound.and_then(|bound| manifest::decode_bound(bound, &data_type));
+                if let Some(min) = decode(summary.and_then(|summary| summary.lower_bound.as_ref())) {
+                    mins[manifest_index] = min;
+                }
+                if let Some(max) = decode(summary.and_then(|summary| summary.upper_bound.as_ref())) {
+                    maxes[manifest_index] = max;
+                }
+            }
+        }
+        Self {
+            bounds,
+            num_manifests: manifest_files.len(),
+        }
+    }
+}
+
+
+impl PruningStatistics for SaManifestStatistics {
+    fn min_values(&self, column: &Column) -> Option<ArrayRef> {
+        let (mins, _) = self.bounds.get(&column.name)?;
+        ScalarValue::iter_to_array(mins.clone()).ok()
+    }
+
+    fn max_values(&self, column: &Column) -> Option<ArrayRef> {
+        let (_, maxes) = self.bounds.get(&column.name)?;
+        ScalarValue::iter_to_array(maxes.clone()).ok()
+    }
+
+    fn num_containers(&self) -> usize {
+        self.num_manifests
+    }
+
+    fn null_counts(&self, _column: &Column) -> Option<ArrayRef> {
+        None
+    }
+
+    fn row_counts(&self, _column: &Column) -> Option<ArrayRef> {
+        None
+    }
+
+    fn contained(&self, _column: &Column, _values: &HashSet<ScalarValue>) -> Option<BooleanArray> {
+        None
+    }
+}
+
+
+/// Statistics of the data files of a manifest: their identity partition values and column bounds.
+struct SaDataFileStatistics<'a> {
+    data_files: &'a [SaIcebergDataFile],
+    /// Field id and type of each top-level column.
+    columns: HashMap<String, (i32, DataType)>,
+    /// Partition field name of each identity partitioned column.
+    identity_partitions: HashMap<String, String>,
+}
+
+
+impl<'a> SaDataFileStatistics<'a> {
+    fn new(table: &SaIcebergTable, spec: Option<&SaIcebergPartitionSpec>, data_files: &'a [SaIcebergDataFile]) -> Self {
+        let columns: HashMap<String, (i32, DataType)> = table.iceberg_schema.fields
+            .iter()
+            .filter_map(|field| Some((field.name.clone(), (field.id, field.field_type.to_arrow_type().ok()?))))
+            .collect();
+        let identity_partitions: HashMap<String, String> = table
+            .get_identity_partitions(spec)
+            .into_iter()
+            .map(|(_, partition_name, column, _)| (column, partition_name))
+            .collect();
+        Self {
+            data_files,
+            columns,
+            identity_partitions,
+        }
+    }
+
+    fn get_bounds(&self, column: &Column, get_bound: fn(&SaIcebergDataFile) -> &HashMap<i32, Vec<u8>>) -> Option<ArrayRef> {
+        let (field_id, data_type) = self.columns.get(&column.name)?;
+        let null: ScalarValue = ScalarValue::try_from(data_type).ok()?;
+        let partition_name: Option<&String> = self.identity_partitions.get(&column.name);
+        let values = self.data_files.iter().map(|data_file| {
+            // An identity partition value is both bounds of its column
+            partition_name
+                .and_then(|partition_name| data_file.partition.get(partition_name))
+                .and_then(|value| manifest::to_scalar(value, data_type))
+                .or_else(|| get_bound(data_file).get(field_id).and_then(|bound| manifest::decode_bound(bound, data_type)))
+                .unwrap_or_else(|| null.clone())
+        });
+        ScalarValue::iter_to_array(values).ok()
+    }
+}
+
+
+impl PruningStatistics for SaDataFileStatistics<'_> {
+    fn min_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.get_bounds(column, |data_file| &data_file.lower_bounds)
+    }
+
+    fn max_values(&self, column: &Column) -> Option<ArrayRef> {
+        self.get_bounds(column, |data_file| &data_file.upper_bounds)
+    }
+
+    fn num_containers(&self) -> usize {
+        self.data_files.len()
+    }
+
+    fn null_counts(&self, column: &Column) -> Option<ArrayRef> {
+        let (field_id, _) = self.columns.get(&column.name)?;
+        let null_counts: UInt64Array = self.data_files
+            .iter()
+            .map(|data_file| data_file.null_value_counts.get(field_id).map(|count| *count as u64))
+            .collect();
+        Some(Arc::new(null_counts))
+    }
+
+    fn row_counts(&self, _column: &Column) -> Option<ArrayRef> {
+        let row_counts: UInt64Array = self.data_files
+            .iter()
+            .map(|data_file| Some(data_file.record_count as u64))
+            .collect();
+        Some(Arc::new(row_counts))
+    }
+
+    fn contained(&self, _column: &Column, _values: &HashSet<ScalarValue>) -> Option<BooleanArray> {
+        None
+    }
+}
+
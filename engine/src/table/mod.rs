@@ -0,0 +1,6 @@
+pub mod delta;
+pub use delta::SaDeltaTable;
+pub mod iceberg;
+pub use iceberg::SaIcebergTable;
+pub mod storage;
+pub use storage::SaTableStorage;
\ No newline at end of file
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+use datafusion::datasource::TableProvider;
+use datafusion::datasource::listing::ListingTableUrl;
+use object_store::ObjectStore;
+use object_store::path::Path;
+use crate::datafusion::SaDataFusion;
+use crate::error::{Result, SaError};
+use crate::helper::{self, SaTableVersion};
+use crate::object_storage::storage::SaStorage;
+use crate::table::delta::{self, SaDeltaLog, SaDeltaTable};
+use crate::table::iceberg::{self, SaIcebergTable};
+
+
+/// Format name forcing a source to be read as an Iceberg table.
+pub const ICEBERG_FORMAT: &str = "iceberg";
+
+/// Format name forcing a source to be read as a Delta table.
+pub const DELTA_FORMAT: &str = "delta";
+
+/// Suffix of the URI of a Delta table reading its history instead of its data.
+pub const HISTORY_SUFFIX: &str = "#history";
+
+
+/// Storage of a table format (Iceberg, Delta), whose files are listed by the table metadata
+/// instead of the object store.
+#[derive(Debug, Clone)]
+pub struct SaTableStorage {
+    file_url: String,
+    protocal: String,
+    object_store: Arc<dyn ObjectStore>,
+    table_provider: Arc<dyn TableProvider>,
+}
+
+
+impl SaTableStorage {
+    /// `uri` without its [`SaTableVersion`] or [`HISTORY_SUFFIX`] suffix.
+    pub fn get_base_uri(uri: &str) -> Result<&str> {
+        let (base_uri, _) = SaTableVersion::split_uri(uri)?;
+        Ok(base_uri.strip_suffix(HISTORY_SUFFIX).unwrap_or(base_uri))
+    }
+
+    /// Opens the table at `uri`, at the version of its [`SaTableVersion`] suffix if any, or its
+    /// history with the [`HISTORY_SUFFIX`].
+    ///
+    /// Directories holding a `metadata/*.metadata.json` file and `*.metadata.json` files are
+    /// Iceberg tables, directories holding a `_delta_log` directory Delta tables; `format_override`
+    /// (`iceberg` or `delta`) forces one of them. `None` for other sources. The object store of
+    /// `uri` must be registered on `sa_datafusion`.
+    pub async fn open(sa_datafusion: &SaDataFusion, uri: &str, format_override: Option<&str>) -> Result<Option<Self>> {
+        let (base_uri, version) = SaTableVersion::split_uri(uri)?;
+        let (base_uri, is_history) = match base_uri.strip_suffix(HISTORY_SUFFIX) {
+            Some(base_uri) => (base_uri, true),
+            None => (base_uri, false),
+        };
+        let forced_format: Option<&str> = format_override
+            .filter(|format| format.eq_ignore_ascii_case(ICEBERG_FORMAT) || format.eq_ignore_ascii_case(DELTA_FORMAT));
+        if format_override.is_some() && forced_format.is_none() {
+            return Self::check_plain_source(uri, version.as_ref(), is_history);
+        }
+        let is_forced = |format: &str| forced_format.is_some_and(|forced_format| forced_format.eq_ignore_ascii_case(format));
+
+        let listing_table_url: ListingTableUrl = ListingTableUrl::parse(base_uri)?;
+        let table_path: &Path = listing_table_url.prefix();
+        let file_name: &str = table_path.filename().unwrap_or_default();
+        let may_be_table: bool = forced_format.is_some()
+            || file_name.ends_with(".metadata.json")
+            || !file_name.contains(['.', '*', '?', '[']);
+        if !may_be_table {
+            return Self::check_plain_source(uri, version.as_ref(), is_history);
+        }
+
+        let object_store: Arc<dyn ObjectStore> = sa_datafusion.get_object_store(base_uri)?;
+        // An extensionless file, e.g. a compressed export, is not a table directory
+        if !file_name.ends_with(".metadata.json") && object_store.head(table_path).await.is_ok() {
+            return Self::check_plain_source(uri, version.as_ref(), is_history);
+        }
+        let mut table_provider: Option<Arc<dyn TableProvider>> = None;
+        if !is_forced(DELTA_FORMAT) {
+            table_provider = Self::open_iceberg(uri, &object_store, &listing_table_url, version.as_ref(), is_history).await?;
+        }
+        if table_provider.is_none() && !is_forced(ICEBERG_FORMAT) {
+            table_provider = Self::open_delta(uri, &object_store, &listing_table_url, version.as_ref(), is_history).await?;
+        }
+        let Some(table_provider) = table_provider else {
+            if let Some(forced_format) = forced_format {
+                return Err(SaError::table_metadata(uri, format!("no {} table found", forced_format.to_lowercase())));
+            }
+            return Self::check_plain_source(uri, version.as_ref(), is_history);
+        };
+        Ok(Some(Self {
+            file_url: uri.to_string(),
+            protocal: helper::uri_scheme(base_uri).unwrap_or_default().to_string(),
+            object_store,
+            table_provider,
+        }))
+    }
+
+    async fn open_iceberg(
+        uri: &str,
+        object_store: &Arc<dyn ObjectStore>,
+        listing_table_url: &ListingTableUrl,
+        version: Option<&SaTableVersion>,
+        is_history: bool,
+    ) -> Result<Option<Arc<dyn TableProvider>>> {
+        let table_path: &Path = listing_table_url.prefix();
+        let Some(metadata_location) = iceberg::find_metadata_location(object_store, table_path).await? else {
+            return Ok(None);
+        };
+        if is_history {
+            return Err(SaError::table_metadata(uri, "only Delta tables have a history"));
+        }
+        let iceberg_table: SaIcebergTable = SaIcebergTable::try_new(
+            uri,
+            object_store.clone(),
+            listing_table_url.object_store(),
+            table_path.clone(),
+            &metadata_location,
+            version
+        ).await?;
+        Ok(Some(Arc::new(iceberg_table)))
+    }
+
+    async fn open_delta(
+        uri: &str,
+        object_store: &Arc<dyn ObjectStore>,
+        listing_table_url: &ListingTableUrl,
+        version: Option<&SaTableVersion>,
+        is_history: bool,
+    ) -> Result<Option<Arc<dyn TableProvider>>> {
+        let table_path: &Path = listing_table_url.prefix();
+        let Some(delta_log) = SaDeltaLog::try_new(uri, object_store.clone(), table_path).await? else {
+            return Ok(None);
+        };
+        if is_history {
+            if version.is_some() {
+                return Err(SaError::table_metadata(uri, "the history of a table has no versions"));
+            }
+            return Ok(Some(Arc::new(delta::history::create_history_table(&delta_log).await?)));
+        }
+        let delta_table: SaDeltaTable = SaDeltaTable::try_new(
+            uri,
+            listing_table_url.object_store(),
+            table_path.clone(),
+            &delta_log,
+            version
+        ).await?;
+        Ok(Some(Arc::new(delta_table)))
+    }
+
+    /// Sources that are not tables have neither versions nor history.
+    fn check_plain_source(uri: &str, version: Option<&SaTableVersion>, is_history: bool) -> Result<Option<Self>> {
+        if version.is_some() {
+            return Err(SaError::table_metadata(uri, "only Iceberg and Delta tables can be read at a version"));
+        }
+        if is_history {
+            return Err(SaError::table_metadata(uri, "only Delta tables have a history"));
+        }
+        Ok(None)
+    }
+}
+
+
+impl SaStorage for SaTableStorage {
+    fn get_protocal(&self) -> String {
+        self.protocal.clone()
+    }
+
+    fn get_table_provider(&self) -> Result<Arc<dyn TableProvider>> {
+        Ok(self.table_provider.clone())
+    }
+
+    fn get_file_url(&self) -> String {
+        self.file_url.clone()
+    }
+
+    fn get_object_store(&self) -> Option<Arc<dyn ObjectStore>> {
+        Some(self.object_store.clone())
+    }
+}
@@ -0,0 +1,60 @@
+//! `az://` and `abfss://` sources read and written through Azurite with [`SaAzureCredentials::Emulator`].
+//!
+//! Start the emulator with a `sqlanywhere` container, then run the ignored tests:
+//!
+//! ```sh
+//! docker run -d -p 10000:10000 mcr.microsoft.com/azure-storage/azurite azurite-blob --blobHost 0.0.0.0
+//! az storage container create -n sqlanywhere --connection-string "UseDevelopmentStorage=true"
+//! cargo test --test azurite -- --ignored
+//! ```
+//!
+//! `AZURITE_BLOB_STORAGE_URL` points at another emulator and `SA_TEST_AZURE_CONTAINER` names
+//! another container.
+mod common;
+
+use std::env;
+use std::sync::Arc;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::assert_batches_eq;
+use engine::builder::pipelines;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use engine::object_storage::SaAzure;
+use engine::object_storage::azure::{SaAzureCredentials, SaAzureFactory};
+use object_store::ObjectStore;
+
+
+#[tokio::test]
+#[ignore = "needs Azurite at AZURITE_BLOB_STORAGE_URL"]
+async fn test_azurite() -> Result<()> {
+    let container: String = env::var("SA_TEST_AZURE_CONTAINER").unwrap_or("sqlanywhere".to_string());
+
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    let azure_factory: Arc<SaAzureFactory> = Arc::new(SaAzureFactory::new(SaAzureCredentials::Emulator));
+    sa_datafusion.register_storage_factory(SaAzure::PROTOCAL, azure_factory.clone());
+    for scheme in SaAzure::ADLS_PROTOCALS {
+        sa_datafusion.register_storage_factory(scheme, azure_factory.clone());
+    }
+    let object_store: Arc<dyn ObjectStore> = SaAzure::new(&container, "sa-test")
+        .with_credentials(SaAzureCredentials::Emulator)
+        .build_object_store()?;
+    common::check_read_write(&sa_datafusion, object_store, &format!("az://{}", container)).await?;
+
+    // The emulator serves the well-known account, also named by ADLS Gen2 URIs
+    let stm: String = format!(
+        "SELECT name FROM 'abfss://{}@devstoreaccount1.dfs.core.windows.net/sa-test/scores.csv' WHERE score < 8",
+        container
+    );
+    let batches: Vec<RecordBatch> = pipelines::sa_query(&sa_datafusion, &stm).await?.collect().await?;
+    assert_batches_eq!(
+        [
+            "+------+",
+            "| name |",
+            "+------+",
+            "| alan |",
+            "+------+",
+        ],
+        &batches
+    );
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Catalog tables whose profiles point the same bucket at different S3 endpoints.
+//!
+//! The tables have an explicit schema and format, so registering them only sends a `HEAD` request
+//! for their file, which local stub servers answer with an error right away.
+use std::env;
+use std::fs;
+use std::net::SocketAddr;
+use engine::builder::pipelines;
+use engine::datafusion::SaDataFusion;
+use engine::error::{Result, SaError};
+use tempfile::TempDir;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+
+const CATALOG: &str = r#"
+[profiles.east]
+s3_endpoint = "http://$east"
+s3_allow_http = true
+
+[profiles.west]
+s3_endpoint = "http://$west"
+s3_allow_http = true
+
+[tables."shop.orders"]
+uri = "s3://sqlanywhere/orders.csv"
+format = "csv"
+schema = "id: Int64"
+profile = "east"
+
+[tables."shop.returns"]
+uri = "s3://sqlanywhere/returns.csv"
+format = "csv"
+schema = "id: Int64"
+profile = "east"
+
+[tables."shop.stock"]
+uri = "s3://sqlanywhere/stock.csv"
+format = "csv"
+schema = "id: Int64"
+profile = "west"
+
+[tables."archive.stock"]
+uri = "s3://sqlanywhere-archive/stock.csv"
+format = "csv"
+schema = "id: Int64"
+profile = "west"
+"#;
+
+
+/// Starts a server answering every request with `400 Bad Request`, which is not retried.
+async fn start_stub_server() -> Result<SocketAddr> {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await?;
+    let address: SocketAddr = listener.local_addr()?;
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let stream: &mut TcpStream = &mut stream;
+            let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\ncontent-length: 0\r\nconnection: close\r\n\r\n").await;
+        }
+    });
+    Ok(address)
+}
+
+
+#[tokio::test]
+async fn test_bucket_with_two_profiles() -> Result<()> {
+    // Without keys, the credentials would be requested from the instance metadata endpoint
+    for key in ["AWS_ACCESS_KEY_ID", "AWS_SECRET_ACCESS_KEY"] {
+        env::set_var(key, "sqlanywhere");
+    }
+    let catalog_dir: TempDir = tempfile::tempdir()?;
+    let catalog_path: String = catalog_dir.path().join("catalog.toml").display().to_string();
+    let (east, west): (SocketAddr, SocketAddr) = (start_stub_server().await?, start_stub_server().await?);
+    let catalog: String = CATALOG
+        .replace("$east", &east.to_string())
+        .replace("$west", &west.to_string());
+    fs::write(&catalog_path, catalog)?;
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    sa_datafusion.load_catalog(&catalog_path)?;
+
+    assert!(pipelines::sa_register_catalog_table_pipeline(&sa_datafusion, "shop.orders").await?);
+    assert!(pipelines::sa_register_catalog_table_pipeline(&sa_datafusion, "shop.returns").await?);
+    assert!(pipelines::sa_register_catalog_table_pipeline(&sa_datafusion, "archive.stock").await?);
+
+    // `shop.orders` would read through the store of the other endpoint
+    let error: SaError = pipelines::sa_register_catalog_table_pipeline(&sa_datafusion, "shop.stock")
+        .await
+        .unwrap_err();
+    match &error {
+        SaError::ObjectStoreConflict { url, registered_settings } => {
+            assert_eq!(url, "s3://sqlanywhere/");
+            assert!(registered_settings.contains(&format!("endpoint http://{}", east)), "{}", registered_settings);
+        },
+        error => panic!("unexpected error: {}", error),
+    }
+    assert!(!sa_datafusion.ctx.table_exist("shop.stock")?);
+
+    // The setting is shared with the sessions of the same runtime
+    let error: SaError = pipelines::sa_register_catalog_table_pipeline(&sa_datafusion.new_session(), "shop.stock")
+        .await
+        .unwrap_err();
+    assert!(matches!(error, SaError::ObjectStoreConflict { .. }), "{}", error);
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::assert_batches_eq;
+use engine::builder::pipelines;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use object_store::ObjectStore;
+use object_store::path::Path;
+
+
+const SCORES_CSV: &str = "id,name,score\n1,ada,9.5\n2,grace,8.0\n3,alan,7.5\n";
+
+
+/// Uploads a CSV file under `sa-test/` of the bucket at `bucket_uri` (e.g. `gs://sqlanywhere`),
+/// queries it, copies part of it to Parquet next to it and queries the copy.
+pub async fn check_read_write(sa_datafusion: &SaDataFusion, object_store: Arc<dyn ObjectStore>, bucket_uri: &str) -> Result<()> {
+    object_store.put(&Path::from("sa-test/scores.csv"), SCORES_CSV.to_string().into()).await?;
+    let scores_uri: String = format!("{}/sa-test/scores.csv", bucket_uri);
+    let passed_uri: String = format!("{}/sa-test/passed.parquet", bucket_uri);
+
+    let stm: String = format!("SELECT COUNT(*) AS students, SUM(score) AS score FROM '{}'", scores_uri);
+    let batches: Vec<RecordBatch> = pipelines::sa_query(sa_datafusion, &stm).await?.collect().await?;
+    assert_batches_eq!(
+        [
+            "+----------+-------+",
+            "| students | score |",
+            "+----------+-------+",
+            "| 3        | 25.0  |",
+            "+----------+-------+",
+        ],
+        &batches
+    );
+
+    let stm: String = format!("COPY (SELECT id, name FROM '{}' WHERE score >= 8) TO '{}'", scores_uri, passed_uri);
+    pipelines::sa_query(sa_datafusion, &stm).await?.collect().await?;
+    let stm: String = format!("SELECT name FROM '{}' ORDER BY id", passed_uri);
+    let batches: Vec<RecordBatch> = pipelines::sa_query(sa_datafusion, &stm).await?.collect().await?;
+    assert_batches_eq!(
+        [
+            "+-------+",
+            "| name  |",
+            "+-------+",
+            "| ada   |",
+            "| grace |",
+            "+-------+",
+        ],
+        &batches
+    );
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! Compressed text sources, detected from their extension or first bytes, or set with
+//! `sa.compression`, written to a temporary directory and read back.
+use std::fs;
+use bytes::Bytes;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::assert_batches_eq;
+use datafusion::common::parsers::CompressionTypeVariant;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use engine::builder::pipelines;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use tempfile::TempDir;
+
+
+const SCORES_CSV: &str = "id,name,score\n1,ada,9.5\n2,grace,8.0\n3,alan,7.5\n";
+const EVENTS_JSONL: &str = "{\"kind\": \"login\"}\n{\"kind\": \"query\"}\n{\"kind\": \"query\"}\n";
+
+const COMPRESSIONS: [(CompressionTypeVariant, &str); 4] = [
+    (CompressionTypeVariant::GZIP, "gz"),
+    (CompressionTypeVariant::ZSTD, "zst"),
+    (CompressionTypeVariant::BZIP2, "bz2"),
+    (CompressionTypeVariant::XZ, "xz"),
+];
+
+
+async fn compress(data: &str, compression: CompressionTypeVariant) -> Result<Vec<u8>> {
+    let data = stream::once(async { Ok(Bytes::from(data.to_string())) }).boxed();
+    let chunks: Vec<Bytes> = FileCompressionType::from(compression).convert_to_compress_stream(data)?.try_collect().await?;
+    Ok(chunks.concat())
+}
+
+
+/// Writes `data` compressed with `compression` to `file_name` of `dir` and returns its URI.
+async fn write_file(dir: &TempDir, file_name: &str, data: &str, compression: CompressionTypeVariant) -> Result<String> {
+    let path: String = dir.path().join(file_name).display().to_string();
+    fs::write(&path, compress(data, compression).await?)?;
+    Ok(format!("file://{}", path))
+}
+
+
+async fn query_scores(sa_datafusion: &SaDataFusion, uri: &str) -> Result<Vec<RecordBatch>> {
+    let stm: String = format!("SELECT COUNT(*) AS students, SUM(score) AS score FROM '{}'", uri);
+    Ok(pipelines::sa_query(sa_datafusion, &stm).await?.collect().await?)
+}
+
+
+fn assert_scores(batches: &[RecordBatch]) {
+    assert_batches_eq!(
+        [
+            "+----------+-------+",
+            "| students | score |",
+            "+----------+-------+",
+            "| 3        | 25.0  |",
+            "+----------+-------+",
+        ],
+        batches
+    );
+}
+
+
+#[tokio::test]
+async fn test_compression_by_extension() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    for (compression, extension) in COMPRESSIONS {
+        let uri: String = write_file(&dir, &format!("scores.csv.{}", extension), SCORES_CSV, compression).await?;
+        assert_scores(&query_scores(&SaDataFusion::new(), &uri).await?);
+
+        let uri: String = write_file(&dir, &format!("events.jsonl.{}", extension), EVENTS_JSONL, compression).await?;
+        let stm: String = format!("SELECT kind, COUNT(*) AS events FROM '{}' GROUP BY kind ORDER BY kind", uri);
+        let batches: Vec<RecordBatch> = pipelines::sa_query(&SaDataFusion::new(), &stm).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+-------+--------+",
+                "| kind  | events |",
+                "+-------+--------+",
+                "| login | 1      |",
+                "| query | 2      |",
+                "+-------+--------+",
+            ],
+            &batches
+        );
+    }
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_compression_by_content() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    for (compression, extension) in COMPRESSIONS {
+        // Neither the format nor the compression is in the name, both are read from the first bytes
+        let uri: String = write_file(&dir, &format!("scores_export_{}", extension), SCORES_CSV, compression).await?;
+        assert_scores(&query_scores(&SaDataFusion::new(), &uri).await?);
+    }
+
+    // A directory of compressed files reads as one table
+    fs::create_dir(dir.path().join("daily"))?;
+    for day in ["01", "02"] {
+        write_file(&dir, &format!("daily/scores_{}.csv.bz2", day), SCORES_CSV, CompressionTypeVariant::BZIP2).await?;
+    }
+    let uri: String = format!("file://{}/daily/", dir.path().display());
+    let stm: String = format!("SELECT COUNT(*) AS scores FROM '{}'", uri);
+    let batches: Vec<RecordBatch> = pipelines::sa_query(&SaDataFusion::new(), &stm).await?.collect().await?;
+    assert_batches_eq!(
+        [
+            "+--------+",
+            "| scores |",
+            "+--------+",
+            "| 6      |",
+            "+--------+",
+        ],
+        &batches
+    );
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_compression_override() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    // zstd data behind a gzip extension is only read when told so
+    let uri: String = write_file(&dir, "scores.csv.gz", SCORES_CSV, CompressionTypeVariant::ZSTD).await?;
+    assert!(query_scores(&SaDataFusion::new(), &uri).await.is_err());
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    pipelines::sa_query(&sa_datafusion, "SET sa.compression = 'zstd'").await?;
+    assert_scores(&query_scores(&sa_datafusion, &uri).await?);
+
+    // With the format set too, nothing is detected
+    let uri: String = write_file(&dir, "export", SCORES_CSV, CompressionTypeVariant::XZ).await?;
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    pipelines::sa_query(&sa_datafusion, "SET sa.format = 'csv'").await?;
+    pipelines::sa_query(&sa_datafusion, "SET sa.compression = 'xz'").await?;
+    assert_scores(&query_scores(&sa_datafusion, &uri).await?);
+
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    pipelines::sa_query(&sa_datafusion, "SET sa.compression = 'lz4'").await?;
+    let error: String = query_scores(&sa_datafusion, &uri).await.unwrap_err().to_string();
+    assert!(error.contains("Unsupported compression 'lz4'"), "{}", error);
+    Ok(())
+}
@@ -0,0 +1,274 @@
+//! Delta tables read from the fixture of `ex_delta_application`: `sales`, partitioned by
+//! `region`, whose log writes eu and us sales, appends eu sales, deletes the us ones (with a
+//! checkpoint of that version) then appends apac sales.
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::assert_batches_eq;
+use datafusion::datasource::physical_plan::ParquetExec;
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion::prelude::DataFrame;
+use engine::builder::pipelines;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+
+const TABLE_PATH: &str = ".data/bin/ex-delta-application/sales";
+
+
+fn get_table_uri() -> String {
+    format!("file://{}/{}", env!("CARGO_MANIFEST_DIR"), TABLE_PATH)
+}
+
+
+async fn query(table_uri: &str, stm: &str) -> Result<Vec<RecordBatch>> {
+    let stm: String = stm.replace("$table", &format!("\"{}\"", table_uri));
+    let df: DataFrame = pipelines::sa_query(&SaDataFusion::new(), &stm).await?;
+    Ok(df.collect().await?)
+}
+
+
+/// Number of data files the Parquet scans of `plan` read.
+fn count_data_files(plan: &Arc<dyn ExecutionPlan>) -> usize {
+    let data_files: usize = plan
+        .as_any()
+        .downcast_ref::<ParquetExec>()
+        .map(|parquet_exec| parquet_exec.base_config().file_groups.iter().map(Vec::len).sum())
+        .unwrap_or_default();
+    data_files + plan.children().into_iter().map(count_data_files).sum::<usize>()
+}
+
+
+/// Copies the fixture table to a temporary directory, with a version 4 committing `actions`.
+fn copy_table_with_commit(actions: &[Value]) -> TempDir {
+    fn copy_dir(source: &Path, target: &Path) {
+        fs::create_dir_all(target).unwrap();
+        for entry in fs::read_dir(source).unwrap() {
+            let entry: fs::DirEntry = entry.unwrap();
+            match entry.file_type().unwrap().is_dir() {
+                true => copy_dir(&entry.path(), &target.join(entry.file_name())),
+                false => {
+                    fs::copy(entry.path(), target.join(entry.file_name())).unwrap();
+                },
+            }
+        }
+    }
+    let table_dir: TempDir = tempfile::tempdir().unwrap();
+    copy_dir(&Path::new(env!("CARGO_MANIFEST_DIR")).join(TABLE_PATH), &table_dir.path().join("sales"));
+
+    let commit_info: Value = json!({"commitInfo": {"operation": "WRITE", "timestamp": 1714640400000_i64}});
+    let lines: Vec<String> = std::iter::once(&commit_info).chain(actions).map(Value::to_string).collect();
+    fs::write(table_dir.path().join("sales/_delta_log/00000000000000000004.json"), lines.join("\n")).unwrap();
+    table_dir
+}
+
+
+/// `metaData` action of the fixture table with `configuration`.
+fn get_metadata(configuration: Value) -> Value {
+    let schema_string: String = json!({"type": "struct", "fields": [
+        {"name": "id", "type": "long", "nullable": false, "metadata": {}},
+        {"name": "region", "type": "string", "nullable": true, "metadata": {}},
+        {"name": "amount", "type": "double", "nullable": true, "metadata": {}},
+        {"name": "sold_at", "type": "timestamp", "nullable": true, "metadata": {}},
+    ]}).to_string();
+    json!({"metaData": {
+        "id": "3f9c2b1a-6d4e-4c8b-a7f2-1e5d9c3b7a60",
+        "format": {"provider": "parquet", "options": {}},
+        "schemaString": schema_string,
+        "partitionColumns": ["region"],
+        "configuration": configuration,
+    }})
+}
+
+
+#[tokio::test]
+async fn test_latest_version() -> Result<()> {
+    // Version 3 replays the checkpoint of version 2 then its own commit, the deleted us sales
+    // being gone from the checkpoint
+    let batches: Vec<RecordBatch> = query(&get_table_uri(), "SELECT * FROM $table ORDER BY id").await?;
+    assert_batches_eq!(
+        [
+            "+----+--------+----------------------+--------+",
+            "| id | amount | sold_at              | region |",
+            "+----+--------+----------------------+--------+",
+            "| 1  | 120.0  | 2023-12-28T10:15:00Z | eu     |",
+            "| 2  | 75.5   | 2023-12-29T16:40:00Z | eu     |",
+            "| 3  | 310.25 | 2023-12-30T11:05:00Z | eu     |",
+            "| 6  | 18.5   | 2024-01-20T12:00:00Z | eu     |",
+            "| 7  | 560.0  | 2024-01-25T14:45:00Z | eu     |",
+            "| 8  | 64.0   | 2024-03-10T03:20:00Z | apac   |",
+            "| 9  | 230.75 | 2024-03-15T06:10:00Z | apac   |",
+            "| 10 | 12.0   | 2024-03-22T01:55:00Z | apac   |",
+            "+----+--------+----------------------+--------+",
+        ],
+        &batches
+    );
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_removed_files() -> Result<()> {
+    // A file removed by a commit after the checkpoint is hidden as well
+    let remove: Value = json!({"remove": {
+        "path": "region=apac/part-00000-1e3d6f5b-2c4a-4b9d-8e0f-5a7b9c1d3e4f.c000.snappy.parquet",
+        "deletionTimestamp": 1714640400000_i64,
+        "dataChange": true,
+    }});
+    let table_dir: TempDir = copy_table_with_commit(&[remove]);
+    let table_uri: String = format!("file://{}/sales", table_dir.path().display());
+
+    let stm: &str = "SELECT region, COUNT(*) AS sales FROM $table GROUP BY region ORDER BY region";
+    let batches: Vec<RecordBatch> = query(&table_uri, stm).await?;
+    assert_batches_eq!(
+        [
+            "+--------+-------+",
+            "| region | sales |",
+            "+--------+-------+",
+            "| eu     | 5     |",
+            "+--------+-------+",
+        ],
+        &batches
+    );
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_version_as_of() -> Result<()> {
+    // Version 1 is before the checkpoint, it replays the commits from the first one
+    let stm: &str = "SELECT region, COUNT(*) AS sales, SUM(amount) AS amount FROM $table FOR VERSION AS OF 1
+        GROUP BY region ORDER BY region";
+    let batches: Vec<RecordBatch> = query(&get_table_uri(), stm).await?;
+    assert_batches_eq!(
+        [
+            "+--------+-------+---------+",
+            "| region | sales | amount  |",
+            "+--------+-------+---------+",
+            "| eu     | 5     | 1084.25 |",
+            "| us     | 2     | 141.99  |",
+            "+--------+-------+---------+",
+        ],
+        &batches
+    );
+    // Version 2 is the checkpoint alone
+    let stm: &str = "SELECT COUNT(*) AS sales FROM $table FOR VERSION AS OF 2";
+    let batches: Vec<RecordBatch> = query(&get_table_uri(), stm).await?;
+    assert_batches_eq!(
+        [
+            "+-------+",
+            "| sales |",
+            "+-------+",
+            "| 5     |",
+            "+-------+",
+        ],
+        &batches
+    );
+    assert!(query(&get_table_uri(), "SELECT * FROM $table FOR VERSION AS OF 42").await.is_err());
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_timestamp_as_of() -> Result<()> {
+    // Version 2 was committed on 2024-03-01, version 3 on 2024-04-01
+    let stm: &str = "SELECT COUNT(*) AS sales FROM $table FOR TIMESTAMP AS OF '2024-03-15 00:00:00'";
+    let batches: Vec<RecordBatch> = query(&get_table_uri(), stm).await?;
+    assert_batches_eq!(
+        [
+            "+-------+",
+            "| sales |",
+            "+-------+",
+            "| 5     |",
+            "+-------+",
+        ],
+        &batches
+    );
+    // The first version was committed on 2024-01-01
+    assert!(query(&get_table_uri(), "SELECT * FROM $table FOR TIMESTAMP AS OF '2023-12-31 00:00:00'").await.is_err());
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_history() -> Result<()> {
+    let table_uri: String = format!("{}#history", get_table_uri());
+    let batches: Vec<RecordBatch> = query(&table_uri, "SELECT version, timestamp, operation FROM $table").await?;
+    assert_batches_eq!(
+        [
+            "+---------+----------------------+-----------+",
+            "| version | timestamp            | operation |",
+            "+---------+----------------------+-----------+",
+            "| 3       | 2024-04-01T09:00:00Z | WRITE     |",
+            "| 2       | 2024-03-01T09:00:00Z | DELETE    |",
+            "| 1       | 2024-02-01T09:00:00Z | WRITE     |",
+            "| 0       | 2024-01-01T09:00:00Z | WRITE     |",
+            "+---------+----------------------+-----------+",
+        ],
+        &batches
+    );
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_pruned_data_files() -> Result<()> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    let table_uri: String = get_table_uri();
+    for (filter, data_files) in [
+        ("true", 3),
+        ("region = 'eu'", 2),
+        // The statistics of the files tell which hold the amounts
+        ("region = 'eu' AND amount > 500", 1),
+        ("amount > 1000", 0),
+        ("region = 'us'", 0),
+    ] {
+        let stm: String = format!("SELECT id FROM \"{}\" WHERE {}", table_uri, filter);
+        let plan: Arc<dyn ExecutionPlan> = pipelines::sa_query(&sa_datafusion, &stm).await?.create_physical_plan().await?;
+        assert_eq!(count_data_files(&plan), data_files, "data files read for {}", filter);
+    }
+    // The us data file of version 1 is skipped by its partition
+    let stm: String = format!("SELECT id FROM \"{}\" FOR VERSION AS OF 1 WHERE region = 'us'", table_uri);
+    let plan: Arc<dyn ExecutionPlan> = pipelines::sa_query(&sa_datafusion, &stm).await?.create_physical_plan().await?;
+    assert_eq!(count_data_files(&plan), 1);
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_unsupported_tables() -> Result<()> {
+    let deletion_vector: Value = json!({"add": {
+        "path": "region=apac/part-00000-1e3d6f5b-2c4a-4b9d-8e0f-5a7b9c1d3e4f.c000.snappy.parquet",
+        "partitionValues": {"region": "apac"},
+        "size": 1224,
+        "modificationTime": 1714640400000_i64,
+        "dataChange": true,
+        "deletionVector": {"storageType": "u", "pathOrInlineDv": "ab^-aqEH.-t@S}K{vb[*k^", "offset": 4, "sizeInBytes": 40, "cardinality": 1},
+    }});
+    let cases: [(Vec<Value>, &str); 4] = [
+        (vec![deletion_vector], "deletion vectors are not supported"),
+        (vec![get_metadata(json!({"delta.columnMapping.mode": "name"}))], "column mapping mode 'name' is not supported"),
+        (vec![json!({"protocol": {"minReaderVersion": 4, "minWriterVersion": 7}})], "reader version 4 is not supported"),
+        (
+            vec![json!({"protocol": {"minReaderVersion": 3, "minWriterVersion": 7, "readerFeatures": ["v2Checkpoint"]}})],
+            "reader feature 'v2Checkpoint' is not supported",
+        ),
+    ];
+    for (actions, reason) in cases {
+        let table_dir: TempDir = copy_table_with_commit(&actions);
+        let table_uri: String = format!("file://{}/sales", table_dir.path().display());
+
+        let error: String = query(&table_uri, "SELECT * FROM $table").await.unwrap_err().to_string();
+        assert!(error.contains(reason), "{}", error);
+        // Versions before the unsupported commit are still read
+        assert_eq!(query(&table_uri, "SELECT * FROM $table FOR VERSION AS OF 3").await?.iter().map(RecordBatch::num_rows).sum::<usize>(), 8);
+    }
+    // Column mapping disabled explicitly is read as usual
+    let table_dir: TempDir = copy_table_with_commit(&[get_metadata(json!({"delta.columnMapping.mode": "none"}))]);
+    let table_uri: String = format!("file://{}/sales", table_dir.path().display());
+    assert_eq!(query(&table_uri, "SELECT * FROM $table").await?.iter().map(RecordBatch::num_rows).sum::<usize>(), 8);
+    Ok(())
+}
@@ -0,0 +1,238 @@
+//! A Flight SQL client querying `SaFlightSqlService` on a free local port.
+use std::fs;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use arrow_flight::sql::client::{FlightSqlServiceClient, PreparedStatement};
+use arrow_flight::sql::{
+    ActionClosePreparedStatementRequest, ActionCreatePreparedStatementRequest, ActionCreatePreparedStatementResult, Any,
+    CommandGetTables, CommandPreparedStatementQuery, ProstMessageExt,
+};
+use arrow_flight::{Action, FlightDescriptor, FlightInfo};
+use bytes::Bytes;
+use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch};
+use datafusion::assert_batches_eq;
+use engine::datafusion::SaDataFusion;
+use engine::server::SaFlightSqlService;
+use futures::TryStreamExt;
+use prost::Message;
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+use tonic::transport::{Channel, Endpoint};
+
+
+type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+/// Starts the service with a catalog of the local example tables and a missing one.
+async fn start_server(catalog_dir: &TempDir) -> TestResult<SocketAddr> {
+    let data_dir: String = format!("{}/.data/bin/ex-local-storage-application", env!("CARGO_MANIFEST_DIR"));
+    let catalog_path: String = catalog_dir.path().join("catalog.toml").display().to_string();
+    fs::write(&catalog_path, format!(
+        r#"
+        [tables."school.scores"]
+        uri = "{0}/scores.csv"
+
+        [tables."school.students"]
+        uri = "{0}/students.csv"
+
+        [tables."archive.students"]
+        uri = "{1}/missing.csv"
+        "#,
+        data_dir,
+        catalog_dir.path().display()
+    ))?;
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    sa_datafusion.load_catalog(&catalog_path)?;
+
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await?;
+    let address: SocketAddr = listener.local_addr()?;
+    tokio::spawn(SaFlightSqlService::new(sa_datafusion).serve(listener));
+    Ok(address)
+}
+
+
+/// A client on a connection of its own.
+async fn connect(address: SocketAddr) -> TestResult<FlightSqlServiceClient<Channel>> {
+    let channel: Channel = Endpoint::from_shared(format!("http://{}", address))?.connect().await?;
+    Ok(FlightSqlServiceClient::new(channel))
+}
+
+
+/// Handle of a statement prepared by `client`, which [`PreparedStatement`] keeps to itself.
+async fn create_prepared_statement(client: &mut FlightSqlServiceClient<Channel>, query: &str) -> TestResult<Bytes> {
+    let create: Action = Action::new(
+        "CreatePreparedStatement",
+        ActionCreatePreparedStatementRequest { query: query.to_string(), transaction_id: None }.as_any().encode_to_vec(),
+    );
+    let results: Vec<arrow_flight::Result> = client.do_action(create).await?.try_collect().await?;
+    let result: ActionCreatePreparedStatementResult = Any::decode(&*results[0].body)?
+        .unpack()?
+        .expect("prepared statement result");
+    Ok(result.prepared_statement_handle)
+}
+
+
+async fn fetch(client: &mut FlightSqlServiceClient<Channel>, flight_info: FlightInfo) -> TestResult<Vec<RecordBatch>> {
+    let mut batches: Vec<RecordBatch> = Vec::new();
+    for endpoint in flight_info.endpoint {
+        let ticket = endpoint.ticket.expect("endpoint with a ticket");
+        batches.extend(client.do_get(ticket).await?.try_collect::<Vec<RecordBatch>>().await?);
+    }
+    Ok(batches)
+}
+
+
+#[tokio::test]
+async fn test_statement() -> TestResult {
+    let catalog_dir: TempDir = tempfile::tempdir()?;
+    let mut client: FlightSqlServiceClient<Channel> = connect(start_server(&catalog_dir).await?).await?;
+
+    let flight_info: FlightInfo = client
+        .execute("SELECT subject, MAX(score) AS score FROM school.scores GROUP BY subject ORDER BY subject".to_string(), None)
+        .await?;
+    let ticket = flight_info.endpoint[0].ticket.clone().expect("endpoint with a ticket");
+    let batches: Vec<RecordBatch> = fetch(&mut client, flight_info).await?;
+    assert_batches_eq!(
+        [
+            "+---------+-------+",
+            "| subject | score |",
+            "+---------+-------+",
+            "| chem    | 98    |",
+            "| math    | 100   |",
+            "| physic  | 100   |",
+            "+---------+-------+",
+        ],
+        &batches
+    );
+    // A result is served once
+    assert!(client.do_get(ticket).await.is_err());
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_prepared_statement() -> TestResult {
+    let catalog_dir: TempDir = tempfile::tempdir()?;
+    let mut client: FlightSqlServiceClient<Channel> = connect(start_server(&catalog_dir).await?).await?;
+
+    let mut prepared_statement: PreparedStatement<Channel> = client
+        .prepare(
+            "SELECT st.name, s.subject, s.score FROM school.scores AS s JOIN school.students AS st ON st.id = s.student_id \
+             WHERE s.score >= $1 ORDER BY s.id".to_string(),
+            None
+        )
+        .await?;
+    assert_eq!(prepared_statement.parameter_schema()?.field(0).name(), "$1");
+    let parameters: ArrayRef = Arc::new(Int64Array::from(vec![99]));
+    prepared_statement.set_parameters(RecordBatch::try_from_iter([("$1", parameters)])?)?;
+    let flight_info: FlightInfo = prepared_statement.execute().await?;
+    let batches: Vec<RecordBatch> = fetch(&mut client, flight_info).await?;
+    assert_batches_eq!(
+        [
+            "+--------+---------+-------+",
+            "| name   | subject | score |",
+            "+--------+---------+-------+",
+            "| Nhu    | math    | 100   |",
+            "| Nathan | physic  | 100   |",
+            "| Nhu    | physic  | 99    |",
+            "+--------+---------+-------+",
+        ],
+        &batches
+    );
+    prepared_statement.close().await?;
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_get_tables() -> TestResult {
+    let catalog_dir: TempDir = tempfile::tempdir()?;
+    let mut client: FlightSqlServiceClient<Channel> = connect(start_server(&catalog_dir).await?).await?;
+
+    // The missing table of the `archive` schema is neither listed nor read
+    for _ in 0..2 {
+        let flight_info: FlightInfo = client
+            .get_tables(CommandGetTables {
+                db_schema_filter_pattern: Some("sch%".to_string()),
+                table_name_filter_pattern: Some("s_ude%".to_string()),
+                ..Default::default()
+            })
+            .await?;
+        let batches: Vec<RecordBatch> = fetch(&mut client, flight_info).await?;
+        assert_batches_eq!(
+            [
+                "+--------------+----------------+------------+------------+",
+                "| catalog_name | db_schema_name | table_name | table_type |",
+                "+--------------+----------------+------------+------------+",
+                "| datafusion   | school         | students   | TABLE      |",
+                "+--------------+----------------+------------+------------+",
+            ],
+            &batches
+        );
+    }
+
+    // A table that cannot be read is skipped
+    let flight_info: FlightInfo = client.get_tables(CommandGetTables::default()).await?;
+    let batches: Vec<RecordBatch> = fetch(&mut client, flight_info).await?;
+    let row_count: usize = batches.iter().map(RecordBatch::num_rows).sum();
+    assert!(row_count >= 2);
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_sessions() -> TestResult {
+    let catalog_dir: TempDir = tempfile::tempdir()?;
+    let address: SocketAddr = start_server(&catalog_dir).await?;
+    let mut client: FlightSqlServiceClient<Channel> = connect(address).await?;
+    let mut other_client: FlightSqlServiceClient<Channel> = connect(address).await?;
+
+    client.execute_update("SET datafusion.catalog.default_schema = 'school'".to_string(), None).await?;
+    let flight_info: FlightInfo = client.execute("SELECT COUNT(*) AS students FROM students".to_string(), None).await?;
+    let batches: Vec<RecordBatch> = fetch(&mut client, flight_info).await?;
+    assert_batches_eq!(
+        [
+            "+----------+",
+            "| students |",
+            "+----------+",
+            "| 2        |",
+            "+----------+",
+        ],
+        &batches
+    );
+    assert!(other_client.execute("SELECT COUNT(*) FROM students".to_string(), None).await.is_err());
+
+    // Another client can neither fetch a result nor use a prepared statement of the session
+    let flight_info: FlightInfo = client.execute("SELECT COUNT(*) AS students FROM students".to_string(), None).await?;
+    let ticket = flight_info.endpoint[0].ticket.clone().expect("endpoint with a ticket");
+    assert!(other_client.do_get(ticket).await.is_err());
+    assert_eq!(fetch(&mut client, flight_info).await?[0].num_rows(), 1);
+
+    let handle: Bytes = create_prepared_statement(&mut client, "SELECT name FROM students WHERE id = 1").await?;
+    let prepared_query: FlightDescriptor = FlightDescriptor::new_cmd(CommandPreparedStatementQuery {
+        prepared_statement_handle: handle.clone(),
+    }.as_any().encode_to_vec());
+    assert!(other_client.inner_mut().get_flight_info(prepared_query.clone()).await.is_err());
+    let close: Action = Action::new(
+        "ClosePreparedStatement",
+        ActionClosePreparedStatementRequest { prepared_statement_handle: handle }.as_any().encode_to_vec(),
+    );
+    assert!(other_client.do_action(close.clone()).await.is_err());
+    let flight_info: FlightInfo = client.inner_mut().get_flight_info(prepared_query.clone()).await?.into_inner();
+    assert_eq!(fetch(&mut client, flight_info).await?[0].num_rows(), 1);
+    client.do_action(close).await?;
+    assert!(client.inner_mut().get_flight_info(prepared_query).await.is_err());
+
+    // A handshake opens a session of its own, found from its token on any connection
+    other_client.handshake("sqlanywhere", "").await?;
+    other_client.execute_update("SET datafusion.catalog.default_schema = 'school'".to_string(), None).await?;
+    let mut token_client: FlightSqlServiceClient<Channel> = connect(address).await?;
+    assert!(token_client.execute("SELECT COUNT(*) FROM students".to_string(), None).await.is_err());
+    token_client.set_token(other_client.token().cloned().expect("session token"));
+    let flight_info: FlightInfo = token_client.execute("SELECT COUNT(*) AS students FROM students".to_string(), None).await?;
+    assert_eq!(fetch(&mut token_client, flight_info).await?[0].num_rows(), 1);
+
+    token_client.set_token("unknown".to_string());
+    assert!(token_client.execute("SELECT 1".to_string(), None).await.is_err());
+    Ok(())
+}
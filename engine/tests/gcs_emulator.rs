@@ -0,0 +1,252 @@
+//! `gs://` sources read and written with [`SaGcsCredentials::Emulator`], through a local
+//! stand-in of the GCS XML API, and through fake-gcs-server.
+//!
+//! To run the ignored tests against fake-gcs-server, start it with a `sqlanywhere` bucket:
+//!
+//! ```sh
+//! docker run -d -p 4443:4443 fsouza/fake-gcs-server -scheme http
+//! curl -X POST -H 'Content-Type: application/json' -d '{"name": "sqlanywhere"}' http://127.0.0.1:4443/storage/v1/b
+//! STORAGE_EMULATOR_HOST=http://127.0.0.1:4443 cargo test --test gcs_emulator -- --ignored
+//! ```
+//!
+//! `SA_TEST_GCS_BUCKET` names another bucket.
+mod common;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use datafusion::arrow::array::RecordBatch;
+use datafusion::assert_batches_eq;
+use engine::builder::pipelines;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use engine::object_storage::SaGcs;
+use engine::object_storage::gcs::{SaGcsCredentials, SaGcsFactory};
+use object_store::ObjectStore;
+use percent_encoding::percent_decode_str;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+
+const BUCKET: &str = "sqlanywhere";
+
+
+/// Objects of the stand-in: their content and generation, which is also their ETag.
+type Objects = Mutex<BTreeMap<String, (Vec<u8>, u64)>>;
+
+
+/// Starts a stand-in of the GCS XML API serving the `sqlanywhere` bucket, and returns its base URL.
+async fn start_fake_gcs() -> Result<String> {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await?;
+    let address: SocketAddr = listener.local_addr()?;
+    let objects: Arc<Objects> = Arc::new(Mutex::new(BTreeMap::new()));
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let objects: Arc<Objects> = objects.clone();
+            tokio::spawn(async move {
+                let _ = serve(stream, &objects).await;
+            });
+        }
+    });
+    Ok(format!("http://{}", address))
+}
+
+
+/// Answers the requests of a connection, one at a time: object uploads, downloads (of a range
+/// or not), deletions and listings.
+async fn serve(mut stream: TcpStream, objects: &Objects) -> std::io::Result<()> {
+    let mut buffer: Vec<u8> = Vec::new();
+    loop {
+        let header_end: usize = loop {
+            if let Some(index) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                break index + 4;
+            }
+            let mut chunk: [u8; 4096] = [0; 4096];
+            let read: usize = stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        };
+        let request: String = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+        buffer.drain(..header_end);
+
+        let mut lines = request.lines();
+        let mut request_line = lines.next().unwrap_or_default().split(' ');
+        let (method, target): (&str, &str) = (request_line.next().unwrap_or_default(), request_line.next().unwrap_or_default());
+        let headers: HashMap<String, String> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+        let content_length: usize = headers.get("content-length").and_then(|length| length.parse().ok()).unwrap_or(0);
+        while buffer.len() < content_length {
+            let mut chunk: [u8; 4096] = [0; 4096];
+            let read: usize = stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+        let body: Vec<u8> = buffer.drain(..content_length).collect();
+
+        let (path, query): (&str, &str) = target.split_once('?').unwrap_or((target, ""));
+        let path: String = percent_decode_str(path).decode_utf8_lossy().to_string();
+        let (bucket, key): (&str, &str) = path.trim_start_matches('/').split_once('/').unwrap_or((path.trim_start_matches('/'), ""));
+        let query: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes()).into_owned().collect();
+
+        let (status, response_headers, response_body): (&str, Vec<(&str, String)>, Vec<u8>) = {
+            let mut objects = objects.lock().expect("objects lock poisoned");
+            match (method, key) {
+                _ if bucket != BUCKET => ("404 Not Found", vec![], vec![]),
+                ("GET", "") => ("200 OK", vec![], list_objects(&objects, &query).into_bytes()),
+                ("PUT", key) => {
+                    let generation: u64 = objects.values().map(|(_, generation)| generation + 1).max().unwrap_or(1);
+                    objects.insert(key.to_string(), (body, generation));
+                    ("200 OK", vec![("etag", format!("\"{}\"", generation)), ("x-goog-generation", generation.to_string())], vec![])
+                },
+                ("DELETE", key) => match objects.remove(key) {
+                    Some(_) => ("204 No Content", vec![], vec![]),
+                    None => ("404 Not Found", vec![], vec![]),
+                },
+                ("GET" | "HEAD", key) => match objects.get(key) {
+                    None => ("404 Not Found", vec![], vec![]),
+                    Some((data, generation)) => {
+                        let mut response_headers: Vec<(&str, String)> = vec![
+                            ("etag", format!("\"{}\"", generation)),
+                            ("x-goog-generation", generation.to_string()),
+                            ("last-modified", "Wed, 01 May 2024 09:00:00 GMT".to_string()),
+                        ];
+                        match headers.get("range").and_then(|range| range.strip_prefix("bytes=")) {
+                            Some(range) => {
+                                let (start, end): (&str, &str) = range.split_once('-').unwrap_or_default();
+                                let (start, end): (usize, usize) = match (start.parse::<usize>(), end.parse::<usize>()) {
+                                    (Ok(start), Ok(end)) => (start, (end + 1).min(data.len())),
+                                    (Ok(start), Err(_)) => (start, data.len()),
+                                    (Err(_), Ok(suffix)) => (data.len().saturating_sub(suffix), data.len()),
+                                    _ => (0, data.len()),
+                                };
+                                response_headers.push(("content-range", format!("bytes {}-{}/{}", start, end - 1, data.len())));
+                                ("206 Partial Content", response_headers, data[start..end].to_vec())
+                            },
+                            None => ("200 OK", response_headers, data.clone()),
+                        }
+                    },
+                },
+                _ => ("405 Method Not Allowed", vec![], vec![]),
+            }
+        };
+
+        let mut response: String = format!("HTTP/1.1 {}\r\ncontent-length: {}\r\n", status, response_body.len());
+        for (name, value) in response_headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n");
+        stream.write_all(response.as_bytes()).await?;
+        if method != "HEAD" {
+            stream.write_all(&response_body).await?;
+        }
+    }
+}
+
+
+/// `ListBucketResult` of the objects under the `prefix` of `query`, the keys past the next
+/// `delimiter` grouped into common prefixes.
+fn list_objects(objects: &BTreeMap<String, (Vec<u8>, u64)>, query: &HashMap<String, String>) -> String {
+    let prefix: &str = query.get("prefix").map(String::as_str).unwrap_or_default();
+    let delimiter: Option<&str> = query.get("delimiter").map(String::as_str);
+    let mut contents: String = String::new();
+    let mut common_prefixes: BTreeSet<String> = BTreeSet::new();
+    for (key, (data, generation)) in objects.range(prefix.to_string()..).take_while(|(key, _)| key.starts_with(prefix)) {
+        match delimiter.and_then(|delimiter| key[prefix.len()..].find(delimiter)) {
+            Some(index) => {
+                common_prefixes.insert(key[..prefix.len() + index + 1].to_string());
+            },
+            None => contents.push_str(&format!(
+                "<Contents><Key>{}</Key><Size>{}</Size><LastModified>2024-05-01T09:00:00.000Z</LastModified><ETag>\"{}\"</ETag></Contents>",
+                key,
+                data.len(),
+                generation
+            )),
+        }
+    }
+    let common_prefixes: String = common_prefixes
+        .iter()
+        .map(|prefix| format!("<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>", prefix))
+        .collect();
+    format!("<ListBucketResult>{}{}</ListBucketResult>", contents, common_prefixes)
+}
+
+
+fn get_credentials(base_url: &str) -> SaGcsCredentials {
+    SaGcsCredentials::Emulator {
+        base_url: base_url.to_string(),
+    }
+}
+
+
+#[tokio::test]
+async fn test_fake_gcs() -> Result<()> {
+    let credentials: SaGcsCredentials = get_credentials(&start_fake_gcs().await?);
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    sa_datafusion.register_storage_factory(SaGcs::PROTOCAL, Arc::new(SaGcsFactory::new(credentials.clone())));
+    let object_store: Arc<dyn ObjectStore> = SaGcs::new(BUCKET, "sa-test")
+        .with_credentials(credentials)
+        .build_object_store()?;
+    common::check_read_write(&sa_datafusion, object_store, &format!("gs://{}", BUCKET)).await
+}
+
+
+#[tokio::test]
+async fn test_fake_gcs_directory() -> Result<()> {
+    let credentials: SaGcsCredentials = get_credentials(&start_fake_gcs().await?);
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    sa_datafusion.register_storage_factory(SaGcs::PROTOCAL, Arc::new(SaGcsFactory::new(credentials.clone())));
+    let object_store: Arc<dyn ObjectStore> = SaGcs::new(BUCKET, "school")
+        .with_credentials(credentials)
+        .build_object_store()?;
+    for (path, data) in [
+        ("school/scores/2023.csv", "student_id,score\n1,90\n2,75\n"),
+        ("school/scores/2024.csv", "student_id,score\n1,95\n"),
+        ("school/students.csv", "id,name\n1,ada\n2,grace\n"),
+    ] {
+        object_store.put(&object_store::path::Path::from(path), data.to_string().into()).await?;
+    }
+
+    // A directory is listed and its files read as one table
+    let stm: String = format!("SELECT COUNT(*) AS scores, MAX(score) AS score FROM 'gs://{}/school/scores/'", BUCKET);
+    let batches: Vec<RecordBatch> = pipelines::sa_query(&sa_datafusion, &stm).await?.collect().await?;
+    assert_batches_eq!(
+        [
+            "+--------+-------+",
+            "| scores | score |",
+            "+--------+-------+",
+            "| 3      | 95    |",
+            "+--------+-------+",
+        ],
+        &batches
+    );
+
+    let stm: String = format!("SELECT * FROM 'gs://{}/school/missing.csv'", BUCKET);
+    assert!(pipelines::sa_query(&sa_datafusion, &stm).await.is_err());
+    // Another bucket of the emulator is unknown
+    let stm: String = "SELECT * FROM 'gs://other/school/students.csv'".to_string();
+    assert!(pipelines::sa_query(&sa_datafusion, &stm).await.is_err());
+    Ok(())
+}
+
+
+#[tokio::test]
+#[ignore = "needs fake-gcs-server at STORAGE_EMULATOR_HOST"]
+async fn test_gcs_emulator() -> Result<()> {
+    let base_url: String = env::var("STORAGE_EMULATOR_HOST").unwrap_or("http://127.0.0.1:4443".to_string());
+    let bucket: String = env::var("SA_TEST_GCS_BUCKET").unwrap_or("sqlanywhere".to_string());
+    let credentials: SaGcsCredentials = SaGcsCredentials::Emulator { base_url };
+
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    sa_datafusion.register_storage_factory(SaGcs::PROTOCAL, Arc::new(SaGcsFactory::new(credentials.clone())));
+    let object_store: Arc<dyn ObjectStore> = SaGcs::new(&bucket, "sa-test")
+        .with_credentials(credentials)
+        .build_object_store()?;
+    common::check_read_write(&sa_datafusion, object_store, &format!("gs://{}", bucket)).await
+}
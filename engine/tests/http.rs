@@ -0,0 +1,228 @@
+//! HTTP sources served by a local server, with and without range requests.
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::assert_batches_eq;
+use engine::builder::pipelines;
+use engine::datafusion::SaDataFusion;
+use engine::error::{Result, SaError};
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+
+/// How the server answers requests for a range of a file.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RangeMode {
+    /// `206 Partial Content` with the range.
+    Supported,
+    /// `200 OK` with the whole file.
+    Ignored,
+    /// `403 Forbidden`, while requests of whole files succeed.
+    Forbidden,
+}
+
+
+/// Local HTTP server of `files`, rejecting the requests without `authorization` when set.
+struct Server {
+    address: SocketAddr,
+    range_requests: Arc<AtomicUsize>,
+}
+
+
+impl Server {
+    async fn start(files: HashMap<String, Vec<u8>>, range_mode: RangeMode, authorization: Option<&str>) -> Result<Self> {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await?;
+        let address: SocketAddr = listener.local_addr()?;
+        let range_requests: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let files: Arc<HashMap<String, Vec<u8>>> = Arc::new(files);
+        let authorization: Option<String> = authorization.map(str::to_string);
+        let server_range_requests: Arc<AtomicUsize> = range_requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (files, authorization, range_requests) = (files.clone(), authorization.clone(), server_range_requests.clone());
+                tokio::spawn(async move {
+                    let _ = serve(stream, &files, range_mode, authorization.as_deref(), &range_requests).await;
+                });
+            }
+        });
+        Ok(Self {
+            address,
+            range_requests,
+        })
+    }
+
+    fn get_uri(&self, path: &str) -> String {
+        format!("http://{}/{}", self.address, path)
+    }
+}
+
+
+/// Answers the requests of a connection, one at a time.
+async fn serve(
+    mut stream: TcpStream,
+    files: &HashMap<String, Vec<u8>>,
+    range_mode: RangeMode,
+    authorization: Option<&str>,
+    range_requests: &AtomicUsize,
+) -> std::io::Result<()> {
+    let mut buffer: Vec<u8> = Vec::new();
+    loop {
+        let header_end: usize = loop {
+            if let Some(index) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                break index + 4;
+            }
+            let mut chunk: [u8; 4096] = [0; 4096];
+            let read: usize = stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        };
+        let request: String = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+        buffer.drain(..header_end);
+
+        let mut lines = request.lines();
+        let mut request_line = lines.next().unwrap_or_default().split(' ');
+        let (method, path): (&str, &str) = (request_line.next().unwrap_or_default(), request_line.next().unwrap_or_default());
+        let headers: HashMap<String, String> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+
+        let file: Option<&Vec<u8>> = files.get(path.trim_start_matches('/'));
+        let range: Option<&str> = headers.get("range").and_then(|range| range.strip_prefix("bytes="));
+        if range.is_some() {
+            range_requests.fetch_add(1, Ordering::Relaxed);
+        }
+        let (status, body, content_range): (&str, &[u8], Option<String>) = match (file, range) {
+            _ if authorization.is_some_and(|authorization| headers.get("authorization").map(String::as_str) != Some(authorization)) => {
+                ("401 Unauthorized", &[], None)
+            },
+            (None, _) => ("404 Not Found", &[], None),
+            (Some(_), Some(_)) if range_mode == RangeMode::Forbidden => ("403 Forbidden", &[], None),
+            (Some(file), Some(range)) if range_mode == RangeMode::Supported => {
+                let (start, end): (&str, &str) = range.split_once('-').unwrap_or_default();
+                let (start, end): (usize, usize) = match (start.parse::<usize>(), end.parse::<usize>()) {
+                    (Ok(start), Ok(end)) => (start, (end + 1).min(file.len())),
+                    (Ok(start), Err(_)) => (start, file.len()),
+                    (Err(_), Ok(suffix)) => (file.len().saturating_sub(suffix), file.len()),
+                    _ => (0, file.len()),
+                };
+                ("206 Partial Content", &file[start..end], Some(format!("bytes {}-{}/{}", start, end - 1, file.len())))
+            },
+            (Some(file), _) => ("200 OK", file.as_slice(), None),
+        };
+
+        let mut response: String = format!("HTTP/1.1 {}\r\ncontent-length: {}\r\n", status, body.len());
+        if let Some(content_range) = content_range {
+            response.push_str(&format!("content-range: {}\r\n", content_range));
+        }
+        response.push_str("\r\n");
+        stream.write_all(response.as_bytes()).await?;
+        if method != "HEAD" {
+            stream.write_all(body).await?;
+        }
+    }
+}
+
+
+/// The example scores as CSV and as Parquet.
+async fn get_files(dir: &TempDir) -> Result<HashMap<String, Vec<u8>>> {
+    let csv_path: String = format!("{}/.data/bin/ex-local-storage-application/scores.csv", env!("CARGO_MANIFEST_DIR"));
+    let parquet_path: String = dir.path().join("scores.parquet").display().to_string();
+    let stm: String = format!("COPY (SELECT * FROM 'file://{}') TO 'file://{}'", csv_path, parquet_path);
+    pipelines::sa_query(&SaDataFusion::new(), &stm).await?.collect().await?;
+    Ok(HashMap::from([
+        ("data/scores.csv".to_string(), std::fs::read(&csv_path)?),
+        ("data/scores.parquet".to_string(), std::fs::read(&parquet_path)?),
+    ]))
+}
+
+
+async fn query(sa_datafusion: &SaDataFusion, uri: &str) -> Result<Vec<RecordBatch>> {
+    let stm: String = format!("SELECT subject, MAX(score) AS score FROM '{}' GROUP BY subject ORDER BY subject", uri);
+    Ok(pipelines::sa_query(sa_datafusion, &stm).await?.collect().await?)
+}
+
+
+fn assert_scores(batches: &[RecordBatch]) {
+    assert_batches_eq!(
+        [
+            "+---------+-------+",
+            "| subject | score |",
+            "+---------+-------+",
+            "| chem    | 98    |",
+            "| math    | 100   |",
+            "| physic  | 100   |",
+            "+---------+-------+",
+        ],
+        batches
+    );
+}
+
+
+#[tokio::test]
+async fn test_range_requests() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    let server: Server = Server::start(get_files(&dir).await?, RangeMode::Supported, None).await?;
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+
+    for path in ["data/scores.csv", "data/scores.parquet"] {
+        assert_scores(&query(&sa_datafusion, &server.get_uri(path)).await?);
+    }
+    assert!(server.range_requests.load(Ordering::Relaxed) > 0);
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_without_range_requests() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    let server: Server = Server::start(get_files(&dir).await?, RangeMode::Ignored, None).await?;
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+
+    assert_scores(&query(&sa_datafusion, &server.get_uri("data/scores.parquet")).await?);
+    // Once the server is known to ignore ranges, the following reads do not ask for them
+    assert_eq!(server.range_requests.load(Ordering::Relaxed), 1);
+    assert_scores(&query(&sa_datafusion, &server.get_uri("data/scores.parquet")).await?);
+    assert_scores(&query(&sa_datafusion, &server.get_uri("data/scores.csv")).await?);
+    assert_eq!(server.range_requests.load(Ordering::Relaxed), 1);
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_range_request_error() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    let server: Server = Server::start(get_files(&dir).await?, RangeMode::Forbidden, None).await?;
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+
+    // A failed range request is an error, not a reason to download the whole file
+    let result: Result<Vec<RecordBatch>> = query(&sa_datafusion, &server.get_uri("data/scores.parquet")).await;
+    assert!(result.is_err());
+    assert!(server.range_requests.load(Ordering::Relaxed) > 0);
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_http_headers() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    let server: Server = Server::start(get_files(&dir).await?, RangeMode::Supported, Some("Bearer sqlanywhere")).await?;
+
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    assert!(query(&sa_datafusion, &server.get_uri("data/scores.csv")).await.is_err());
+
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    pipelines::sa_query(&sa_datafusion, "SET sa.http_headers = 'Authorization: Bearer sqlanywhere; X-Client: test'").await?;
+    assert_scores(&query(&sa_datafusion, &server.get_uri("data/scores.parquet")).await?);
+
+    // Other headers for the same server would be sent through the registered store
+    sa_datafusion.set_option("http_headers", "Authorization: Bearer other")?;
+    let result: Result<Vec<RecordBatch>> = query(&sa_datafusion, &server.get_uri("data/scores.csv")).await;
+    assert!(matches!(result, Err(SaError::ObjectStoreConflict { .. })), "{:?}", result.map(|_| ()));
+    Ok(())
+}
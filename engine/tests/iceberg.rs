@@ -0,0 +1,214 @@
+//! Iceberg tables read from the fixture warehouse of `ex_iceberg_application`: `db/events`,
+//! partitioned by `category`, whose three snapshots append books and games events then delete
+//! the games ones.
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::assert_batches_eq;
+use datafusion::datasource::physical_plan::ParquetExec;
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion::prelude::DataFrame;
+use engine::builder::pipelines;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+
+const TABLE_PATH: &str = ".data/bin/ex-iceberg-application/warehouse/db/events";
+const FIRST_SNAPSHOT_ID: i64 = 3051729675574597004;
+
+
+fn get_table_uri() -> String {
+    format!("file://{}/{}", env!("CARGO_MANIFEST_DIR"), TABLE_PATH)
+}
+
+
+async fn query(table_uri: &str, stm: &str) -> Result<Vec<RecordBatch>> {
+    let stm: String = stm.replace("$table", &format!("\"{}\"", table_uri));
+    let df: DataFrame = pipelines::sa_query(&SaDataFusion::new(), &stm).await?;
+    Ok(df.collect().await?)
+}
+
+
+/// Number of data files the Parquet scans of `plan` read.
+fn count_data_files(plan: &Arc<dyn ExecutionPlan>) -> usize {
+    let data_files: usize = plan
+        .as_any()
+        .downcast_ref::<ParquetExec>()
+        .map(|parquet_exec| parquet_exec.base_config().file_groups.iter().map(Vec::len).sum())
+        .unwrap_or_default();
+    data_files + plan.children().into_iter().map(count_data_files).sum::<usize>()
+}
+
+
+/// Copies the fixture table to a temporary directory, with `fields` as its current schema.
+fn copy_table_with_schema(fields: Value) -> TempDir {
+    fn copy_dir(source: &Path, target: &Path) {
+        fs::create_dir_all(target).unwrap();
+        for entry in fs::read_dir(source).unwrap() {
+            let entry: fs::DirEntry = entry.unwrap();
+            match entry.file_type().unwrap().is_dir() {
+                true => copy_dir(&entry.path(), &target.join(entry.file_name())),
+                false => {
+                    fs::copy(entry.path(), target.join(entry.file_name())).unwrap();
+                },
+            }
+        }
+    }
+    let table_dir: TempDir = tempfile::tempdir().unwrap();
+    copy_dir(&Path::new(env!("CARGO_MANIFEST_DIR")).join(TABLE_PATH), &table_dir.path().join("events"));
+
+    let metadata_path: std::path::PathBuf = table_dir.path().join("events/metadata/v3.metadata.json");
+    let mut metadata: Value = serde_json::from_slice(&fs::read(&metadata_path).unwrap()).unwrap();
+    metadata["schemas"].as_array_mut().unwrap().push(json!({"type": "struct", "schema-id": 1, "fields": fields}));
+    metadata["current-schema-id"] = json!(1);
+    fs::write(&metadata_path, serde_json::to_vec(&metadata).unwrap()).unwrap();
+    table_dir
+}
+
+
+#[tokio::test]
+async fn test_current_snapshot() -> Result<()> {
+    let batches: Vec<RecordBatch> = query(&get_table_uri(), "SELECT * FROM $table ORDER BY id").await?;
+    assert_batches_eq!(
+        [
+            "+----+----------+--------+------------+",
+            "| id | category | amount | event_date |",
+            "+----+----------+--------+------------+",
+            "| 1  | books    | 12.5   | 2024-01-02 |",
+            "| 2  | books    | 30.0   | 2024-01-05 |",
+            "| 3  | books    | 18.25  | 2024-01-09 |",
+            "| 6  | books    | 8.75   | 2024-02-03 |",
+            "| 7  | books    | 42.0   | 2024-02-10 |",
+            "+----+----------+--------+------------+",
+        ],
+        &batches
+    );
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_version_as_of() -> Result<()> {
+    let stm: String = format!("SELECT id, category FROM $table FOR VERSION AS OF {} ORDER BY id", FIRST_SNAPSHOT_ID);
+    let batches: Vec<RecordBatch> = query(&get_table_uri(), &stm).await?;
+    assert_batches_eq!(
+        [
+            "+----+----------+",
+            "| id | category |",
+            "+----+----------+",
+            "| 1  | books    |",
+            "| 2  | books    |",
+            "| 3  | books    |",
+            "| 4  | games    |",
+            "| 5  | games    |",
+            "+----+----------+",
+        ],
+        &batches
+    );
+    assert!(query(&get_table_uri(), "SELECT * FROM $table FOR VERSION AS OF 42").await.is_err());
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_timestamp_as_of() -> Result<()> {
+    let stm: &str = "SELECT category, COUNT(*) AS events FROM $table FOR TIMESTAMP AS OF '2024-02-15 00:00:00'
+        GROUP BY category ORDER BY category";
+    let batches: Vec<RecordBatch> = query(&get_table_uri(), stm).await?;
+    assert_batches_eq!(
+        [
+            "+----------+--------+",
+            "| category | events |",
+            "+----------+--------+",
+            "| books    | 5      |",
+            "| games    | 2      |",
+            "+----------+--------+",
+        ],
+        &batches
+    );
+    // The first snapshot was committed on 2024-01-01
+    assert!(query(&get_table_uri(), "SELECT * FROM $table FOR TIMESTAMP AS OF '2023-12-31 00:00:00'").await.is_err());
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_pruned_data_files() -> Result<()> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    let table_uri: String = get_table_uri();
+    for (filter, data_files) in [
+        ("true", 2),
+        ("category = 'books'", 2),
+        ("category = 'books' AND amount > 40", 1),
+        ("category = 'games'", 0),
+    ] {
+        let stm: String = format!("SELECT id FROM \"{}\" WHERE {}", table_uri, filter);
+        let plan: Arc<dyn ExecutionPlan> = pipelines::sa_query(&sa_datafusion, &stm).await?.create_physical_plan().await?;
+        assert_eq!(count_data_files(&plan), data_files, "data files read for {}", filter);
+    }
+    // The games data file of the first snapshot is skipped by its partition
+    let stm: String = format!("SELECT id FROM \"{}\" FOR VERSION AS OF {} WHERE category = 'games'", table_uri, FIRST_SNAPSHOT_ID);
+    let plan: Arc<dyn ExecutionPlan> = pipelines::sa_query(&sa_datafusion, &stm).await?.create_physical_plan().await?;
+    assert_eq!(count_data_files(&plan), 1);
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_renamed_column() -> Result<()> {
+    let table_dir: TempDir = copy_table_with_schema(json!([
+        {"id": 1, "name": "id", "required": true, "type": "long"},
+        {"id": 2, "name": "category", "required": false, "type": "string"},
+        {"id": 3, "name": "total", "required": false, "type": "double"},
+        {"id": 4, "name": "event_date", "required": false, "type": "date"},
+    ]));
+    let table_uri: String = format!("file://{}/events", table_dir.path().display());
+
+    let batches: Vec<RecordBatch> = query(&table_uri, "SELECT id, total FROM $table WHERE total > 20 ORDER BY id").await?;
+    assert_batches_eq!(
+        [
+            "+----+-------+",
+            "| id | total |",
+            "+----+-------+",
+            "| 2  | 30.0  |",
+            "| 7  | 42.0  |",
+            "+----+-------+",
+        ],
+        &batches
+    );
+    // Past snapshots keep the schema they were written with
+    let stm: String = format!("SELECT SUM(amount) AS amount FROM $table FOR VERSION AS OF {}", FIRST_SNAPSHOT_ID);
+    let batches: Vec<RecordBatch> = query(&table_uri, &stm).await?;
+    assert_batches_eq!(
+        [
+            "+--------+",
+            "| amount |",
+            "+--------+",
+            "| 140.74 |",
+            "+--------+",
+        ],
+        &batches
+    );
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_reused_column_name() -> Result<()> {
+    // `amount` now names a new field, data files without field ids cannot tell which one they hold
+    let table_dir: TempDir = copy_table_with_schema(json!([
+        {"id": 1, "name": "id", "required": true, "type": "long"},
+        {"id": 2, "name": "category", "required": false, "type": "string"},
+        {"id": 3, "name": "price", "required": false, "type": "double"},
+        {"id": 4, "name": "event_date", "required": false, "type": "date"},
+        {"id": 5, "name": "amount", "required": false, "type": "double"},
+    ]));
+    let table_uri: String = format!("file://{}/events", table_dir.path().display());
+
+    let error: String = query(&table_uri, "SELECT price, amount FROM $table").await.unwrap_err().to_string();
+    assert!(error.contains("several fields had this name"), "{}", error);
+    Ok(())
+}
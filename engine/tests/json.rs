@@ -0,0 +1,138 @@
+//! JSON lines and JSON array sources, with nested columns and a bounded schema inference.
+use std::fs;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::assert_batches_eq;
+use datafusion::prelude::DataFrame;
+use engine::builder::pipelines;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use tempfile::TempDir;
+
+
+const STUDENTS_JSONL: &str = r#"{"id": 1, "name": "ada", "addr": {"city": "London", "zip": "N1"}, "tags": ["math", "chem"]}
+{"id": 2, "name": "grace", "addr": {"city": "New York", "zip": "10001"}, "tags": ["physic"]}
+"#;
+const STUDENTS_JSON_ARRAY: &str = r#"[
+  {"id": 1, "name": "ada", "addr": {"city": "London", "zip": "N1"}, "tags": ["math", "chem"]},
+  {"id": 2, "name": "grace, \"amazing\" [sic]", "addr": {"city": "New York", "zip": "10001"}, "tags": ["physic"]}
+]
+"#;
+
+
+/// Writes `data` to `file_name` of `dir` and returns its URI.
+fn write_file(dir: &TempDir, file_name: &str, data: &str) -> Result<String> {
+    let path: String = dir.path().join(file_name).display().to_string();
+    fs::write(&path, data)?;
+    Ok(format!("file://{}", path))
+}
+
+
+async fn query(sa_datafusion: &SaDataFusion, stm: &str) -> Result<Vec<RecordBatch>> {
+    Ok(pipelines::sa_query(sa_datafusion, stm).await?.collect().await?)
+}
+
+
+/// Columns of `uri` after the `settings` statements.
+async fn get_columns(uri: &str, settings: &[&str]) -> Result<Vec<String>> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    for setting in settings {
+        pipelines::sa_query(&sa_datafusion, setting).await?;
+    }
+    let df: DataFrame = pipelines::sa_query(&sa_datafusion, &format!("SELECT * FROM '{}'", uri)).await?;
+    Ok(df.schema().fields().iter().map(|field| field.name().clone()).collect())
+}
+
+
+#[tokio::test]
+async fn test_json_lines_extensions() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    for file_name in ["students.json", "students.jsonl", "students.ndjson", "students_export"] {
+        let uri: String = write_file(&dir, file_name, STUDENTS_JSONL)?;
+        let batches: Vec<RecordBatch> = query(&SaDataFusion::new(), &format!("SELECT id, name FROM '{}' ORDER BY id", uri)).await?;
+        assert_batches_eq!(
+            [
+                "+----+-------+",
+                "| id | name  |",
+                "+----+-------+",
+                "| 1  | ada   |",
+                "| 2  | grace |",
+                "+----+-------+",
+            ],
+            &batches
+        );
+    }
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_nested_columns() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    let uri: String = write_file(&dir, "students.jsonl", STUDENTS_JSONL)?;
+    let stm: String = format!(
+        "SELECT id, addr['city'] AS city, tags[1] AS first_tag, array_length(tags) AS tags FROM '{}' ORDER BY id",
+        uri
+    );
+    let batches: Vec<RecordBatch> = query(&SaDataFusion::new(), &stm).await?;
+    assert_batches_eq!(
+        [
+            "+----+----------+-----------+------+",
+            "| id | city     | first_tag | tags |",
+            "+----+----------+-----------+------+",
+            "| 1  | London   | math      | 2    |",
+            "| 2  | New York | physic    | 1    |",
+            "+----+----------+-----------+------+",
+        ],
+        &batches
+    );
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_json_array() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    let uri: String = write_file(&dir, "students.json", STUDENTS_JSON_ARRAY)?;
+    let stm: String = format!("SELECT id, name, addr['zip'] AS zip, tags FROM '{}' ORDER BY id", uri);
+    let expected: [&str; 6] = [
+        "+----+------------------------+-------+--------------+",
+        "| id | name                   | zip   | tags         |",
+        "+----+------------------------+-------+--------------+",
+        "| 1  | ada                    | N1    | [math, chem] |",
+        "| 2  | grace, \"amazing\" [sic] | 10001 | [physic]     |",
+        "+----+------------------------+-------+--------------+",
+    ];
+
+    // A `.json` file is read as JSON lines unless told otherwise
+    assert!(query(&SaDataFusion::new(), &stm).await.is_err());
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    pipelines::sa_query(&sa_datafusion, "SET sa.json_array = true").await?;
+    assert_batches_eq!(expected, &query(&sa_datafusion, &stm).await?);
+
+    // Without an extension, the leading `[` tells the format
+    let uri: String = write_file(&dir, "students_export", STUDENTS_JSON_ARRAY)?;
+    let stm: String = format!("SELECT id, name, addr['zip'] AS zip, tags FROM '{}' ORDER BY id", uri);
+    assert_batches_eq!(expected, &query(&SaDataFusion::new(), &stm).await?);
+
+    let uri: String = write_file(&dir, "broken.json", "[{\"id\": 1}, {\"id\": 2}")?;
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    pipelines::sa_query(&sa_datafusion, "SET sa.json_array = true").await?;
+    let error: String = query(&sa_datafusion, &format!("SELECT * FROM '{}'", uri)).await.unwrap_err().to_string();
+    assert!(error.contains("Unterminated JSON array"), "{}", error);
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_inference_record_limit() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    // `score` only appears in the second record
+    let uri: String = write_file(&dir, "scores.jsonl", "{\"id\": 1}\n{\"id\": 2, \"score\": 9.5}\n")?;
+    assert_eq!(get_columns(&uri, &[]).await?, ["id", "score"]);
+    assert_eq!(get_columns(&uri, &["SET sa.schema_sample_size = 1"]).await?, ["id"]);
+
+    let uri: String = write_file(&dir, "scores.json", "[{\"id\": 1}, {\"id\": 2, \"score\": 9.5}]")?;
+    assert_eq!(get_columns(&uri, &["SET sa.json_array = true"]).await?, ["id", "score"]);
+    assert_eq!(get_columns(&uri, &["SET sa.json_array = true", "SET sa.schema_sample_size = 1"]).await?, ["id"]);
+    Ok(())
+}
@@ -0,0 +1,50 @@
+//! `s3://` sources read and written through MinIO with the `sa.s3_*` session options.
+//!
+//! Start MinIO with a `sqlanywhere` bucket, then run the ignored tests:
+//!
+//! ```sh
+//! docker run -d -p 9000:9000 minio/minio server /data
+//! docker run --rm --network host --entrypoint sh minio/mc -c \
+//!     "mc alias set local http://127.0.0.1:9000 minioadmin minioadmin && mc mb local/sqlanywhere"
+//! cargo test --test minio -- --ignored
+//! ```
+//!
+//! `SA_TEST_MINIO_ENDPOINT` points at another server and `SA_TEST_S3_BUCKET` names another bucket;
+//! the keys are `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`, `minioadmin` by default.
+mod common;
+
+use std::env;
+use std::sync::Arc;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use engine::object_storage::SaS3;
+use engine::object_storage::s3::{SaS3Factory, SaS3Options};
+use object_store::ObjectStore;
+
+
+#[tokio::test]
+#[ignore = "needs MinIO at SA_TEST_MINIO_ENDPOINT"]
+async fn test_minio() -> Result<()> {
+    let endpoint: String = env::var("SA_TEST_MINIO_ENDPOINT").unwrap_or("http://127.0.0.1:9000".to_string());
+    let bucket: String = env::var("SA_TEST_S3_BUCKET").unwrap_or("sqlanywhere".to_string());
+    for key in ["AWS_ACCESS_KEY_ID", "AWS_SECRET_ACCESS_KEY"] {
+        if env::var_os(key).is_none() {
+            env::set_var(key, "minioadmin");
+        }
+    }
+
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    sa_datafusion.set_option("s3_endpoint", &endpoint)?;
+    sa_datafusion.set_option("s3_path_style", "true")?;
+    sa_datafusion.set_option("s3_allow_http", "true")?;
+    let options: SaS3Options = SaS3Options {
+        endpoint: Some(endpoint),
+        is_path_style: Some(true),
+        allow_http: Some(true),
+        profile: None,
+    };
+    let object_store: Arc<dyn ObjectStore> = SaS3::new_from_s3_uri(&format!("s3://{}/", bucket))?
+        .with_options(options)
+        .build_object_store(SaS3Factory::DEFAULT_REGION)?;
+    common::check_read_write(&sa_datafusion, object_store, &format!("s3://{}", bucket)).await
+}
@@ -0,0 +1,214 @@
+//! A Postgres client querying `SaPgWireServer` on a free local port.
+use std::fs;
+use std::net::SocketAddr;
+use engine::datafusion::SaDataFusion;
+use engine::server::SaPgWireServer;
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+use tokio_postgres::types::Type;
+use tokio_postgres::{Client, NoTls, Row, SimpleQueryMessage, SimpleQueryRow, Statement};
+
+
+type TestResult<T = ()> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+
+/// Starts the server with a catalog of the local example tables.
+async fn start_server(catalog_dir: &TempDir) -> TestResult<SocketAddr> {
+    let data_dir: String = format!("{}/.data/bin/ex-local-storage-application", env!("CARGO_MANIFEST_DIR"));
+    let catalog_path: String = catalog_dir.path().join("catalog.toml").display().to_string();
+    fs::write(&catalog_path, format!(
+        r#"
+        [tables."school.scores"]
+        uri = "{0}/scores.csv"
+
+        [tables."school.students"]
+        uri = "{0}/students.csv"
+        "#,
+        data_dir
+    ))?;
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    sa_datafusion.load_catalog(&catalog_path)?;
+
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await?;
+    let address: SocketAddr = listener.local_addr()?;
+    tokio::spawn(SaPgWireServer::try_new(sa_datafusion)?.serve(listener));
+    Ok(address)
+}
+
+
+/// A client on a connection of its own.
+async fn connect(address: SocketAddr) -> TestResult<Client> {
+    let (client, connection) = tokio_postgres::connect(
+        &format!("host={} port={} user=sqlanywhere dbname=datafusion", address.ip(), address.port()),
+        NoTls,
+    ).await?;
+    tokio::spawn(connection);
+    Ok(client)
+}
+
+
+/// Rows of the simple query `stm`, as text.
+async fn simple_query(client: &Client, stm: &str) -> TestResult<Vec<Vec<Option<String>>>> {
+    let rows: Vec<Vec<Option<String>>> = client
+        .simple_query(stm)
+        .await?
+        .into_iter()
+        .filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some(row),
+            _ => None,
+        })
+        .map(|row: SimpleQueryRow| (0..row.len()).map(|index| row.get(index).map(str::to_string)).collect())
+        .collect();
+    Ok(rows)
+}
+
+
+fn text(values: &[&str]) -> Vec<Option<String>> {
+    values.iter().map(|value| Some(value.to_string())).collect()
+}
+
+
+#[tokio::test]
+async fn test_types() -> TestResult {
+    let catalog_dir: TempDir = tempfile::tempdir()?;
+    let client: Client = connect(start_server(&catalog_dir).await?).await?;
+
+    let stm: &str = "SELECT true AS bool_value, CAST(2 AS SMALLINT) AS int2_value, CAST(4 AS INT) AS int4_value, \
+        CAST(8 AS BIGINT) AS int8_value, CAST(1.5 AS REAL) AS float4_value, CAST(2.5 AS DOUBLE) AS float8_value, \
+        CAST(12.34 AS DECIMAL(10, 2)) AS numeric_value, 'text' AS text_value, CAST('bytes' AS BYTEA) AS bytea_value, \
+        CAST('2024-02-29' AS DATE) AS date_value, CAST('2024-02-29T12:30:00' AS TIMESTAMP) AS timestamp_value, \
+        make_array(1, 2, 3) AS int8_array_value, arrow_cast(1, 'UInt8') AS uint8_value, \
+        named_struct('a', 1) AS struct_value";
+    let statement: Statement = client.prepare(stm).await?;
+    let types: Vec<(&str, Type)> = statement.columns().iter().map(|column| (column.name(), column.type_().clone())).collect();
+    assert_eq!(
+        types,
+        [
+            ("bool_value", Type::BOOL),
+            ("int2_value", Type::INT2),
+            ("int4_value", Type::INT4),
+            ("int8_value", Type::INT8),
+            ("float4_value", Type::FLOAT4),
+            ("float8_value", Type::FLOAT8),
+            ("numeric_value", Type::NUMERIC),
+            ("text_value", Type::TEXT),
+            ("bytea_value", Type::BYTEA),
+            ("date_value", Type::DATE),
+            ("timestamp_value", Type::TIMESTAMP),
+            ("int8_array_value", Type::INT8_ARRAY),
+            ("uint8_value", Type::INT2),
+            // Types Postgres has no counterpart for are sent as text
+            ("struct_value", Type::TEXT),
+        ]
+    );
+
+    // The extended protocol sends the values in binary
+    let row: Row = client.query_one(&statement, &[]).await?;
+    assert!(row.get::<_, bool>("bool_value"));
+    assert_eq!(row.get::<_, i16>("int2_value"), 2);
+    assert_eq!(row.get::<_, i32>("int4_value"), 4);
+    assert_eq!(row.get::<_, i64>("int8_value"), 8);
+    assert_eq!(row.get::<_, f32>("float4_value"), 1.5);
+    assert_eq!(row.get::<_, f64>("float8_value"), 2.5);
+    assert_eq!(row.get::<_, &str>("text_value"), "text");
+    assert_eq!(row.get::<_, &[u8]>("bytea_value"), b"bytes");
+    assert_eq!(row.get::<_, Vec<i64>>("int8_array_value"), [1, 2, 3]);
+    assert_eq!(row.get::<_, i16>("uint8_value"), 1);
+    assert_eq!(row.get::<_, &str>("struct_value"), "{a: 1}");
+
+    // The simple protocol sends them as text
+    let rows: Vec<Vec<Option<String>>> = simple_query(&client, stm).await?;
+    assert_eq!(
+        rows,
+        [text(&["t", "2", "4", "8", "1.5", "2.5", "12.34", "text", "\\x6279746573", "2024-02-29", "2024-02-29 12:30:00.000000", "{1,2,3}", "1", "{a: 1}"])]
+    );
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_extended_protocol() -> TestResult {
+    let catalog_dir: TempDir = tempfile::tempdir()?;
+    let client: Client = connect(start_server(&catalog_dir).await?).await?;
+
+    let statement: Statement = client
+        .prepare(
+            "SELECT st.name, s.subject, s.score FROM school.scores AS s JOIN school.students AS st ON st.id = s.student_id \
+             WHERE s.score >= $1 AND s.subject = $2 ORDER BY st.name",
+        )
+        .await?;
+    // Parameter types are inferred from the columns they are compared with
+    assert_eq!(statement.params(), [Type::INT8, Type::TEXT]);
+    let rows: Vec<Row> = client.query(&statement, &[&90_i64, &"math"]).await?;
+    let rows: Vec<(String, String, i64)> = rows.iter().map(|row| (row.get(0), row.get(1), row.get(2))).collect();
+    assert!(!rows.is_empty());
+    assert!(rows.iter().all(|(_, subject, score)| subject == "math" && *score >= 90));
+    // The same statement runs again with other parameters
+    let rows: Vec<Row> = client.query(&statement, &[&101_i64, &"math"]).await?;
+    assert!(rows.is_empty());
+
+    // Session statements are answered by the server
+    client.batch_execute("BEGIN; SET application_name = 'test'; COMMIT").await?;
+    let rows: Vec<Vec<Option<String>>> = simple_query(&client, "SHOW server_encoding").await?;
+    assert_eq!(rows, [text(&["UTF8"])]);
+
+    // Errors carry the SQLSTATE clients tell them apart by
+    let error = client.prepare("SELEC 1").await.expect_err("syntax error");
+    assert_eq!(error.code().map(|code| code.code()), Some("42601"));
+    let error = client.prepare("SELECT missing FROM school.scores").await.expect_err("unknown column");
+    assert_eq!(error.code().map(|code| code.code()), Some("42000"));
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_pg_catalog() -> TestResult {
+    let catalog_dir: TempDir = tempfile::tempdir()?;
+    let client: Client = connect(start_server(&catalog_dir).await?).await?;
+
+    // The catalog tables are listed as psql does, before they are queried
+    let rows: Vec<Row> = client
+        .query(
+            "SELECT n.nspname, c.relname FROM pg_catalog.pg_class c \
+             JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace WHERE n.nspname = $1 ORDER BY 1, 2",
+            &[&"school"],
+        )
+        .await?;
+    let tables: Vec<(String, String)> = rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+    assert_eq!(tables, [("school".to_string(), "scores".to_string()), ("school".to_string(), "students".to_string())]);
+
+    let rows: Vec<Vec<Option<String>>> = simple_query(
+        &client,
+        "SELECT a.attname, t.typname FROM pg_catalog.pg_attribute a JOIN pg_catalog.pg_class c ON c.oid = a.attrelid \
+         JOIN pg_catalog.pg_type t ON t.oid = a.atttypid WHERE c.relname = 'students' ORDER BY a.attnum",
+    ).await?;
+    assert_eq!(rows.first(), Some(&text(&["id", "int8"])));
+
+    let rows: Vec<Vec<Option<String>>> = simple_query(&client, "SELECT datname FROM pg_catalog.pg_database").await?;
+    assert_eq!(rows, [text(&["datafusion"])]);
+    let rows: Vec<Vec<Option<String>>> = simple_query(&client, "SELECT version()").await?;
+    assert!(rows[0][0].as_deref().is_some_and(|version| version.starts_with("PostgreSQL")));
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_sessions() -> TestResult {
+    let catalog_dir: TempDir = tempfile::tempdir()?;
+    let address: SocketAddr = start_server(&catalog_dir).await?;
+    let client: Client = connect(address).await?;
+    let other_client: Client = connect(address).await?;
+
+    // Settings apply to the connection that sets them
+    client.batch_execute("SET datafusion.execution.batch_size = 1024").await?;
+    let rows: Vec<Vec<Option<String>>> = simple_query(&client, "SHOW datafusion.execution.batch_size").await?;
+    assert_eq!(rows, [text(&["datafusion.execution.batch_size", "1024"])]);
+    let rows: Vec<Vec<Option<String>>> = simple_query(&other_client, "SHOW datafusion.execution.batch_size").await?;
+    assert_eq!(rows, [text(&["datafusion.execution.batch_size", "8192"])]);
+
+    // Tables are shared
+    client.batch_execute("CREATE TABLE shared AS SELECT 1 AS value").await?;
+    let rows: Vec<Vec<Option<String>>> = simple_query(&other_client, "SELECT value FROM shared").await?;
+    assert_eq!(rows, [text(&["1"])]);
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Explicit schemas, type hints, sample sizes and the all-`Utf8` mode set with `sa.schema*`.
+use std::fs;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::DataType;
+use datafusion::assert_batches_eq;
+use datafusion::prelude::DataFrame;
+use engine::builder::pipelines;
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use tempfile::TempDir;
+
+
+fn get_scores_uri() -> String {
+    format!("file://{}/.data/bin/ex-local-storage-application/scores.csv", env!("CARGO_MANIFEST_DIR"))
+}
+
+
+/// Data types of the columns of `uri` after the `settings` statements.
+async fn get_data_types(uri: &str, settings: &[&str]) -> Result<Vec<(String, DataType)>> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    for setting in settings {
+        pipelines::sa_query(&sa_datafusion, setting).await?;
+    }
+    let df: DataFrame = pipelines::sa_query(&sa_datafusion, &format!("SELECT * FROM '{}'", uri)).await?;
+    Ok(df.schema().fields().iter().map(|field| (field.name().clone(), field.data_type().clone())).collect())
+}
+
+
+fn to_data_types(data_types: &[(&str, DataType)]) -> Vec<(String, DataType)> {
+    data_types.iter().map(|(name, data_type)| (name.to_string(), data_type.clone())).collect()
+}
+
+
+#[tokio::test]
+async fn test_inferred_schema() -> Result<()> {
+    let data_types: Vec<(String, DataType)> = get_data_types(&get_scores_uri(), &[]).await?;
+    assert_eq!(
+        data_types,
+        to_data_types(&[("id", DataType::Int64), ("student_id", DataType::Int64), ("subject", DataType::Utf8), ("score", DataType::Int64)])
+    );
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_type_hints() -> Result<()> {
+    let data_types: Vec<(String, DataType)> = get_data_types(&get_scores_uri(), &["SET sa.schema_hints = 'score: Float64, id: Utf8'"]).await?;
+    assert_eq!(
+        data_types,
+        to_data_types(&[("id", DataType::Utf8), ("student_id", DataType::Int64), ("subject", DataType::Utf8), ("score", DataType::Float64)])
+    );
+
+    // Partition columns take hints too
+    let dir: TempDir = tempfile::tempdir()?;
+    fs::create_dir_all(dir.path().join("year=2024"))?;
+    fs::write(dir.path().join("year=2024/scores.csv"), "id,score\n1,98\n")?;
+    let uri: String = format!("file://{}/", dir.path().display());
+    let data_types: Vec<(String, DataType)> = get_data_types(&uri, &["SET sa.schema_hints = 'year: Utf8'"]).await?;
+    assert_eq!(data_types, to_data_types(&[("id", DataType::Int64), ("score", DataType::Int64), ("year", DataType::Utf8)]));
+
+    // A misspelt column is an error rather than a hint quietly ignored
+    let error: String = get_data_types(&get_scores_uri(), &["SET sa.schema_hints = 'scroe: Float64'"]).await.unwrap_err().to_string();
+    assert!(error.contains("Type hint of unknown column 'scroe'"), "{}", error);
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_explicit_schema() -> Result<()> {
+    let sa_datafusion: SaDataFusion = SaDataFusion::new();
+    pipelines::sa_query(&sa_datafusion, "SET sa.schema = 'id: Int32, student_id: Int32, subject: Utf8, score: Decimal128(5, 2)'").await?;
+    let stm: String = format!("SELECT subject, SUM(score) AS score FROM '{}' GROUP BY subject ORDER BY subject", get_scores_uri());
+    let batches: Vec<RecordBatch> = pipelines::sa_query(&sa_datafusion, &stm).await?.collect().await?;
+    assert_batches_eq!(
+        [
+            "+---------+--------+",
+            "| subject | score  |",
+            "+---------+--------+",
+            "| chem    | 98.00  |",
+            "| math    | 198.00 |",
+            "| physic  | 199.00 |",
+            "+---------+--------+",
+        ],
+        &batches
+    );
+
+    // Hints override the explicit schema as well
+    let data_types: Vec<(String, DataType)> = get_data_types(
+        &get_scores_uri(),
+        &["SET sa.schema = 'id: Int32, student_id: Int32, subject: Utf8, score: Int32'", "SET sa.schema_hints = 'score: Float64'"],
+    ).await?;
+    assert_eq!(data_types[3], ("score".to_string(), DataType::Float64));
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_sample_size() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    let path: String = dir.path().join("codes.csv").display().to_string();
+    fs::write(&path, "id,code\n1,10\n2,20\n3,30\n4,A40\n")?;
+    let uri: String = format!("file://{}", path);
+
+    // Every record is read by default, so the last one makes the column text
+    let data_types: Vec<(String, DataType)> = get_data_types(&uri, &[]).await?;
+    assert_eq!(data_types[1], ("code".to_string(), DataType::Utf8));
+    let data_types: Vec<(String, DataType)> = get_data_types(&uri, &["SET sa.schema_sample_size = 2"]).await?;
+    assert_eq!(data_types[1], ("code".to_string(), DataType::Int64));
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_all_utf8() -> Result<()> {
+    let data_types: Vec<(String, DataType)> = get_data_types(
+        &get_scores_uri(),
+        &["SET sa.schema_all_utf8 = true", "SET sa.schema_hints = 'score: Int32'"],
+    ).await?;
+    assert_eq!(
+        data_types,
+        to_data_types(&[("id", DataType::Utf8), ("student_id", DataType::Utf8), ("subject", DataType::Utf8), ("score", DataType::Int32)])
+    );
+    Ok(())
+}
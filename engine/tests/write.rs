@@ -0,0 +1,153 @@
+//! Results written to `file://` with `COPY ... TO` and [`pipelines::sa_write_pipeline`], then read back.
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::assert_batches_eq;
+use datafusion::parquet::basic::Compression;
+use datafusion::parquet::file::reader::{FileReader, SerializedFileReader};
+use engine::builder::pipelines;
+use engine::builder::write::{self, SaWriteOptions};
+use engine::datafusion::SaDataFusion;
+use engine::error::Result;
+use tempfile::TempDir;
+
+
+fn get_scores_uri() -> String {
+    format!("file://{}/.data/bin/ex-local-storage-application/scores.csv", env!("CARGO_MANIFEST_DIR"))
+}
+
+
+async fn query(stm: &str) -> Result<Vec<RecordBatch>> {
+    Ok(pipelines::sa_query(&SaDataFusion::new(), stm).await?.collect().await?)
+}
+
+
+/// Runs the `COPY` statement `stm` and returns the number of rows it wrote.
+async fn copy(stm: &str) -> Result<u64> {
+    Ok(write::get_row_count(&query(stm).await?))
+}
+
+
+/// Asserts that `uri` holds the example scores.
+async fn assert_scores(uri: &str) -> Result<()> {
+    let stm: String = format!("SELECT subject, COUNT(*) AS scores, SUM(score) AS score FROM '{}' GROUP BY subject ORDER BY subject", uri);
+    assert_batches_eq!(
+        [
+            "+---------+--------+-------+",
+            "| subject | scores | score |",
+            "+---------+--------+-------+",
+            "| chem    | 1      | 98    |",
+            "| math    | 2      | 198   |",
+            "| physic  | 2      | 199   |",
+            "+---------+--------+-------+",
+        ],
+        &query(&stm).await?
+    );
+    Ok(())
+}
+
+
+/// Names of the entries of the directory `path`, sorted.
+fn list_dir(path: &Path) -> Result<Vec<String>> {
+    let mut names: Vec<String> = fs::read_dir(path)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().to_string()))
+        .collect::<Result<_>>()?;
+    names.sort();
+    Ok(names)
+}
+
+
+#[tokio::test]
+async fn test_copy_formats() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    for extension in ["parquet", "csv", "json", "arrow"] {
+        let target: String = format!("file://{}/scores.{}", dir.path().display(), extension);
+        let stm: String = format!("COPY (SELECT * FROM '{}') TO '{}'", get_scores_uri(), target);
+        assert_eq!(copy(&stm).await?, 5);
+        assert!(dir.path().join(format!("scores.{}", extension)).is_file());
+        assert_scores(&target).await?;
+    }
+
+    // `STORED AS` wins over the extension, and options reach the format
+    let target: String = format!("file://{}/scores.out", dir.path().display());
+    let stm: String = format!(
+        "COPY (SELECT * FROM '{}') TO '{}' STORED AS PARQUET OPTIONS (compression 'zstd(3)', max_row_group_size 2)",
+        get_scores_uri(),
+        target
+    );
+    assert_eq!(copy(&stm).await?, 5);
+    let reader: SerializedFileReader<fs::File> = SerializedFileReader::new(fs::File::open(dir.path().join("scores.out"))?)?;
+    assert_eq!(reader.metadata().num_row_groups(), 3);
+    // The level is not kept in the file footer
+    assert!(matches!(reader.metadata().row_group(0).column(0).compression(), Compression::ZSTD(_)));
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_copy_partition_by() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    let target: String = format!("file://{}/by_subject/", dir.path().display());
+    let stm: String = format!("COPY (SELECT * FROM '{}') TO '{}' STORED AS PARQUET PARTITIONED BY (subject)", get_scores_uri(), target);
+    assert_eq!(copy(&stm).await?, 5);
+    assert_eq!(list_dir(&dir.path().join("by_subject"))?, ["subject=chem", "subject=math", "subject=physic"]);
+    // The partition column is read back from the paths
+    assert_scores(&target).await?;
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_copy_directory() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    // A target ending with `/` is a directory of files named by DataFusion
+    let target: String = format!("file://{}/scores/", dir.path().display());
+    let stm: String = format!("COPY (SELECT * FROM '{}') TO '{}' STORED AS CSV", get_scores_uri(), target);
+    assert_eq!(copy(&stm).await?, 5);
+    let files: Vec<String> = list_dir(&dir.path().join("scores"))?;
+    assert!(!files.is_empty() && files.iter().all(|file| file.ends_with(".csv")), "{:?}", files);
+    assert_scores(&target).await?;
+    Ok(())
+}
+
+
+#[tokio::test]
+async fn test_write_pipeline() -> Result<()> {
+    let dir: TempDir = tempfile::tempdir()?;
+    let stm: String = format!("SELECT * FROM '{}'", get_scores_uri());
+    let options: HashMap<String, String> = HashMap::new();
+
+    // A single file, with its format from the extension
+    let target: String = format!("file://{}/scores.json", dir.path().display());
+    assert_eq!(pipelines::sa_write_pipeline(&stm, &options, &target, &SaWriteOptions::new()).await?, 5);
+    assert!(dir.path().join("scores.json").is_file());
+    assert_scores(&target).await?;
+
+    // A directory although the target has an extension
+    let target: String = format!("file://{}/scores_dir.csv", dir.path().display());
+    let write_options: SaWriteOptions = SaWriteOptions::new().with_single_file(false).with_format_option("delimiter", ";");
+    assert_eq!(pipelines::sa_write_pipeline(&stm, &options, &target, &write_options).await?, 5);
+    let files: Vec<String> = list_dir(&dir.path().join("scores_dir.csv"))?;
+    assert!(!files.is_empty());
+    let content: String = fs::read_to_string(dir.path().join("scores_dir.csv").join(&files[0]))?;
+    assert!(content.starts_with("id;student_id;subject;score"), "{}", content);
+
+    // Partitions, with the format named and the row groups bounded
+    let target: String = format!("file://{}/by_subject", dir.path().display());
+    let write_options: SaWriteOptions = SaWriteOptions::new()
+        .with_format("parquet")
+        .with_compression("snappy")
+        .with_row_group_size(1)
+        .with_partition_by(vec!["subject".to_string()]);
+    assert_eq!(pipelines::sa_write_pipeline(&stm, &options, &target, &write_options).await?, 5);
+    assert_eq!(list_dir(&dir.path().join("by_subject"))?, ["subject=chem", "subject=math", "subject=physic"]);
+    assert_scores(&format!("{}/", target)).await?;
+
+    // A single file needs a file name
+    let target: String = format!("file://{}/single/", dir.path().display());
+    let write_options: SaWriteOptions = SaWriteOptions::new().with_format("csv").with_single_file(true);
+    assert!(pipelines::sa_write_pipeline(&stm, &options, &target, &write_options).await.is_err());
+    assert!(!dir.path().join("single").exists());
+    Ok(())
+}
@@ -2,7 +2,7 @@ import asyncio
 
 import pandas as pd
 import sa_rust
-from pyarrow import RecordBatchStreamReader, ipc
+import pyarrow as pa
 from pyarrow.lib import Table
 
 
@@ -21,12 +21,10 @@ async def run():
         ON
             st.id = s.student_id
     """
-    binary_data: list = await sa_rust.execute_sql(stm)
-    binary_bytes: bytes = bytes(binary_data)
-    reader: RecordBatchStreamReader = ipc.open_stream(binary_bytes)
+    reader: sa_rust.RecordBatchReader = await sa_rust.execute_sql(stm)
 
-    # Read all Arrow data (without unnecessary deserialization)
-    table: Table = reader.read_all()
+    # Read the Arrow batches through the C stream interface, without copy
+    table: Table = pa.table(reader)
 
     # Convert to Pandas DataFrame
     df: pd.DataFrame = table.to_pandas()